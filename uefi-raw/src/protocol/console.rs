@@ -1,9 +1,11 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
+pub mod edid;
 pub mod serial;
 
 use crate::{Boolean, Char16, Event, Guid, PhysicalAddress, Status, guid};
 use bitflags::bitflags;
+use core::ffi::c_void;
 use core::ptr;
 
 bitflags! {
@@ -74,6 +76,86 @@ impl SimpleTextInputProtocol {
     pub const GUID: Guid = guid!("387477c1-69c7-11d2-8e39-00a0c969723b");
 }
 
+bitflags! {
+    /// State of the shift, control, alt and logo keys.
+    #[repr(transparent)]
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct KeyShiftState: u32 {
+        const RIGHT_SHIFT_PRESSED = 0x0000_0001;
+        const LEFT_SHIFT_PRESSED = 0x0000_0002;
+        const RIGHT_CONTROL_PRESSED = 0x0000_0004;
+        const LEFT_CONTROL_PRESSED = 0x0000_0008;
+        const RIGHT_ALT_PRESSED = 0x0000_0010;
+        const LEFT_ALT_PRESSED = 0x0000_0020;
+        const RIGHT_LOGO_PRESSED = 0x0000_0040;
+        const LEFT_LOGO_PRESSED = 0x0000_0080;
+        const MENU_KEY_PRESSED = 0x0000_0100;
+        const SYS_REQ_PRESSED = 0x0000_0200;
+
+        /// If set, the other bits are valid.
+        const SHIFT_STATE_VALID = 0x8000_0000;
+    }
+}
+
+bitflags! {
+    /// State of the toggle keys, like caps lock.
+    #[repr(transparent)]
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct KeyToggleState: u8 {
+        const SCROLL_LOCK_ACTIVE = 0x01;
+        const NUM_LOCK_ACTIVE = 0x02;
+        const CAPS_LOCK_ACTIVE = 0x04;
+
+        /// If set, partial keystrokes are reported, for example a modifier
+        /// key pressed on its own.
+        const KEY_STATE_EXPOSED = 0x40;
+
+        /// If set, the other bits are valid.
+        const TOGGLE_STATE_VALID = 0x80;
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[repr(C)]
+pub struct KeyState {
+    pub key_shift_state: KeyShiftState,
+    pub key_toggle_state: KeyToggleState,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[repr(C)]
+pub struct KeyData {
+    pub key: InputKey,
+    pub key_state: KeyState,
+}
+
+pub type KeyNotifyFunction = unsafe extern "efiapi" fn(key_data: *mut KeyData) -> Status;
+
+#[derive(Debug)]
+#[repr(C)]
+pub struct SimpleTextInputExProtocol {
+    pub reset: unsafe extern "efiapi" fn(this: *mut Self, extended_verification: Boolean) -> Status,
+    pub read_key_stroke_ex:
+        unsafe extern "efiapi" fn(this: *mut Self, key_data: *mut KeyData) -> Status,
+    pub wait_for_key_ex: Event,
+    pub set_state: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        key_toggle_state: *const KeyToggleState,
+    ) -> Status,
+    pub register_key_notify: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        key_data: *const KeyData,
+        key_notification_function: KeyNotifyFunction,
+        notify_handle: *mut *mut c_void,
+    ) -> Status,
+    pub unregister_key_notify:
+        unsafe extern "efiapi" fn(this: *mut Self, notification_handle: *mut c_void) -> Status,
+}
+
+impl SimpleTextInputExProtocol {
+    pub const GUID: Guid = guid!("dd9e7534-7762-4698-8c14-f58517a625aa");
+}
+
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
 #[repr(C)]
 pub struct SimpleTextOutputMode {
@@ -0,0 +1,56 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::{Guid, Handle, Status, guid};
+use bitflags::bitflags;
+
+#[derive(Debug)]
+#[repr(C)]
+pub struct EdidDiscoveredProtocol {
+    pub size_of_edid: u32,
+    pub edid: *const u8,
+}
+
+impl EdidDiscoveredProtocol {
+    pub const GUID: Guid = guid!("1c0c34f6-d380-41fa-a049-8ad06c1a66aa");
+}
+
+#[derive(Debug)]
+#[repr(C)]
+pub struct EdidActiveProtocol {
+    pub size_of_edid: u32,
+    pub edid: *const u8,
+}
+
+impl EdidActiveProtocol {
+    pub const GUID: Guid = guid!("bd8c1056-9f36-44ec-92a8-a6337f817986");
+}
+
+bitflags! {
+    /// Attributes returned by [`EdidOverrideProtocol::get_edid`].
+    #[repr(transparent)]
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct EdidOverrideAttributes: u32 {
+        /// The EDID of the display must not be overridden.
+        const DONT_OVERRIDE = 0x01;
+
+        /// The display is hot-pluggable, and the returned EDID is only used
+        /// while the display is not connected.
+        const ENABLE_HOT_PLUG = 0x02;
+    }
+}
+
+#[derive(Debug)]
+#[repr(C)]
+pub struct EdidOverrideProtocol {
+    pub get_edid: unsafe extern "efiapi" fn(
+        this: *const Self,
+        child_handle: *const Handle,
+        attributes: *mut EdidOverrideAttributes,
+        edid_size: *mut usize,
+        edid: *mut *const u8,
+    ) -> Status,
+}
+
+impl EdidOverrideProtocol {
+    pub const GUID: Guid = guid!("48ecb431-fb72-45c0-a922-f458fe040bd5");
+}
@@ -9,6 +9,8 @@ pub fn test() {
 
     global::alloc_vec();
     global::alloc_alignment();
+
+    tracking::alloc_stats();
 }
 
 /// Tests that directly use UEFI boot services to allocate memory.
@@ -131,3 +133,65 @@ mod global {
         }
     }
 }
+
+/// Tests for [`uefi::allocator::TrackingAllocator`].
+mod tracking {
+    use core::alloc::{GlobalAlloc, Layout};
+    use uefi::allocator::{AllocatorStats, TrackingAllocator};
+    use uefi::boot::MemoryType;
+
+    /// Tests that the statistics and the list of outstanding allocations are
+    /// updated correctly for pool, aligned, and page allocations.
+    pub fn alloc_stats() {
+        info!("Testing the tracking allocator");
+
+        let allocator = TrackingAllocator::new()
+            .with_memory_type(MemoryType::LOADER_DATA)
+            .with_large_allocation_threshold(0x4000);
+        let layouts = [
+            Layout::from_size_align(24, 8).unwrap(),
+            Layout::from_size_align(0x100, 0x100).unwrap(),
+            Layout::from_size_align(0x8000, 16).unwrap(),
+        ];
+
+        let ptrs = layouts.map(|layout| {
+            let ptr = unsafe { allocator.alloc(layout) };
+            assert!(!ptr.is_null());
+            assert_eq!(ptr as usize % layout.align(), 0, "Wrong alignment");
+            unsafe { ptr.write_bytes(0xaa, layout.size()) };
+            ptr
+        });
+
+        let live_bytes = 24 + 0x100 + 0x8000;
+        assert_eq!(
+            allocator.stats(),
+            AllocatorStats {
+                live_allocations: 3,
+                live_bytes,
+                peak_bytes: live_bytes,
+                total_allocations: 3,
+            }
+        );
+
+        let mut outstanding = 0;
+        allocator.for_each_outstanding(|info| {
+            assert!(ptrs.contains(&(info.address as *mut u8)));
+            outstanding += 1;
+        });
+        assert_eq!(outstanding, 3);
+
+        for (ptr, layout) in ptrs.into_iter().zip(layouts) {
+            unsafe { allocator.dealloc(ptr, layout) };
+        }
+        assert_eq!(
+            allocator.stats(),
+            AllocatorStats {
+                live_allocations: 0,
+                live_bytes: 0,
+                peak_bytes: live_bytes,
+                total_allocations: 3,
+            }
+        );
+        allocator.for_each_outstanding(|_| panic!("allocation was not freed"));
+    }
+}
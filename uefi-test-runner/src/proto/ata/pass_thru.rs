@@ -3,7 +3,8 @@
 use uefi::boot;
 use uefi::boot::{OpenProtocolAttributes, OpenProtocolParams};
 use uefi::proto::ata::AtaRequestBuilder;
-use uefi::proto::ata::pass_thru::AtaPassThru;
+use uefi::proto::ata::commands::AtaFeatures;
+use uefi::proto::ata::pass_thru::{AtaDevice, AtaPassThru};
 
 pub fn test() {
     info!("Running ATA PassThru tests");
@@ -49,6 +50,7 @@ fn is_testdrive_present() -> bool {
                 let serial = core::str::from_utf8(&serial_bfr).unwrap().trim();
                 if serial == "AtaPassThru" {
                     info!("Found Testdisk at handle: {handle:?}");
+                    test_commands(&mut device);
                     return true; // found our testdrive!
                 }
             }
@@ -57,3 +59,25 @@ fn is_testdrive_present() -> bool {
 
     false
 }
+
+fn test_commands(device: &mut AtaDevice) {
+    info!("Testing ATA commands");
+
+    let identify = device.identify().unwrap();
+    assert_eq!(identify.serial_number, "AtaPassThru");
+    assert_eq!(identify.model_number, "AtaPassThru");
+    assert_eq!(identify.logical_sector_size, 512);
+    assert!(identify.sectors > 0);
+    assert!(identify.features.contains(AtaFeatures::SMART));
+
+    device.smart_enable().unwrap();
+    assert!(!device.smart_status().unwrap());
+    let smart = device.smart_data().unwrap();
+    let thresholds = device.smart_thresholds().unwrap();
+    assert!(
+        !smart
+            .attributes
+            .iter()
+            .any(|attribute| attribute.is_failing(&thresholds))
+    );
+}
@@ -1,8 +1,17 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 use crate::{HostRequest, send_request_to_host};
+use alloc::vec;
+use core::fmt::Write;
 use uefi::boot::{self, OpenProtocolAttributes, OpenProtocolParams};
-use uefi::proto::console::gop::{BltOp, BltPixel, FrameBuffer, GraphicsOutput, PixelFormat};
+use uefi::fs::FileSystem;
+use uefi::proto::console::edid::EdidActive;
+use uefi::proto::console::gop::{
+    BltOp, BltPixel, BltRegion, Canvas, FrameBuffer, GraphicsConsole, GraphicsOutput, Image,
+    PixelFormat, Rect,
+};
+use uefi::proto::console::text::Color;
+use uefi::{Handle, cstr16};
 
 pub unsafe fn test() {
     info!("Running graphics output protocol test");
@@ -23,6 +32,7 @@ pub unsafe fn test() {
         .expect("failed to open Graphics Output Protocol")
     };
 
+    check_edid(handle, gop);
     set_graphics_mode(gop);
     fill_color(gop);
     draw_fb(gop);
@@ -31,6 +41,41 @@ pub unsafe fn test() {
     if cfg!(not(target_arch = "aarch64")) {
         send_request_to_host(HostRequest::Screenshot("gop_test"));
     }
+    capture_screen(gop);
+
+    draw_console(gop);
+    draw_canvas(gop);
+}
+
+// Parse the EDID of the display, if the video driver provides one.
+fn check_edid(handle: Handle, gop: &GraphicsOutput) {
+    let edid = unsafe {
+        boot::open_protocol::<EdidActive>(
+            OpenProtocolParams {
+                handle,
+                agent: boot::image_handle(),
+                controller: None,
+            },
+            OpenProtocolAttributes::GetProtocol,
+        )
+    };
+    let Ok(edid) = edid else {
+        info!("EdidActive protocol is not supported");
+        return;
+    };
+    match edid.edid() {
+        Ok(edid) => {
+            info!(
+                "Display: {:?}, preferred timing: {:?}",
+                edid.name(),
+                edid.preferred_timing()
+            );
+            if let Some(mode) = gop.preferred_mode(&edid) {
+                assert!(edid.supports_resolution(mode.info().resolution()));
+            }
+        }
+        Err(err) => info!("No valid active EDID: {err}"),
+    }
 }
 
 // Set a larger graphics mode.
@@ -106,3 +151,73 @@ fn draw_fb(gop: &mut GraphicsOutput) {
     fill_rectangle((50, 30), (150, 600), [250, 128, 64]);
     fill_rectangle((400, 120), (750, 450), [16, 128, 255]);
 }
+
+// Take a screenshot on the device and store it on the ESP.
+fn capture_screen(gop: &mut GraphicsOutput) {
+    let screenshot = gop.capture().expect("Failed to capture screen");
+    assert_eq!((screenshot.width(), screenshot.height()), (1024, 768));
+
+    let sfs = boot::get_image_file_system(boot::image_handle()).expect("Failed to open ESP");
+    let mut fs = FileSystem::new(sfs);
+    let path = cstr16!("gop_test.png");
+    fs.write(path, screenshot.encode_png())
+        .expect("Failed to write screenshot");
+    let png = fs.read(path).expect("Failed to read screenshot");
+    fs.remove_file(path).expect("Failed to remove screenshot");
+
+    assert_eq!(Image::decode(&png).unwrap(), screenshot);
+    assert_eq!(Image::decode(&screenshot.encode_bmp()).unwrap(), screenshot);
+}
+
+// Render text to the frame buffer.
+fn draw_console(gop: &mut GraphicsOutput) {
+    // See `draw_fb`.
+    if cfg!(target_arch = "aarch64") {
+        return;
+    }
+
+    let mut console = GraphicsConsole::new(gop).expect("Failed to create graphics console");
+    assert_eq!((console.columns(), console.rows()), (128, 48));
+
+    console.set_color(Color::Yellow.into(), Color::Blue.into());
+    console.enable_cursor(true);
+    writeln!(console, "Hello from the graphics console!").unwrap();
+    write!(console, "Second line").unwrap();
+    assert_eq!(console.cursor_position(), (11, 1));
+}
+
+// Draw to a canvas and copy it to the screen.
+fn draw_canvas(gop: &mut GraphicsOutput) {
+    let white = BltPixel::new(255, 255, 255);
+    let red = BltPixel::new(255, 0, 0);
+
+    let mut canvas = Canvas::new(64, 64);
+    canvas.clear(BltPixel::new(0, 0, 0));
+    canvas.draw_rect(Rect::new(0, 0, 64, 64), white);
+    canvas.draw_line((0, 0), (63, 63), white);
+    let image = Image::new(2, 2, vec![red; 4]).with_alpha(vec![255, 0, 255, 0]);
+    canvas.draw_image(&image, (10, 20));
+    canvas
+        .flush(gop, (800, 600))
+        .expect("Failed to flush canvas");
+    assert!(canvas.damage().is_empty());
+
+    // Read the canvas back from the screen.
+    let mut pixels = vec![BltPixel::new(0, 0, 0); 64 * 64];
+    gop.blt(BltOp::VideoToBltBuffer {
+        buffer: &mut pixels,
+        src: (800, 600),
+        dest: BltRegion::Full,
+        dims: (64, 64),
+    })
+    .expect("Failed to read canvas from screen");
+    assert_eq!(pixels, canvas.pixels());
+
+    // A canvas partly off screen is clipped.
+    let (width, height) = gop.current_mode_info().resolution();
+    canvas.draw_rect(Rect::new(0, 0, 64, 64), red);
+    canvas
+        .flush(gop, (width - 32, height - 32))
+        .expect("Failed to flush clipped canvas");
+    assert!(canvas.damage().is_empty());
+}
@@ -0,0 +1,41 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use alloc::vec::Vec;
+use uefi::proto::console::text::{InputEx, Key, KeyShiftState, ScanCode};
+use uefi::{Status, boot};
+
+pub fn test() {
+    info!("Running text input ex protocol test");
+    let handle = boot::get_handle_for_protocol::<InputEx>().expect("missing InputEx protocol");
+    let input =
+        boot::open_protocol_exclusive::<InputEx>(handle).expect("failed to open InputEx protocol");
+
+    // No keys are pressed during the tests.
+    while let Some(stroke) = input.read_key_stroke().expect("failed to read key stroke") {
+        info!("Discarding key stroke: {stroke:?}");
+    }
+
+    let notifications: Vec<_> = (0..InputEx::MAX_KEY_NOTIFICATIONS)
+        .map(|_| {
+            input
+                .register_key_notify(
+                    Key::Special(ScanCode::FUNCTION_12),
+                    Some(KeyShiftState::empty()),
+                    None,
+                    |stroke| info!("F12 pressed: {stroke:?}"),
+                )
+                .expect("failed to register key notification")
+        })
+        .collect();
+    let err = input
+        .register_key_notify(Key::Special(ScanCode::FUNCTION_11), None, None, |_| {})
+        .unwrap_err();
+    assert_eq!(err.status(), Status::OUT_OF_RESOURCES);
+
+    // Dropping a notification frees its slot.
+    drop(notifications);
+    let notification = input
+        .register_key_notify(Key::Special(ScanCode::FUNCTION_11), None, None, |_| {})
+        .expect("failed to register key notification");
+    drop(notification);
+}
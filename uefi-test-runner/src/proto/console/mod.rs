@@ -6,6 +6,8 @@ pub fn test() {
     info!("Testing console protocols");
 
     system::with_stdout(stdout::test);
+    tui::test();
+    input_ex::test();
 
     unsafe {
         serial::test();
@@ -15,6 +17,8 @@ pub fn test() {
 }
 
 mod gop;
+mod input_ex;
 mod pointer;
 mod serial;
 mod stdout;
+mod tui;
@@ -1,7 +1,7 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 use uefi::boot;
-use uefi::proto::console::pointer::Pointer;
+use uefi::proto::console::pointer::{AbsolutePointer, Pointer};
 
 pub fn test() {
     info!("Running pointer protocol test");
@@ -22,4 +22,30 @@ pub fn test() {
     } else {
         info!("Pointer state has not changed since the last query");
     }
+
+    test_absolute();
+}
+
+fn test_absolute() {
+    // QEMU is not configured with a touchscreen or tablet.
+    let Ok(handle) = boot::get_handle_for_protocol::<AbsolutePointer>() else {
+        info!("AbsolutePointer protocol is not supported");
+        return;
+    };
+    info!("Running absolute pointer protocol test");
+    let mut pointer = boot::open_protocol_exclusive::<AbsolutePointer>(handle)
+        .expect("failed to open absolute pointer protocol");
+    pointer
+        .reset(false)
+        .expect("failed to reset absolute pointer device");
+
+    let mode = *pointer.mode();
+    info!("Absolute pointer mode: {mode:#?}");
+    if let Some(state) = pointer
+        .read_state()
+        .expect("failed to retrieve absolute pointer state")
+    {
+        let (x, y) = pointer.screen_position(&state, (1024, 768));
+        assert!(x < 1024 && y < 768);
+    }
 }
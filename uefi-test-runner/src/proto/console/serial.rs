@@ -1,8 +1,10 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 use crate::reconnect_serial_to_console;
-use uefi::proto::console::serial::{ControlBits, Serial};
-use uefi::{Result, ResultExt, Status, boot};
+use core::time::Duration;
+use uefi::proto::console::serial::{ControlBits, Serial, Terminal};
+use uefi::proto::console::text::{Key, ScanCode};
+use uefi::{Char16, Result, ResultExt, Status, boot};
 
 // For the duration of this function, the serial device is opened in
 // exclusive mode. That means logs will not work, which means we should
@@ -31,11 +33,26 @@ fn serial_test_helper(serial: &mut Serial) -> Result {
     let mut input = [0u8; MSG_LEN];
     serial.read(&mut input).discard_errdata()?;
 
+    // Escape sequences sent by a terminal are translated to keys.
+    serial.write(b"\x1b[Ax").discard_errdata()?;
+    let mut terminal = Terminal::new(serial);
+    let timeout = Some(Duration::from_millis(100));
+    let keys = [
+        terminal.read_key(timeout)?,
+        terminal.read_key(timeout)?,
+        terminal.read_key(timeout)?,
+    ];
+    let expected_keys = [
+        Some(Key::Special(ScanCode::UP)),
+        Some(Key::Printable(Char16::try_from('x').unwrap())),
+        None,
+    ];
+
     // Clean up after ourselves
     serial.reset()?;
     serial.set_control_bits(old_ctrl_bits & ControlBits::SETTABLE)?;
 
-    if OUTPUT == input {
+    if OUTPUT == input && keys == expected_keys {
         Ok(())
     } else {
         Err(Status::ABORTED.into())
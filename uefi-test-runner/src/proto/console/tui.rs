@@ -0,0 +1,38 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use core::time::Duration;
+use uefi::prelude::*;
+use uefi::proto::console::text::tui::{Menu, ProgressBar, Tui};
+
+pub fn test() {
+    info!("Running text UI test");
+
+    // Nothing may be logged while the console is borrowed by the UI.
+    system::with_stdin(|input| {
+        system::with_stdout(|output| {
+            let mut tui = Tui::new(output, input).expect("Failed to create text UI");
+            tui.set_largest_mode().expect("Failed to change text mode");
+
+            let theme = *tui.theme();
+            let screen = tui.screen();
+            let (columns, rows) = (screen.columns(), screen.rows());
+            let mut menu = Menu::new(["First", "Second", "Third"]);
+            menu.draw(screen, (2, 2), (columns - 4, rows - 4), &theme);
+            tui.render().expect("Failed to render menu");
+
+            let mut bar = ProgressBar::new(4);
+            for value in 0..=4 {
+                bar.set_value(value);
+                tui.show_progress("Loading", &bar)
+                    .expect("Failed to render progress bar");
+            }
+
+            // Waiting for a key must return after the timeout if there is no
+            // input.
+            tui.read_key(Some(Duration::from_millis(10)))
+                .expect("Failed to wait for key");
+
+            output.reset(false).unwrap();
+        })
+    });
+}
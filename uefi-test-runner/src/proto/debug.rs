@@ -2,7 +2,13 @@
 
 use alloc::vec::Vec;
 use core::ffi::c_void;
+#[cfg(target_arch = "x86_64")]
+use core::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+use uefi::Status;
 use uefi::boot;
+#[cfg(target_arch = "x86_64")]
+use uefi::proto::debug::log_exception;
 use uefi::proto::debug::{DebugPort, DebugSupport, ExceptionType, ProcessorArch, SystemContext};
 
 pub fn test() {
@@ -114,6 +120,18 @@ fn test_debug_support() {
                             ExceptionType::EXCEPT_EBC_DEBUG,
                         )
                         .expect("Error while registering exception callback");
+                    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+                    assert_eq!(
+                        debug_support
+                            .register_exception_handler(
+                                0,
+                                ExceptionType::EXCEPT_EBC_DEBUG,
+                                |_, _| {}
+                            )
+                            .unwrap_err()
+                            .status(),
+                        Status::UNSUPPORTED
+                    );
                 },
                 #[cfg(target_arch = "x86_64")]
                 ProcessorArch::X86_64 => unsafe {
@@ -129,6 +147,7 @@ fn test_debug_support() {
                     debug_support
                         .register_exception_callback(0, None, ExceptionType::EXCEPT_X64_DEBUG)
                         .expect("Error while deregistering exception callback");
+                    test_exception_handler(&debug_support);
                 },
                 #[cfg(target_arch = "aarch64")]
                 ProcessorArch::AARCH_64 => unsafe {
@@ -155,6 +174,30 @@ fn test_debug_support() {
     }
 }
 
+#[cfg(target_arch = "x86_64")]
+fn test_exception_handler(debug_support: &DebugSupport) {
+    static BREAKPOINTS: AtomicUsize = AtomicUsize::new(0);
+
+    info!("Registering exception handler");
+    let handler = debug_support
+        .register_exception_handler(
+            0,
+            ExceptionType::EXCEPT_X64_BREAKPOINT,
+            |exception_type, context| {
+                log_exception(exception_type, context);
+                BREAKPOINTS.fetch_add(1, Ordering::Relaxed);
+            },
+        )
+        .expect("Error while registering exception handler");
+
+    // The breakpoint exception is a trap, so execution resumes after `int3`.
+    unsafe { core::arch::asm!("int3") };
+    assert_eq!(BREAKPOINTS.load(Ordering::Relaxed), 1);
+
+    info!("Unregistering exception handler");
+    drop(handler);
+}
+
 fn test_invalidate_instruction_cache(debug_support: &mut DebugSupport) {
     info!("Invalidating instruction cache");
     let mut addr = 0x0;
@@ -9,6 +9,7 @@ use uefi::boot::{
 use uefi::data_types::Align;
 use uefi::prelude::*;
 use uefi::proto::media::block::BlockIO;
+use uefi::proto::media::block_device::{BlockCache, BlockDevice};
 use uefi::proto::media::disk::{DiskIo, DiskIo2, DiskIo2Token};
 use uefi::proto::media::disk_info::{DiskInfo, DiskInfoInterface};
 use uefi::proto::media::file::{
@@ -16,6 +17,7 @@ use uefi::proto::media::file::{
 };
 use uefi::proto::media::fs::SimpleFileSystem;
 use uefi::proto::media::partition::{MbrOsType, PartitionInfo};
+use uefi::proto::media::storage_security::StorageSecurityCommand;
 use uefi::runtime::{Daylight, Time, TimeParams};
 
 #[repr(align(8))]
@@ -290,6 +292,77 @@ fn test_raw_disk_io(handle: Handle) {
     info!("Raw disk I/O succeeded");
 }
 
+/// Tests reading the disk through the `BlockDevice` trait and a cache.
+fn test_block_device(handle: Handle) {
+    info!("Testing block device cache");
+
+    // See `get_block_media_id` for why this is not opened exclusively.
+    let mut block_io = unsafe {
+        boot::open_protocol::<BlockIO>(
+            OpenProtocolParams {
+                handle,
+                agent: boot::image_handle(),
+                controller: None,
+            },
+            OpenProtocolAttributes::GetProtocol,
+        )
+        .expect("Failed to get block I/O protocol")
+    };
+    let block_count = block_io.block_count();
+    assert_eq!(block_io.block_size(), 512);
+
+    let mut cache = BlockCache::new(&mut *block_io, 4);
+    assert_eq!(cache.size(), block_count * 512);
+
+    // Verify the MBR signature with an unaligned read.
+    let mut signature = [0; 2];
+    cache.read(510, &mut signature).unwrap();
+    assert_eq!(signature, [0x55, 0xaa]);
+    cache.read(0, &mut [0; 16]).unwrap();
+    assert_eq!(cache.stats().hits, 1);
+    assert_eq!(cache.stats().misses, 1);
+
+    info!("Block device cache succeeded");
+}
+
+fn test_storage_security() {
+    info!("Testing storage security command protocol");
+
+    let Ok(handles) = boot::find_handles::<StorageSecurityCommand>() else {
+        info!("No storage security command protocol found, skipping");
+        return;
+    };
+
+    for handle in handles {
+        let media_id = boot::open_protocol_exclusive::<BlockIO>(handle)
+            .expect("Failed to get block I/O protocol")
+            .media()
+            .media_id();
+        let mut storage = boot::open_protocol_exclusive::<StorageSecurityCommand>(handle)
+            .expect("Failed to get storage security command protocol");
+
+        let mut buffer = [0; 512];
+        match storage.supported_protocols(media_id, &mut buffer) {
+            Ok(protocols) => info!("Supported security protocols: {protocols:02x?}"),
+            // The drive does not implement the security commands.
+            Err(err) if matches!(err.status(), Status::UNSUPPORTED | Status::DEVICE_ERROR) => {
+                continue;
+            }
+            Err(err) => panic!("Failed to list security protocols: {err:?}"),
+        }
+
+        let mut buffer = [0; 2048];
+        if let Ok(discovery) = storage.tcg_discovery(media_id, &mut buffer) {
+            info!(
+                "TCG discovery: {discovery:?}, locked: {}",
+                discovery.is_locked()
+            );
+        }
+    }
+
+    info!("Storage security command protocol succeeded");
+}
+
 /// Asynchronous disk I/O task context
 #[repr(C)]
 struct DiskIoTask {
@@ -486,6 +559,8 @@ pub fn test() {
     crate::fs::test(sfs).unwrap();
 
     test_raw_disk_io(handle);
+    test_block_device(handle);
     test_raw_disk_io2(handle);
     test_disk_info();
+    test_storage_security();
 }
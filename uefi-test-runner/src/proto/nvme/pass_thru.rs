@@ -44,6 +44,7 @@ fn has_nvme_drive() -> bool {
             let serial = core::str::from_utf8(&bfr[4..24]).unwrap().trim();
             info!("Found NVMe with serial: '{serial}'");
             if serial == "uefi-rsNvmePassThru" {
+                test_admin_commands(&nvme_pt);
                 return true;
             }
         }
@@ -51,3 +52,33 @@ fn has_nvme_drive() -> bool {
 
     false
 }
+
+fn test_admin_commands(nvme_pt: &NvmePassThru) {
+    info!("Testing NVMe admin commands");
+
+    let mut controller = nvme_pt.controller();
+    let identify = controller.identify_controller().unwrap();
+    assert_eq!(identify.serial_number, "uefi-rsNvmePassThru");
+    info!(
+        "- Model: '{}', firmware: '{}'",
+        identify.model_number, identify.firmware_revision
+    );
+
+    let smart = controller.smart_log().unwrap();
+    assert!(smart.temperature > 0);
+    assert!(smart.critical_warning.is_empty());
+
+    let slots = controller.firmware_slot_log().unwrap();
+    assert_eq!(
+        slots.revision(slots.active_slot),
+        Some(identify.firmware_revision.as_str())
+    );
+
+    let max_entries = usize::from(identify.error_log_entries).min(16);
+    controller.error_log(max_entries).unwrap();
+
+    let mut namespace = nvme_pt.iter_namespaces().next().unwrap();
+    let identify = namespace.identify_namespace().unwrap();
+    let block_size = identify.block_size().unwrap();
+    assert_eq!(identify.size * u64::from(block_size), 1024 * 1024 * 10);
+}
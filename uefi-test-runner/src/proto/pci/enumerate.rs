@@ -0,0 +1,61 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use uefi::boot;
+use uefi::proto::pci::config::{Bar, ConfigHeader, ConfigSpace};
+use uefi::proto::pci::root_bridge::PciRootBridgeIo;
+
+use super::root_bridge::get_open_protocol;
+
+const MASS_STORAGE_CTRL_CLASS_CODE: u8 = 0x1;
+const SATA_CTRL_SUBCLASS_CODE: u8 = 0x6;
+
+pub fn test() {
+    let pci_handles = boot::find_handles::<PciRootBridgeIo>().unwrap();
+
+    let mut sata_ctrl_cnt = 0;
+    for pci_handle in pci_handles {
+        let mut pci_proto = get_open_protocol::<PciRootBridgeIo>(pci_handle);
+        let buses = pci_proto.bus_range().unwrap();
+        info!("PCI root bridge buses: {buses:?}");
+
+        let functions = pci_proto.enumerate().unwrap();
+        assert!(!functions.is_empty());
+        for function in functions {
+            assert!(buses.contains(&function.address.bus));
+            let mut config = pci_proto.function(function.address);
+            assert_eq!(config.header().unwrap(), function.header);
+
+            let common = function.header.common();
+            let capabilities = config.capabilities().count();
+            info!(
+                "PCI function {:02x}:{:02x}.{:x}: {:04x}:{:04x} {}, {capabilities} capabilities",
+                function.address.bus,
+                function.address.dev,
+                function.address.fun,
+                common.vendor_id,
+                common.device_id,
+                common.class.name(),
+            );
+
+            if common.class.class == MASS_STORAGE_CTRL_CLASS_CODE
+                && common.class.subclass == SATA_CTRL_SUBCLASS_CODE
+            {
+                sata_ctrl_cnt += 1;
+                assert!(matches!(function.header, ConfigHeader::Type0(_)));
+                assert!(capabilities > 0);
+
+                // The AHCI registers are in a memory BAR.
+                let bars = config.bars().unwrap();
+                info!("SATA controller BARs: {bars:x?}");
+                assert!(
+                    bars.iter()
+                        .flatten()
+                        .any(|bar| matches!(bar, Bar::Memory { .. }) && bar.size() >= 0x1000)
+                );
+                // Sizing restored the BARs.
+                assert_eq!(config.header().unwrap(), function.header);
+            }
+        }
+    }
+    assert!(sata_ctrl_cnt > 0);
+}
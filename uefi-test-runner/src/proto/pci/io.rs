@@ -0,0 +1,95 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use alloc::vec::Vec;
+use uefi::Handle;
+use uefi::boot::{self, MemoryType, OpenProtocolAttributes, OpenProtocolParams, ScopedProtocol};
+use uefi::proto::ProtocolPointer;
+use uefi::proto::pci::io::{PciIo, PciIoAttributes, PciIoOperation};
+use uefi::proto::pci::root_bridge::PciRootBridgeIo;
+
+pub fn test() {
+    let handles = boot::find_handles::<PciIo>().unwrap();
+    assert!(!handles.is_empty());
+
+    let root_bridge_handles = boot::find_handles::<PciRootBridgeIo>().unwrap();
+    let mut root_bridges: Vec<_> = root_bridge_handles
+        .iter()
+        .map(|&handle| get_open_protocol::<PciRootBridgeIo>(handle))
+        .collect();
+
+    let mut dma_tested = false;
+    for handle in handles {
+        let mut pci = get_open_protocol::<PciIo>(handle);
+        let location = pci.location().unwrap();
+
+        let id = pci.config().read_one::<u32>(0).unwrap();
+        assert_ne!(id, 0xffff_ffff);
+        info!(
+            "PCI I/O device {location}: vendor={:04x}, device={:04x}",
+            id & 0xffff,
+            id >> 16
+        );
+
+        // The configuration space read through the root bridge of the
+        // controller must match.
+        let root_bridge = root_bridges
+            .iter_mut()
+            .find(|root_bridge| root_bridge.segment_nr() == location.segment)
+            .expect("no root bridge for the segment of the controller");
+        let root_bridge_id = root_bridge
+            .pci()
+            .read_one::<u32>(location.address().with_register(0))
+            .unwrap();
+        assert_eq!(id, root_bridge_id);
+
+        let mut words = [0u16; 2];
+        pci.config().read(0, &mut words).unwrap();
+        assert_eq!(u32::from(words[0]) | (u32::from(words[1]) << 16), id);
+
+        let attributes = pci.attributes().unwrap();
+        let supported = pci.supported_attributes().unwrap();
+        assert!(supported.contains(attributes));
+
+        if !dma_tested && attributes.contains(PciIoAttributes::BUS_MASTER) {
+            test_dma(&mut pci);
+            dma_tested = true;
+        }
+    }
+    if !dma_tested {
+        info!("No bus master PCI device, skipping the DMA test");
+    }
+}
+
+fn test_dma(pci: &mut PciIo) {
+    let mut buffer = pci
+        .allocate_buffer(MemoryType::BOOT_SERVICES_DATA, 1, PciIoAttributes::empty())
+        .unwrap();
+    assert_eq!(buffer.len(), boot::PAGE_SIZE);
+    buffer.fill(0xa5);
+
+    let len = buffer.len();
+    let mut mapping = buffer.map(PciIoOperation::BusMasterCommonBuffer).unwrap();
+    assert_eq!(mapping.len(), len);
+    assert_ne!(mapping.device_address(), 0);
+
+    // The common buffer and the controller remain accessible while mapped.
+    assert!(mapping.buffer().iter().all(|&byte| byte == 0xa5));
+    mapping.buffer_mut()[0] = 0x5a;
+    mapping.pci().flush().unwrap();
+    mapping.unmap().unwrap();
+
+    assert_eq!(buffer[0], 0x5a);
+    assert!(buffer[1..].iter().all(|&byte| byte == 0xa5));
+    buffer.pci().flush().unwrap();
+}
+
+fn get_open_protocol<P: ProtocolPointer + ?Sized>(handle: Handle) -> ScopedProtocol<P> {
+    // The PCI I/O protocols are opened by the drivers of the devices, so
+    // they cannot be opened exclusively without disconnecting them.
+    let open_opts = OpenProtocolParams {
+        handle,
+        agent: boot::image_handle(),
+        controller: None,
+    };
+    unsafe { boot::open_protocol(open_opts, OpenProtocolAttributes::GetProtocol).unwrap() }
+}
@@ -1,7 +1,11 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
+pub mod enumerate;
+pub mod io;
 pub mod root_bridge;
 
 pub fn test() {
     root_bridge::test();
+    enumerate::test();
+    io::test();
 }
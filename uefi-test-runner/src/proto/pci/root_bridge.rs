@@ -67,7 +67,7 @@ pub fn test() {
     assert!(sata_ctrl_cnt > 0);
 }
 
-fn get_open_protocol<P: ProtocolPointer + ?Sized>(handle: Handle) -> ScopedProtocol<P> {
+pub fn get_open_protocol<P: ProtocolPointer + ?Sized>(handle: Handle) -> ScopedProtocol<P> {
     let open_opts = OpenProtocolParams {
         handle,
         agent: image_handle(),
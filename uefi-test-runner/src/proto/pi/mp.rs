@@ -1,5 +1,6 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
+use alloc::vec::Vec;
 use core::ffi::c_void;
 use core::ptr;
 use core::sync::atomic::{AtomicUsize, Ordering};
@@ -28,6 +29,7 @@ pub fn test() {
     test_get_processor_info(mp_support);
     test_startup_all_aps(mp_support);
     test_startup_this_ap(mp_support);
+    test_run_closures(mp_support);
     test_enable_disable_ap(mp_support);
     test_switch_bsp_and_who_am_i(mp_support);
 }
@@ -121,6 +123,76 @@ fn test_startup_this_ap(mps: &MpServices) {
     }
 }
 
+fn test_run_closures(mps: &MpServices) {
+    // Each AP returns its own value, the BSP none
+    let results = mps.run_on_aps(false, None, |cpu| cpu * 10).unwrap();
+    assert_eq!(results.len(), NUM_CPUS);
+    assert_eq!(results[0], None);
+    for (cpu, result) in results.iter().enumerate().skip(1) {
+        assert_eq!(*result, Some(cpu * 10));
+    }
+
+    // The closure can borrow from the BSP stack
+    let counter = AtomicUsize::new(0);
+    let results = mps
+        .run_on_aps(true, None, |_| counter.fetch_add(1, Ordering::Relaxed))
+        .unwrap();
+    assert_eq!(counter.load(Ordering::Relaxed), NUM_CPUS - 1);
+    assert_eq!(results.iter().flatten().count(), NUM_CPUS - 1);
+
+    assert_eq!(mps.run_on_ap(2, None, |cpu| cpu + 1).unwrap(), 3);
+    let ret = mps.run_on_ap(0, None, |cpu| cpu);
+    assert_eq!(
+        ret.map_err(|err| err.status()),
+        Err(Status::INVALID_PARAMETER)
+    );
+
+    // Make sure that timeout works
+    let ret = mps.run_on_aps(false, Some(Duration::from_millis(50)), |_| {
+        boot::stall(Duration::from_millis(100));
+    });
+    assert_eq!(ret.map_err(|err| err.status()), Err(Status::TIMEOUT));
+
+    // Non-blocking mode
+    let mut task = mps.spawn_on_aps(false, None, |cpu| cpu * 2).unwrap();
+    while !task.is_finished().unwrap() {
+        boot::stall(Duration::from_millis(1));
+    }
+    // The task stays finished once the completion has been seen.
+    assert!(task.is_finished().unwrap());
+    let results = task.wait().unwrap();
+    for (cpu, result) in results.iter().enumerate().skip(1) {
+        assert_eq!(*result, Some(cpu * 2));
+    }
+
+    // Timeout in non-blocking mode leaves the entries of the APs empty
+    let task = mps
+        .spawn_on_aps(false, Some(Duration::from_millis(50)), |cpu| {
+            boot::stall(Duration::from_millis(100));
+            cpu
+        })
+        .unwrap();
+    let results = task.wait().unwrap();
+    assert!(results.iter().all(Option::is_none));
+
+    // The BSP is included when running on each processor
+    let results = mps.run_on_each_processor(|cpu| cpu).unwrap();
+    assert_eq!(results, (0..NUM_CPUS).map(Some).collect::<Vec<_>>());
+
+    // The APIC IDs read on each processor are distinct
+    #[cfg(target_arch = "x86_64")]
+    {
+        let cpuids = mps.cpuid_on_each_processor(1, 0).unwrap();
+        let mut apic_ids: Vec<u32> = cpuids
+            .iter()
+            .map(|cpuid| cpuid.unwrap().ebx >> 24)
+            .collect();
+        apic_ids.sort_unstable();
+        apic_ids.dedup();
+        assert_eq!(apic_ids.len(), NUM_CPUS);
+    }
+}
+
 fn test_enable_disable_ap(mps: &MpServices) {
     // Disable second CPU
     mps.enable_disable_ap(1, false, None).unwrap();
@@ -1,12 +1,16 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
+use alloc::vec::Vec;
+use uefi::proto::media::block_device::BlockDevice;
 use uefi::proto::scsi::ScsiRequestBuilder;
+use uefi::proto::scsi::commands::ScsiBlockDevice;
 use uefi::proto::scsi::pass_thru::ExtScsiPassThru;
 
 pub fn test() {
     info!("Running extended SCSI Pass Thru tests");
     test_allocating_api();
     test_reusing_buffer_api();
+    test_commands();
 }
 
 fn test_allocating_api() {
@@ -110,3 +114,44 @@ fn test_reusing_buffer_api() {
 
     assert!(found_drive);
 }
+
+fn test_commands() {
+    info!("Testing SCSI commands");
+
+    let scsi_ctrl_handles = uefi::boot::find_handles::<ExtScsiPassThru>().unwrap();
+    for handle in scsi_ctrl_handles {
+        let scsi_pt = uefi::boot::open_protocol_exclusive::<ExtScsiPassThru>(handle).unwrap();
+        for mut device in scsi_pt.iter_devices() {
+            let Ok(inquiry) = device.inquiry() else {
+                continue;
+            };
+            if inquiry.vendor != "uefi-rs" || inquiry.product != "ExtScsiPassThru" {
+                continue;
+            }
+            assert_eq!(inquiry.peripheral_device_type, 0);
+
+            let mode = device.mode_sense(0x08, 0).unwrap();
+            assert!(!mode.write_protected);
+            assert!(mode.write_cache_enabled().is_some());
+
+            let mut disk = ScsiBlockDevice::new(device).unwrap();
+            assert_eq!(disk.capacity().size(), 1024 * 1024 * 10);
+
+            // The disk is empty, so its last blocks can be overwritten.
+            let block_size = disk.block_size();
+            let lba = disk.block_count() - 2;
+            let data: Vec<u8> = (0..block_size * 2).map(|i| i as u8).collect();
+            disk.write_blocks(lba, &data).unwrap();
+            disk.flush().unwrap();
+            let mut buffer = vec![0; block_size * 2];
+            disk.read_blocks(lba, &mut buffer).unwrap();
+            assert_eq!(buffer, data);
+
+            // Reading past the end is rejected.
+            assert!(disk.read_blocks(lba + 1, &mut buffer).is_err());
+            return;
+        }
+    }
+
+    panic!("SCSI test disk not found");
+}
@@ -0,0 +1,45 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use uefi::proto::usb::descriptor::class;
+use uefi::proto::usb::enumerate;
+
+/// Enumerates the USB interfaces and checks that their configuration tree
+/// matches the descriptors returned by the protocol.
+pub fn test() {
+    info!("Testing USB descriptor tree");
+
+    let devices = enumerate::devices().expect("failed to enumerate USB devices");
+    assert!(!devices.is_empty());
+
+    for device in devices {
+        info!(
+            "USB interface {:04x}:{:04x} {:?} {:?}",
+            device.device.id_vendor, device.device.id_product, device.manufacturer, device.product
+        );
+
+        let configuration = device
+            .configuration
+            .expect("failed to read the active configuration");
+        assert_eq!(
+            configuration.interfaces.len(),
+            usize::from(configuration.descriptor.num_interfaces)
+        );
+
+        let interface = configuration
+            .interface(device.interface.interface_number)
+            .expect("interface missing from the configuration");
+        let setting = interface
+            .alternate_settings
+            .iter()
+            .find(|setting| setting.descriptor == device.interface)
+            .expect("alternate setting missing from the configuration");
+        assert_eq!(
+            setting.endpoints.len(),
+            usize::from(setting.descriptor.num_endpoints)
+        );
+
+        if setting.descriptor.interface_class == class::CDC {
+            assert!(setting.cdc().next().is_some());
+        }
+    }
+}
@@ -0,0 +1,44 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use uefi::boot::{self, OpenProtocolAttributes, OpenProtocolParams};
+use uefi::proto::usb::host_controller::{HostControllerState, PortStatus, Usb2HostController};
+
+/// Checks the capabilities, state and root hub ports of the USB host
+/// controllers. QEMU has an xHCI controller with a network device attached.
+pub fn test() {
+    info!("Testing USB2 host controller protocol");
+
+    let handles =
+        boot::find_handles::<Usb2HostController>().expect("failed to find USB host controllers");
+
+    let mut connected_ports = 0;
+    for handle in handles {
+        // The protocol is not opened exclusively, which would disconnect
+        // the USB bus driver and the devices.
+        let mut hc = unsafe {
+            boot::open_protocol::<Usb2HostController>(
+                OpenProtocolParams {
+                    handle,
+                    agent: boot::image_handle(),
+                    controller: None,
+                },
+                OpenProtocolAttributes::GetProtocol,
+            )
+            .expect("failed to open USB2 host controller protocol")
+        };
+
+        let capability = hc.capability().expect("failed to get capabilities");
+        info!("USB host controller {:?}: {capability:?}", hc.revision());
+        assert_ne!(capability.num_ports, 0);
+        assert_eq!(hc.state().unwrap(), HostControllerState::OPERATIONAL);
+
+        for port in 0..capability.num_ports {
+            let status = hc.port_status(port).expect("failed to get port status");
+            if status.port_status.contains(PortStatus::CONNECTION) {
+                connected_ports += 1;
+            }
+        }
+    }
+
+    assert_ne!(connected_ports, 0);
+}
@@ -4,6 +4,10 @@ pub fn test() {
     info!("Testing USB protocols");
 
     io::test();
+    descriptor::test();
+    host_controller::test();
 }
 
+mod descriptor;
+mod host_controller;
 mod io;
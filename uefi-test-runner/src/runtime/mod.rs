@@ -2,12 +2,14 @@
 
 mod vars;
 
+use uefi::helpers::crash_report;
 use uefi::runtime::{self, Daylight, Time, TimeParams};
 
 pub fn test() {
     info!("Testing runtime services");
     vars::test();
     test_time();
+    test_crash_report();
 }
 
 fn test_time() {
@@ -37,3 +39,12 @@ fn test_time() {
     info!("After setting time: {now}");
     assert_eq!(now.year(), 2020);
 }
+
+fn test_crash_report() {
+    let mut buf = [0; crash_report::MAX_REPORT_SIZE];
+
+    // Clearing works whether or not a report exists.
+    crash_report::clear().unwrap();
+    crash_report::clear().unwrap();
+    assert!(crash_report::read(&mut buf).unwrap().is_none());
+}
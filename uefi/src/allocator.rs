@@ -1,18 +1,21 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-//! This module exports [`Allocator`].
+//! This module exports [`Allocator`] and [`TrackingAllocator`].
 //!
-//! The allocator can be used as global Rust allocator using the
-//! `global_allocator` crate feature. See [`helpers`] for more info.
+//! The [`Allocator`] can be used as global Rust allocator using the
+//! `global_allocator` crate feature. See [`helpers`] for more info. The
+//! [`TrackingAllocator`] is a configurable alternative that also keeps
+//! statistics and helps finding leaks.
 //!
 //! [`helpers`]: uefi::helpers
 
 use crate::boot::{self, AllocateType};
 use crate::mem::memory_map::MemoryType;
 use crate::proto::loaded_image::LoadedImage;
+use crate::util::Lock;
 use core::alloc::{GlobalAlloc, Layout};
 use core::ptr::{self, NonNull};
-use core::sync::atomic::{AtomicU32, Ordering};
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
 use uefi_raw::table::boot::PAGE_SIZE;
 
 /// Get the memory type to use for allocation.
@@ -90,6 +93,65 @@ const fn layout_allows_page_alloc_shortcut(layout: &Layout) -> bool {
     layout.size() % PAGE_SIZE == 0 && layout.align() == PAGE_SIZE
 }
 
+/// Allocates memory for `layout` with the given `memory_type`.
+///
+/// If `use_pages` is true, whole pages are allocated with
+/// [`boot::allocate_pages`]; the alignment of `layout` must not exceed
+/// [`PAGE_SIZE`] in that case. Otherwise, the memory is allocated from pool.
+fn alloc_raw(memory_type: MemoryType, layout: Layout, use_pages: bool) -> *mut u8 {
+    match (use_pages, layout.align()) {
+        // Allocating pages is actually very expected in UEFI OS loaders, so
+        // it makes sense to provide this optimization.
+        (true, _) => {
+            // To spammy, but useful for manual testing.
+            // log::trace!("Taking PAGE_SIZE shortcut for layout={layout:?}");
+            let count = layout.size().div_ceil(PAGE_SIZE);
+            boot::allocate_pages(AllocateType::AnyPages, memory_type, count)
+                .map(|ptr| ptr.as_ptr())
+                .unwrap_or(ptr::null_mut())
+        }
+        (false, 0..=8 /* UEFI default alignment */) => {
+            // The requested alignment is less than or equal to eight, and
+            // `allocate_pool` always provides eight-byte alignment, so we can
+            // use `allocate_pool` directly.
+            boot::allocate_pool(memory_type, layout.size())
+                .map(|ptr| ptr.as_ptr())
+                .unwrap_or(ptr::null_mut())
+        }
+        (false, 9..) => alloc_pool_aligned(memory_type, layout.size(), layout.align()),
+    }
+}
+
+/// Frees memory allocated by [`alloc_raw`] with the same `layout` and
+/// `use_pages`.
+///
+/// This will panic after exiting boot services.
+unsafe fn dealloc_raw(ptr: *mut u8, layout: Layout, use_pages: bool) {
+    let ptr = NonNull::new(ptr).unwrap();
+
+    match (use_pages, layout.align()) {
+        (true, _) => {
+            // To spammy, but useful for manual testing.
+            // log::trace!("Taking PAGE_SIZE shortcut for layout={layout:?}");
+            let count = layout.size().div_ceil(PAGE_SIZE);
+            unsafe { boot::free_pages(ptr, count).unwrap() }
+        }
+        (false, 0..=8 /* UEFI default alignment */) => {
+            // Warning: this will panic after exiting boot services.
+            unsafe { boot::free_pool(ptr) }.unwrap();
+        }
+        (false, 9..) => {
+            let ptr = ptr.as_ptr().cast::<*mut u8>();
+            // Retrieve the pointer to the full allocation that was packed right
+            // before the aligned allocation in `alloc`.
+            let actual_alloc_ptr = unsafe { ptr.sub(1).read() };
+            let ptr = NonNull::new(actual_alloc_ptr).unwrap();
+            // Warning: this will panic after exiting boot services.
+            unsafe { boot::free_pool(ptr) }.unwrap();
+        }
+    }
+}
+
 /// Allocator using UEFI boot services.
 ///
 /// This type implements [`GlobalAlloc`] and can be marked with the
@@ -112,60 +174,370 @@ unsafe impl GlobalAlloc for Allocator {
             return ptr::null_mut();
         }
 
-        let memory_type = get_memory_type();
-        let use_page_shortcut = layout_allows_page_alloc_shortcut(&layout);
-
-        match (use_page_shortcut, layout.align()) {
-            // Allocating pages is actually very expected in UEFI OS loaders, so
-            // it makes sense to provide this optimization.
-            (true, _) => {
-                // To spammy, but useful for manual testing.
-                // log::trace!("Taking PAGE_SIZE shortcut for layout={layout:?}");
-                let count = layout.size().div_ceil(PAGE_SIZE);
-                boot::allocate_pages(AllocateType::AnyPages, memory_type, count)
-                    .map(|ptr| ptr.as_ptr())
-                    .unwrap_or(ptr::null_mut())
+        let use_pages = layout_allows_page_alloc_shortcut(&layout);
+        alloc_raw(get_memory_type(), layout, use_pages)
+    }
+
+    /// Deallocate memory using the UEFI boot services.
+    ///
+    /// This will panic after exiting boot services.
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let use_pages = layout_allows_page_alloc_shortcut(&layout);
+        unsafe { dealloc_raw(ptr, layout, use_pages) }
+    }
+}
+
+/// Number of return addresses recorded per allocation in debug builds, if
+/// enabled with [`TrackingAllocator::capture_call_sites`].
+pub const CALL_SITE_DEPTH: usize = 8;
+
+/// Bookkeeping data stored right in front of every allocation handed out by
+/// [`TrackingAllocator`]. All live allocations form a doubly linked list.
+#[repr(C)]
+struct Header {
+    prev: *mut Self,
+    next: *mut Self,
+    /// Layout of the user allocation.
+    layout: Layout,
+    #[cfg(debug_assertions)]
+    call_site: [usize; CALL_SITE_DEPTH],
+}
+
+/// Head of the list of live allocations.
+#[derive(Debug)]
+struct AllocationList {
+    head: *mut Header,
+}
+
+// The list is only accessed with the lock held.
+unsafe impl Send for AllocationList {}
+
+/// Returns the layout of the full allocation for a user allocation with
+/// `layout`, and the offset of the user allocation within it.
+fn tracked_layout(layout: Layout) -> Option<(Layout, usize)> {
+    let header = Layout::new::<Header>();
+    let (full, offset) = header.extend(layout).ok()?;
+    Some((full.pad_to_align(), offset))
+}
+
+/// Statistics of a [`TrackingAllocator`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct AllocatorStats {
+    /// Number of allocations that have not been freed yet.
+    pub live_allocations: usize,
+    /// Total size in bytes of all allocations that have not been freed yet.
+    pub live_bytes: usize,
+    /// Highest value of `live_bytes` so far.
+    pub peak_bytes: usize,
+    /// Number of allocations made so far, including freed ones.
+    pub total_allocations: usize,
+}
+
+/// Information about a live allocation of a [`TrackingAllocator`].
+#[derive(Clone, Copy, Debug)]
+pub struct AllocationInfo<'a> {
+    /// Address of the allocation.
+    pub address: usize,
+    /// Size of the allocation in bytes.
+    pub size: usize,
+    /// Alignment of the allocation in bytes.
+    pub align: usize,
+    /// Return addresses on the stack at the time of the allocation, innermost
+    /// first. The first entries usually belong to the allocation machinery of
+    /// `alloc`.
+    ///
+    /// Only recorded in debug builds with
+    /// [`TrackingAllocator::capture_call_sites`]; empty otherwise.
+    pub call_site: &'a [usize],
+}
+
+/// Allocator using UEFI boot services that keeps track of all allocations.
+///
+/// In contrast to [`Allocator`], the memory type can be configured, and
+/// allocations above a configurable size are served directly with
+/// [`boot::allocate_pages`] instead of from pool. The allocator keeps
+/// [statistics], and can list all allocations that have not been freed yet.
+/// This is useful to find leaks before exiting boot services, after which
+/// memory can no longer be freed:
+///
+/// ```no_run
+/// use uefi::allocator::TrackingAllocator;
+/// use uefi::boot::MemoryType;
+///
+/// #[global_allocator]
+/// static ALLOCATOR: TrackingAllocator = TrackingAllocator::new()
+///     .with_memory_type(MemoryType::LOADER_DATA)
+///     .with_large_allocation_threshold(64 * 1024);
+///
+/// fn before_exit_boot_services() {
+///     log::info!("allocator stats: {:?}", ALLOCATOR.stats());
+///     ALLOCATOR.log_outstanding();
+/// }
+/// ```
+///
+/// Every allocation carries a small header, so this allocator uses a bit
+/// more memory than [`Allocator`]. To use it as global allocator, don't
+/// enable the `global_allocator` feature.
+///
+/// Note that if boot services are not active (anymore),
+/// [`TrackingAllocator::alloc`] will return a null pointer and
+/// [`TrackingAllocator::dealloc`] will panic.
+///
+/// [statistics]: Self::stats
+#[derive(Debug)]
+pub struct TrackingAllocator {
+    memory_type: Option<MemoryType>,
+    large_allocation_threshold: usize,
+    capture_call_sites: bool,
+    live_allocations: AtomicUsize,
+    live_bytes: AtomicUsize,
+    peak_bytes: AtomicUsize,
+    total_allocations: AtomicUsize,
+    allocations: Lock<AllocationList>,
+}
+
+impl TrackingAllocator {
+    /// Creates a new allocator.
+    ///
+    /// By default, memory is allocated with the data type of the loaded image
+    /// (like [`Allocator`]), and all allocations that are not a multiple of
+    /// [`PAGE_SIZE`] are served from pool.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            memory_type: None,
+            large_allocation_threshold: usize::MAX,
+            capture_call_sites: false,
+            live_allocations: AtomicUsize::new(0),
+            live_bytes: AtomicUsize::new(0),
+            peak_bytes: AtomicUsize::new(0),
+            total_allocations: AtomicUsize::new(0),
+            allocations: Lock::new(AllocationList {
+                head: ptr::null_mut(),
+            }),
+        }
+    }
+
+    /// Allocates all memory with `memory_type`.
+    #[must_use]
+    pub const fn with_memory_type(mut self, memory_type: MemoryType) -> Self {
+        self.memory_type = Some(memory_type);
+        self
+    }
+
+    /// Serves allocations of at least `bytes` bytes directly with
+    /// [`boot::allocate_pages`] instead of from pool.
+    #[must_use]
+    pub const fn with_large_allocation_threshold(mut self, bytes: usize) -> Self {
+        self.large_allocation_threshold = bytes;
+        self
+    }
+
+    /// Records the return addresses on the stack for every allocation, see
+    /// [`AllocationInfo::call_site`]. This has no effect in release builds.
+    ///
+    /// # Safety
+    ///
+    /// The return addresses are found by following the frame pointers, so
+    /// all code must be compiled with frame pointers
+    /// (`-C force-frame-pointers=yes`).
+    #[must_use]
+    pub const unsafe fn capture_call_sites(mut self) -> Self {
+        self.capture_call_sites = true;
+        self
+    }
+
+    /// Returns the current allocation statistics.
+    #[must_use]
+    pub fn stats(&self) -> AllocatorStats {
+        AllocatorStats {
+            live_allocations: self.live_allocations.load(Ordering::Relaxed),
+            live_bytes: self.live_bytes.load(Ordering::Relaxed),
+            peak_bytes: self.peak_bytes.load(Ordering::Relaxed),
+            total_allocations: self.total_allocations.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Calls `f` for every allocation that has not been freed yet, most
+    /// recent first.
+    ///
+    /// `f` must not allocate or free memory with this allocator, otherwise
+    /// it will deadlock.
+    pub fn for_each_outstanding(&self, mut f: impl FnMut(&AllocationInfo)) {
+        self.allocations.lock_no_preempt(|list| {
+            let mut header = list.head;
+            // SAFETY: the list only contains headers of live allocations.
+            while let Some(h) = unsafe { header.as_ref() } {
+                // The layout was valid when the allocation was made.
+                let (_, offset) = tracked_layout(h.layout).unwrap();
+                f(&AllocationInfo {
+                    address: header as usize + offset,
+                    size: h.layout.size(),
+                    align: h.layout.align(),
+                    #[cfg(debug_assertions)]
+                    call_site: {
+                        let len = h.call_site.iter().take_while(|&&addr| addr != 0).count();
+                        &h.call_site[..len]
+                    },
+                    #[cfg(not(debug_assertions))]
+                    call_site: &[],
+                });
+                header = h.next;
             }
-            (false, 0..=8 /* UEFI default alignment */) => {
-                // The requested alignment is less than or equal to eight, and
-                // `allocate_pool` always provides eight-byte alignment, so we can
-                // use `allocate_pool` directly.
-                boot::allocate_pool(memory_type, layout.size())
-                    .map(|ptr| ptr.as_ptr())
-                    .unwrap_or(ptr::null_mut())
+        })
+    }
+
+    /// Logs all allocations that have not been freed yet as warnings.
+    ///
+    /// The logger must not allocate memory with this allocator.
+    pub fn log_outstanding(&self) {
+        self.for_each_outstanding(|info| {
+            log::warn!(
+                "outstanding allocation: {:#x}, size={}, align={}, call site={:x?}",
+                info.address,
+                info.size,
+                info.align,
+                info.call_site
+            );
+        });
+    }
+
+    fn memory_type(&self) -> MemoryType {
+        self.memory_type.unwrap_or_else(get_memory_type)
+    }
+
+    /// Whether the full allocation described by `layout` is served with
+    /// [`boot::allocate_pages`].
+    const fn use_pages(&self, layout: &Layout) -> bool {
+        layout_allows_page_alloc_shortcut(layout)
+            || (layout.size() >= self.large_allocation_threshold && layout.align() <= PAGE_SIZE)
+    }
+}
+
+impl Default for TrackingAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+    /// Allocate memory using the UEFI boot services, and record the
+    /// allocation.
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if !boot::are_boot_services_active() {
+            return ptr::null_mut();
+        }
+        let Some((full_layout, offset)) = tracked_layout(layout) else {
+            return ptr::null_mut();
+        };
+
+        let full = alloc_raw(
+            self.memory_type(),
+            full_layout,
+            self.use_pages(&full_layout),
+        );
+        if full.is_null() {
+            return full;
+        }
+
+        let header = full.cast::<Header>();
+        // SAFETY: the full allocation starts with space for the header.
+        unsafe {
+            header.write(Header {
+                prev: ptr::null_mut(),
+                next: ptr::null_mut(),
+                layout,
+                #[cfg(debug_assertions)]
+                call_site: [0; CALL_SITE_DEPTH],
+            });
+        }
+
+        #[cfg(debug_assertions)]
+        if self.capture_call_sites {
+            // SAFETY: the caller of `capture_call_sites` guarantees that
+            // frame pointers are available.
+            let iter = unsafe { crate::helpers::backtrace::FramePointerIter::current() };
+            let call_site = unsafe { &mut (*header).call_site };
+            for (dst, addr) in call_site.iter_mut().zip(iter) {
+                *dst = addr;
             }
-            (false, 9..) => alloc_pool_aligned(memory_type, layout.size(), layout.align()),
         }
+
+        self.allocations.lock_no_preempt(|list| unsafe {
+            (*header).next = list.head;
+            if let Some(head) = list.head.as_mut() {
+                head.prev = header;
+            }
+            list.head = header;
+        });
+
+        self.live_allocations.fetch_add(1, Ordering::Relaxed);
+        self.total_allocations.fetch_add(1, Ordering::Relaxed);
+        let live_bytes =
+            self.live_bytes.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        self.peak_bytes.fetch_max(live_bytes, Ordering::Relaxed);
+
+        unsafe { full.add(offset) }
     }
 
     /// Deallocate memory using the UEFI boot services.
     ///
     /// This will panic after exiting boot services.
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
-        let ptr = NonNull::new(ptr).unwrap();
-
-        let use_page_shortcut = layout_allows_page_alloc_shortcut(&layout);
+        // The layout is the same as in `alloc`, where it was valid.
+        let (full_layout, offset) = tracked_layout(layout).unwrap();
+        let full = unsafe { ptr.sub(offset) };
+        let header = full.cast::<Header>();
 
-        match (use_page_shortcut, layout.align()) {
-            (true, _) => {
-                // To spammy, but useful for manual testing.
-                // log::trace!("Taking PAGE_SIZE shortcut for layout={layout:?}");
-                let count = layout.size().div_ceil(PAGE_SIZE);
-                unsafe { boot::free_pages(ptr, count).unwrap() }
-            }
-            (false, 0..=8 /* UEFI default alignment */) => {
-                // Warning: this will panic after exiting boot services.
-                unsafe { boot::free_pool(ptr) }.unwrap();
+        self.allocations.lock_no_preempt(|list| unsafe {
+            let Header { prev, next, .. } = *header;
+            if let Some(next) = next.as_mut() {
+                next.prev = prev;
             }
-            (false, 9..) => {
-                let ptr = ptr.as_ptr().cast::<*mut u8>();
-                // Retrieve the pointer to the full allocation that was packed right
-                // before the aligned allocation in `alloc`.
-                let actual_alloc_ptr = unsafe { ptr.sub(1).read() };
-                let ptr = NonNull::new(actual_alloc_ptr).unwrap();
-                // Warning: this will panic after exiting boot services.
-                unsafe { boot::free_pool(ptr) }.unwrap();
+            match prev.as_mut() {
+                Some(prev) => prev.next = next,
+                None => list.head = next,
             }
-        }
+        });
+
+        self.live_allocations.fetch_sub(1, Ordering::Relaxed);
+        self.live_bytes.fetch_sub(layout.size(), Ordering::Relaxed);
+
+        unsafe { dealloc_raw(full, full_layout, self.use_pages(&full_layout)) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracked_layout() {
+        let header = size_of::<Header>();
+
+        let (full, offset) = tracked_layout(Layout::new::<u8>()).unwrap();
+        assert_eq!(offset, header);
+        assert_eq!(full.size(), header + align_of::<Header>());
+        assert_eq!(full.align(), align_of::<Header>());
+
+        let layout = Layout::from_size_align(PAGE_SIZE, PAGE_SIZE).unwrap();
+        let (full, offset) = tracked_layout(layout).unwrap();
+        assert_eq!(offset, PAGE_SIZE);
+        assert_eq!(full.size(), 2 * PAGE_SIZE);
+        assert_eq!(full.align(), PAGE_SIZE);
+    }
+
+    #[test]
+    fn test_use_pages() {
+        let allocator = TrackingAllocator::new().with_large_allocation_threshold(0x10000);
+        let layout = |size, align| Layout::from_size_align(size, align).unwrap();
+
+        assert!(!allocator.use_pages(&layout(0x100, 8)));
+        assert!(allocator.use_pages(&layout(0x10000, 8)));
+        assert!(allocator.use_pages(&layout(PAGE_SIZE, PAGE_SIZE)));
+        // Pages can't provide a larger alignment.
+        assert!(!allocator.use_pages(&layout(0x20000, 2 * PAGE_SIZE)));
+
+        assert!(!TrackingAllocator::new().use_pages(&layout(0x10000, 8)));
     }
 }
@@ -0,0 +1,112 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Minimal stack unwinding by following the chain of frame pointers.
+//!
+//! This only produces meaningful results if the code on the stack was
+//! compiled with frame pointers, e.g. with `-C force-frame-pointers=yes`.
+//! Without frame pointers, the walk usually stops early because the frame
+//! pointer register holds an implausible value.
+
+/// Maximum distance between two consecutive frame records. A larger gap is
+/// treated as the end of the chain.
+const MAX_FRAME_SIZE: usize = 1024 * 1024;
+
+/// Iterator over the return addresses on the stack, innermost first, found
+/// by following the chain of frame records.
+///
+/// The walk stops at the first frame record that is null, misaligned, or not
+/// located above the previous one.
+#[derive(Clone, Debug)]
+pub struct FramePointerIter {
+    fp: usize,
+}
+
+impl FramePointerIter {
+    /// Starts unwinding at the frame record pointed to by `fp`.
+    ///
+    /// # Safety
+    ///
+    /// `fp` must be null or point to a valid frame record, and all frame
+    /// records reachable from it must be readable.
+    #[must_use]
+    pub const unsafe fn new(fp: usize) -> Self {
+        Self { fp }
+    }
+
+    /// Starts unwinding at the frame of the caller.
+    ///
+    /// On architectures without frame pointer support, the iterator is
+    /// empty.
+    ///
+    /// # Safety
+    ///
+    /// All code on the current stack must be compiled with frame pointers.
+    #[inline(always)]
+    #[must_use]
+    pub unsafe fn current() -> Self {
+        let fp: usize;
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "x86_64")] {
+                unsafe { core::arch::asm!("mov {}, rbp", out(reg) fp, options(nomem, nostack)) };
+            } else if #[cfg(target_arch = "x86")] {
+                unsafe { core::arch::asm!("mov {}, ebp", out(reg) fp, options(nomem, nostack)) };
+            } else if #[cfg(target_arch = "aarch64")] {
+                unsafe { core::arch::asm!("mov {}, x29", out(reg) fp, options(nomem, nostack)) };
+            } else {
+                fp = 0;
+            }
+        }
+        unsafe { Self::new(fp) }
+    }
+}
+
+impl Iterator for FramePointerIter {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let fp = self.fp;
+        if fp == 0 || fp % align_of::<usize>() != 0 {
+            return None;
+        }
+
+        // On all supported architectures, a frame record consists of the
+        // caller's frame pointer followed by the return address.
+        let record = fp as *const usize;
+        // SAFETY: valid per the requirements of `new`.
+        let (next_fp, return_addr) = unsafe { (record.read(), record.add(1).read()) };
+
+        // The stack grows downwards, so the caller's frame must be above.
+        self.fp = if next_fp > fp && next_fp - fp <= MAX_FRAME_SIZE {
+            next_fp
+        } else {
+            0
+        };
+
+        (return_addr != 0).then_some(return_addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_frame_pointer_iter() {
+        // Three frame records; the last one ends the chain.
+        let mut stack = [0usize; 6];
+        let ptr = stack.as_mut_ptr();
+        let base = ptr as usize;
+        let size = size_of::<usize>();
+        let records = [base + 2 * size, 0x1000, base + 4 * size, 0x2000, 0, 0x3000];
+        unsafe { ptr.copy_from_nonoverlapping(records.as_ptr(), records.len()) };
+
+        let iter = unsafe { FramePointerIter::new(base) };
+        assert_eq!(iter.collect::<Vec<_>>(), [0x1000, 0x2000, 0x3000]);
+
+        // A record pointing downwards ends the chain.
+        unsafe { ptr.add(2).write(base) };
+        let iter = unsafe { FramePointerIter::new(base) };
+        assert_eq!(iter.collect::<Vec<_>>(), [0x1000, 0x2000]);
+    }
+}
@@ -0,0 +1,401 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Persistent crash reports stored in a UEFI variable.
+//!
+//! When enabled with [`enable`], the default panic handler (feature
+//! `panic_handler`) stores the panic message, its location, and optionally a
+//! backtrace in the variable [`NAME`] of vendor [`VENDOR`]. On the next boot
+//! (or, for volatile storage, later during the same boot, e.g. from the
+//! kernel), the report can be retrieved with [`read`] and removed with
+//! [`clear`]:
+//!
+//! ```no_run
+//! use uefi::helpers::crash_report;
+//!
+//! let mut buf = [0; crash_report::MAX_REPORT_SIZE];
+//! if let Some(report) = crash_report::read(&mut buf)? {
+//!     log::warn!("last boot crashed at {}:{}: {}", report.file(), report.line(), report.message());
+//!     for addr in report.backtrace() {
+//!         log::warn!("  at {addr:#x}");
+//!     }
+//!     crash_report::clear()?;
+//! }
+//! # uefi::Result::Ok(())
+//! ```
+//!
+//! Custom panic handlers can store reports with [`save`].
+
+use super::backtrace::FramePointerIter;
+use crate::runtime::{self, VariableAttributes, VariableVendor};
+use crate::{CStr16, Error, Result, Status, cstr16, guid, table};
+use core::fmt::{self, Write};
+use core::panic::{Location, PanicInfo};
+use core::sync::atomic::{AtomicU8, Ordering};
+
+/// Name of the variable holding the crash report.
+pub const NAME: &CStr16 = cstr16!("UefiRsCrashReport");
+
+/// Vendor of the variable holding the crash report.
+pub const VENDOR: VariableVendor = VariableVendor(guid!("5d1b6a3c-0a5e-4a4e-9c1f-1f3b0b8e2d47"));
+
+/// Maximum length of the stored panic message, in bytes. Longer messages are
+/// truncated.
+pub const MAX_MESSAGE_LEN: usize = 512;
+
+/// Maximum length of the stored file name, in bytes. Longer file names are
+/// truncated.
+pub const MAX_FILE_LEN: usize = 256;
+
+/// Maximum number of stored backtrace entries.
+pub const MAX_FRAMES: usize = 32;
+
+/// Identifies the format of the stored report.
+const MAGIC: [u8; 4] = *b"UCR1";
+
+/// Size of the fixed part of the report: magic, line, column, file length,
+/// message length, and frame count.
+const HEADER_SIZE: usize = 4 + 4 + 4 + 2 + 2 + 2;
+
+/// Maximum size of a serialized crash report. A buffer of this size is
+/// always large enough for [`read`].
+pub const MAX_REPORT_SIZE: usize = HEADER_SIZE + MAX_FILE_LEN + MAX_MESSAGE_LEN + MAX_FRAMES * 8;
+
+/// Where the crash report is stored.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Storage {
+    /// The report is kept in memory only and is lost on reset. It can still
+    /// be read later during the same boot, e.g. by the OS.
+    Volatile,
+
+    /// The report is kept in non-volatile storage and survives a reset.
+    NonVolatile,
+}
+
+impl Storage {
+    const fn attributes(self) -> VariableAttributes {
+        let attrs =
+            VariableAttributes::BOOTSERVICE_ACCESS.union(VariableAttributes::RUNTIME_ACCESS);
+        match self {
+            Self::Volatile => attrs,
+            Self::NonVolatile => attrs.union(VariableAttributes::NON_VOLATILE),
+        }
+    }
+}
+
+/// Bit set in [`MODE`] if reports are enabled.
+const MODE_ENABLED: u8 = 1 << 0;
+/// Bit set in [`MODE`] if reports are stored in non-volatile storage.
+const MODE_NON_VOLATILE: u8 = 1 << 1;
+/// Bit set in [`MODE`] if a backtrace is captured.
+const MODE_BACKTRACE: u8 = 1 << 2;
+
+/// Crash report configuration used by the panic handler.
+static MODE: AtomicU8 = AtomicU8::new(0);
+
+/// Makes the default panic handler store a crash report in `storage`.
+///
+/// If `backtrace` is true, the return addresses on the stack are captured by
+/// following the frame pointers. This requires that all code is compiled with
+/// frame pointers (`-C force-frame-pointers=yes`); otherwise, the panic
+/// handler may read arbitrary memory.
+pub fn enable(storage: Storage, backtrace: bool) {
+    let mut mode = MODE_ENABLED;
+    if storage == Storage::NonVolatile {
+        mode |= MODE_NON_VOLATILE;
+    }
+    if backtrace {
+        mode |= MODE_BACKTRACE;
+    }
+    MODE.store(mode, Ordering::Release);
+}
+
+/// Stops the default panic handler from storing crash reports.
+pub fn disable() {
+    MODE.store(0, Ordering::Release);
+}
+
+/// Stores a report for `info` if enabled with [`enable`].
+#[cfg(feature = "panic_handler")]
+pub(crate) fn save_if_enabled(info: &PanicInfo) {
+    let mode = MODE.load(Ordering::Acquire);
+    if mode & MODE_ENABLED == 0 {
+        return;
+    }
+    let storage = if mode & MODE_NON_VOLATILE != 0 {
+        Storage::NonVolatile
+    } else {
+        Storage::Volatile
+    };
+    // Errors can't be reported in the panic handler.
+    let _ = save(info, storage, mode & MODE_BACKTRACE != 0);
+}
+
+/// Stores a crash report for the panic described by `info` in `storage`.
+///
+/// This does not allocate and can be called after exiting boot services, as
+/// long as the firmware supports variable storage at runtime.
+///
+/// If `backtrace` is true, a backtrace is captured by following the frame
+/// pointers; see [`enable`] for the requirements.
+///
+/// # Errors
+///
+/// * [`Status::NOT_READY`]: runtime services are not available.
+///
+/// See [`runtime::set_variable`] for the errors returned by the firmware.
+pub fn save(info: &PanicInfo, storage: Storage, backtrace: bool) -> Result {
+    // Runtime functions panic if the system table is not set, which must be
+    // avoided here since this is called from the panic handler.
+    let st = table::system_table_raw().ok_or(Status::NOT_READY)?;
+    // SAFETY: valid per requirements of `set_system_table`.
+    if unsafe { st.as_ref() }.runtime_services.is_null() {
+        return Err(Status::NOT_READY.into());
+    }
+
+    let mut frames = [0u64; MAX_FRAMES];
+    let mut frame_count = 0;
+    if backtrace {
+        // SAFETY: the caller opted in, see `enable`.
+        let iter = unsafe { FramePointerIter::current() };
+        for (dst, addr) in frames.iter_mut().zip(iter) {
+            *dst = addr as u64;
+            frame_count += 1;
+        }
+    }
+
+    let mut buf = [0u8; MAX_REPORT_SIZE];
+    let len = encode(
+        &mut buf,
+        &format_args!("{}", info.message()),
+        info.location(),
+        &frames[..frame_count],
+    );
+
+    runtime::set_variable(NAME, &VENDOR, storage.attributes(), &buf[..len])
+}
+
+/// Reads the last stored crash report into `buf`.
+///
+/// Returns `None` if there is no crash report. A buffer of
+/// [`MAX_REPORT_SIZE`] bytes is always large enough.
+///
+/// # Errors
+///
+/// * [`Status::BUFFER_TOO_SMALL`]: `buf` is not large enough.
+/// * [`Status::VOLUME_CORRUPTED`]: the variable does not contain a valid
+///   crash report.
+///
+/// See [`runtime::get_variable`] for other errors returned by the firmware.
+pub fn read(buf: &mut [u8]) -> Result<Option<CrashReport<'_>>> {
+    let data = match runtime::get_variable(NAME, &VENDOR, buf) {
+        Ok((data, _)) => data,
+        Err(err) if err.status() == Status::NOT_FOUND => return Ok(None),
+        Err(err) => return Err(Error::from(err.status())),
+    };
+    CrashReport::parse(data)
+        .map(Some)
+        .ok_or_else(|| Status::VOLUME_CORRUPTED.into())
+}
+
+/// Deletes the stored crash report, if any.
+pub fn clear() -> Result {
+    match runtime::delete_variable(NAME, &VENDOR) {
+        Err(err) if err.status() == Status::NOT_FOUND => Ok(()),
+        other => other,
+    }
+}
+
+/// A crash report read with [`read`].
+#[derive(Clone, Copy, Debug)]
+pub struct CrashReport<'a> {
+    file: &'a str,
+    line: u32,
+    column: u32,
+    message: &'a str,
+    frames: &'a [u8],
+}
+
+impl<'a> CrashReport<'a> {
+    /// Parses a serialized crash report.
+    fn parse(data: &'a [u8]) -> Option<Self> {
+        let (header, rest) = data.split_at_checked(HEADER_SIZE)?;
+        if header[..4] != MAGIC {
+            return None;
+        }
+        let u32_at = |i: usize| u32::from_le_bytes(header[i..i + 4].try_into().unwrap());
+        let u16_at = |i: usize| usize::from(u16::from_le_bytes([header[i], header[i + 1]]));
+
+        let (file, rest) = rest.split_at_checked(u16_at(12))?;
+        let (message, rest) = rest.split_at_checked(u16_at(14))?;
+        let frames = rest.get(..u16_at(16) * 8)?;
+
+        Some(Self {
+            file: core::str::from_utf8(file).ok()?,
+            line: u32_at(4),
+            column: u32_at(8),
+            message: core::str::from_utf8(message).ok()?,
+            frames,
+        })
+    }
+
+    /// File in which the panic occurred.
+    #[must_use]
+    pub const fn file(&self) -> &'a str {
+        self.file
+    }
+
+    /// Line at which the panic occurred, or zero if unknown.
+    #[must_use]
+    pub const fn line(&self) -> u32 {
+        self.line
+    }
+
+    /// Column at which the panic occurred, or zero if unknown.
+    #[must_use]
+    pub const fn column(&self) -> u32 {
+        self.column
+    }
+
+    /// The panic message. It may have been truncated to
+    /// [`MAX_MESSAGE_LEN`] bytes.
+    #[must_use]
+    pub const fn message(&self) -> &'a str {
+        self.message
+    }
+
+    /// Return addresses of the backtrace, innermost first. Empty if no
+    /// backtrace was captured.
+    pub fn backtrace(&self) -> impl Iterator<Item = u64> + 'a {
+        self.frames
+            .chunks_exact(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+    }
+}
+
+impl fmt::Display for CrashReport<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "panicked at {}:{}:{}:\n{}",
+            self.file, self.line, self.column, self.message
+        )
+    }
+}
+
+/// [`fmt::Write`] implementation filling a fixed buffer, silently
+/// truncating the output at a character boundary.
+struct TruncatingWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl Write for TruncatingWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut n = s.len().min(self.buf.len() - self.len);
+        while !s.is_char_boundary(n) {
+            n -= 1;
+        }
+        self.buf[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+        Ok(())
+    }
+}
+
+/// Serializes a crash report into `buf` and returns its length.
+///
+/// All values are stored in little-endian byte order: the [`MAGIC`], line
+/// and column (`u32`), the lengths of the file name and message and the
+/// number of frames (`u16`), the file name and message (UTF-8), and finally
+/// the frames (`u64`).
+fn encode(
+    buf: &mut [u8; MAX_REPORT_SIZE],
+    message: &fmt::Arguments,
+    location: Option<&Location>,
+    frames: &[u64],
+) -> usize {
+    let (file, line, column) = location
+        .map(|loc| (loc.file(), loc.line(), loc.column()))
+        .unwrap_or(("<unknown>", 0, 0));
+
+    let mut pos = HEADER_SIZE;
+    let mut writer = TruncatingWriter {
+        buf: &mut buf[pos..pos + MAX_FILE_LEN],
+        len: 0,
+    };
+    let _ = writer.write_str(file);
+    let file_len = writer.len;
+    pos += file_len;
+
+    let mut writer = TruncatingWriter {
+        buf: &mut buf[pos..pos + MAX_MESSAGE_LEN],
+        len: 0,
+    };
+    let _ = writer.write_fmt(*message);
+    let message_len = writer.len;
+    pos += message_len;
+
+    let frames = &frames[..frames.len().min(MAX_FRAMES)];
+    for frame in frames {
+        buf[pos..pos + 8].copy_from_slice(&frame.to_le_bytes());
+        pos += 8;
+    }
+
+    let header = &mut buf[..HEADER_SIZE];
+    header[..4].copy_from_slice(&MAGIC);
+    header[4..8].copy_from_slice(&line.to_le_bytes());
+    header[8..12].copy_from_slice(&column.to_le_bytes());
+    header[12..14].copy_from_slice(&(file_len as u16).to_le_bytes());
+    header[14..16].copy_from_slice(&(message_len as u16).to_le_bytes());
+    header[16..18].copy_from_slice(&(frames.len() as u16).to_le_bytes());
+    pos
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_roundtrip() {
+        let mut buf = [0; MAX_REPORT_SIZE];
+        let location = Location::caller();
+        let len = encode(
+            &mut buf,
+            &format_args!("oops: {}", 42),
+            Some(location),
+            &[0x1234, 0xdead_beef_0000],
+        );
+        assert_eq!(len, HEADER_SIZE + location.file().len() + 8 + 16);
+
+        let report = CrashReport::parse(&buf[..len]).unwrap();
+        assert_eq!(report.file(), location.file());
+        assert_eq!(report.line(), location.line());
+        assert_eq!(report.column(), location.column());
+        assert_eq!(report.message(), "oops: 42");
+        assert_eq!(
+            report.backtrace().collect::<Vec<_>>(),
+            [0x1234, 0xdead_beef_0000]
+        );
+
+        // Truncated data is rejected.
+        assert!(CrashReport::parse(&buf[..len - 1]).is_none());
+        assert!(CrashReport::parse(&buf[..4]).is_none());
+    }
+
+    #[test]
+    fn test_truncation() {
+        let mut buf = [0; MAX_REPORT_SIZE];
+        // Multi-byte characters must not be split.
+        let message: String = core::iter::repeat_n('ü', MAX_MESSAGE_LEN).collect();
+        let frames = [1; MAX_FRAMES + 1];
+        let len = encode(&mut buf, &format_args!("{message}"), None, &frames);
+        assert_eq!(len, MAX_REPORT_SIZE - MAX_FILE_LEN + "<unknown>".len());
+
+        let report = CrashReport::parse(&buf[..len]).unwrap();
+        assert_eq!(report.file(), "<unknown>");
+        assert_eq!(report.line(), 0);
+        assert_eq!(report.message().len(), MAX_MESSAGE_LEN);
+        assert_eq!(report.backtrace().count(), MAX_FRAMES);
+    }
+}
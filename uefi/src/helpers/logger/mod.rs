@@ -1,18 +1,31 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 //! This optional feature adds support for the `log` crate, providing
-//! a custom logger implementation which writes to a UEFI text output protocol.
+//! custom logger implementations which write to UEFI protocols.
 //!
-//! The main export of this module is the `Logger` structure,
-//! which implements the `log` crate's trait `Log`.
+//! The main exports of this module are the [`Logger`] structure, which is
+//! set up by [`helpers::init`] and writes to the UEFI console, and the
+//! [`MultiLogger`] structure (requires the `alloc` feature), which writes to
+//! any number of [`LogSink`]s with individual log levels.
 //!
 //! # Implementation details
 //!
-//! The implementation is not the most efficient, since there is no buffering done,
-//! and the messages have to be converted from UTF-8 to UEFI's UCS-2.
+//! The [`Logger`] is not the most efficient, since there is no buffering done,
+//! and the messages have to be converted from UTF-8 to UEFI's UCS-2. The
+//! [`MultiLogger`] can avoid this by wrapping sinks in [`Buffered`].
 //!
 //! The last part also means that some Unicode characters might not be
 //! supported by the UEFI console. Don't expect emoji output support.
+//!
+//! [`helpers::init`]: super::init
+
+#[cfg(feature = "alloc")]
+mod multi;
+mod sink;
+
+#[cfg(feature = "alloc")]
+pub use multi::MultiLogger;
+pub use sink::{Buffered, LogSink, RingBuffer, StdoutSink};
 
 use crate::proto::console::text::Output;
 use crate::system;
@@ -27,7 +40,7 @@ static LOGGER: Logger = Logger::new();
 ///
 /// This is unsafe because you must arrange for the logger to be reset with
 /// disable() on exit from UEFI boot services.
-pub unsafe fn init() {
+pub(crate) unsafe fn init() {
     // Connect the logger to stdout.
     system::with_stdout(|stdout| unsafe {
         LOGGER.set_output(stdout);
@@ -40,7 +53,7 @@ pub unsafe fn init() {
     log::set_max_level(log::STATIC_MAX_LEVEL);
 }
 
-pub fn disable() {
+pub(crate) fn disable() {
     LOGGER.disable();
 }
 
@@ -132,6 +145,12 @@ impl Logger {
     }
 }
 
+impl Default for Logger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl log::Log for Logger {
     fn enabled(&self, _metadata: &log::Metadata) -> bool {
         // We decide in `log` already if something is printed. We do not
@@ -0,0 +1,222 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Logger writing to multiple sinks. See [`MultiLogger`].
+
+use super::DecoratedLog;
+use super::sink::LogSink;
+use crate::proto::misc::Timestamp;
+use crate::util::Lock;
+use crate::{Status, boot};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicPtr, AtomicU64, Ordering};
+use core::{mem, ptr};
+use log::LevelFilter;
+
+/// Logging implementation which writes to any number of [`LogSink`]s.
+///
+/// Each sink has its own [`LevelFilter`], so for example everything can be
+/// recorded in a [`RingBuffer`] while only warnings and errors are shown on
+/// the console. Sinks backed by UEFI protocols are skipped automatically after
+/// exiting boot services, while sinks such as the [`RingBuffer`] keep working.
+///
+/// Sinks may buffer their output (see [`Buffered`]). All sinks are flushed
+/// when [`log::Log::flush`] is called, which happens automatically on panic
+/// (with the `panic_handler` feature) and before exiting boot services.
+///
+/// This logger is used instead of the simple logger set up by
+/// [`helpers::init`]; don't call that function when using this logger.
+///
+/// ```no_run
+/// use log::LevelFilter;
+/// use uefi::helpers::logger::{Buffered, MultiLogger, StdoutSink};
+/// use uefi::proto::console::serial::Serial;
+/// use uefi::{boot, Result};
+///
+/// static LOGGER: MultiLogger = MultiLogger::new();
+///
+/// fn init_logging() -> Result {
+///     LOGGER.add_sink(Buffered::<_, 256>::new(StdoutSink), LevelFilter::Info)?;
+///
+///     let handle = boot::get_handle_for_protocol::<Serial>()?;
+///     let serial = boot::open_protocol_exclusive::<Serial>(handle)?;
+///     LOGGER.add_sink(serial, LevelFilter::Trace)?;
+///
+///     LOGGER.install().unwrap();
+///     Ok(())
+/// }
+/// ```
+///
+/// [`Buffered`]: super::Buffered
+/// [`RingBuffer`]: super::RingBuffer
+/// [`helpers::init`]: crate::helpers::init
+#[derive(Debug)]
+pub struct MultiLogger {
+    sinks: Lock<Vec<SinkEntry>>,
+    /// `get_timestamp` function of the [`Timestamp`] protocol, or null.
+    get_timestamp: AtomicPtr<()>,
+    /// Frequency of the timestamp counter in Hz.
+    timestamp_frequency: AtomicU64,
+}
+
+struct SinkEntry {
+    sink: Box<dyn LogSink>,
+    level: LevelFilter,
+}
+
+impl fmt::Debug for SinkEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SinkEntry")
+            .field("level", &self.level)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MultiLogger {
+    /// Creates a new logger without any sinks.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            sinks: Lock::new(Vec::new()),
+            get_timestamp: AtomicPtr::new(ptr::null_mut()),
+            timestamp_frequency: AtomicU64::new(0),
+        }
+    }
+
+    /// Sets this logger as the global logger of the [`log`] crate.
+    ///
+    /// The maximum log level is set to the most verbose level of all sinks
+    /// added so far, and is updated when more sinks are added.
+    pub fn install(&'static self) -> Result<(), log::SetLoggerError> {
+        log::set_logger(self)?;
+        log::set_max_level(self.max_level());
+        Ok(())
+    }
+
+    /// Adds `sink`, which receives all messages at or above `level`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::ACCESS_DENIED`]: the logger is currently in use, e.g. when
+    ///   called from a sink.
+    ///
+    /// [`Status::ACCESS_DENIED`]: crate::Status::ACCESS_DENIED
+    pub fn add_sink(&self, sink: impl LogSink + 'static, level: LevelFilter) -> crate::Result {
+        let entry = SinkEntry {
+            sink: Box::new(sink),
+            level,
+        };
+        self.sinks
+            .try_lock_no_preempt(|sinks| sinks.push(entry))
+            .ok_or(Status::ACCESS_DENIED)?;
+
+        if ptr::addr_eq(log::logger(), self) {
+            log::set_max_level(self.max_level());
+        }
+        Ok(())
+    }
+
+    /// Prefixes every message with the time elapsed since the timestamp
+    /// counter of `timestamp` was started.
+    ///
+    /// Timestamps are only included while boot services are active.
+    pub fn set_timestamp(&self, timestamp: &Timestamp) -> crate::Result {
+        let properties = timestamp.get_properties()?;
+        self.timestamp_frequency
+            .store(properties.frequency, Ordering::Release);
+        self.get_timestamp
+            .store(timestamp.get_timestamp_fn() as *mut (), Ordering::Release);
+        Ok(())
+    }
+
+    /// Returns the most verbose level of all sinks.
+    fn max_level(&self) -> LevelFilter {
+        self.sinks
+            .try_lock_no_preempt(|sinks| sinks.iter().map(|entry| entry.level).max())
+            .flatten()
+            .unwrap_or(LevelFilter::Off)
+    }
+
+    /// Returns the current timestamp in microseconds, if available.
+    fn timestamp_us(&self) -> Option<u64> {
+        let get_timestamp = self.get_timestamp.load(Ordering::Acquire);
+        let frequency = self.timestamp_frequency.load(Ordering::Acquire);
+        if get_timestamp.is_null() || frequency == 0 || !boot::are_boot_services_active() {
+            return None;
+        }
+
+        // SAFETY: the pointer was stored by `set_timestamp` from a valid
+        // protocol, and boot services are still active.
+        let get_timestamp: unsafe extern "efiapi" fn() -> u64 =
+            unsafe { mem::transmute(get_timestamp) };
+        let ticks = unsafe { get_timestamp() };
+        Some((u128::from(ticks) * 1_000_000 / u128::from(frequency)) as u64)
+    }
+}
+
+impl Default for MultiLogger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Adapter to use a [`LogSink`] as [`fmt::Write`].
+struct SinkWriter<'a>(&'a mut dyn LogSink);
+
+impl Write for SinkWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.write_str(s)
+    }
+}
+
+impl log::Log for MultiLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= self.max_level()
+    }
+
+    fn log(&self, record: &log::Record) {
+        let timestamp = self.timestamp_us();
+        let boot_services_active = boot::are_boot_services_active();
+
+        // If the logger is already in use (e.g. a sink is logging itself),
+        // the message is dropped.
+        self.sinks.try_lock_no_preempt(|sinks| {
+            let sinks = sinks.iter_mut().filter(|entry| {
+                record.level() <= entry.level
+                    && (boot_services_active || !entry.sink.requires_boot_services())
+            });
+            for entry in sinks {
+                let mut writer = SinkWriter(&mut *entry.sink);
+                // Ignore all errors. Since we're in the logger implementation
+                // we can't log the error. We also don't want to panic, since
+                // logging is generally not critical functionality.
+                if let Some(us) = timestamp {
+                    let _ = write!(writer, "[{:>5}.{:06}] ", us / 1_000_000, us % 1_000_000);
+                }
+                let _ = DecoratedLog::write(
+                    &mut writer,
+                    record.level(),
+                    record.args(),
+                    record.file().unwrap_or("<unknown file>"),
+                    record.line().unwrap_or(0),
+                );
+            }
+        });
+    }
+
+    fn flush(&self) {
+        let boot_services_active = boot::are_boot_services_active();
+        self.sinks.try_lock_no_preempt(|sinks| {
+            for entry in sinks {
+                if boot_services_active || !entry.sink.requires_boot_services() {
+                    let _ = entry.sink.flush();
+                }
+            }
+        });
+    }
+}
+
+// The sinks are only accessed with the lock held.
+unsafe impl Sync for MultiLogger {}
+unsafe impl Send for MultiLogger {}
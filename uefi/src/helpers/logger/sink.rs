@@ -0,0 +1,359 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Destinations for log output, used by [`MultiLogger`].
+//!
+//! [`MultiLogger`]: super::MultiLogger
+
+use crate::boot::ScopedProtocol;
+use crate::proto::console::serial::Serial;
+use crate::proto::console::text::Output;
+use crate::proto::debug::DebugPort;
+use crate::proto::media::file::{File, RegularFile};
+use crate::table;
+use crate::util::Lock;
+use core::fmt::{self, Write};
+
+/// A destination for log messages.
+///
+/// Sinks receive already formatted UTF-8 text. A sink is free to buffer the
+/// text it receives, as long as everything is written out when
+/// [`flush`] is called.
+///
+/// [`flush`]: Self::flush
+pub trait LogSink {
+    /// Write `s` to the sink.
+    fn write_str(&mut self, s: &str) -> fmt::Result;
+
+    /// Write out any buffered text.
+    fn flush(&mut self) -> fmt::Result {
+        Ok(())
+    }
+
+    /// Whether the sink can only be used while boot services are active.
+    ///
+    /// Sinks for which this returns `true` are skipped after exiting boot
+    /// services. This is the case for all sinks backed by a UEFI protocol.
+    fn requires_boot_services(&self) -> bool {
+        true
+    }
+}
+
+/// Sink writing to the [`Output`] protocol attached to stdout.
+///
+/// The stdout protocol is looked up in the system table on every write, so
+/// this sink follows changes of the console, e.g. after reconnecting it.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StdoutSink;
+
+impl LogSink for StdoutSink {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let st = table::system_table_raw().ok_or(fmt::Error)?;
+        // SAFETY: valid per requirements of `set_system_table`.
+        let st = unsafe { st.as_ref() };
+        if st.boot_services.is_null() {
+            return Err(fmt::Error);
+        }
+
+        let stdout: *mut Output = st.stdout.cast();
+        // SAFETY: `Output` is a `repr(transparent)` wrapper around the raw
+        // output type. The pointer in the system table is either null or
+        // valid while boot services are active.
+        let stdout = unsafe { stdout.as_mut() }.ok_or(fmt::Error)?;
+        stdout.write_str(s)
+    }
+}
+
+impl LogSink for ScopedProtocol<Serial> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        Write::write_str(&mut **self, s)
+    }
+}
+
+impl LogSink for ScopedProtocol<DebugPort> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        /// Time to wait for the device to accept data, in microseconds.
+        const TIMEOUT: u32 = 1000;
+
+        let mut data = s.as_bytes();
+        while !data.is_empty() {
+            match self.write(TIMEOUT, data) {
+                Ok(()) => break,
+                // Retry with the remainder, unless nothing was written at all.
+                Err(err) if *err.data() > 0 => data = &data[*err.data()..],
+                Err(_) => return Err(fmt::Error),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl LogSink for RegularFile {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write(s.as_bytes()).map_err(|_| fmt::Error)
+    }
+
+    fn flush(&mut self) -> fmt::Result {
+        File::flush(self).map_err(|_| fmt::Error)
+    }
+}
+
+/// Wrapper buffering the output of another [`LogSink`] in a fixed-size
+/// buffer of `N` bytes.
+///
+/// The text is passed on to the inner sink when the buffer is full or when
+/// the sink is flushed. This is useful for sinks where each write is costly,
+/// such as the [`StdoutSink`] which converts every write to UCS-2.
+#[derive(Debug)]
+pub struct Buffered<S: LogSink, const N: usize> {
+    inner: S,
+    buf: [u8; N],
+    len: usize,
+}
+
+impl<S: LogSink, const N: usize> Buffered<S, N> {
+    /// Creates a new buffer wrapping `inner`.
+    pub const fn new(inner: S) -> Self {
+        Self {
+            inner,
+            buf: [0; N],
+            len: 0,
+        }
+    }
+
+    /// Returns a reference to the wrapped sink.
+    pub const fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Passes the buffered text on to the inner sink.
+    fn flush_buf(&mut self) -> fmt::Result {
+        let len = core::mem::take(&mut self.len);
+        // The buffer only ever holds complete UTF-8 sequences.
+        let s = core::str::from_utf8(&self.buf[..len]).map_err(|_| fmt::Error)?;
+        self.inner.write_str(s)
+    }
+}
+
+impl<S: LogSink, const N: usize> LogSink for Buffered<S, N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.len + s.len() > N {
+            self.flush_buf()?;
+        }
+        if s.len() > N {
+            return self.inner.write_str(s);
+        }
+        self.buf[self.len..self.len + s.len()].copy_from_slice(s.as_bytes());
+        self.len += s.len();
+        Ok(())
+    }
+
+    fn flush(&mut self) -> fmt::Result {
+        self.flush_buf()?;
+        self.inner.flush()
+    }
+
+    fn requires_boot_services(&self) -> bool {
+        self.inner.requires_boot_services()
+    }
+}
+
+/// In-memory ring buffer keeping the last `N` bytes of log output.
+///
+/// The ring buffer does not depend on boot services, so it keeps recording
+/// after [`exit_boot_services`], and its contents can be retrieved at any
+/// time with [`read`]. Typically, it is placed in a `static` and registered
+/// as a sink by reference:
+///
+/// ```no_run
+/// use uefi::helpers::logger::{MultiLogger, RingBuffer};
+///
+/// static LOGGER: MultiLogger = MultiLogger::new();
+/// static RING: RingBuffer<4096> = RingBuffer::new();
+///
+/// LOGGER.add_sink(&RING, log::LevelFilter::Trace).unwrap();
+///
+/// // Later, e.g. in the kernel:
+/// let mut buf = [0; 4096];
+/// let len = RING.read(&mut buf);
+/// ```
+///
+/// Once the buffer is full, the oldest bytes are overwritten. Note that this
+/// may leave a partial UTF-8 sequence at the start of the buffer.
+///
+/// [`exit_boot_services`]: crate::boot::exit_boot_services
+/// [`read`]: Self::read
+#[derive(Debug)]
+pub struct RingBuffer<const N: usize> {
+    inner: Lock<RingBufferInner<N>>,
+}
+
+#[derive(Debug)]
+struct RingBufferInner<const N: usize> {
+    buf: [u8; N],
+    /// Index of the oldest byte.
+    start: usize,
+    /// Number of valid bytes.
+    len: usize,
+}
+
+impl<const N: usize> RingBuffer<N> {
+    /// Creates a new, empty ring buffer.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            inner: Lock::new(RingBufferInner {
+                buf: [0; N],
+                start: 0,
+                len: 0,
+            }),
+        }
+    }
+
+    /// Returns the number of bytes currently stored.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.inner.lock_no_preempt(|inner| inner.len)
+    }
+
+    /// Returns whether the buffer is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Copies the stored bytes into `out`, oldest first, and returns the
+    /// number of bytes copied.
+    ///
+    /// If `out` is smaller than [`len`], only the most recent bytes are
+    /// copied.
+    ///
+    /// [`len`]: Self::len
+    pub fn read(&self, out: &mut [u8]) -> usize {
+        self.inner.lock_no_preempt(|inner| {
+            let count = inner.len.min(out.len());
+            let skip = inner.len - count;
+            for (i, byte) in out[..count].iter_mut().enumerate() {
+                *byte = inner.buf[(inner.start + skip + i) % N];
+            }
+            count
+        })
+    }
+
+    /// Removes all stored bytes.
+    pub fn clear(&self) {
+        self.inner.lock_no_preempt(|inner| {
+            inner.start = 0;
+            inner.len = 0;
+        })
+    }
+
+    fn push(&self, data: &[u8]) {
+        if N == 0 {
+            return;
+        }
+        self.inner.lock_no_preempt(|inner| {
+            // Only the last `N` bytes can be kept.
+            let data = &data[data.len().saturating_sub(N)..];
+            for &byte in data {
+                let end = (inner.start + inner.len) % N;
+                inner.buf[end] = byte;
+                if inner.len == N {
+                    inner.start = (inner.start + 1) % N;
+                } else {
+                    inner.len += 1;
+                }
+            }
+        })
+    }
+}
+
+impl<const N: usize> Default for RingBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> LogSink for &RingBuffer<N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push(s.as_bytes());
+        Ok(())
+    }
+
+    fn requires_boot_services(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::String;
+
+    /// Sink collecting everything into a string.
+    #[derive(Default)]
+    struct StringSink {
+        s: String,
+        writes: usize,
+    }
+
+    impl LogSink for StringSink {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.s.push_str(s);
+            self.writes += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_buffered() {
+        let mut sink = Buffered::<_, 8>::new(StringSink::default());
+        sink.write_str("abc").unwrap();
+        sink.write_str("def").unwrap();
+        assert_eq!(sink.get_ref().writes, 0);
+
+        // Does not fit anymore, so the buffer is passed on first.
+        sink.write_str("ghi").unwrap();
+        assert_eq!(sink.get_ref().s, "abcdef");
+
+        // Larger than the buffer, so it's written directly.
+        sink.write_str("0123456789").unwrap();
+        assert_eq!(sink.get_ref().s, "abcdefghi0123456789");
+
+        sink.write_str("üx").unwrap();
+        sink.flush().unwrap();
+        assert_eq!(sink.get_ref().s, "abcdefghi0123456789üx");
+        assert_eq!(sink.get_ref().writes, 4);
+    }
+
+    #[test]
+    fn test_ring_buffer() {
+        let ring = RingBuffer::<8>::new();
+        let mut buf = [0; 8];
+        assert!(ring.is_empty());
+        assert_eq!(ring.read(&mut buf), 0);
+
+        let mut sink = &ring;
+        sink.write_str("hello").unwrap();
+        assert_eq!(ring.read(&mut buf), 5);
+        assert_eq!(&buf[..5], b"hello");
+
+        // Wrap around, dropping the oldest bytes.
+        sink.write_str(" world").unwrap();
+        assert_eq!(ring.len(), 8);
+        assert_eq!(ring.read(&mut buf), 8);
+        assert_eq!(&buf, b"lo world");
+
+        // Reading into a smaller buffer returns the most recent bytes.
+        let mut small = [0; 3];
+        assert_eq!(ring.read(&mut small), 3);
+        assert_eq!(&small, b"rld");
+
+        // Writes larger than the whole buffer keep the tail.
+        sink.write_str("0123456789").unwrap();
+        assert_eq!(ring.read(&mut buf), 8);
+        assert_eq!(&buf, b"23456789");
+
+        ring.clear();
+        assert!(ring.is_empty());
+    }
+}
@@ -10,13 +10,18 @@
 //!   the stdout text protocol of UEFI (as long as boot services were not
 //!   excited) and to the [debugcon device](https://phip1611.de/blog/how-to-use-qemus-debugcon-feature/)
 //!   (only on x86)  (feature `log-debugcon`).
+//! - a [`MultiLogger`] (features `logger` and `alloc`) which logs to multiple
+//!   sinks with individual log levels.
 //! - [`print!`][print_macro] and [`println!`][println_macro] macros defaulting
 //!   to the uefi boot service stdout stream
-//! - default panic handler (feature `panic_handler`)
+//! - default panic handler (feature `panic_handler`), which can optionally
+//!   store a [crash report](crash_report) in a UEFI variable
+//! - a minimal [frame pointer based stack unwinder](backtrace)
 //!
 //! **PLEASE NOTE** that these helpers are meant for the pre exit boot service
 //! epoch.
 //!
+//! [`MultiLogger`]: logger::MultiLogger
 //! [print_macro]: uefi::print!
 //! [println_macro]: uefi::println!
 
@@ -24,10 +29,13 @@ use crate::Result;
 #[doc(hidden)]
 pub use println::_print;
 
+pub mod backtrace;
+pub mod crash_report;
+
 #[cfg(feature = "global_allocator")]
 mod global_allocator;
 #[cfg(feature = "logger")]
-mod logger;
+pub mod logger;
 #[cfg(feature = "panic_handler")]
 mod panic_handler;
 mod println;
@@ -57,6 +65,10 @@ pub fn init() -> Result<()> {
 
 #[allow(clippy::missing_const_for_fn)]
 pub(crate) fn exit() {
+    // Write out anything buffered by the logger while the console is still
+    // available.
+    log::logger().flush();
+
     #[cfg(feature = "logger")]
     logger::disable();
 }
@@ -7,6 +7,11 @@ use cfg_if::cfg_if;
 
 #[panic_handler]
 fn panic_handler(info: &core::panic::PanicInfo) -> ! {
+    // Write out buffered log messages before the panic message.
+    log::logger().flush();
+
+    super::crash_report::save_if_enabled(info);
+
     println!("[PANIC]: {}", info);
 
     // Give the user some time to read the message
@@ -138,7 +138,9 @@
 //!   some code might use the internal UEFI allocator.
 //! - `logger`: Logging implementation for the standard [`log`] crate
 //!   that prints output to the UEFI console. No buffering is done; this
-//!   is not a high-performance logger.
+//!   is not a high-performance logger. Together with `alloc`, this also
+//!   enables [`helpers::logger::MultiLogger`], a buffered logger writing to
+//!   multiple sinks.
 //! - `log-debugcon`: Whether the logger set up by `logger` should also log
 //!   to the debugcon device (available in QEMU or Cloud Hypervisor on x86).
 //! - `panic_handler`: Add a default panic handler that logs to `stdout`.
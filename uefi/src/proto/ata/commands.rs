@@ -0,0 +1,728 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Typed ATA commands: IDENTIFY DEVICE, SMART and the Security feature set.
+//!
+//! The helpers in this module are methods of [`AtaDevice`]. If the device
+//! aborts a command, the pass thru protocol returns
+//! [`Status::DEVICE_ERROR`].
+//!
+//! # Example
+//!
+//! ```
+//! use uefi::Result;
+//! use uefi::proto::ata::pass_thru::AtaPassThru;
+//!
+//! fn list_drives(ata: &AtaPassThru) -> Result {
+//!     for mut device in ata.iter_devices() {
+//!         // Not every device address has a drive connected.
+//!         let Ok(identify) = device.identify() else {
+//!             continue;
+//!         };
+//!         log::info!(
+//!             "{} ({} bytes, SMART: {})",
+//!             identify.model_number,
+//!             identify.capacity(),
+//!             identify.features.contains(uefi::proto::ata::commands::AtaFeatures::SMART)
+//!         );
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use super::AtaRequestBuilder;
+use super::pass_thru::AtaDevice;
+use crate::{Result, Status};
+use alloc::string::String;
+use alloc::vec::Vec;
+use bitflags::bitflags;
+
+/// ATA command opcodes.
+mod command {
+    pub const SMART: u8 = 0xb0;
+    pub const IDENTIFY_DEVICE: u8 = 0xec;
+    pub const SECURITY_SET_PASSWORD: u8 = 0xf1;
+    pub const SECURITY_UNLOCK: u8 = 0xf2;
+    pub const SECURITY_ERASE_PREPARE: u8 = 0xf3;
+    pub const SECURITY_ERASE_UNIT: u8 = 0xf4;
+    pub const SECURITY_FREEZE_LOCK: u8 = 0xf5;
+    pub const SECURITY_DISABLE_PASSWORD: u8 = 0xf6;
+}
+
+/// Features of the SMART command.
+mod smart {
+    pub const READ_DATA: u8 = 0xd0;
+    pub const READ_THRESHOLDS: u8 = 0xd1;
+    pub const ENABLE_OPERATIONS: u8 = 0xd8;
+    pub const RETURN_STATUS: u8 = 0xda;
+}
+
+/// Size of the data of IDENTIFY DEVICE, SMART and the security commands.
+const SECTOR_SIZE: usize = 512;
+
+/// Maximum length of a security password.
+pub const MAX_PASSWORD_LEN: usize = 32;
+
+impl AtaDevice<'_> {
+    /// Sends a PIO data-in command and returns the 512 bytes read.
+    fn read_sector(&mut self, command: u8, features: u8) -> Result<Vec<u8>> {
+        let request = AtaRequestBuilder::read_pio(self.io_align(), command)
+            .map_err(|_| Status::INVALID_PARAMETER)?
+            .with_features(features)
+            .with_sector_count(1);
+        let request = smart_signature(command, request)
+            .with_read_buffer(SECTOR_SIZE)
+            .map_err(|_| Status::INVALID_PARAMETER)?
+            .build();
+        let response = self.execute_command(request)?;
+        Ok(response.read_buffer().unwrap_or_default().to_vec())
+    }
+
+    /// Sends a PIO data-out command with `data`.
+    fn write_sector(&mut self, command: u8, data: &[u8; SECTOR_SIZE]) -> Result {
+        let request = AtaRequestBuilder::write_pio(self.io_align(), command)
+            .map_err(|_| Status::INVALID_PARAMETER)?
+            .with_sector_count(1)
+            .with_write_data(data)
+            .map_err(|_| Status::INVALID_PARAMETER)?
+            .build();
+        self.execute_command(request).map(|_| ())
+    }
+
+    /// Sends a command without data transfer. Returns the cylinder registers
+    /// of the status block.
+    fn non_data(&mut self, command: u8, features: u8) -> Result<(u8, u8)> {
+        let request = AtaRequestBuilder::non_data(self.io_align(), command)
+            .map_err(|_| Status::INVALID_PARAMETER)?
+            .with_features(features);
+        let response = self.execute_command(smart_signature(command, request).build())?;
+        let status = response.status();
+        Ok((status.cylinder_low, status.cylinder_high))
+    }
+
+    /// Reads the IDENTIFY DEVICE data.
+    ///
+    /// This is also a way to probe whether a drive is connected at the
+    /// address of this device.
+    pub fn identify(&mut self) -> Result<IdentifyDevice> {
+        let data = self.read_sector(command::IDENTIFY_DEVICE, 0)?;
+        IdentifyDevice::parse(&data).ok_or_else(|| Status::CRC_ERROR.into())
+    }
+
+    /// Enables the SMART feature set, which is required by the other SMART
+    /// commands.
+    pub fn smart_enable(&mut self) -> Result {
+        self.non_data(command::SMART, smart::ENABLE_OPERATIONS)
+            .map(|_| ())
+    }
+
+    /// Returns whether the device reports that a SMART threshold is
+    /// exceeded, meaning that the device is likely to fail.
+    pub fn smart_status(&mut self) -> Result<bool> {
+        let registers = self.non_data(command::SMART, smart::RETURN_STATUS)?;
+        Ok(registers == (0xf4, 0x2c))
+    }
+
+    /// Reads the SMART attributes.
+    pub fn smart_data(&mut self) -> Result<SmartData> {
+        let data = self.read_sector(command::SMART, smart::READ_DATA)?;
+        SmartData::parse(&data).ok_or_else(|| Status::BAD_BUFFER_SIZE.into())
+    }
+
+    /// Reads the SMART attribute thresholds.
+    ///
+    /// This command is obsolete since ACS-3, but widely supported.
+    pub fn smart_thresholds(&mut self) -> Result<Vec<SmartThreshold>> {
+        let data = self.read_sector(command::SMART, smart::READ_THRESHOLDS)?;
+        SmartThreshold::parse(&data).ok_or_else(|| Status::BAD_BUFFER_SIZE.into())
+    }
+
+    /// Sets the user or master password of the Security feature set.
+    ///
+    /// Setting the user password enables the security. With
+    /// [`SecurityLevel::High`], the drive can then be unlocked with the user
+    /// or the master password. With [`SecurityLevel::Maximum`], the master
+    /// password only allows erasing the drive. `level` is ignored when
+    /// setting the master password.
+    ///
+    /// # Errors
+    ///
+    /// [`Status::INVALID_PARAMETER`] if the password is longer than
+    /// [`MAX_PASSWORD_LEN`].
+    pub fn security_set_password(
+        &mut self,
+        kind: PasswordKind,
+        password: &[u8],
+        level: SecurityLevel,
+    ) -> Result {
+        let mut data = password_block(kind, password)?;
+        if level == SecurityLevel::Maximum {
+            data[1] |= 1;
+        }
+        self.write_sector(command::SECURITY_SET_PASSWORD, &data)
+    }
+
+    /// Unlocks the drive.
+    ///
+    /// Drives with security enabled are locked after power on. After 5
+    /// failed attempts, the drive must be power cycled before trying again.
+    ///
+    /// # Errors
+    ///
+    /// [`Status::INVALID_PARAMETER`] if the password is longer than
+    /// [`MAX_PASSWORD_LEN`].
+    pub fn security_unlock(&mut self, kind: PasswordKind, password: &[u8]) -> Result {
+        let data = password_block(kind, password)?;
+        self.write_sector(command::SECURITY_UNLOCK, &data)
+    }
+
+    /// Disables the security, by removing the user password. The drive must
+    /// be unlocked.
+    ///
+    /// # Errors
+    ///
+    /// [`Status::INVALID_PARAMETER`] if the password is longer than
+    /// [`MAX_PASSWORD_LEN`].
+    pub fn security_disable_password(&mut self, kind: PasswordKind, password: &[u8]) -> Result {
+        let data = password_block(kind, password)?;
+        self.write_sector(command::SECURITY_DISABLE_PASSWORD, &data)
+    }
+
+    /// Prevents changes to the security settings until the next power
+    /// cycle.
+    ///
+    /// Firmware commonly freezes drives before booting an OS, so that
+    /// malware cannot set a password.
+    pub fn security_freeze_lock(&mut self) -> Result {
+        self.non_data(command::SECURITY_FREEZE_LOCK, 0).map(|_| ())
+    }
+
+    /// Erases all user data of the drive, and disables the security.
+    ///
+    /// The user password must be set, and the drive must not be frozen. The
+    /// command has no timeout, and can take hours on hard drives, see
+    /// [`IdentifyDevice::security_erase_time`].
+    ///
+    /// **All data of the drive is lost.**
+    ///
+    /// # Errors
+    ///
+    /// [`Status::INVALID_PARAMETER`] if the password is longer than
+    /// [`MAX_PASSWORD_LEN`].
+    pub fn security_erase_unit(
+        &mut self,
+        kind: PasswordKind,
+        password: &[u8],
+        enhanced: bool,
+    ) -> Result {
+        let mut data = password_block(kind, password)?;
+        if enhanced {
+            data[0] |= 1 << 1;
+        }
+        self.non_data(command::SECURITY_ERASE_PREPARE, 0)?;
+        self.write_sector(command::SECURITY_ERASE_UNIT, &data)
+    }
+}
+
+/// Sets the signature required by SMART commands in the cylinder registers.
+const fn smart_signature(command: u8, request: AtaRequestBuilder<'_>) -> AtaRequestBuilder<'_> {
+    if command == command::SMART {
+        request.with_cylinder(0x4f, 0xc2)
+    } else {
+        request
+    }
+}
+
+/// Builds the data of the security commands.
+fn password_block(kind: PasswordKind, password: &[u8]) -> Result<[u8; SECTOR_SIZE]> {
+    if password.len() > MAX_PASSWORD_LEN {
+        return Err(Status::INVALID_PARAMETER.into());
+    }
+    let mut data = [0; SECTOR_SIZE];
+    data[0] = kind as u8;
+    data[2..2 + password.len()].copy_from_slice(password);
+    Ok(data)
+}
+
+/// Password of the Security feature set.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u8)]
+pub enum PasswordKind {
+    /// The user password.
+    User = 0,
+    /// The master password.
+    Master = 1,
+}
+
+/// Security level of a user password.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum SecurityLevel {
+    /// The master password can unlock the drive.
+    #[default]
+    High,
+    /// The master password can only erase the drive.
+    Maximum,
+}
+
+bitflags! {
+    /// Features supported by an ATA device.
+    #[repr(transparent)]
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct AtaFeatures: u32 {
+        /// LBA addressing.
+        const LBA = 1 << 0;
+        /// DMA transfers.
+        const DMA = 1 << 1;
+        /// 48-bit addresses.
+        const LBA48 = 1 << 2;
+        /// The SMART feature set.
+        const SMART = 1 << 3;
+        /// The SMART feature set is enabled.
+        const SMART_ENABLED = 1 << 4;
+        /// SMART self-tests.
+        const SMART_SELF_TEST = 1 << 5;
+        /// The Security feature set.
+        const SECURITY = 1 << 6;
+        /// Volatile write cache.
+        const WRITE_CACHE = 1 << 7;
+        /// The write cache is enabled.
+        const WRITE_CACHE_ENABLED = 1 << 8;
+        /// FLUSH CACHE EXT.
+        const FLUSH_CACHE_EXT = 1 << 9;
+        /// TRIM with DATA SET MANAGEMENT.
+        const TRIM = 1 << 10;
+    }
+}
+
+bitflags! {
+    /// State of the Security feature set, from word 128 of the IDENTIFY
+    /// DEVICE data.
+    #[repr(transparent)]
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct SecurityStatus: u16 {
+        /// The Security feature set is supported.
+        const SUPPORTED = 1 << 0;
+        /// A user password is set.
+        const ENABLED = 1 << 1;
+        /// The drive is locked.
+        const LOCKED = 1 << 2;
+        /// The security settings are frozen.
+        const FROZEN = 1 << 3;
+        /// Too many failed unlock attempts. The drive must be power cycled.
+        const COUNT_EXPIRED = 1 << 4;
+        /// Enhanced security erase is supported.
+        const ENHANCED_ERASE = 1 << 5;
+        /// The security level is [`SecurityLevel::Maximum`].
+        const LEVEL_MAXIMUM = 1 << 8;
+    }
+}
+
+/// Reads word `index`.
+const fn word(data: &[u8], index: usize) -> u16 {
+    u16::from_le_bytes([data[index * 2], data[index * 2 + 1]])
+}
+
+/// Reads a string of `words` words. ATA strings store the first character
+/// of each pair in the high byte, and are padded with spaces.
+fn ata_string(data: &[u8], index: usize, words: usize) -> String {
+    let bytes: Vec<u8> = data[index * 2..(index + words) * 2]
+        .chunks_exact(2)
+        .flat_map(|pair| [pair[1], pair[0]])
+        .collect();
+    String::from_utf8_lossy(&bytes)
+        .trim_matches([' ', '\0'])
+        .into()
+}
+
+/// Checks whether a word is valid: bit 14 is set, and bit 15 is clear.
+const fn is_valid(word: u16) -> bool {
+    word & 0xc000 == 0x4000
+}
+
+/// IDENTIFY DEVICE data.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IdentifyDevice {
+    /// Serial number.
+    pub serial_number: String,
+    /// Firmware revision.
+    pub firmware_revision: String,
+    /// Model number.
+    pub model_number: String,
+    /// Number of addressable logical sectors.
+    pub sectors: u64,
+    /// Size of a logical sector in bytes.
+    pub logical_sector_size: u32,
+    /// Size of a physical sector in bytes.
+    pub physical_sector_size: u32,
+    /// Supported and enabled features.
+    pub features: AtaFeatures,
+    /// State of the Security feature set.
+    pub security: SecurityStatus,
+    /// Estimated time of a [`security_erase_unit`], in minutes. Zero if not
+    /// reported.
+    ///
+    /// [`security_erase_unit`]: AtaDevice::security_erase_unit
+    pub security_erase_time: u16,
+    /// Estimated time of an enhanced [`security_erase_unit`], in minutes.
+    /// Zero if not reported.
+    ///
+    /// [`security_erase_unit`]: AtaDevice::security_erase_unit
+    pub enhanced_security_erase_time: u16,
+    /// Nominal media rotation rate in rpm. 1 for non-rotating media like
+    /// SSDs, and 0 if not reported.
+    pub rotation_rate: u16,
+}
+
+impl IdentifyDevice {
+    /// Parses the IDENTIFY DEVICE data.
+    ///
+    /// Returns `None` if `data` is shorter than 512 bytes, if the checksum
+    /// is invalid, or if the sector sizes don't fit in a `u32`.
+    #[must_use]
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let data = data.get(..SECTOR_SIZE)?;
+        // The checksum is optional, and signaled by 0xa5 in word 255.
+        if data[510] == 0xa5 && data.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) != 0 {
+            return None;
+        }
+
+        let w = |index| word(data, index);
+        let mut features = AtaFeatures::empty();
+        let mut set = |feature, enabled| features.set(feature, enabled);
+        set(AtaFeatures::LBA, w(49) & (1 << 9) != 0);
+        set(AtaFeatures::DMA, w(49) & (1 << 8) != 0);
+        if is_valid(w(83)) {
+            set(AtaFeatures::LBA48, w(83) & (1 << 10) != 0);
+            set(AtaFeatures::FLUSH_CACHE_EXT, w(83) & (1 << 13) != 0);
+            set(AtaFeatures::SMART, w(82) & (1 << 0) != 0);
+            set(AtaFeatures::SECURITY, w(82) & (1 << 1) != 0);
+            set(AtaFeatures::WRITE_CACHE, w(82) & (1 << 5) != 0);
+        }
+        if is_valid(w(84)) {
+            set(AtaFeatures::SMART_SELF_TEST, w(84) & (1 << 1) != 0);
+        }
+        if is_valid(w(87)) {
+            set(AtaFeatures::SMART_ENABLED, w(85) & (1 << 0) != 0);
+            set(AtaFeatures::WRITE_CACHE_ENABLED, w(85) & (1 << 5) != 0);
+        }
+        set(AtaFeatures::TRIM, w(169) & 1 != 0);
+
+        let sectors = if features.contains(AtaFeatures::LBA48) {
+            (0..4).fold(0, |sectors, i| sectors | u64::from(w(100 + i)) << (16 * i))
+        } else {
+            u64::from(w(60)) | u64::from(w(61)) << 16
+        };
+
+        let mut logical_sector_size = 512;
+        let mut physical_sector_size = 512;
+        let sector_info = w(106);
+        if is_valid(sector_info) {
+            if sector_info & (1 << 12) != 0 {
+                let words = u32::from(w(117)) | u32::from(w(118)) << 16;
+                logical_sector_size = words.checked_mul(2)?;
+            }
+            if sector_info & (1 << 13) != 0 {
+                physical_sector_size = logical_sector_size.checked_mul(1 << (sector_info & 0xf))?;
+            } else {
+                physical_sector_size = logical_sector_size;
+            }
+        }
+
+        // Bit 15 of the erase times selects the extended format.
+        let erase_time = |word: u16| {
+            let units = if word & 0x8000 != 0 {
+                word & 0x7fff
+            } else {
+                word & 0xff
+            };
+            units.saturating_mul(2)
+        };
+
+        Some(Self {
+            serial_number: ata_string(data, 10, 10),
+            firmware_revision: ata_string(data, 23, 4),
+            model_number: ata_string(data, 27, 20),
+            sectors,
+            logical_sector_size,
+            physical_sector_size,
+            features,
+            security: SecurityStatus::from_bits_truncate(w(128)),
+            security_erase_time: erase_time(w(89)),
+            enhanced_security_erase_time: erase_time(w(90)),
+            rotation_rate: w(217),
+        })
+    }
+
+    /// Returns the capacity of the drive in bytes, saturating at
+    /// `u64::MAX`.
+    #[must_use]
+    pub const fn capacity(&self) -> u64 {
+        self.sectors.saturating_mul(self.logical_sector_size as u64)
+    }
+}
+
+/// SMART attribute.
+///
+/// The meaning of the attributes and of their raw values is vendor
+/// specific, but some IDs are used consistently, for example 5 for the
+/// reallocated sector count, 9 for the power-on hours and 194 for the
+/// temperature.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SmartAttribute {
+    /// Attribute ID.
+    pub id: u8,
+    /// Status flags. Bit 0 is set for attributes predicting failures.
+    pub flags: u16,
+    /// Normalized value, usually from 1 to 253, where higher is better.
+    pub value: u8,
+    /// Worst normalized value.
+    pub worst: u8,
+    /// Raw value.
+    pub raw: u64,
+}
+
+impl SmartAttribute {
+    /// Returns whether the normalized value is at or below the threshold of
+    /// the attribute in `thresholds`.
+    #[must_use]
+    pub fn is_failing(&self, thresholds: &[SmartThreshold]) -> bool {
+        thresholds
+            .iter()
+            .find(|threshold| threshold.id == self.id)
+            .is_some_and(|threshold| threshold.threshold != 0 && self.value <= threshold.threshold)
+    }
+}
+
+/// Data returned by SMART READ DATA.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SmartData {
+    /// Attributes with a non-zero ID.
+    pub attributes: Vec<SmartAttribute>,
+    /// Status of the off-line data collection.
+    pub offline_collection_status: u8,
+    /// Status of the last self-test, in the high nibble: 0 for success, 15
+    /// for a running test.
+    pub self_test_status: u8,
+}
+
+/// Number of attribute entries in the SMART data.
+const SMART_ENTRIES: usize = 30;
+/// Size of an attribute entry in the SMART data.
+const SMART_ENTRY_SIZE: usize = 12;
+
+impl SmartData {
+    /// Parses the SMART data.
+    ///
+    /// Returns `None` if `data` is shorter than 512 bytes.
+    #[must_use]
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let data = data.get(..SECTOR_SIZE)?;
+        let attributes = data[2..2 + SMART_ENTRIES * SMART_ENTRY_SIZE]
+            .chunks_exact(SMART_ENTRY_SIZE)
+            .filter(|entry| entry[0] != 0)
+            .map(|entry| SmartAttribute {
+                id: entry[0],
+                flags: u16::from_le_bytes([entry[1], entry[2]]),
+                value: entry[3],
+                worst: entry[4],
+                raw: entry[5..11]
+                    .iter()
+                    .rev()
+                    .fold(0, |raw, b| raw << 8 | u64::from(*b)),
+            })
+            .collect();
+        Some(Self {
+            attributes,
+            offline_collection_status: data[362],
+            self_test_status: data[363],
+        })
+    }
+
+    /// Returns the attribute with the given `id`.
+    #[must_use]
+    pub fn attribute(&self, id: u8) -> Option<&SmartAttribute> {
+        self.attributes.iter().find(|attribute| attribute.id == id)
+    }
+}
+
+/// SMART attribute threshold.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SmartThreshold {
+    /// Attribute ID.
+    pub id: u8,
+    /// Normalized value at or below which the attribute is failing. Zero
+    /// for attributes that never fail.
+    pub threshold: u8,
+}
+
+impl SmartThreshold {
+    /// Parses the data of SMART READ THRESHOLDS, skipping entries with an
+    /// ID of zero.
+    ///
+    /// Returns `None` if `data` is shorter than 512 bytes.
+    #[must_use]
+    pub fn parse(data: &[u8]) -> Option<Vec<Self>> {
+        let data = data.get(..SECTOR_SIZE)?;
+        let thresholds = data[2..2 + SMART_ENTRIES * SMART_ENTRY_SIZE]
+            .chunks_exact(SMART_ENTRY_SIZE)
+            .filter(|entry| entry[0] != 0)
+            .map(|entry| Self {
+                id: entry[0],
+                threshold: entry[1],
+            })
+            .collect();
+        Some(thresholds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stores `s` as an ATA string at word `index`.
+    fn put_string(data: &mut [u8], index: usize, words: usize, s: &str) {
+        let mut bytes = alloc::vec![b' '; words * 2];
+        bytes[..s.len()].copy_from_slice(s.as_bytes());
+        for (i, pair) in bytes.chunks_exact(2).enumerate() {
+            data[(index + i) * 2] = pair[1];
+            data[(index + i) * 2 + 1] = pair[0];
+        }
+    }
+
+    fn put_word(data: &mut [u8], index: usize, word: u16) {
+        data[index * 2..index * 2 + 2].copy_from_slice(&word.to_le_bytes());
+    }
+
+    fn identify_data() -> [u8; SECTOR_SIZE] {
+        let mut data = [0; SECTOR_SIZE];
+        put_string(&mut data, 10, 10, "AtaPassThru");
+        put_string(&mut data, 23, 4, "2.5+");
+        put_string(&mut data, 27, 20, "QEMU HARDDISK");
+        put_word(&mut data, 49, 0x0300);
+        put_word(&mut data, 60, 0x5000);
+        put_word(&mut data, 82, 0x4023);
+        put_word(&mut data, 83, 0x7400);
+        put_word(&mut data, 84, 0x4002);
+        put_word(&mut data, 85, 0x0021);
+        put_word(&mut data, 87, 0x4000);
+        put_word(&mut data, 89, 30);
+        put_word(&mut data, 100, 0x0000);
+        put_word(&mut data, 101, 0x0001);
+        // 4 KiB physical sectors.
+        put_word(&mut data, 106, 0x6003);
+        put_word(&mut data, 128, 0x0021);
+        put_word(&mut data, 217, 1);
+        data
+    }
+
+    #[test]
+    fn test_identify() {
+        let data = identify_data();
+        let identify = IdentifyDevice::parse(&data).unwrap();
+        assert_eq!(identify.serial_number, "AtaPassThru");
+        assert_eq!(identify.firmware_revision, "2.5+");
+        assert_eq!(identify.model_number, "QEMU HARDDISK");
+        assert_eq!(identify.sectors, 0x10000);
+        assert_eq!(identify.logical_sector_size, 512);
+        assert_eq!(identify.physical_sector_size, 4096);
+        assert_eq!(identify.capacity(), 0x10000 * 512);
+        assert_eq!(
+            identify.features,
+            AtaFeatures::LBA
+                | AtaFeatures::DMA
+                | AtaFeatures::LBA48
+                | AtaFeatures::FLUSH_CACHE_EXT
+                | AtaFeatures::SMART
+                | AtaFeatures::SMART_ENABLED
+                | AtaFeatures::SMART_SELF_TEST
+                | AtaFeatures::SECURITY
+                | AtaFeatures::WRITE_CACHE
+                | AtaFeatures::WRITE_CACHE_ENABLED
+        );
+        assert_eq!(
+            identify.security,
+            SecurityStatus::SUPPORTED | SecurityStatus::ENHANCED_ERASE
+        );
+        assert_eq!(identify.security_erase_time, 60);
+        assert_eq!(identify.rotation_rate, 1);
+    }
+
+    #[test]
+    fn test_identify_sector_size_overflow() {
+        // Logical sector size of 2^32 bytes.
+        let mut data = identify_data();
+        put_word(&mut data, 106, 0x5000);
+        put_word(&mut data, 117, 0x0000);
+        put_word(&mut data, 118, 0x8000);
+        assert_eq!(IdentifyDevice::parse(&data), None);
+
+        // 2^15 logical sectors of 128 KiB per physical sector.
+        put_word(&mut data, 106, 0x700f);
+        put_word(&mut data, 117, 0x0000);
+        put_word(&mut data, 118, 0x0001);
+        assert_eq!(IdentifyDevice::parse(&data), None);
+
+        // Capacity past `u64::MAX`.
+        let mut data = identify_data();
+        for word in 100..104 {
+            put_word(&mut data, word, 0xffff);
+        }
+        let identify = IdentifyDevice::parse(&data).unwrap();
+        assert_eq!(identify.capacity(), u64::MAX);
+    }
+
+    #[test]
+    fn test_identify_checksum() {
+        let mut data = identify_data();
+        data[510] = 0xa5;
+        let sum = data.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+        data[511] = sum.wrapping_neg();
+        assert!(IdentifyDevice::parse(&data).is_some());
+
+        data[0] ^= 1;
+        assert_eq!(IdentifyDevice::parse(&data), None);
+        assert_eq!(IdentifyDevice::parse(&data[..511]), None);
+    }
+
+    #[test]
+    fn test_smart() {
+        let mut data = [0; SECTOR_SIZE];
+        data[2..14].copy_from_slice(&[5, 0x33, 0, 100, 99, 8, 0, 0, 0, 0, 0, 0]);
+        data[14..26].copy_from_slice(&[194, 0x22, 0, 64, 50, 36, 0, 20, 0, 45, 0, 0]);
+        data[363] = 0xf0;
+        let smart = SmartData::parse(&data).unwrap();
+        assert_eq!(smart.attributes.len(), 2);
+        assert_eq!(
+            smart.attribute(194),
+            Some(&SmartAttribute {
+                id: 194,
+                flags: 0x22,
+                value: 64,
+                worst: 50,
+                raw: 0x2d_0014_0024,
+            })
+        );
+        assert_eq!(smart.self_test_status, 0xf0);
+
+        let mut data = [0; SECTOR_SIZE];
+        data[2..4].copy_from_slice(&[5, 100]);
+        data[14..16].copy_from_slice(&[194, 0]);
+        let thresholds = SmartThreshold::parse(&data).unwrap();
+        assert_eq!(thresholds.len(), 2);
+        assert!(smart.attributes[0].is_failing(&thresholds));
+        assert!(!smart.attributes[1].is_failing(&thresholds));
+
+        assert_eq!(SmartData::parse(&data[..511]), None);
+        assert_eq!(SmartThreshold::parse(&data[..511]), None);
+    }
+
+    #[test]
+    fn test_password_block() {
+        let data = password_block(PasswordKind::Master, b"secret").unwrap();
+        assert_eq!(data[0], 1);
+        assert_eq!(&data[2..8], b"secret");
+        assert!(data[8..].iter().all(|b| *b == 0));
+
+        let err = password_block(PasswordKind::User, &[0; 33]).unwrap_err();
+        assert_eq!(err.status(), Status::INVALID_PARAMETER);
+    }
+}
@@ -111,6 +111,11 @@ impl AtaDevice<'_> {
         ptr::from_ref(self.proto).cast_mut()
     }
 
+    /// Returns the alignment requirement of the controller for I/O buffers.
+    pub(super) fn io_align(&self) -> u32 {
+        unsafe { (*self.proto.mode).io_align.max(1) }
+    }
+
     /// Returns the port number of the device.
     ///
     /// # Details
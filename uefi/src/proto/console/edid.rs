@@ -0,0 +1,561 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! EDID (Extended Display Identification Data) protocols and parsing.
+//!
+//! The EDID describes a display: its manufacturer, physical size and the
+//! video modes it supports. The graphics driver installs the EDID protocols
+//! on the handle of each [`GraphicsOutput`] protocol:
+//! - [`EdidDiscovered`] is the EDID read from the display.
+//! - [`EdidActive`] is the EDID used by the driver, which may have been
+//!   replaced by the platform with [`EdidOverride`].
+//!
+//! [`Edid`] parses the base block of an EDID 1.3 or 1.4 structure, and
+//! [`GraphicsOutput::preferred_mode`] selects the mode matching the display.
+//!
+//! # Example
+//!
+//! ```
+//! use uefi::proto::console::edid::EdidActive;
+//! use uefi::proto::console::gop::GraphicsOutput;
+//! use uefi::{Result, boot};
+//!
+//! fn set_native_resolution() -> Result {
+//!     let handle = boot::get_handle_for_protocol::<GraphicsOutput>()?;
+//!     let mut gop = boot::open_protocol_exclusive::<GraphicsOutput>(handle)?;
+//!     let edid = boot::open_protocol_exclusive::<EdidActive>(handle)?;
+//!     let Ok(edid) = edid.edid() else {
+//!         return Ok(());
+//!     };
+//!     if let Some(mode) = gop.preferred_mode(&edid) {
+//!         gop.set_mode(&mode)?;
+//!     }
+//!     Ok(())
+//! }
+//! ```
+//!
+//! [`GraphicsOutput`]: crate::proto::console::gop::GraphicsOutput
+//! [`GraphicsOutput::preferred_mode`]: crate::proto::console::gop::GraphicsOutput::preferred_mode
+
+use crate::proto::unsafe_protocol;
+use crate::{Handle, Result, Status, StatusExt};
+use core::fmt::{self, Display, Formatter};
+use core::{ptr, slice};
+use uefi_raw::protocol::console::edid::{
+    EdidActiveProtocol, EdidDiscoveredProtocol, EdidOverrideProtocol,
+};
+
+pub use uefi_raw::protocol::console::edid::EdidOverrideAttributes;
+
+/// EDID Discovered [`Protocol`]. Contains the EDID read from the display.
+///
+/// [`Protocol`]: uefi::proto::Protocol
+#[derive(Debug)]
+#[repr(transparent)]
+#[unsafe_protocol(EdidDiscoveredProtocol::GUID)]
+pub struct EdidDiscovered(EdidDiscoveredProtocol);
+
+impl EdidDiscovered {
+    /// Returns the raw EDID, which is empty if the display did not provide
+    /// one.
+    #[must_use]
+    pub const fn raw_edid(&self) -> &[u8] {
+        unsafe { edid_slice(self.0.edid, self.0.size_of_edid) }
+    }
+
+    /// Parses the EDID.
+    pub fn edid(&self) -> core::result::Result<Edid<'_>, EdidError> {
+        Edid::parse(self.raw_edid())
+    }
+}
+
+/// EDID Active [`Protocol`]. Contains the EDID used by the graphics driver.
+///
+/// [`Protocol`]: uefi::proto::Protocol
+#[derive(Debug)]
+#[repr(transparent)]
+#[unsafe_protocol(EdidActiveProtocol::GUID)]
+pub struct EdidActive(EdidActiveProtocol);
+
+impl EdidActive {
+    /// Returns the raw EDID, which is empty if there is no active EDID.
+    #[must_use]
+    pub const fn raw_edid(&self) -> &[u8] {
+        unsafe { edid_slice(self.0.edid, self.0.size_of_edid) }
+    }
+
+    /// Parses the EDID.
+    pub fn edid(&self) -> core::result::Result<Edid<'_>, EdidError> {
+        Edid::parse(self.raw_edid())
+    }
+}
+
+/// EDID Override [`Protocol`]. Installed by the platform to replace the EDID
+/// of displays.
+///
+/// [`Protocol`]: uefi::proto::Protocol
+#[derive(Debug)]
+#[repr(transparent)]
+#[unsafe_protocol(EdidOverrideProtocol::GUID)]
+pub struct EdidOverride(EdidOverrideProtocol);
+
+impl EdidOverride {
+    /// Returns the attributes and the replacement EDID for the display of
+    /// `child_handle`, or `None` if there is no override for it.
+    ///
+    /// The EDID is empty if only the attributes are overridden.
+    ///
+    /// # Errors
+    ///
+    /// - [`Status::INVALID_PARAMETER`] if `child_handle` is invalid
+    pub fn get_edid(
+        &self,
+        child_handle: Handle,
+    ) -> Result<Option<(EdidOverrideAttributes, &[u8])>> {
+        let mut attributes = EdidOverrideAttributes::empty();
+        let mut size = 0;
+        let mut edid = ptr::null();
+        let status = unsafe {
+            (self.0.get_edid)(
+                &self.0,
+                &child_handle.as_ptr(),
+                &mut attributes,
+                &mut size,
+                &mut edid,
+            )
+        };
+        match status {
+            Status::UNSUPPORTED => Ok(None),
+            status => status.to_result_with_val(|| {
+                let edid = if edid.is_null() {
+                    &[][..]
+                } else {
+                    unsafe { slice::from_raw_parts(edid, size) }
+                };
+                Some((attributes, edid))
+            }),
+        }
+    }
+}
+
+const unsafe fn edid_slice<'a>(edid: *const u8, size: u32) -> &'a [u8] {
+    if edid.is_null() {
+        &[]
+    } else {
+        unsafe { slice::from_raw_parts(edid, size as usize) }
+    }
+}
+
+/// Error returned when parsing an [`Edid`] fails.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EdidError {
+    /// The data is shorter than the 128-byte base block.
+    TooShort,
+
+    /// The data does not start with the EDID header.
+    InvalidHeader,
+
+    /// The checksum of the base block is wrong.
+    InvalidChecksum,
+}
+
+impl Display for EdidError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::TooShort => "EDID is too short",
+            Self::InvalidHeader => "invalid EDID header",
+            Self::InvalidChecksum => "invalid EDID checksum",
+        };
+        f.write_str(s)
+    }
+}
+
+impl core::error::Error for EdidError {}
+
+/// Parsed EDID base block.
+///
+/// Extension blocks, like CTA-861 blocks, are not parsed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Edid<'a> {
+    data: &'a [u8; EDID_BLOCK_SIZE],
+}
+
+const EDID_BLOCK_SIZE: usize = 128;
+const EDID_HEADER: [u8; 8] = [0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00];
+
+impl<'a> Edid<'a> {
+    /// Parses the base block at the start of `data`, checking its header and
+    /// checksum.
+    pub fn parse(data: &'a [u8]) -> core::result::Result<Self, EdidError> {
+        let data: &[u8; EDID_BLOCK_SIZE] = data
+            .get(..EDID_BLOCK_SIZE)
+            .and_then(|data| data.try_into().ok())
+            .ok_or(EdidError::TooShort)?;
+        if data[..8] != EDID_HEADER {
+            return Err(EdidError::InvalidHeader);
+        }
+        if data.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte)) != 0 {
+            return Err(EdidError::InvalidChecksum);
+        }
+        Ok(Self { data })
+    }
+
+    /// Returns the three-letter manufacturer ID, like `*b"DEL"`.
+    #[must_use]
+    pub fn manufacturer_id(&self) -> [u8; 3] {
+        let id = u16::from_be_bytes([self.data[8], self.data[9]]);
+        let letter = |shift: u16| b'A' - 1 + ((id >> shift) & 0x1f) as u8;
+        [letter(10), letter(5), letter(0)]
+    }
+
+    /// Returns the manufacturer's product code.
+    #[must_use]
+    pub const fn product_code(&self) -> u16 {
+        u16::from_le_bytes([self.data[10], self.data[11]])
+    }
+
+    /// Returns the serial number, or 0 if not used.
+    #[must_use]
+    pub const fn serial_number(&self) -> u32 {
+        u32::from_le_bytes([self.data[12], self.data[13], self.data[14], self.data[15]])
+    }
+
+    /// Returns the week and year of manufacture. The week is 0 if unknown,
+    /// and 255 if the year is the model year.
+    #[must_use]
+    pub const fn manufacture_date(&self) -> (u8, u16) {
+        (self.data[16], 1990 + self.data[17] as u16)
+    }
+
+    /// Returns the EDID version and revision, like `(1, 4)`.
+    #[must_use]
+    pub const fn version(&self) -> (u8, u8) {
+        (self.data[18], self.data[19])
+    }
+
+    /// Returns the width and height of the screen in centimeters, or `None`
+    /// if unknown.
+    #[must_use]
+    pub const fn physical_size(&self) -> Option<(u8, u8)> {
+        match (self.data[21], self.data[22]) {
+            (0, _) | (_, 0) => None,
+            size => Some(size),
+        }
+    }
+
+    /// Returns the number of extension blocks following the base block.
+    #[must_use]
+    pub const fn extension_count(&self) -> u8 {
+        self.data[126]
+    }
+
+    /// Returns the display name, if present.
+    #[must_use]
+    pub fn name(&self) -> Option<&'a str> {
+        self.descriptor_text(0xfc)
+    }
+
+    /// Returns the serial number as text, if present.
+    #[must_use]
+    pub fn serial_number_text(&self) -> Option<&'a str> {
+        self.descriptor_text(0xff)
+    }
+
+    /// Returns the detailed timings, in order of preference.
+    pub fn detailed_timings(&self) -> impl Iterator<Item = DetailedTiming> + 'a {
+        self.descriptors().filter_map(DetailedTiming::parse)
+    }
+
+    /// Returns the preferred timing, which is the native resolution of
+    /// flat panel displays.
+    #[must_use]
+    pub fn preferred_timing(&self) -> Option<DetailedTiming> {
+        self.descriptors().next().and_then(DetailedTiming::parse)
+    }
+
+    /// Returns the video modes supported by the display, from the detailed,
+    /// standard and established timings. The list may contain duplicates.
+    pub fn supported_modes(&self) -> impl Iterator<Item = VideoMode> + 'a {
+        let data = self.data;
+        let detailed = self.detailed_timings().map(|timing| timing.mode());
+        let standard = data[38..54].chunks_exact(2).filter_map(|timing| {
+            if timing == [0x01, 0x01] || timing[0] == 0 {
+                return None;
+            }
+            let width = (usize::from(timing[0]) + 31) * 8;
+            let height = match timing[1] >> 6 {
+                0 if data[18] == 1 && data[19] < 3 => width,
+                0 => width * 10 / 16,
+                1 => width * 3 / 4,
+                2 => width * 4 / 5,
+                _ => width * 9 / 16,
+            };
+            Some(VideoMode {
+                resolution: (width, height),
+                refresh_rate: u32::from(timing[1] & 0x3f) + 60,
+            })
+        });
+        let established = ESTABLISHED_TIMINGS
+            .iter()
+            .enumerate()
+            .filter(move |(bit, _)| data[35 + bit / 8] & (0x80 >> (bit % 8)) != 0)
+            .map(|(_, &(width, height, refresh_rate))| VideoMode {
+                resolution: (width, height),
+                refresh_rate,
+            });
+        detailed.chain(standard).chain(established)
+    }
+
+    /// Returns whether the display supports the given resolution.
+    #[must_use]
+    pub fn supports_resolution(&self, resolution: (usize, usize)) -> bool {
+        self.supported_modes()
+            .any(|mode| mode.resolution == resolution)
+    }
+
+    /// Returns the four 18-byte descriptors.
+    fn descriptors(&self) -> impl Iterator<Item = &'a [u8; 18]> + 'a {
+        self.data[54..126]
+            .chunks_exact(18)
+            .map(|descriptor| descriptor.try_into().unwrap())
+    }
+
+    fn descriptor_text(&self, tag: u8) -> Option<&'a str> {
+        let descriptor = self
+            .descriptors()
+            .find(|descriptor| descriptor[..3] == [0, 0, 0] && descriptor[3] == tag)?;
+        let text = &descriptor[5..];
+        let end = text
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .unwrap_or(text.len());
+        core::str::from_utf8(&text[..end]).ok().map(str::trim_end)
+    }
+}
+
+/// Established timings, in the order of their bits starting at byte 35.
+const ESTABLISHED_TIMINGS: [(usize, usize, u32); 17] = [
+    (720, 400, 70),
+    (720, 400, 88),
+    (640, 480, 60),
+    (640, 480, 67),
+    (640, 480, 72),
+    (640, 480, 75),
+    (800, 600, 56),
+    (800, 600, 60),
+    (800, 600, 72),
+    (800, 600, 75),
+    (832, 624, 75),
+    (1024, 768, 87),
+    (1024, 768, 60),
+    (1024, 768, 70),
+    (1024, 768, 75),
+    (1280, 1024, 75),
+    (1152, 870, 75),
+];
+
+/// Resolution and refresh rate of a video mode supported by a display.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct VideoMode {
+    /// Width and height in pixels.
+    pub resolution: (usize, usize),
+
+    /// Refresh rate in Hz.
+    pub refresh_rate: u32,
+}
+
+/// Detailed timing descriptor of an [`Edid`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DetailedTiming {
+    /// Pixel clock in kHz.
+    pub pixel_clock: u32,
+    /// Number of visible pixels per line.
+    pub horizontal_active: u16,
+    /// Number of blanking pixels per line.
+    pub horizontal_blanking: u16,
+    /// Number of visible lines.
+    pub vertical_active: u16,
+    /// Number of blanking lines.
+    pub vertical_blanking: u16,
+    /// Width of the image in millimeters, or 0 if unknown.
+    pub width_mm: u16,
+    /// Height of the image in millimeters, or 0 if unknown.
+    pub height_mm: u16,
+    /// Whether the mode is interlaced.
+    pub interlaced: bool,
+}
+
+impl DetailedTiming {
+    fn parse(d: &[u8; 18]) -> Option<Self> {
+        let pixel_clock = u16::from_le_bytes([d[0], d[1]]);
+        if pixel_clock == 0 {
+            // Display descriptor.
+            return None;
+        }
+        let join = |low: u8, high: u8| u16::from(low) | (u16::from(high) << 8);
+        Some(Self {
+            pixel_clock: u32::from(pixel_clock) * 10,
+            horizontal_active: join(d[2], d[4] >> 4),
+            horizontal_blanking: join(d[3], d[4] & 0xf),
+            vertical_active: join(d[5], d[7] >> 4),
+            vertical_blanking: join(d[6], d[7] & 0xf),
+            width_mm: join(d[12], d[14] >> 4),
+            height_mm: join(d[13], d[14] & 0xf),
+            interlaced: d[17] & 0x80 != 0,
+        })
+    }
+
+    /// Returns the width and height in pixels.
+    #[must_use]
+    pub fn resolution(&self) -> (usize, usize) {
+        (
+            usize::from(self.horizontal_active),
+            usize::from(self.vertical_active),
+        )
+    }
+
+    /// Returns the refresh rate in mHz.
+    #[must_use]
+    pub fn refresh_rate_millihertz(&self) -> u32 {
+        let total = u64::from(self.horizontal_active + self.horizontal_blanking)
+            * u64::from(self.vertical_active + self.vertical_blanking);
+        if total == 0 {
+            return 0;
+        }
+        (u64::from(self.pixel_clock) * 1_000_000 / total) as u32
+    }
+
+    /// Returns the resolution and the refresh rate, rounded to Hz.
+    #[must_use]
+    pub fn mode(&self) -> VideoMode {
+        VideoMode {
+            resolution: self.resolution(),
+            refresh_rate: (self.refresh_rate_millihertz() + 500) / 1000,
+        }
+    }
+}
+
+/// Selects the item whose resolution matches the preferred timing of `edid`,
+/// or else the largest item whose resolution is supported by the display.
+pub(crate) fn select_mode<T>(
+    items: impl Iterator<Item = T>,
+    resolution: impl Fn(&T) -> (usize, usize),
+    edid: &Edid<'_>,
+) -> Option<T> {
+    let preferred = edid.preferred_timing().map(|timing| timing.resolution());
+    let mut best: Option<(bool, usize, T)> = None;
+    for item in items {
+        let res = resolution(&item);
+        let is_preferred = Some(res) == preferred;
+        if !is_preferred && !edid.supports_resolution(res) {
+            continue;
+        }
+        let key = (is_preferred, res.0 * res.1);
+        if best.as_ref().is_none_or(|(p, area, _)| key > (*p, *area)) {
+            best = Some((key.0, key.1, item));
+        }
+    }
+    best.map(|(_, _, item)| item)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the EDID of a 1920x1080 monitor with a size of 53x30 cm.
+    fn sample_edid() -> [u8; 128] {
+        let mut edid = [0; 128];
+        edid[..8].copy_from_slice(&EDID_HEADER);
+        // "DEL", product 0xa0c3, serial 0x12345678, week 20 of 2022.
+        edid[8..20]
+            .copy_from_slice(&[0x10, 0xac, 0xc3, 0xa0, 0x78, 0x56, 0x34, 0x12, 20, 32, 1, 4]);
+        edid[21] = 53;
+        edid[22] = 30;
+        // 640x480@60 and 1024x768@60.
+        edid[35] = 0x20;
+        edid[36] = 0x08;
+        // 1280x1024@60 (5:4) and 1680x1050@60 (16:10), the rest unused.
+        edid[38..54].copy_from_slice(&[0x81, 0x80, 0xb3, 0x00, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1]);
+        // 1920x1080 at 148.5 MHz, 60 Hz.
+        edid[54..72].copy_from_slice(&[
+            0x02, 0x3a, 0x80, 0x18, 0x71, 0x38, 0x2d, 0x40, 0x58, 0x2c, 0x45, 0x00, 0x0f, 0x28,
+            0x21, 0x00, 0x00, 0x1e,
+        ]);
+        edid[72..90].copy_from_slice(b"\0\0\0\xfc\0DELL P2422H\n ");
+        edid[90..108].copy_from_slice(b"\0\0\0\x10\0\0\0\0\0\0\0\0\0\0\0\0\0\0");
+        edid[108..126].copy_from_slice(b"\0\0\0\xff\0ABC123\n      ");
+        edid[127] = 0u8.wrapping_sub(edid.iter().fold(0u8, |sum, &b| sum.wrapping_add(b)));
+        edid
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let mut edid = sample_edid();
+        assert_eq!(Edid::parse(&edid[..127]), Err(EdidError::TooShort));
+        edid[100] ^= 1;
+        assert_eq!(Edid::parse(&edid), Err(EdidError::InvalidChecksum));
+        edid[0] = 1;
+        assert_eq!(Edid::parse(&edid), Err(EdidError::InvalidHeader));
+    }
+
+    #[test]
+    fn test_monitor_info() {
+        let data = sample_edid();
+        let edid = Edid::parse(&data).unwrap();
+        assert_eq!(&edid.manufacturer_id(), b"DEL");
+        assert_eq!(edid.product_code(), 0xa0c3);
+        assert_eq!(edid.serial_number(), 0x1234_5678);
+        assert_eq!(edid.manufacture_date(), (20, 2022));
+        assert_eq!(edid.version(), (1, 4));
+        assert_eq!(edid.physical_size(), Some((53, 30)));
+        assert_eq!(edid.name(), Some("DELL P2422H"));
+        assert_eq!(edid.serial_number_text(), Some("ABC123"));
+        assert_eq!(edid.extension_count(), 0);
+    }
+
+    #[test]
+    fn test_timings() {
+        let data = sample_edid();
+        let edid = Edid::parse(&data).unwrap();
+        let timing = edid.preferred_timing().unwrap();
+        assert_eq!(timing.pixel_clock, 148_500);
+        assert_eq!(timing.resolution(), (1920, 1080));
+        assert_eq!(
+            (timing.horizontal_blanking, timing.vertical_blanking),
+            (280, 45)
+        );
+        assert_eq!((timing.width_mm, timing.height_mm), (527, 296));
+        assert_eq!(timing.refresh_rate_millihertz(), 60_000);
+        assert_eq!(edid.detailed_timings().count(), 1);
+
+        let modes: alloc::vec::Vec<_> = edid
+            .supported_modes()
+            .map(|mode| (mode.resolution, mode.refresh_rate))
+            .collect();
+        assert_eq!(
+            modes,
+            [
+                ((1920, 1080), 60),
+                ((1280, 1024), 60),
+                ((1680, 1050), 60),
+                ((640, 480), 60),
+                ((1024, 768), 60),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_select_mode() {
+        let data = sample_edid();
+        let edid = Edid::parse(&data).unwrap();
+        let select = |modes: &[(usize, usize)]| select_mode(modes.iter().copied(), |&m| m, &edid);
+        assert_eq!(
+            select(&[(640, 480), (2560, 1440), (1920, 1080), (1024, 768)]),
+            Some((1920, 1080))
+        );
+        assert_eq!(
+            select(&[(640, 480), (2560, 1440), (1280, 1024), (1024, 768)]),
+            Some((1280, 1024))
+        );
+        assert_eq!(select(&[(800, 600), (2560, 1440)]), None);
+    }
+}
@@ -0,0 +1,481 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Off-screen drawing with damage tracking. See [`Canvas`].
+
+use super::pixel::RawFrameBuffer;
+use super::{BltOp, BltPixel, BltRegion, GraphicsOutput, Image, ModeInfo};
+use crate::Result;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Number of damaged regions tracked separately before they are merged into
+/// their bounding box.
+const MAX_DAMAGE: usize = 16;
+
+/// Rectangle in pixel coordinates.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Rect {
+    /// X coordinate of the left edge.
+    pub x: usize,
+    /// Y coordinate of the top edge.
+    pub y: usize,
+    /// Width in pixels.
+    pub width: usize,
+    /// Height in pixels.
+    pub height: usize,
+}
+
+impl Rect {
+    /// Creates a new rectangle.
+    #[must_use]
+    pub const fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Returns whether the rectangle contains no pixels.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// Returns the exclusive end coordinates.
+    const fn end(&self) -> (usize, usize) {
+        (
+            self.x.saturating_add(self.width),
+            self.y.saturating_add(self.height),
+        )
+    }
+
+    /// Returns the part of the rectangle that is also part of `other`.
+    #[must_use]
+    pub fn intersection(&self, other: &Self) -> Self {
+        let (x, y) = (self.x.max(other.x), self.y.max(other.y));
+        let (x_end, y_end) = (
+            self.end().0.min(other.end().0),
+            self.end().1.min(other.end().1),
+        );
+        Self::new(x, y, x_end.saturating_sub(x), y_end.saturating_sub(y))
+    }
+
+    /// Returns the smallest rectangle containing both rectangles.
+    #[must_use]
+    pub fn union(&self, other: &Self) -> Self {
+        if self.is_empty() {
+            return *other;
+        }
+        if other.is_empty() {
+            return *self;
+        }
+        let (x, y) = (self.x.min(other.x), self.y.min(other.y));
+        let (x_end, y_end) = (
+            self.end().0.max(other.end().0),
+            self.end().1.max(other.end().1),
+        );
+        Self::new(x, y, x_end - x, y_end - y)
+    }
+
+    /// Returns whether the rectangles overlap or share an edge.
+    const fn touches(&self, other: &Self) -> bool {
+        self.x <= other.end().0
+            && other.x <= self.end().0
+            && self.y <= other.end().1
+            && other.y <= self.end().1
+    }
+}
+
+/// Off-screen buffer for drawing, which is copied to the screen with
+/// [`flush`].
+///
+/// All drawing operations are clipped to the canvas. The canvas keeps track
+/// of the regions that were changed since the last flush, so only those are
+/// copied to the screen.
+///
+/// ```no_run
+/// use uefi::boot;
+/// use uefi::proto::console::gop::{BltPixel, Canvas, GraphicsOutput, Image, Rect};
+///
+/// # fn main() -> uefi::Result {
+/// # let logo_file = &[];
+/// let handle = boot::get_handle_for_protocol::<GraphicsOutput>()?;
+/// let mut gop = boot::open_protocol_exclusive::<GraphicsOutput>(handle)?;
+///
+/// let mut canvas = Canvas::for_mode(&gop.current_mode_info());
+/// canvas.clear(BltPixel::new(0, 0, 0));
+/// let logo = Image::decode(logo_file).expect("invalid logo");
+/// canvas.draw_image(&logo, (100, 100));
+/// canvas.flush(&mut gop, (0, 0))?;
+///
+/// // Only the progress bar is copied to the screen.
+/// canvas.fill_rect(Rect::new(100, 400, 50, 8), BltPixel::new(255, 255, 255));
+/// canvas.flush(&mut gop, (0, 0))?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`flush`]: Self::flush
+#[derive(Clone, Debug)]
+pub struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<BltPixel>,
+    damage: Vec<Rect>,
+}
+
+impl Canvas {
+    /// Creates a black canvas of the given size.
+    #[must_use]
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![BltPixel::new(0, 0, 0); width * height],
+            damage: Vec::new(),
+        }
+    }
+
+    /// Creates a black canvas covering the whole screen in the given mode.
+    #[must_use]
+    pub fn for_mode(info: &ModeInfo) -> Self {
+        let (width, height) = info.resolution();
+        Self::new(width, height)
+    }
+
+    /// Returns the width of the canvas in pixels.
+    #[must_use]
+    pub const fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of the canvas in pixels.
+    #[must_use]
+    pub const fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the pixels of the canvas row by row.
+    #[must_use]
+    pub fn pixels(&self) -> &[BltPixel] {
+        &self.pixels
+    }
+
+    /// Returns the pixel at (`x`, `y`), or `None` if it's outside of the
+    /// canvas.
+    #[must_use]
+    pub fn pixel(&self, x: usize, y: usize) -> Option<BltPixel> {
+        (x < self.width && y < self.height).then(|| self.pixels[y * self.width + x])
+    }
+
+    /// Returns the regions changed since the last flush.
+    #[must_use]
+    pub fn damage(&self) -> &[Rect] {
+        &self.damage
+    }
+
+    /// Marks the whole canvas as changed, so that the next flush copies all
+    /// of it to the screen.
+    pub fn invalidate(&mut self) {
+        self.damage.clear();
+        self.damage.push(self.bounds());
+    }
+
+    /// Fills the whole canvas with `color`.
+    pub fn clear(&mut self, color: BltPixel) {
+        self.fill_rect(self.bounds(), color);
+    }
+
+    /// Sets the pixel at (`x`, `y`) to `color`.
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: BltPixel) {
+        self.fill_rect(Rect::new(x, y, 1, 1), color);
+    }
+
+    /// Fills `rect` with `color`.
+    pub fn fill_rect(&mut self, rect: Rect, color: BltPixel) {
+        let rect = rect.intersection(&self.bounds());
+        for y in rect.y..rect.y + rect.height {
+            let start = y * self.width + rect.x;
+            self.pixels[start..start + rect.width].fill(color);
+        }
+        self.add_damage(rect);
+    }
+
+    /// Draws the one pixel wide outline of `rect` in `color`.
+    pub fn draw_rect(&mut self, rect: Rect, color: BltPixel) {
+        if rect.is_empty() {
+            return;
+        }
+        let (right, bottom) = (rect.end().0 - 1, rect.end().1 - 1);
+        self.fill_rect(Rect::new(rect.x, rect.y, rect.width, 1), color);
+        self.fill_rect(Rect::new(rect.x, bottom, rect.width, 1), color);
+        self.fill_rect(Rect::new(rect.x, rect.y, 1, rect.height), color);
+        self.fill_rect(Rect::new(right, rect.y, 1, rect.height), color);
+    }
+
+    /// Draws a one pixel wide line from `start` to `end`, both inclusive.
+    pub fn draw_line(&mut self, start: (usize, usize), end: (usize, usize), color: BltPixel) {
+        // Bresenham's algorithm, working for all octants.
+        let (mut x, mut y) = (start.0 as isize, start.1 as isize);
+        let (x_end, y_end) = (end.0 as isize, end.1 as isize);
+        let dx = (x_end - x).abs();
+        let dy = -(y_end - y).abs();
+        let (step_x, step_y) = ((x_end - x).signum(), (y_end - y).signum());
+        let mut error = dx + dy;
+        loop {
+            if let Some(pixel) = self.pixel_mut(x as usize, y as usize) {
+                *pixel = color;
+            }
+            if x == x_end && y == y_end {
+                break;
+            }
+            let e2 = 2 * error;
+            if e2 >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if e2 <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+
+        let (x0, x1) = (start.0.min(end.0), start.0.max(end.0));
+        let (y0, y1) = (start.1.min(end.1), start.1.max(end.1));
+        self.add_damage(Rect::new(x0, y0, x1 - x0 + 1, y1 - y0 + 1).intersection(&self.bounds()));
+    }
+
+    /// Draws `image` with its top left corner at `pos`.
+    ///
+    /// If the image has an alpha channel, it is blended with the existing
+    /// contents of the canvas.
+    pub fn draw_image(&mut self, image: &Image, pos: (usize, usize)) {
+        let rect =
+            Rect::new(pos.0, pos.1, image.width(), image.height()).intersection(&self.bounds());
+        for y in 0..rect.height {
+            let src = y * image.width();
+            let dest = (rect.y + y) * self.width + rect.x;
+            let src_pixels = &image.pixels()[src..src + rect.width];
+            let dest_pixels = &mut self.pixels[dest..dest + rect.width];
+            match image.alpha() {
+                None => dest_pixels.copy_from_slice(src_pixels),
+                Some(alpha) => {
+                    let alpha = &alpha[src..src + rect.width];
+                    for ((dest, src), &alpha) in dest_pixels.iter_mut().zip(src_pixels).zip(alpha) {
+                        *dest = blend(*dest, *src, alpha);
+                    }
+                }
+            }
+        }
+        self.add_damage(rect);
+    }
+
+    /// Copies the changed regions of the canvas to the screen using
+    /// [`GraphicsOutput::blt`], with the canvas placed at `dest`.
+    ///
+    /// This works in all graphics modes, as the firmware converts the pixels
+    /// to the format of the frame buffer. Parts of the canvas outside of the
+    /// screen are skipped.
+    pub fn flush(&mut self, gop: &mut GraphicsOutput, dest: (usize, usize)) -> Result {
+        let (width, height) = gop.current_mode_info().resolution();
+        let screen = Rect::new(0, 0, width, height);
+        for rect in &self.damage {
+            let on_screen = Rect::new(dest.0 + rect.x, dest.1 + rect.y, rect.width, rect.height)
+                .intersection(&screen);
+            if on_screen.is_empty() {
+                continue;
+            }
+            gop.blt(BltOp::BufferToVideo {
+                buffer: &self.pixels,
+                src: BltRegion::SubRectangle {
+                    coords: (on_screen.x - dest.0, on_screen.y - dest.1),
+                    px_stride: self.width,
+                },
+                dest: (on_screen.x, on_screen.y),
+                dims: (on_screen.width, on_screen.height),
+            })?;
+        }
+        self.damage.clear();
+        Ok(())
+    }
+
+    /// Copies the changed regions of the canvas directly to a frame buffer of
+    /// `size` bytes at `frame_buffer`, laid out as described by `info`, with
+    /// the canvas placed at `dest`.
+    ///
+    /// Pixels are converted to the [`PixelFormat`] of the mode, including
+    /// custom bit masks. Unlike [`flush`], this does not require boot
+    /// services.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the mode does not allow direct frame buffer
+    ///   access.
+    /// * [`Status::BAD_BUFFER_SIZE`]: `size` is too small for the mode.
+    ///
+    /// # Safety
+    ///
+    /// `frame_buffer` must be valid for reads and writes of `size` bytes.
+    ///
+    /// [`PixelFormat`]: super::PixelFormat
+    /// [`flush`]: Self::flush
+    /// [`Status::UNSUPPORTED`]: crate::Status::UNSUPPORTED
+    /// [`Status::BAD_BUFFER_SIZE`]: crate::Status::BAD_BUFFER_SIZE
+    pub unsafe fn flush_to_frame_buffer(
+        &mut self,
+        frame_buffer: *mut u8,
+        size: usize,
+        info: &ModeInfo,
+        dest: (usize, usize),
+    ) -> Result {
+        let mut frame_buffer = unsafe { RawFrameBuffer::new(frame_buffer, size, info) }?;
+        for rect in &self.damage {
+            for y in rect.y..rect.y + rect.height {
+                for x in rect.x..rect.x + rect.width {
+                    let value = frame_buffer.encode(self.pixels[y * self.width + x]);
+                    frame_buffer.write(dest.0 + x, dest.1 + y, value);
+                }
+            }
+        }
+        self.damage.clear();
+        Ok(())
+    }
+
+    const fn bounds(&self) -> Rect {
+        Rect::new(0, 0, self.width, self.height)
+    }
+
+    fn pixel_mut(&mut self, x: usize, y: usize) -> Option<&mut BltPixel> {
+        (x < self.width && y < self.height).then(|| &mut self.pixels[y * self.width + x])
+    }
+
+    /// Records that `rect` was changed, merging it with overlapping or
+    /// adjacent regions.
+    fn add_damage(&mut self, mut rect: Rect) {
+        if rect.is_empty() {
+            return;
+        }
+        // Merging may make the rectangle touch regions it didn't before, so
+        // repeat until nothing changes.
+        while let Some(index) = self.damage.iter().position(|other| other.touches(&rect)) {
+            rect = rect.union(&self.damage.swap_remove(index));
+        }
+        if self.damage.len() == MAX_DAMAGE {
+            rect = self
+                .damage
+                .drain(..)
+                .fold(rect, |acc, other| acc.union(&other));
+        }
+        self.damage.push(rect);
+    }
+}
+
+/// Blends `src` over `dest` with the opacity `alpha`.
+fn blend(dest: BltPixel, src: BltPixel, alpha: u8) -> BltPixel {
+    let mix = |dest: u8, src: u8| {
+        let alpha = u16::from(alpha);
+        ((u16::from(src) * alpha + u16::from(dest) * (255 - alpha) + 127) / 255) as u8
+    };
+    BltPixel::new(
+        mix(dest.red, src.red),
+        mix(dest.green, src.green),
+        mix(dest.blue, src.blue),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::console::gop::PixelBitmask;
+    use crate::proto::console::gop::pixel::tests::mode_info;
+    use uefi_raw::protocol::console::GraphicsPixelFormat;
+
+    const WHITE: BltPixel = BltPixel::new(255, 255, 255);
+    const BLACK: BltPixel = BltPixel::new(0, 0, 0);
+
+    #[test]
+    fn test_rect() {
+        let a = Rect::new(0, 0, 4, 4);
+        let b = Rect::new(2, 3, 4, 4);
+        assert_eq!(a.intersection(&b), Rect::new(2, 3, 2, 1));
+        assert_eq!(a.union(&b), Rect::new(0, 0, 6, 7));
+        assert!(a.intersection(&Rect::new(5, 5, 1, 1)).is_empty());
+        assert!(a.touches(&Rect::new(4, 0, 1, 1)));
+        assert!(!a.touches(&Rect::new(5, 0, 1, 1)));
+    }
+
+    #[test]
+    fn test_damage() {
+        let mut canvas = Canvas::new(100, 100);
+        assert!(canvas.damage().is_empty());
+
+        canvas.set_pixel(1, 1, WHITE);
+        canvas.fill_rect(Rect::new(50, 50, 10, 10), WHITE);
+        assert_eq!(
+            canvas.damage(),
+            [Rect::new(1, 1, 1, 1), Rect::new(50, 50, 10, 10)]
+        );
+
+        // Bridges both regions, so everything is merged.
+        canvas.draw_line((1, 2), (50, 49), WHITE);
+        assert_eq!(canvas.damage(), [Rect::new(1, 1, 59, 59)]);
+
+        // Clipped to the canvas.
+        canvas.invalidate();
+        canvas.fill_rect(Rect::new(90, 90, 20, 20), BLACK);
+        assert_eq!(canvas.damage(), [Rect::new(0, 0, 100, 100)]);
+
+        // Too many separate regions are merged into one.
+        let mut canvas = Canvas::new(100, 100);
+        for i in 0..=MAX_DAMAGE {
+            canvas.set_pixel(i * 2, i * 2, WHITE);
+        }
+        assert_eq!(canvas.damage(), [Rect::new(0, 0, 33, 33)]);
+    }
+
+    #[test]
+    fn test_draw_line() {
+        let mut canvas = Canvas::new(5, 3);
+        canvas.draw_line((4, 2), (0, 0), WHITE);
+        let lit: Vec<_> = (0..3)
+            .flat_map(|y| (0..5).map(move |x| (x, y)))
+            .filter(|&(x, y)| canvas.pixel(x, y) == Some(WHITE))
+            .collect();
+        assert_eq!(lit, [(0, 0), (1, 0), (2, 1), (3, 1), (4, 2)]);
+    }
+
+    #[test]
+    fn test_draw_image() {
+        let mut canvas = Canvas::new(3, 2);
+        canvas.clear(BltPixel::new(0, 0, 200));
+        let image = Image::new(2, 2, vec![WHITE; 4]).with_alpha(vec![255, 0, 128, 255]);
+        canvas.draw_image(&image, (2, 0));
+        assert_eq!(canvas.pixel(2, 0), Some(WHITE));
+        assert_eq!(canvas.pixel(2, 1), Some(BltPixel::new(128, 128, 228)));
+        assert_eq!(canvas.pixel(1, 0), Some(BltPixel::new(0, 0, 200)));
+
+        canvas.draw_rect(Rect::new(0, 0, 3, 2), BLACK);
+        assert!(canvas.pixels().iter().all(|&p| p == BLACK));
+    }
+
+    #[test]
+    fn test_flush_to_frame_buffer() {
+        let mask = PixelBitmask {
+            red: 0x0f00,
+            green: 0x00f0,
+            blue: 0x000f,
+            reserved: 0xf000,
+        };
+        let info = mode_info((4, 2), 4, GraphicsPixelFormat::PIXEL_BIT_MASK, mask);
+        let mut buf = [0u16; 8];
+
+        let mut canvas = Canvas::new(2, 2);
+        canvas.set_pixel(1, 0, BltPixel::new(255, 0, 255));
+        unsafe { canvas.flush_to_frame_buffer(buf.as_mut_ptr().cast(), 16, &info, (2, 1)) }
+            .unwrap();
+        assert!(canvas.damage().is_empty());
+        assert_eq!(buf, [0, 0, 0, 0, 0, 0, 0, 0x0f0f]);
+    }
+}
@@ -0,0 +1,403 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Text console rendering to the frame buffer. See [`GraphicsConsole`].
+
+use super::font::{self, GLYPH_HEIGHT, GLYPH_WIDTH};
+use super::pixel::RawFrameBuffer;
+use super::{BltPixel, GraphicsOutput, ModeInfo, PixelFormat};
+use crate::{Result, Status};
+use core::fmt;
+
+/// Number of columns between tab stops.
+const TAB_WIDTH: usize = 8;
+
+/// Text console drawing directly to the frame buffer of a [`GraphicsOutput`].
+///
+/// The console renders text with a built-in 8x16 bitmap font. It supports
+/// printable ASCII characters as well as `\n`, `\r`, `\t` and backspace
+/// (`\x08`); all other characters are shown as `?`. Text wraps at the end of
+/// a line, and the screen scrolls up once the last line is full.
+///
+/// The mode information and the address of the frame buffer are captured
+/// when the console is created. The protocol is not used afterwards, so the
+/// console keeps working after [`exit_boot_services`] as long as the firmware
+/// leaves the frame buffer in place. Changing the graphics mode invalidates
+/// the console.
+///
+/// Modes without direct frame buffer access ([`PixelFormat::BltOnly`]) are
+/// not supported.
+///
+/// With the `logger` feature, the console can also be used as a [`LogSink`].
+///
+/// ```no_run
+/// use core::fmt::Write;
+/// use uefi::boot;
+/// use uefi::proto::console::gop::{GraphicsConsole, GraphicsOutput};
+/// use uefi::proto::console::text::Color;
+///
+/// # fn main() -> uefi::Result {
+/// let handle = boot::get_handle_for_protocol::<GraphicsOutput>()?;
+/// let mut gop = boot::open_protocol_exclusive::<GraphicsOutput>(handle)?;
+///
+/// let mut console = GraphicsConsole::new(&mut gop)?;
+/// console.set_color(Color::Yellow.into(), Color::Black.into());
+/// console.clear();
+/// writeln!(console, "Hello from the frame buffer!").unwrap();
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`exit_boot_services`]: crate::boot::exit_boot_services
+/// [`LogSink`]: crate::helpers::logger::LogSink
+#[derive(Debug)]
+pub struct GraphicsConsole {
+    frame_buffer: RawFrameBuffer,
+    columns: usize,
+    rows: usize,
+    column: usize,
+    row: usize,
+    foreground: BltPixel,
+    background: BltPixel,
+    cursor_enabled: bool,
+    /// Whether the cursor is currently drawn on the screen.
+    cursor_drawn: bool,
+}
+
+impl GraphicsConsole {
+    /// Creates a console for the current mode of `gop`.
+    ///
+    /// The screen is not cleared, call [`clear`] for that. Initially, the
+    /// cursor is hidden and text is drawn light gray on black.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the current mode does not allow direct
+    ///   frame buffer access, or is too small to fit a single character.
+    ///
+    /// [`clear`]: Self::clear
+    pub fn new(gop: &mut GraphicsOutput) -> Result<Self> {
+        let info = gop.current_mode_info();
+        if info.pixel_format() == PixelFormat::BltOnly {
+            return Err(Status::UNSUPPORTED.into());
+        }
+
+        let mut frame_buffer = gop.frame_buffer();
+        // SAFETY: the frame buffer matches the current mode, and stays valid
+        // until the mode is changed.
+        unsafe { Self::from_raw_parts(frame_buffer.as_mut_ptr(), frame_buffer.size(), &info) }
+    }
+
+    /// Creates a console for a frame buffer of `size` bytes at `base`, laid
+    /// out as described by `info`.
+    ///
+    /// This is useful if only the frame buffer information was kept after
+    /// exiting boot services.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: `info` does not allow direct frame buffer
+    ///   access, or the screen is too small to fit a single character.
+    /// * [`Status::BAD_BUFFER_SIZE`]: `size` is too small for the mode.
+    ///
+    /// # Safety
+    ///
+    /// `base` must be valid for reads and writes of `size` bytes for the
+    /// whole lifetime of the console.
+    pub unsafe fn from_raw_parts(base: *mut u8, size: usize, info: &ModeInfo) -> Result<Self> {
+        let frame_buffer = unsafe { RawFrameBuffer::new(base, size, info) }?;
+        let (width, height) = frame_buffer.resolution();
+        let columns = width / GLYPH_WIDTH;
+        let rows = height / GLYPH_HEIGHT;
+        if columns == 0 || rows == 0 {
+            return Err(Status::UNSUPPORTED.into());
+        }
+
+        Ok(Self {
+            frame_buffer,
+            columns,
+            rows,
+            column: 0,
+            row: 0,
+            foreground: BltPixel::new(0x98, 0x98, 0x98),
+            background: BltPixel::new(0, 0, 0),
+            cursor_enabled: false,
+            cursor_drawn: false,
+        })
+    }
+
+    /// Returns the number of text columns.
+    #[must_use]
+    pub const fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// Returns the number of text rows.
+    #[must_use]
+    pub const fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Returns the cursor position as (column, row).
+    ///
+    /// Right after a character was written to the last column, the column is
+    /// equal to [`columns`]; the next character wraps to the next line.
+    ///
+    /// [`columns`]: Self::columns
+    #[must_use]
+    pub const fn cursor_position(&self) -> (usize, usize) {
+        (self.column, self.row)
+    }
+
+    /// Moves the cursor to the given position.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the position is outside of the screen.
+    pub fn set_cursor_position(&mut self, column: usize, row: usize) -> Result {
+        if column >= self.columns || row >= self.rows {
+            return Err(Status::UNSUPPORTED.into());
+        }
+        self.update(|console| {
+            console.column = column;
+            console.row = row;
+        });
+        Ok(())
+    }
+
+    /// Shows or hides the cursor, which is drawn as an underline.
+    pub fn enable_cursor(&mut self, enabled: bool) {
+        self.update(|console| console.cursor_enabled = enabled);
+    }
+
+    /// Sets the colors used for text written afterwards.
+    ///
+    /// A text mode [`Color`] can be converted to the corresponding pixel
+    /// value with [`Into`].
+    ///
+    /// [`Color`]: crate::proto::console::text::Color
+    pub fn set_color(&mut self, foreground: BltPixel, background: BltPixel) {
+        self.update(|console| {
+            console.foreground = foreground;
+            console.background = background;
+        });
+    }
+
+    /// Fills the whole screen with the background color and moves the
+    /// cursor to the top left corner.
+    pub fn clear(&mut self) {
+        self.update(|console| {
+            let background = console.frame_buffer.encode(console.background);
+            console
+                .frame_buffer
+                .fill((0, 0), (usize::MAX, usize::MAX), background);
+            console.column = 0;
+            console.row = 0;
+        });
+    }
+
+    /// Runs `f` with the cursor removed from the screen, and draws it again
+    /// afterwards.
+    fn update(&mut self, f: impl FnOnce(&mut Self)) {
+        if self.cursor_drawn {
+            self.invert_cursor();
+        }
+        f(self);
+        if self.cursor_enabled {
+            self.invert_cursor();
+        }
+    }
+
+    /// Inverts the pixels of the underline at the cursor position.
+    fn invert_cursor(&mut self) {
+        let x = self.column.min(self.columns - 1) * GLYPH_WIDTH;
+        let y = self.row * GLYPH_HEIGHT + GLYPH_HEIGHT - 2;
+        self.frame_buffer.invert((x, y), (GLYPH_WIDTH, 2));
+        self.cursor_drawn = !self.cursor_drawn;
+    }
+
+    /// Draws `c` at the cursor position and advances the cursor.
+    fn put_char(&mut self, c: char) {
+        match c {
+            '\n' => self.new_line(),
+            '\r' => self.column = 0,
+            '\t' => {
+                self.column = (self.column / TAB_WIDTH + 1) * TAB_WIDTH;
+                self.column = self.column.min(self.columns);
+            }
+            '\x08' => self.column = self.column.min(self.columns - 1).saturating_sub(1),
+            c => {
+                if self.column == self.columns {
+                    self.new_line();
+                }
+                self.draw_glyph(c);
+                self.column += 1;
+            }
+        }
+    }
+
+    fn draw_glyph(&mut self, c: char) {
+        let foreground = self.frame_buffer.encode(self.foreground);
+        let background = self.frame_buffer.encode(self.background);
+        let x = self.column * GLYPH_WIDTH;
+        let y = self.row * GLYPH_HEIGHT;
+        for (dy, bits) in font::glyph(c).iter().enumerate() {
+            for dx in 0..GLYPH_WIDTH {
+                let value = if bits & (0x80 >> dx) != 0 {
+                    foreground
+                } else {
+                    background
+                };
+                self.frame_buffer.write(x + dx, y + dy, value);
+            }
+        }
+    }
+
+    fn new_line(&mut self) {
+        self.column = 0;
+        if self.row + 1 < self.rows {
+            self.row += 1;
+        } else {
+            self.scroll();
+        }
+    }
+
+    /// Moves all text up by one line and clears the last line.
+    fn scroll(&mut self) {
+        let last = (self.rows - 1) * GLYPH_HEIGHT;
+        self.frame_buffer.copy_lines(GLYPH_HEIGHT, 0, last);
+
+        let background = self.frame_buffer.encode(self.background);
+        self.frame_buffer
+            .fill((0, last), (usize::MAX, GLYPH_HEIGHT), background);
+    }
+}
+
+impl fmt::Write for GraphicsConsole {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.update(|console| s.chars().for_each(|c| console.put_char(c)));
+        Ok(())
+    }
+}
+
+#[cfg(feature = "logger")]
+impl crate::helpers::logger::LogSink for GraphicsConsole {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        fmt::Write::write_str(self, s)
+    }
+
+    fn requires_boot_services(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::console::gop::PixelBitmask;
+    use crate::proto::console::gop::pixel::tests::mode_info;
+    use core::fmt::Write;
+    use uefi_raw::protocol::console::GraphicsPixelFormat;
+
+    const WIDTH: usize = 20;
+    const HEIGHT: usize = 34;
+    const STRIDE: usize = 24;
+
+    fn new_console(buf: &mut [u32; STRIDE * HEIGHT]) -> GraphicsConsole {
+        let info = mode_info(
+            (WIDTH, HEIGHT),
+            STRIDE,
+            GraphicsPixelFormat::PIXEL_BLUE_GREEN_RED_RESERVED_8_BIT_PER_COLOR,
+            PixelBitmask::default(),
+        );
+        let mut console = unsafe {
+            GraphicsConsole::from_raw_parts(buf.as_mut_ptr().cast(), 4 * buf.len(), &info)
+        }
+        .unwrap();
+        console.set_color(BltPixel::new(0xff, 0x80, 0x00), BltPixel::new(0, 0, 0x10));
+        console.clear();
+        console
+    }
+
+    /// Checks that the cell at (`column`, `row`) shows `c`.
+    fn assert_cell(buf: &[u32], column: usize, row: usize, c: char) {
+        for (dy, bits) in font::glyph(c).iter().enumerate() {
+            for dx in 0..GLYPH_WIDTH {
+                let pixel = buf[(row * GLYPH_HEIGHT + dy) * STRIDE + column * GLYPH_WIDTH + dx];
+                let expected = if bits & (0x80 >> dx) != 0 {
+                    0xff_80_00
+                } else {
+                    0x00_00_10
+                };
+                assert_eq!(
+                    pixel, expected,
+                    "{c:?} at ({column}, {row}), pixel ({dx}, {dy})"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_write() {
+        let mut buf = [0; STRIDE * HEIGHT];
+        let mut console = new_console(&mut buf);
+        assert_eq!((console.columns(), console.rows()), (2, 2));
+
+        write!(console, "A").unwrap();
+        assert_eq!(console.cursor_position(), (1, 0));
+        assert_cell(&buf, 0, 0, 'A');
+        assert_cell(&buf, 1, 0, ' ');
+        // Pixels outside of the text area are cleared too, but the padding
+        // at the end of the scan lines is left alone.
+        assert_eq!(buf[HEIGHT * STRIDE - STRIDE + WIDTH - 1], 0x10);
+        assert_eq!(buf[STRIDE - 1], 0);
+    }
+
+    #[test]
+    fn test_wrap_and_scroll() {
+        let mut buf = [0; STRIDE * HEIGHT];
+        let mut console = new_console(&mut buf);
+
+        // The second line is filled, but only scrolled once more text follows.
+        write!(console, "ABCD").unwrap();
+        assert_eq!(console.cursor_position(), (2, 1));
+        write!(console, "E\r\u{e9}").unwrap();
+        assert_eq!(console.cursor_position(), (1, 1));
+        assert_cell(&buf, 0, 0, 'C');
+        assert_cell(&buf, 1, 0, 'D');
+        assert_cell(&buf, 0, 1, '?');
+        assert_cell(&buf, 1, 1, ' ');
+    }
+
+    #[test]
+    fn test_control_characters() {
+        let mut buf = [0; STRIDE * HEIGHT];
+        let mut console = new_console(&mut buf);
+
+        write!(console, "A\tB").unwrap();
+        assert_eq!(console.cursor_position(), (1, 1));
+        write!(console, "\x08C\nD").unwrap();
+        assert_eq!(console.cursor_position(), (1, 1));
+        assert_cell(&buf, 0, 0, 'C');
+        assert_cell(&buf, 0, 1, 'D');
+    }
+
+    #[test]
+    fn test_cursor() {
+        let mut buf = [0; STRIDE * HEIGHT];
+        let mut console = new_console(&mut buf);
+        assert!(console.set_cursor_position(2, 0).is_err());
+        console.set_cursor_position(1, 1).unwrap();
+        console.enable_cursor(true);
+        // The underline inverts the last two lines of the cell.
+        assert_eq!(buf[(HEIGHT - 4) * STRIDE + 8], !0x10);
+        assert_eq!(buf[(HEIGHT - 5) * STRIDE + 8], 0x10);
+
+        // The cursor is removed before drawing and when it's disabled.
+        let mut console = new_console(&mut buf);
+        console.set_cursor_position(1, 1).unwrap();
+        console.enable_cursor(true);
+        write!(console, "A").unwrap();
+        console.enable_cursor(false);
+        assert_cell(&buf, 1, 1, 'A');
+    }
+}
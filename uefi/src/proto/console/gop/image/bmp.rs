@@ -0,0 +1,420 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Decoder and encoder for Windows bitmap (BMP) files.
+
+use super::{Image, ImageDecodeError, Reader, pixel_count};
+use crate::proto::console::gop::BltPixel;
+use alloc::vec::Vec;
+
+pub(super) const SIGNATURE: &[u8] = b"BM";
+
+/// Size of the file header preceding the info header.
+const FILE_HEADER_SIZE: usize = 14;
+
+/// Size of the `BITMAPCOREHEADER` used by OS/2 bitmaps.
+const CORE_HEADER_SIZE: u32 = 12;
+/// Size of the `BITMAPINFOHEADER`.
+const INFO_HEADER_SIZE: u32 = 40;
+/// Size of the `BITMAPV3INFOHEADER`, the first one including an alpha mask.
+const V3_HEADER_SIZE: u32 = 56;
+/// Size of the `BITMAPV4HEADER`.
+const V4_HEADER_SIZE: u32 = 108;
+
+const BI_RGB: u32 = 0;
+const BI_BITFIELDS: u32 = 3;
+const BI_ALPHABITFIELDS: u32 = 6;
+
+/// Bit masks of the color channels in 16 and 32 bit images.
+#[derive(Clone, Copy, Debug, Default)]
+struct Masks {
+    red: u32,
+    green: u32,
+    blue: u32,
+    alpha: u32,
+}
+
+pub(super) fn decode(data: &[u8]) -> Result<Image, ImageDecodeError> {
+    let mut reader = Reader::new(data);
+    if reader.bytes(2)? != SIGNATURE {
+        return Err(ImageDecodeError::UnknownFormat);
+    }
+    let _file_size = reader.u32_le()?;
+    let _reserved = reader.u32_le()?;
+    let data_offset = reader.u32_le()? as usize;
+
+    let header_size = reader.u32_le()?;
+    let (width, height, bpp, compression) = if header_size == CORE_HEADER_SIZE {
+        let width = i32::from(reader.u16_le()?);
+        let height = i32::from(reader.u16_le()?);
+        let _planes = reader.u16_le()?;
+        (width, height, reader.u16_le()?, BI_RGB)
+    } else if header_size >= INFO_HEADER_SIZE {
+        let width = reader.u32_le()? as i32;
+        let height = reader.u32_le()? as i32;
+        let _planes = reader.u16_le()?;
+        let bpp = reader.u16_le()?;
+        (width, height, bpp, reader.u32_le()?)
+    } else {
+        return Err(ImageDecodeError::Malformed);
+    };
+
+    // Negative heights indicate that the rows are stored top-down.
+    let top_down = height < 0;
+    let width = usize::try_from(width).map_err(|_| ImageDecodeError::Malformed)?;
+    let height = height.unsigned_abs() as usize;
+    let count = pixel_count(width, height)?;
+
+    let masks = match (bpp, compression) {
+        (16 | 32, BI_BITFIELDS | BI_ALPHABITFIELDS) => {
+            // The masks follow the info header, and are part of newer headers.
+            let mut reader = Reader::new(data);
+            reader.bytes(FILE_HEADER_SIZE + INFO_HEADER_SIZE as usize)?;
+            Masks {
+                red: reader.u32_le()?,
+                green: reader.u32_le()?,
+                blue: reader.u32_le()?,
+                alpha: if compression == BI_ALPHABITFIELDS || header_size >= V3_HEADER_SIZE {
+                    reader.u32_le()?
+                } else {
+                    0
+                },
+            }
+        }
+        (16, BI_RGB) => Masks {
+            red: 0x7c00,
+            green: 0x03e0,
+            blue: 0x001f,
+            alpha: 0,
+        },
+        (32, BI_RGB) => Masks {
+            red: 0x00ff_0000,
+            green: 0x0000_ff00,
+            blue: 0x0000_00ff,
+            alpha: 0,
+        },
+        (1 | 4 | 8 | 24, BI_RGB) => Masks::default(),
+        (1 | 4 | 8 | 16 | 24 | 32, _) => return Err(ImageDecodeError::Unsupported),
+        _ => return Err(ImageDecodeError::Malformed),
+    };
+
+    let palette = if bpp <= 8 {
+        read_palette(data, header_size, compression, bpp)?
+    } else {
+        Vec::new()
+    };
+
+    let row_size = (width * usize::from(bpp)).div_ceil(32) * 4;
+    let image_data = data
+        .get(data_offset..)
+        .and_then(|rest| rest.get(..row_size * height))
+        .ok_or(ImageDecodeError::UnexpectedEnd)?;
+
+    let mut pixels = Vec::with_capacity(count);
+    let mut alpha = Vec::with_capacity(if masks.alpha != 0 { count } else { 0 });
+    for y in 0..height {
+        let row = if top_down { y } else { height - 1 - y };
+        let row = &image_data[row * row_size..(row + 1) * row_size];
+        for x in 0..width {
+            let pixel = match bpp {
+                1 | 4 | 8 => {
+                    let bit = x * usize::from(bpp);
+                    let shift = 8 - usize::from(bpp) - bit % 8;
+                    let index = (row[bit / 8] >> shift) & (u8::MAX >> (8 - bpp));
+                    *palette
+                        .get(usize::from(index))
+                        .ok_or(ImageDecodeError::Malformed)?
+                }
+                24 => BltPixel::new(row[x * 3 + 2], row[x * 3 + 1], row[x * 3]),
+                _ => {
+                    let value = if bpp == 16 {
+                        u32::from(u16::from_le_bytes([row[x * 2], row[x * 2 + 1]]))
+                    } else {
+                        u32::from_le_bytes(row[x * 4..x * 4 + 4].try_into().unwrap())
+                    };
+                    if masks.alpha != 0 {
+                        alpha.push(channel(value, masks.alpha));
+                    }
+                    BltPixel::new(
+                        channel(value, masks.red),
+                        channel(value, masks.green),
+                        channel(value, masks.blue),
+                    )
+                }
+            };
+            pixels.push(pixel);
+        }
+    }
+
+    let image = Image::new(width, height, pixels);
+    Ok(if masks.alpha != 0 {
+        image.with_alpha(alpha)
+    } else {
+        image
+    })
+}
+
+/// Encodes `image` as a 24 bit image, or as a 32 bit image with a
+/// `BITMAPV4HEADER` if it has an alpha channel.
+pub(super) fn encode(image: &Image) -> Vec<u8> {
+    let (header_size, bpp, compression) = match image.alpha() {
+        None => (INFO_HEADER_SIZE, 24u16, BI_RGB),
+        Some(_) => (V4_HEADER_SIZE, 32, BI_BITFIELDS),
+    };
+    let row_size = (image.width() * usize::from(bpp)).div_ceil(32) * 4;
+    let image_size = row_size * image.height();
+    let data_offset = FILE_HEADER_SIZE + header_size as usize;
+
+    let dimension = |value: usize| i32::try_from(value).expect("image too large for BMP");
+    let size = |value: usize| u32::try_from(value).expect("image too large for BMP");
+    let mut out = Vec::with_capacity(data_offset + image_size);
+    out.extend_from_slice(SIGNATURE);
+    out.extend_from_slice(&size(data_offset + image_size).to_le_bytes());
+    out.extend_from_slice(&[0; 4]);
+    out.extend_from_slice(&size(data_offset).to_le_bytes());
+
+    out.extend_from_slice(&header_size.to_le_bytes());
+    out.extend_from_slice(&dimension(image.width()).to_le_bytes());
+    // Positive heights indicate that the rows are stored bottom-up.
+    out.extend_from_slice(&dimension(image.height()).to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&bpp.to_le_bytes());
+    out.extend_from_slice(&compression.to_le_bytes());
+    out.extend_from_slice(&size(image_size).to_le_bytes());
+    if image.alpha().is_some() {
+        // Resolution and palette size, followed by the masks.
+        out.resize(FILE_HEADER_SIZE + INFO_HEADER_SIZE as usize, 0);
+        for mask in [0x00ff_0000u32, 0x0000_ff00, 0x0000_00ff, 0xff00_0000] {
+            out.extend_from_slice(&mask.to_le_bytes());
+        }
+        // Color space type `LCS_sRGB`.
+        out.extend_from_slice(b"BGRs");
+    }
+    out.resize(data_offset, 0);
+
+    for y in (0..image.height()).rev() {
+        let row_start = out.len();
+        for x in 0..image.width() {
+            let index = y * image.width() + x;
+            let pixel = image.pixels()[index];
+            out.extend_from_slice(&[pixel.blue, pixel.green, pixel.red]);
+            if let Some(alpha) = image.alpha() {
+                out.push(alpha[index]);
+            }
+        }
+        out.resize(row_start + row_size, 0);
+    }
+    out
+}
+
+/// Reads the color table of an image with `bpp` bits per pixel.
+fn read_palette(
+    data: &[u8],
+    header_size: u32,
+    compression: u32,
+    bpp: u16,
+) -> Result<Vec<BltPixel>, ImageDecodeError> {
+    let mut reader = Reader::new(data);
+    reader.bytes(FILE_HEADER_SIZE + 32)?;
+    let colors_used = if header_size >= INFO_HEADER_SIZE {
+        reader.u32_le()? as usize
+    } else {
+        0
+    };
+    let count = match colors_used {
+        0 => 1 << bpp,
+        count => count.min(1 << bpp),
+    };
+
+    // OS/2 bitmaps use 3 bytes per entry, all others 4 bytes.
+    let entry_size = if header_size == CORE_HEADER_SIZE {
+        3
+    } else {
+        4
+    };
+    let mut offset = FILE_HEADER_SIZE + header_size as usize;
+    if header_size == INFO_HEADER_SIZE && compression == BI_BITFIELDS {
+        offset += 12;
+    }
+
+    let mut reader = Reader::new(data);
+    reader.bytes(offset)?;
+    (0..count)
+        .map(|_| {
+            let entry = reader.bytes(entry_size)?;
+            Ok(BltPixel::new(entry[2], entry[1], entry[0]))
+        })
+        .collect()
+}
+
+/// Extracts the channel selected by `mask` from `value`, scaled to 8 bits.
+fn channel(value: u32, mask: u32) -> u8 {
+    if mask == 0 {
+        return 0;
+    }
+    let shift = mask.trailing_zeros();
+    let max = u64::from(mask >> shift);
+    let value = u64::from((value & mask) >> shift);
+    ((value * 255 + max / 2) / max) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a BMP file from an info header of `header_size` bytes,
+    /// followed by `extra` and the pixel data.
+    fn bmp(
+        header_size: u32,
+        (width, height): (i32, i32),
+        bpp: u16,
+        compression: u32,
+        extra: &[u8],
+        pixels: &[u8],
+    ) -> Vec<u8> {
+        let data_offset = FILE_HEADER_SIZE + header_size as usize + extra.len();
+        let mut data = Vec::new();
+        data.extend_from_slice(b"BM");
+        data.extend_from_slice(&((data_offset + pixels.len()) as u32).to_le_bytes());
+        data.extend_from_slice(&[0; 4]);
+        data.extend_from_slice(&(data_offset as u32).to_le_bytes());
+        data.extend_from_slice(&header_size.to_le_bytes());
+        data.extend_from_slice(&width.to_le_bytes());
+        data.extend_from_slice(&height.to_le_bytes());
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&bpp.to_le_bytes());
+        data.extend_from_slice(&compression.to_le_bytes());
+        data.resize(FILE_HEADER_SIZE + header_size as usize, 0);
+        data.extend_from_slice(extra);
+        data.extend_from_slice(pixels);
+        data
+    }
+
+    #[test]
+    fn test_24bpp() {
+        // Bottom-up, with rows padded to 4 bytes.
+        #[rustfmt::skip]
+        let pixels = [
+            1, 2, 3, 4, 5, 6, 0, 0,
+            7, 8, 9, 10, 11, 12, 0, 0,
+        ];
+        let image =
+            Image::decode(&bmp(INFO_HEADER_SIZE, (2, 2), 24, BI_RGB, &[], &pixels)).unwrap();
+        assert_eq!(
+            image.pixels(),
+            [
+                BltPixel::new(9, 8, 7),
+                BltPixel::new(12, 11, 10),
+                BltPixel::new(3, 2, 1),
+                BltPixel::new(6, 5, 4),
+            ]
+        );
+        assert_eq!(image.alpha(), None);
+
+        assert_eq!(
+            Image::decode(&bmp(
+                INFO_HEADER_SIZE,
+                (2, 2),
+                24,
+                BI_RGB,
+                &[],
+                &pixels[..12]
+            )),
+            Err(ImageDecodeError::UnexpectedEnd)
+        );
+    }
+
+    #[test]
+    fn test_palette() {
+        // Two colors, top-down.
+        let palette = [0, 0, 0xff, 0, 0xff, 0, 0, 0];
+        let pixels = [0b1010_0000, 0, 0, 0, 0b0100_0000, 0, 0, 0];
+        let mut data = bmp(INFO_HEADER_SIZE, (3, -2), 1, BI_RGB, &palette, &pixels);
+        // Colors used.
+        data[FILE_HEADER_SIZE + 32] = 2;
+        let image = Image::decode(&data).unwrap();
+        let (red, blue) = (BltPixel::new(0xff, 0, 0), BltPixel::new(0, 0, 0xff));
+        assert_eq!(image.pixels(), [blue, red, blue, red, blue, red]);
+    }
+
+    #[test]
+    fn test_palette_4bpp() {
+        // Three colors, bottom-up.
+        let palette = [0, 0, 0xff, 0, 0xff, 0, 0, 0, 0, 0xff, 0, 0];
+        let pixels = [0x21, 0x00, 0, 0, 0x10, 0x20, 0, 0];
+        let mut data = bmp(INFO_HEADER_SIZE, (3, 2), 4, BI_RGB, &palette, &pixels);
+        data[FILE_HEADER_SIZE + 32] = 3;
+        let image = Image::decode(&data).unwrap();
+        let (red, blue, green) = (
+            BltPixel::new(0xff, 0, 0),
+            BltPixel::new(0, 0, 0xff),
+            BltPixel::new(0, 0xff, 0),
+        );
+        assert_eq!(image.pixels(), [blue, red, green, green, blue, red]);
+    }
+
+    #[test]
+    fn test_palette_8bpp() {
+        // Three colors, top-down, using the highest index of the palette.
+        let mut palette = [0; 256 * 4];
+        palette[..8].copy_from_slice(&[0, 0, 0xff, 0, 0xff, 0, 0, 0]);
+        palette[255 * 4..].copy_from_slice(&[0, 0xff, 0, 0]);
+        let pixels = [0, 1, 0xff, 0];
+        let data = bmp(INFO_HEADER_SIZE, (3, -1), 8, BI_RGB, &palette, &pixels);
+        let image = Image::decode(&data).unwrap();
+        assert_eq!(
+            image.pixels(),
+            [
+                BltPixel::new(0xff, 0, 0),
+                BltPixel::new(0, 0, 0xff),
+                BltPixel::new(0, 0xff, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bitfields() {
+        // 16-bit RGB565 with the masks following the info header.
+        let masks = [0x00, 0xf8, 0, 0, 0xe0, 0x07, 0, 0, 0x1f, 0, 0, 0];
+        let pixels = [0x00, 0xf8, 0xe0, 0x07];
+        let data = bmp(INFO_HEADER_SIZE, (2, 1), 16, BI_BITFIELDS, &masks, &pixels);
+        let image = Image::decode(&data).unwrap();
+        assert_eq!(
+            image.pixels(),
+            [BltPixel::new(0xff, 0, 0), BltPixel::new(0, 0xff, 0)]
+        );
+
+        // 32-bit with alpha in a V4 header.
+        let mut data = bmp(108, (1, 1), 32, BI_BITFIELDS, &[], &[1, 2, 3, 0x80]);
+        let masks: [u32; 4] = [0x00ff_0000, 0x0000_ff00, 0x0000_00ff, 0xff00_0000];
+        for (i, mask) in masks.iter().enumerate() {
+            let offset = FILE_HEADER_SIZE + 40 + 4 * i;
+            data[offset..offset + 4].copy_from_slice(&mask.to_le_bytes());
+        }
+        let image = Image::decode(&data).unwrap();
+        assert_eq!(image.pixels(), [BltPixel::new(3, 2, 1)]);
+        assert_eq!(image.alpha(), Some([0x80].as_slice()));
+    }
+
+    #[test]
+    fn test_unsupported() {
+        const BI_RLE8: u32 = 1;
+        assert_eq!(
+            Image::decode(&bmp(INFO_HEADER_SIZE, (1, 1), 8, BI_RLE8, &[], &[0; 4])),
+            Err(ImageDecodeError::Unsupported)
+        );
+    }
+
+    #[test]
+    fn test_encode() {
+        let pixels: Vec<_> = (0..15u8)
+            .map(|i| BltPixel::new(i, 2 * i, 255 - i))
+            .collect();
+        let image = Image::new(5, 3, pixels);
+        let encoded = image.encode_bmp();
+        assert_eq!(encoded.len(), 54 + 3 * 16);
+        assert_eq!(Image::decode(&encoded).unwrap(), image);
+
+        let image = image.with_alpha((0..15).map(|i| i * 17).collect());
+        assert_eq!(Image::decode(&image.encode_bmp()).unwrap(), image);
+    }
+}
@@ -0,0 +1,194 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Compression of zlib streams ([RFC 1950], [RFC 1951]), as used by PNG.
+//!
+//! Only the fixed Huffman codes are used, with a single-entry hash table for
+//! finding matches. This is far from optimal, but compresses the large
+//! uniform areas typical for screenshots well.
+//!
+//! [RFC 1950]: https://www.rfc-editor.org/rfc/rfc1950
+//! [RFC 1951]: https://www.rfc-editor.org/rfc/rfc1951
+
+use super::inflate::{DIST_BASE, DIST_EXTRA, LENGTH_BASE, LENGTH_EXTRA, adler32};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Size of the sliding window.
+const WINDOW_SIZE: usize = 32 * 1024;
+
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+
+/// Number of bits of the hash of the next [`MIN_MATCH`] bytes.
+const HASH_BITS: u32 = 15;
+
+const END_OF_BLOCK: u16 = 256;
+
+/// Compresses `data` into a zlib stream.
+pub(super) fn zlib_compress(data: &[u8]) -> Vec<u8> {
+    let mut out = BitWriter::new(data.len() / 4);
+    // Deflate with a 32 KiB window, default compression level.
+    out.bytes.extend_from_slice(&[0x78, 0x9c]);
+
+    // A single final block using the fixed codes.
+    out.write(1, 1);
+    out.write(1, 2);
+
+    // Most recent position for each hash.
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut pos = 0;
+    while pos < data.len() {
+        let (len, distance) = find_match(data, pos, &mut head);
+        if len >= MIN_MATCH {
+            write_length(&mut out, len);
+            write_distance(&mut out, distance);
+            // Keep the hash table up to date for the skipped positions.
+            for skipped in pos + 1..pos + len {
+                if let Some(hash) = hash(data, skipped) {
+                    head[hash] = skipped;
+                }
+            }
+            pos += len;
+        } else {
+            write_symbol(&mut out, u16::from(data[pos]));
+            pos += 1;
+        }
+    }
+    write_symbol(&mut out, END_OF_BLOCK);
+
+    let mut bytes = out.finish();
+    bytes.extend_from_slice(&adler32(data).to_be_bytes());
+    bytes
+}
+
+/// Hashes the [`MIN_MATCH`] bytes at `pos`, if there are enough bytes left.
+fn hash(data: &[u8], pos: usize) -> Option<usize> {
+    let bytes = data.get(pos..pos + MIN_MATCH)?;
+    let value = (u32::from(bytes[0]) << 16) | (u32::from(bytes[1]) << 8) | u32::from(bytes[2]);
+    Some((value.wrapping_mul(0x9e37_79b1) >> (32 - HASH_BITS)) as usize)
+}
+
+/// Returns the length and distance of a match for the bytes at `pos`, and
+/// records `pos` in the hash table. The length is 0 if there is no match.
+fn find_match(data: &[u8], pos: usize, head: &mut [usize]) -> (usize, usize) {
+    let Some(hash) = hash(data, pos) else {
+        return (0, 0);
+    };
+    let candidate = core::mem::replace(&mut head[hash], pos);
+    if candidate == usize::MAX || pos - candidate > WINDOW_SIZE {
+        return (0, 0);
+    }
+    let max_len = (data.len() - pos).min(MAX_MATCH);
+    let len = (0..max_len)
+        .take_while(|&i| data[candidate + i] == data[pos + i])
+        .count();
+    (len, pos - candidate)
+}
+
+/// Writes a literal or length symbol using the fixed Huffman code.
+fn write_symbol(out: &mut BitWriter, symbol: u16) {
+    let (code, len) = match symbol {
+        0..=143 => (0x30 + symbol, 8),
+        144..=255 => (0x190 + symbol - 144, 9),
+        256..=279 => (symbol - 256, 7),
+        _ => (0xc0 + symbol - 280, 8),
+    };
+    // Huffman codes are stored starting with the most significant bit.
+    out.write(u32::from(code.reverse_bits() >> (16 - len)), len);
+}
+
+fn write_length(out: &mut BitWriter, len: usize) {
+    let index = LENGTH_BASE
+        .iter()
+        .rposition(|&base| usize::from(base) <= len)
+        .unwrap();
+    write_symbol(out, 257 + index as u16);
+    out.write(
+        (len - usize::from(LENGTH_BASE[index])) as u32,
+        LENGTH_EXTRA[index],
+    );
+}
+
+fn write_distance(out: &mut BitWriter, distance: usize) {
+    let index = DIST_BASE
+        .iter()
+        .rposition(|&base| usize::from(base) <= distance)
+        .unwrap();
+    // All distance codes are 5 bits long.
+    out.write(u32::from((index as u8).reverse_bits() >> 3), 5);
+    out.write(
+        (distance - usize::from(DIST_BASE[index])) as u32,
+        DIST_EXTRA[index],
+    );
+}
+
+/// Writes values of up to 16 bits, starting with the least significant bit.
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    count: u8,
+}
+
+impl BitWriter {
+    fn new(capacity: usize) -> Self {
+        Self {
+            bytes: Vec::with_capacity(capacity),
+            buffer: 0,
+            count: 0,
+        }
+    }
+
+    fn write(&mut self, value: u32, n: u8) {
+        self.buffer |= value << self.count;
+        self.count += n;
+        while self.count >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    /// Pads the last byte with zeros and returns the written bytes.
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::console::gop::image::inflate::zlib_decompress;
+
+    fn round_trip(data: &[u8]) -> Vec<u8> {
+        let compressed = zlib_compress(data);
+        assert_eq!(zlib_decompress(&compressed, data.len()).unwrap(), data);
+        compressed
+    }
+
+    #[test]
+    fn test_round_trip() {
+        round_trip(&[]);
+        round_trip(b"a");
+        round_trip(b"hello hello hello");
+
+        // All literals, including those with 9 bit codes.
+        let bytes: Vec<u8> = (0..=255).collect();
+        round_trip(&bytes);
+
+        // Long runs and matches across the whole window.
+        let data: Vec<u8> = (0..100_000u32)
+            .map(|i| {
+                if i % 40_000 < 20_000 {
+                    0
+                } else {
+                    (i % 251) as u8
+                }
+            })
+            .collect();
+        let compressed = round_trip(&data);
+        assert!(compressed.len() < data.len() / 10);
+    }
+}
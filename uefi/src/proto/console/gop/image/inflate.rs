@@ -0,0 +1,414 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Decompression of zlib streams ([RFC 1950], [RFC 1951]), as used by PNG.
+//!
+//! This is a straightforward implementation optimized for size rather than
+//! speed, which is plenty for boot logos and similar images.
+//!
+//! [RFC 1950]: https://www.rfc-editor.org/rfc/rfc1950
+//! [RFC 1951]: https://www.rfc-editor.org/rfc/rfc1951
+
+use super::ImageDecodeError;
+use alloc::vec::Vec;
+
+/// Maximum number of bits in a Huffman code.
+const MAX_BITS: usize = 15;
+
+/// Base lengths for length symbols 257..=285.
+pub(super) const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+
+/// Number of extra bits for length symbols 257..=285.
+pub(super) const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+/// Base distances for distance symbols 0..=29.
+pub(super) const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+
+/// Number of extra bits for distance symbols 0..=29.
+pub(super) const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// Order in which code length code lengths are stored in dynamic blocks.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// Decompresses the zlib stream `data`, which must decompress to at most
+/// `max_len` bytes.
+pub(super) fn zlib_decompress(data: &[u8], max_len: usize) -> Result<Vec<u8>, ImageDecodeError> {
+    let [cmf, flg, ..] = *data else {
+        return Err(ImageDecodeError::UnexpectedEnd);
+    };
+    if cmf & 0x0f != 8 || ((u16::from(cmf) << 8) | u16::from(flg)) % 31 != 0 {
+        return Err(ImageDecodeError::Malformed);
+    }
+    // Preset dictionaries are not used by PNG.
+    if flg & 0x20 != 0 {
+        return Err(ImageDecodeError::Unsupported);
+    }
+
+    let mut bits = BitReader::new(&data[2..]);
+    let mut out = Vec::with_capacity(max_len);
+    inflate(&mut bits, &mut out, max_len)?;
+
+    let checksum = bits.trailer().ok_or(ImageDecodeError::UnexpectedEnd)?;
+    if checksum != adler32(&out) {
+        return Err(ImageDecodeError::Malformed);
+    }
+    Ok(out)
+}
+
+/// Decompresses raw DEFLATE blocks from `bits` into `out`.
+fn inflate(
+    bits: &mut BitReader,
+    out: &mut Vec<u8>,
+    max_len: usize,
+) -> Result<(), ImageDecodeError> {
+    loop {
+        let last = bits.read(1)? == 1;
+        match bits.read(2)? {
+            0 => stored_block(bits, out, max_len)?,
+            1 => {
+                let (lit_len, dist) = fixed_codes();
+                compressed_block(bits, out, max_len, &lit_len, &dist)?;
+            }
+            2 => {
+                let (lit_len, dist) = dynamic_codes(bits)?;
+                compressed_block(bits, out, max_len, &lit_len, &dist)?;
+            }
+            _ => return Err(ImageDecodeError::Malformed),
+        }
+        if last {
+            return Ok(());
+        }
+    }
+}
+
+fn stored_block(
+    bits: &mut BitReader,
+    out: &mut Vec<u8>,
+    max_len: usize,
+) -> Result<(), ImageDecodeError> {
+    bits.align();
+    let len = bits.read(16)?;
+    let nlen = bits.read(16)?;
+    if len != !nlen & 0xffff {
+        return Err(ImageDecodeError::Malformed);
+    }
+    let len = len as usize;
+    if out.len() + len > max_len {
+        return Err(ImageDecodeError::Malformed);
+    }
+    out.extend_from_slice(bits.bytes(len)?);
+    Ok(())
+}
+
+fn compressed_block(
+    bits: &mut BitReader,
+    out: &mut Vec<u8>,
+    max_len: usize,
+    lit_len: &Huffman,
+    dist: &Huffman,
+) -> Result<(), ImageDecodeError> {
+    loop {
+        let symbol = usize::from(lit_len.decode(bits)?);
+        match symbol {
+            0..=255 => {
+                if out.len() == max_len {
+                    return Err(ImageDecodeError::Malformed);
+                }
+                out.push(symbol as u8);
+            }
+            256 => return Ok(()),
+            257..=285 => {
+                let index = symbol - 257;
+                let len =
+                    usize::from(LENGTH_BASE[index]) + bits.read(LENGTH_EXTRA[index])? as usize;
+
+                let index = usize::from(dist.decode(bits)?);
+                if index >= DIST_BASE.len() {
+                    return Err(ImageDecodeError::Malformed);
+                }
+                let distance =
+                    usize::from(DIST_BASE[index]) + bits.read(DIST_EXTRA[index])? as usize;
+
+                if distance > out.len() || out.len() + len > max_len {
+                    return Err(ImageDecodeError::Malformed);
+                }
+                // The ranges may overlap, so copy byte by byte.
+                let start = out.len() - distance;
+                for i in start..start + len {
+                    out.push(out[i]);
+                }
+            }
+            _ => return Err(ImageDecodeError::Malformed),
+        }
+    }
+}
+
+/// Returns the literal/length and distance codes of fixed Huffman blocks.
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths = [0; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    // The fixed codes are complete, so this can't fail.
+    let lit_len = Huffman::new(&lengths).unwrap();
+    let dist = Huffman::new(&[5; 30]).unwrap();
+    (lit_len, dist)
+}
+
+/// Reads the literal/length and distance codes of a dynamic Huffman block.
+fn dynamic_codes(bits: &mut BitReader) -> Result<(Huffman, Huffman), ImageDecodeError> {
+    let num_lit_len = bits.read(5)? as usize + 257;
+    let num_dist = bits.read(5)? as usize + 1;
+    let num_code_len = bits.read(4)? as usize + 4;
+    if num_lit_len > 286 || num_dist > 30 {
+        return Err(ImageDecodeError::Malformed);
+    }
+
+    let mut code_len_lengths = [0; 19];
+    for &index in &CODE_LENGTH_ORDER[..num_code_len] {
+        code_len_lengths[index] = bits.read(3)? as u8;
+    }
+    let code_len = Huffman::new(&code_len_lengths)?;
+
+    // Both code lengths are encoded as a single sequence.
+    let mut lengths = [0; 286 + 30];
+    let mut i = 0;
+    while i < num_lit_len + num_dist {
+        let symbol = code_len.decode(bits)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths[..i].last().ok_or(ImageDecodeError::Malformed)?;
+                (previous, 3 + bits.read(2)?)
+            }
+            17 => (0, 3 + bits.read(3)?),
+            _ => (0, 11 + bits.read(7)?),
+        };
+        let end = i + repeat as usize;
+        if end > num_lit_len + num_dist {
+            return Err(ImageDecodeError::Malformed);
+        }
+        lengths[i..end].fill(value);
+        i = end;
+    }
+
+    // A block without an end-of-block code can't be decoded.
+    if lengths[256] == 0 {
+        return Err(ImageDecodeError::Malformed);
+    }
+    let lit_len = Huffman::new(&lengths[..num_lit_len])?;
+    let dist = Huffman::new(&lengths[num_lit_len..num_lit_len + num_dist])?;
+    Ok((lit_len, dist))
+}
+
+/// Canonical Huffman code.
+struct Huffman {
+    /// Number of codes of each length.
+    counts: [u16; MAX_BITS + 1],
+    /// Symbols ordered by code.
+    symbols: [u16; 288],
+}
+
+impl Huffman {
+    /// Builds the code for the given code lengths of each symbol.
+    fn new(lengths: &[u8]) -> Result<Self, ImageDecodeError> {
+        let mut counts = [0; MAX_BITS + 1];
+        for &len in lengths {
+            counts[usize::from(len)] += 1;
+        }
+        counts[0] = 0;
+
+        // Reject over-subscribed codes. Incomplete codes are allowed, e.g. a
+        // single distance code.
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - i32::from(count);
+            if left < 0 {
+                return Err(ImageDecodeError::Malformed);
+            }
+        }
+
+        let mut offsets = [0; MAX_BITS + 1];
+        for len in 1..MAX_BITS {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = [0; 288];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                let offset = &mut offsets[usize::from(len)];
+                symbols[usize::from(*offset)] = symbol as u16;
+                *offset += 1;
+            }
+        }
+        Ok(Self { counts, symbols })
+    }
+
+    /// Decodes the next symbol from `bits`.
+    fn decode(&self, bits: &mut BitReader) -> Result<u16, ImageDecodeError> {
+        // First code of the current length, and index of its symbol.
+        let mut code = 0;
+        let mut first = 0;
+        let mut index = 0;
+        for &count in &self.counts[1..] {
+            code |= bits.read(1)? as i32;
+            let count = i32::from(count);
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(ImageDecodeError::Malformed)
+    }
+}
+
+/// Reader for the bit stream of DEFLATE, which packs bits starting with the
+/// least significant bit of each byte.
+struct BitReader<'a> {
+    data: &'a [u8],
+    /// Index of the next byte to load into `bits`.
+    pos: usize,
+    bits: u32,
+    count: u8,
+}
+
+impl<'a> BitReader<'a> {
+    const fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            bits: 0,
+            count: 0,
+        }
+    }
+
+    /// Reads `n` bits, `n` being at most 16.
+    fn read(&mut self, n: u8) -> Result<u32, ImageDecodeError> {
+        while self.count < n {
+            let byte = *self
+                .data
+                .get(self.pos)
+                .ok_or(ImageDecodeError::UnexpectedEnd)?;
+            self.bits |= u32::from(byte) << self.count;
+            self.pos += 1;
+            self.count += 8;
+        }
+        let value = self.bits & ((1 << n) - 1);
+        self.bits >>= n;
+        self.count -= n;
+        Ok(value)
+    }
+
+    /// Discards the remaining bits of the current byte.
+    const fn align(&mut self) {
+        let skip = self.count % 8;
+        self.bits >>= skip;
+        self.count -= skip;
+    }
+
+    /// Reads `len` whole bytes. Must only be called when aligned.
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], ImageDecodeError> {
+        // Give back whole bytes that were already loaded.
+        self.pos -= usize::from(self.count / 8);
+        self.bits = 0;
+        self.count = 0;
+
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or(ImageDecodeError::UnexpectedEnd)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    /// Reads the big-endian 32-bit value following the last block.
+    fn trailer(&mut self) -> Option<u32> {
+        self.align();
+        let bytes = self.bytes(4).ok()?;
+        Some(u32::from_be_bytes(bytes.try_into().unwrap()))
+    }
+}
+
+/// Computes the Adler-32 checksum of `data`.
+pub(super) fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    // Largest number of bytes that can be summed without overflow.
+    const CHUNK: usize = 5552;
+
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(CHUNK) {
+        for &byte in chunk {
+            a += u32::from(byte);
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stored() {
+        // zlib.compress(b"hello", level=0)
+        let data = [
+            0x78, 0x01, 0x01, 0x05, 0x00, 0xfa, 0xff, 0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x06, 0x2c,
+            0x02, 0x15,
+        ];
+        assert_eq!(zlib_decompress(&data, 5).unwrap(), b"hello");
+        assert_eq!(zlib_decompress(&data, 4), Err(ImageDecodeError::Malformed));
+        assert_eq!(
+            zlib_decompress(&data[..12], 5),
+            Err(ImageDecodeError::UnexpectedEnd)
+        );
+    }
+
+    #[test]
+    fn test_fixed() {
+        // zlib.compress(b"abcabcabcabcabc")
+        let data = [
+            0x78, 0x9c, 0x4b, 0x4c, 0x4a, 0x4e, 0x44, 0x42, 0x00, 0x2d, 0xf5, 0x05, 0xbf,
+        ];
+        assert_eq!(zlib_decompress(&data, 100).unwrap(), b"abcabcabcabcabc");
+
+        // Corrupt the checksum.
+        let mut data = data;
+        data[12] ^= 1;
+        assert_eq!(
+            zlib_decompress(&data, 100),
+            Err(ImageDecodeError::Malformed)
+        );
+    }
+
+    #[test]
+    fn test_dynamic() {
+        // zlib.compress(data, 9) with the data below.
+        let data = [
+            0x78, 0xda, 0xdd, 0x8c, 0xc1, 0x09, 0x00, 0x30, 0x0c, 0x02, 0x67, 0x3d, 0x75, 0xff,
+            0x19, 0x9a, 0xa4, 0x94, 0x76, 0x86, 0x82, 0x8f, 0x13, 0x4e, 0xb1, 0xa1, 0x82, 0x02,
+            0x49, 0xc3, 0x84, 0x21, 0x49, 0xde, 0x4d, 0x44, 0xa5, 0x0c, 0xf7, 0xa2, 0xed, 0x6b,
+            0xd2, 0xa6, 0x8e, 0xe9, 0x67, 0xc5, 0x47, 0xff, 0x0b, 0x4b, 0x97, 0x72, 0x6e,
+        ];
+        let expected: Vec<u8> = (0..300)
+            .map(|i| b"aaaaaaabbbcd"[(i * i * 7 + i * 3 + (i >> 3)) % 12])
+            .collect();
+        assert_eq!(zlib_decompress(&data, 300).unwrap(), expected);
+    }
+}
@@ -0,0 +1,260 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Decoding of BMP, PNG and QOI images, and encoding of BMP and PNG images.
+//! See [`Image`].
+
+mod bmp;
+mod deflate;
+mod inflate;
+mod png;
+mod qoi;
+
+use super::BltPixel;
+use alloc::vec::Vec;
+use core::fmt::{self, Display, Formatter};
+
+/// Largest number of pixels of a decoded image. This keeps corrupted headers
+/// from causing huge allocations.
+const MAX_PIXELS: usize = 1 << 26;
+
+/// Image in the pixel format used by [`GraphicsOutput::blt`], with an
+/// optional alpha channel.
+///
+/// Images can be decoded from BMP, PNG and QOI files, encoded as BMP and PNG
+/// files, and drawn to a [`Canvas`] with alpha blending:
+///
+/// ```no_run
+/// use uefi::proto::console::gop::{Canvas, Image};
+///
+/// # fn draw(canvas: &mut Canvas, logo_file: &[u8]) {
+/// let logo = Image::decode(logo_file).expect("invalid logo");
+/// canvas.draw_image(&logo, (100, 100));
+/// # }
+/// ```
+///
+/// [`GraphicsOutput::blt`]: super::GraphicsOutput::blt
+/// [`Canvas`]: super::Canvas
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<BltPixel>,
+    alpha: Option<Vec<u8>>,
+}
+
+impl Image {
+    /// Creates an opaque image from `pixels`, which are stored row by row,
+    /// starting at the top left corner.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of pixels does not match the dimensions.
+    #[must_use]
+    pub fn new(width: usize, height: usize, pixels: Vec<BltPixel>) -> Self {
+        assert_eq!(Some(pixels.len()), width.checked_mul(height));
+        Self {
+            width,
+            height,
+            pixels,
+            alpha: None,
+        }
+    }
+
+    /// Adds an alpha channel to the image, which holds the opacity of each
+    /// pixel, from 0 (transparent) to 255 (opaque).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `alpha` does not match the number of pixels.
+    #[must_use]
+    pub fn with_alpha(mut self, alpha: Vec<u8>) -> Self {
+        assert_eq!(alpha.len(), self.pixels.len());
+        self.alpha = Some(alpha);
+        self
+    }
+
+    /// Decodes a BMP, PNG or QOI image, detecting the format from the
+    /// signature at the start of `data`.
+    pub fn decode(data: &[u8]) -> Result<Self, ImageDecodeError> {
+        if data.starts_with(bmp::SIGNATURE) {
+            Self::decode_bmp(data)
+        } else if data.starts_with(png::SIGNATURE) {
+            Self::decode_png(data)
+        } else if data.starts_with(qoi::SIGNATURE) {
+            Self::decode_qoi(data)
+        } else {
+            Err(ImageDecodeError::UnknownFormat)
+        }
+    }
+
+    /// Decodes a BMP image.
+    ///
+    /// Uncompressed images with 1, 4, 8, 16, 24 or 32 bits per pixel are
+    /// supported, including custom bit fields and alpha masks. Run-length
+    /// encoded images are not supported.
+    pub fn decode_bmp(data: &[u8]) -> Result<Self, ImageDecodeError> {
+        bmp::decode(data)
+    }
+
+    /// Decodes a PNG image.
+    ///
+    /// All color types and bit depths are supported, including interlaced
+    /// images and transparency from the `tRNS` chunk. Sample values with 16
+    /// bits are reduced to 8 bits, and ancillary chunks like gamma
+    /// information are ignored.
+    pub fn decode_png(data: &[u8]) -> Result<Self, ImageDecodeError> {
+        png::decode(data)
+    }
+
+    /// Decodes a QOI image.
+    pub fn decode_qoi(data: &[u8]) -> Result<Self, ImageDecodeError> {
+        qoi::decode(data)
+    }
+
+    /// Encodes the image as a BMP file.
+    ///
+    /// Opaque images are stored with 24 bits per pixel, images with an alpha
+    /// channel with 32 bits per pixel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the image is too large for the file format.
+    #[must_use]
+    pub fn encode_bmp(&self) -> Vec<u8> {
+        bmp::encode(self)
+    }
+
+    /// Encodes the image as a PNG file.
+    ///
+    /// Opaque images are stored as RGB, images with an alpha channel as RGBA,
+    /// both with 8 bits per sample. The compression is fast but simple, so
+    /// files are larger than with dedicated encoders.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the image is too large for the file format.
+    #[must_use]
+    pub fn encode_png(&self) -> Vec<u8> {
+        png::encode(self)
+    }
+
+    /// Returns the width of the image in pixels.
+    #[must_use]
+    pub const fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of the image in pixels.
+    #[must_use]
+    pub const fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the pixels of the image row by row, starting at the top left
+    /// corner.
+    ///
+    /// This buffer can be passed directly to [`GraphicsOutput::blt`].
+    ///
+    /// [`GraphicsOutput::blt`]: super::GraphicsOutput::blt
+    #[must_use]
+    pub fn pixels(&self) -> &[BltPixel] {
+        &self.pixels
+    }
+
+    /// Returns the opacity of each pixel, or `None` if the image is opaque.
+    #[must_use]
+    pub fn alpha(&self) -> Option<&[u8]> {
+        self.alpha.as_deref()
+    }
+
+    /// Returns the pixel buffer, dropping the alpha channel.
+    #[must_use]
+    pub fn into_pixels(self) -> Vec<BltPixel> {
+        self.pixels
+    }
+}
+
+/// Error returned when decoding an [`Image`] fails.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ImageDecodeError {
+    /// The data is not in any of the supported formats.
+    UnknownFormat,
+
+    /// The data ends unexpectedly.
+    UnexpectedEnd,
+
+    /// The data is corrupted or violates the file format.
+    Malformed,
+
+    /// The image uses a feature of the file format that is not supported.
+    Unsupported,
+
+    /// The image is too large.
+    TooLarge,
+}
+
+impl Display for ImageDecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::UnknownFormat => "unknown image format",
+            Self::UnexpectedEnd => "unexpected end of image data",
+            Self::Malformed => "malformed image data",
+            Self::Unsupported => "unsupported image feature",
+            Self::TooLarge => "image too large",
+        };
+        f.write_str(s)
+    }
+}
+
+impl core::error::Error for ImageDecodeError {}
+
+/// Returns the number of pixels of an image, checking that it is within
+/// [`MAX_PIXELS`].
+fn pixel_count(width: usize, height: usize) -> Result<usize, ImageDecodeError> {
+    width
+        .checked_mul(height)
+        .filter(|&count| count <= MAX_PIXELS)
+        .ok_or(ImageDecodeError::TooLarge)
+}
+
+/// Cursor for reading the fields of a file header.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    const fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], ImageDecodeError> {
+        let bytes = self
+            .data
+            .get(self.pos..)
+            .and_then(|rest| rest.get(..len))
+            .ok_or(ImageDecodeError::UnexpectedEnd)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], ImageDecodeError> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, ImageDecodeError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16_le(&mut self) -> Result<u16, ImageDecodeError> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    fn u32_le(&mut self) -> Result<u32, ImageDecodeError> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    fn u32_be(&mut self) -> Result<u32, ImageDecodeError> {
+        self.array().map(u32::from_be_bytes)
+    }
+}
@@ -0,0 +1,593 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Decoder and encoder for Portable Network Graphics (PNG) files.
+
+use super::deflate::zlib_compress;
+use super::inflate::zlib_decompress;
+use super::{Image, ImageDecodeError, Reader, pixel_count};
+use crate::proto::console::gop::BltPixel;
+use alloc::vec;
+use alloc::vec::Vec;
+
+pub(super) const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+const COLOR_GRAY: u8 = 0;
+const COLOR_RGB: u8 = 2;
+const COLOR_PALETTE: u8 = 3;
+const COLOR_GRAY_ALPHA: u8 = 4;
+const COLOR_RGBA: u8 = 6;
+
+/// Origin and spacing of the pixels of the seven Adam7 passes, as
+/// (x, y, dx, dy).
+const ADAM7: [(usize, usize, usize, usize); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+/// Contents of the `IHDR` chunk.
+#[derive(Clone, Copy, Debug)]
+struct Header {
+    width: usize,
+    height: usize,
+    bit_depth: u8,
+    color_type: u8,
+    interlaced: bool,
+}
+
+impl Header {
+    /// Returns the number of samples per pixel.
+    const fn channels(&self) -> usize {
+        match self.color_type {
+            COLOR_RGB => 3,
+            COLOR_GRAY_ALPHA => 2,
+            COLOR_RGBA => 4,
+            _ => 1,
+        }
+    }
+
+    /// Returns the number of bits per pixel.
+    const fn bits_per_pixel(&self) -> usize {
+        self.channels() * self.bit_depth as usize
+    }
+
+    /// Returns the size in bytes of a row of `width` pixels, excluding the
+    /// filter type byte.
+    const fn row_size(&self, width: usize) -> usize {
+        (width * self.bits_per_pixel()).div_ceil(8)
+    }
+
+    /// Returns the (x, y, dx, dy) of each pass.
+    const fn passes(&self) -> &'static [(usize, usize, usize, usize)] {
+        if self.interlaced {
+            &ADAM7
+        } else {
+            &[(0, 0, 1, 1)]
+        }
+    }
+
+    /// Returns the dimensions of the reduced image of a pass.
+    const fn pass_size(&self, (x, y, dx, dy): (usize, usize, usize, usize)) -> (usize, usize) {
+        (
+            self.width.saturating_sub(x).div_ceil(dx),
+            self.height.saturating_sub(y).div_ceil(dy),
+        )
+    }
+}
+
+/// Transparency information from the `tRNS` chunk.
+#[derive(Debug)]
+enum Transparency {
+    None,
+    /// Alpha of each palette entry.
+    Palette(Vec<u8>),
+    /// Gray or RGB sample values of the single transparent color.
+    Key([u16; 3]),
+}
+
+pub(super) fn decode(data: &[u8]) -> Result<Image, ImageDecodeError> {
+    let mut reader = Reader::new(data);
+    if reader.bytes(SIGNATURE.len())? != SIGNATURE {
+        return Err(ImageDecodeError::UnknownFormat);
+    }
+
+    let mut header = None;
+    let mut palette = Vec::new();
+    let mut transparency = Transparency::None;
+    let mut compressed = Vec::new();
+    loop {
+        let len = reader.u32_be()? as usize;
+        let chunk_type: [u8; 4] = reader.array()?;
+        let chunk = reader.bytes(len)?;
+        let crc = reader.u32_be()?;
+        if crc != crc32(&[&chunk_type, chunk]) {
+            return Err(ImageDecodeError::Malformed);
+        }
+
+        match &chunk_type {
+            b"IHDR" => header = Some(parse_header(chunk)?),
+            b"PLTE" => {
+                palette = chunk
+                    .chunks_exact(3)
+                    .map(|rgb| BltPixel::new(rgb[0], rgb[1], rgb[2]))
+                    .collect();
+            }
+            b"tRNS" => {
+                let header = header.ok_or(ImageDecodeError::Malformed)?;
+                let sample = |i: usize| {
+                    chunk
+                        .get(i * 2..i * 2 + 2)
+                        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+                        .ok_or(ImageDecodeError::Malformed)
+                };
+                transparency = match header.color_type {
+                    COLOR_PALETTE => Transparency::Palette(chunk.to_vec()),
+                    COLOR_GRAY => Transparency::Key([sample(0)?; 3]),
+                    COLOR_RGB => Transparency::Key([sample(0)?, sample(1)?, sample(2)?]),
+                    _ => return Err(ImageDecodeError::Malformed),
+                };
+            }
+            b"IDAT" => compressed.extend_from_slice(chunk),
+            b"IEND" => break,
+            // Unknown critical chunks can't be ignored.
+            _ if chunk_type[0] & 0x20 == 0 => return Err(ImageDecodeError::Unsupported),
+            _ => {}
+        }
+    }
+
+    let header = header.ok_or(ImageDecodeError::Malformed)?;
+    if header.color_type == COLOR_PALETTE && palette.is_empty() {
+        return Err(ImageDecodeError::Malformed);
+    }
+
+    // Each row of each pass starts with the filter type.
+    let mut expected_len = 0;
+    for &pass in header.passes() {
+        let (width, height) = header.pass_size(pass);
+        if width > 0 {
+            expected_len += (header.row_size(width) + 1) * height;
+        }
+    }
+    let mut raw = zlib_decompress(&compressed, expected_len)?;
+    if raw.len() != expected_len {
+        return Err(ImageDecodeError::UnexpectedEnd);
+    }
+
+    let count = header.width * header.height;
+    let mut pixels = vec![BltPixel::new(0, 0, 0); count];
+    let has_alpha = matches!(header.color_type, COLOR_GRAY_ALPHA | COLOR_RGBA)
+        || !matches!(transparency, Transparency::None);
+    let mut alpha = vec![0xff; if has_alpha { count } else { 0 }];
+
+    let bpp = header.bits_per_pixel().div_ceil(8);
+    let mut raw = raw.as_mut_slice();
+    for &pass in header.passes() {
+        let (width, height) = header.pass_size(pass);
+        if width == 0 || height == 0 {
+            continue;
+        }
+        let row_size = header.row_size(width);
+        let (pass_data, rest) = raw.split_at_mut((row_size + 1) * height);
+        raw = rest;
+
+        unfilter(pass_data, row_size, bpp)?;
+        let (x0, y0, dx, dy) = pass;
+        for (row_index, row) in pass_data.chunks_exact(row_size + 1).enumerate() {
+            let row = &row[1..];
+            for col in 0..width {
+                let index = (y0 + row_index * dy) * header.width + x0 + col * dx;
+                let (pixel, a) = read_pixel(&header, row, col, &palette, &transparency)?;
+                pixels[index] = pixel;
+                if has_alpha {
+                    alpha[index] = a;
+                }
+            }
+        }
+    }
+
+    let image = Image::new(header.width, header.height, pixels);
+    Ok(if has_alpha {
+        image.with_alpha(alpha)
+    } else {
+        image
+    })
+}
+
+fn parse_header(chunk: &[u8]) -> Result<Header, ImageDecodeError> {
+    let mut reader = Reader::new(chunk);
+    let width = reader.u32_be()? as usize;
+    let height = reader.u32_be()? as usize;
+    let bit_depth = reader.u8()?;
+    let color_type = reader.u8()?;
+    let compression = reader.u8()?;
+    let filter = reader.u8()?;
+    let interlace = reader.u8()?;
+
+    let valid_depth = match color_type {
+        COLOR_GRAY => matches!(bit_depth, 1 | 2 | 4 | 8 | 16),
+        COLOR_PALETTE => matches!(bit_depth, 1 | 2 | 4 | 8),
+        COLOR_RGB | COLOR_GRAY_ALPHA | COLOR_RGBA => matches!(bit_depth, 8 | 16),
+        _ => false,
+    };
+    if !valid_depth || compression != 0 || filter != 0 || interlace > 1 || width == 0 {
+        return Err(ImageDecodeError::Malformed);
+    }
+    pixel_count(width, height)?;
+
+    Ok(Header {
+        width,
+        height,
+        bit_depth,
+        color_type,
+        interlaced: interlace == 1,
+    })
+}
+
+/// Reverses the filters of the rows in `data`, in place. Each row consists
+/// of the filter type followed by `row_size` bytes.
+fn unfilter(data: &mut [u8], row_size: usize, bpp: usize) -> Result<(), ImageDecodeError> {
+    let stride = row_size + 1;
+    for start in (0..data.len()).step_by(stride) {
+        let (previous, current) = data.split_at_mut(start);
+        let prior = (start > 0).then(|| &previous[start - row_size..]);
+        let (filter, row) = current[..stride].split_first_mut().unwrap();
+
+        for i in 0..row_size {
+            let a = if i >= bpp { row[i - bpp] } else { 0 };
+            let b = prior.map_or(0, |prior| prior[i]);
+            let c = match prior {
+                Some(prior) if i >= bpp => prior[i - bpp],
+                _ => 0,
+            };
+            let predictor = match *filter {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((u16::from(a) + u16::from(b)) / 2) as u8,
+                4 => paeth(a, b, c),
+                _ => return Err(ImageDecodeError::Malformed),
+            };
+            row[i] = row[i].wrapping_add(predictor);
+        }
+    }
+    Ok(())
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = i16::from(a) + i16::from(b) - i16::from(c);
+    let pa = (p - i16::from(a)).abs();
+    let pb = (p - i16::from(b)).abs();
+    let pc = (p - i16::from(c)).abs();
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// Returns the pixel and its alpha value at column `col` of an unfiltered
+/// `row`.
+fn read_pixel(
+    header: &Header,
+    row: &[u8],
+    col: usize,
+    palette: &[BltPixel],
+    transparency: &Transparency,
+) -> Result<(BltPixel, u8), ImageDecodeError> {
+    let depth = usize::from(header.bit_depth);
+    let channels = header.channels();
+    // Returns the raw value of sample `i` of the pixel.
+    let sample = |i: usize| -> u16 {
+        let bit = (col * channels + i) * depth;
+        match depth {
+            16 => u16::from_be_bytes([row[bit / 8], row[bit / 8 + 1]]),
+            8 => u16::from(row[bit / 8]),
+            _ => u16::from(row[bit / 8] >> (8 - depth - bit % 8)) & ((1 << depth) - 1),
+        }
+    };
+    // Scales a raw sample value to 8 bits.
+    let scale = |value: u16| -> u8 {
+        match depth {
+            16 => (value >> 8) as u8,
+            _ => (u32::from(value) * 255 / ((1 << depth) - 1)) as u8,
+        }
+    };
+
+    let alpha_for_key = |key: [u16; 3]| {
+        let transparent = match header.color_type {
+            COLOR_GRAY => sample(0) == key[0],
+            _ => [sample(0), sample(1), sample(2)] == key,
+        };
+        if transparent { 0 } else { 0xff }
+    };
+
+    Ok(match header.color_type {
+        COLOR_GRAY => {
+            let v = scale(sample(0));
+            let alpha = match transparency {
+                Transparency::Key(key) => alpha_for_key(*key),
+                _ => 0xff,
+            };
+            (BltPixel::new(v, v, v), alpha)
+        }
+        COLOR_RGB => {
+            let pixel = BltPixel::new(scale(sample(0)), scale(sample(1)), scale(sample(2)));
+            let alpha = match transparency {
+                Transparency::Key(key) => alpha_for_key(*key),
+                _ => 0xff,
+            };
+            (pixel, alpha)
+        }
+        COLOR_PALETTE => {
+            let index = usize::from(sample(0));
+            let pixel = *palette.get(index).ok_or(ImageDecodeError::Malformed)?;
+            let alpha = match transparency {
+                Transparency::Palette(alpha) => alpha.get(index).copied().unwrap_or(0xff),
+                _ => 0xff,
+            };
+            (pixel, alpha)
+        }
+        COLOR_GRAY_ALPHA => {
+            let v = scale(sample(0));
+            (BltPixel::new(v, v, v), scale(sample(1)))
+        }
+        _ => {
+            let pixel = BltPixel::new(scale(sample(0)), scale(sample(1)), scale(sample(2)));
+            (pixel, scale(sample(3)))
+        }
+    })
+}
+
+/// Encodes `image` as an 8-bit RGB or RGBA image, depending on whether it has
+/// an alpha channel.
+pub(super) fn encode(image: &Image) -> Vec<u8> {
+    let channels = if image.alpha().is_some() { 4 } else { 3 };
+    let row_size = image.width() * channels;
+
+    let mut raw = Vec::with_capacity((row_size + 1) * image.height());
+    let mut previous = vec![0; row_size];
+    let mut row = Vec::with_capacity(row_size);
+    let mut filtered = vec![0; row_size];
+    for y in 0..image.height() {
+        row.clear();
+        for x in 0..image.width() {
+            let index = y * image.width() + x;
+            let pixel = image.pixels()[index];
+            row.extend_from_slice(&[pixel.red, pixel.green, pixel.blue]);
+            if let Some(alpha) = image.alpha() {
+                row.push(alpha[index]);
+            }
+        }
+
+        // Use the filter with the smallest sum of absolute differences, as
+        // recommended by the PNG specification.
+        let filter = (0..5)
+            .min_by_key(|&filter| {
+                apply_filter(filter, &row, &previous, channels, &mut filtered);
+                filtered
+                    .iter()
+                    .map(|&byte| usize::from((byte as i8).unsigned_abs()))
+                    .sum::<usize>()
+            })
+            .unwrap();
+        apply_filter(filter, &row, &previous, channels, &mut filtered);
+        raw.push(filter);
+        raw.extend_from_slice(&filtered);
+        core::mem::swap(&mut previous, &mut row);
+    }
+
+    let dimension = |value: usize| u32::try_from(value).expect("image too large for PNG");
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&dimension(image.width()).to_be_bytes());
+    header.extend_from_slice(&dimension(image.height()).to_be_bytes());
+    let color_type = if channels == 4 { COLOR_RGBA } else { COLOR_RGB };
+    header.extend_from_slice(&[8, color_type, 0, 0, 0]);
+
+    let mut out = SIGNATURE.to_vec();
+    write_chunk(&mut out, b"IHDR", &header);
+    write_chunk(&mut out, b"IDAT", &zlib_compress(&raw));
+    write_chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+/// Applies `filter` to `row`, the inverse of [`unfilter`].
+fn apply_filter(filter: u8, row: &[u8], previous: &[u8], bpp: usize, out: &mut [u8]) {
+    for i in 0..row.len() {
+        let a = if i >= bpp { row[i - bpp] } else { 0 };
+        let b = previous[i];
+        let c = if i >= bpp { previous[i - bpp] } else { 0 };
+        let predictor = match filter {
+            0 => 0,
+            1 => a,
+            2 => b,
+            3 => ((u16::from(a) + u16::from(b)) / 2) as u8,
+            _ => paeth(a, b, c),
+        };
+        out[i] = row[i].wrapping_sub(predictor);
+    }
+}
+
+fn write_chunk(out: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    let len = u32::try_from(data.len()).expect("image too large for PNG");
+    out.extend_from_slice(&len.to_be_bytes());
+    out.extend_from_slice(chunk_type);
+    out.extend_from_slice(data);
+    out.extend_from_slice(&crc32(&[chunk_type, data]).to_be_bytes());
+}
+
+/// Computes the CRC-32 of the concatenation of `parts`.
+fn crc32(parts: &[&[u8]]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in parts.iter().copied().flatten() {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a PNG file from the given chunks.
+    fn png(chunks: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+        let mut data = SIGNATURE.to_vec();
+        for (chunk_type, chunk) in chunks {
+            write_chunk(&mut data, chunk_type, chunk);
+        }
+        data
+    }
+
+    fn ihdr(width: u32, height: u32, bit_depth: u8, color_type: u8, interlace: u8) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&width.to_be_bytes());
+        data.extend_from_slice(&height.to_be_bytes());
+        data.extend_from_slice(&[bit_depth, color_type, 0, 0, interlace]);
+        data
+    }
+
+    /// Wraps `data` in an uncompressed zlib stream.
+    fn zlib_stored(data: &[u8]) -> Vec<u8> {
+        let mut out = vec![0x78, 0x01, 0x01];
+        let len = data.len() as u16;
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(data);
+        let (mut a, mut b) = (1u32, 0u32);
+        for &byte in data {
+            a = (a + u32::from(byte)) % 65521;
+            b = (b + a) % 65521;
+        }
+        out.extend_from_slice(&((b << 16) | a).to_be_bytes());
+        out
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(&[b"IEND"]), 0xae42_6082);
+    }
+
+    #[test]
+    fn test_rgba_filters() {
+        // 2x3 RGBA image with a different filter on each row.
+        #[rustfmt::skip]
+        let raw = [
+            // Sub
+            1, 10, 20, 30, 255, 1, 1, 1, 0,
+            // Up
+            2, 1, 1, 1, 0, 2, 2, 2, 0,
+            // Paeth
+            4, 0, 0, 0, 0, 0, 0, 0, 128,
+        ];
+        let data = png(&[
+            (b"IHDR", &ihdr(2, 3, 8, COLOR_RGBA, 0)),
+            (b"IDAT", &zlib_stored(&raw)),
+            (b"IEND", &[]),
+        ]);
+        let image = Image::decode(&data).unwrap();
+        assert_eq!(
+            image.pixels(),
+            [
+                BltPixel::new(10, 20, 30),
+                BltPixel::new(11, 21, 31),
+                BltPixel::new(11, 21, 31),
+                BltPixel::new(13, 23, 33),
+                BltPixel::new(11, 21, 31),
+                BltPixel::new(13, 23, 33),
+            ]
+        );
+        assert_eq!(image.alpha().unwrap(), [255, 255, 255, 255, 255, 127]);
+
+        // Corrupted checksum.
+        let mut data = data;
+        let len = data.len();
+        data[len - 13] ^= 1;
+        assert_eq!(Image::decode(&data), Err(ImageDecodeError::Malformed));
+    }
+
+    #[test]
+    fn test_palette_transparency() {
+        // 4x1 image with 2 bits per pixel.
+        let raw = [0, 0b00_01_10_11];
+        let palette = [255, 0, 0, 0, 255, 0, 0, 0, 255, 9, 9, 9];
+        let data = png(&[
+            (b"IHDR", &ihdr(4, 1, 2, COLOR_PALETTE, 0)),
+            (b"PLTE", &palette),
+            (b"tRNS", &[0, 128]),
+            (b"IDAT", &zlib_stored(&raw)),
+            (b"IEND", &[]),
+        ]);
+        let image = Image::decode(&data).unwrap();
+        assert_eq!(
+            image.pixels(),
+            [
+                BltPixel::new(255, 0, 0),
+                BltPixel::new(0, 255, 0),
+                BltPixel::new(0, 0, 255),
+                BltPixel::new(9, 9, 9),
+            ]
+        );
+        assert_eq!(image.alpha().unwrap(), [0, 128, 255, 255]);
+    }
+
+    #[test]
+    fn test_interlaced_gray() {
+        // 3x3 16-bit grayscale image. Only the Adam7 passes 1, 4, 5, 6 and 7
+        // contain pixels.
+        #[rustfmt::skip]
+        let raw = [
+            // (0, 0)
+            0, 0x00, 0xff,
+            // (2, 0)
+            0, 0x02, 0xff,
+            // (0, 2), (2, 2)
+            0, 0x06, 0xff, 0x08, 0xff,
+            // (1, 0) and (1, 2)
+            0, 0x01, 0xff,
+            0, 0x07, 0xff,
+            // (0, 1), (1, 1), (2, 1)
+            0, 0x03, 0xff, 0x04, 0xff, 0x05, 0xff,
+        ];
+        // The zlib stream may be split across multiple IDAT chunks.
+        let compressed = zlib_stored(&raw);
+        let data = png(&[
+            (b"IHDR", &ihdr(3, 3, 16, COLOR_GRAY, 1)),
+            (b"tRNS", &[0x04, 0xff]),
+            (b"IDAT", &compressed[..9]),
+            (b"IDAT", &compressed[9..]),
+            (b"IEND", &[]),
+        ]);
+        let image = Image::decode(&data).unwrap();
+        let gray = |v| BltPixel::new(v, v, v);
+        assert_eq!(image.pixels(), [0, 1, 2, 3, 4, 5, 6, 7, 8].map(gray));
+        assert_eq!(
+            image.alpha().unwrap(),
+            [255, 255, 255, 255, 0, 255, 255, 255, 255]
+        );
+    }
+
+    #[test]
+    fn test_encode() {
+        let pixels: Vec<_> = (0..40u8)
+            .map(|i| BltPixel::new(i * 6, 255 - i, (i % 8) * 30))
+            .collect();
+        let image = Image::new(8, 5, pixels);
+        let encoded = image.encode_png();
+        assert_eq!(Image::decode(&encoded).unwrap(), image);
+
+        let image = image.with_alpha((0..40).map(|i| i * 3).collect());
+        assert_eq!(Image::decode(&image.encode_png()).unwrap(), image);
+    }
+}
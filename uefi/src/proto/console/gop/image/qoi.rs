@@ -0,0 +1,127 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Decoder for the [Quite OK Image Format](https://qoiformat.org/).
+
+use super::{Image, ImageDecodeError, Reader, pixel_count};
+use crate::proto::console::gop::BltPixel;
+use alloc::vec::Vec;
+
+pub(super) const SIGNATURE: &[u8] = b"qoif";
+
+const OP_RGB: u8 = 0xfe;
+const OP_RGBA: u8 = 0xff;
+
+pub(super) fn decode(data: &[u8]) -> Result<Image, ImageDecodeError> {
+    let mut reader = Reader::new(data);
+    if reader.bytes(4)? != SIGNATURE {
+        return Err(ImageDecodeError::UnknownFormat);
+    }
+    let width = reader.u32_be()? as usize;
+    let height = reader.u32_be()? as usize;
+    let channels = reader.u8()?;
+    let _colorspace = reader.u8()?;
+    if !matches!(channels, 3 | 4) {
+        return Err(ImageDecodeError::Malformed);
+    }
+
+    let count = pixel_count(width, height)?;
+    let mut pixels = Vec::with_capacity(count);
+    let mut alpha = Vec::with_capacity(count);
+
+    let mut index = [[0u8; 4]; 64];
+    let mut px = [0, 0, 0, 255];
+    let mut run = 0;
+    while pixels.len() < count {
+        if run > 0 {
+            run -= 1;
+        } else {
+            let op = reader.u8()?;
+            match op {
+                OP_RGB => px[..3].copy_from_slice(reader.bytes(3)?),
+                OP_RGBA => px = reader.array()?,
+                _ => match op >> 6 {
+                    // QOI_OP_INDEX
+                    0 => px = index[usize::from(op)],
+                    // QOI_OP_DIFF
+                    1 => {
+                        px[0] = px[0].wrapping_add((op >> 4) & 3).wrapping_sub(2);
+                        px[1] = px[1].wrapping_add((op >> 2) & 3).wrapping_sub(2);
+                        px[2] = px[2].wrapping_add(op & 3).wrapping_sub(2);
+                    }
+                    // QOI_OP_LUMA
+                    2 => {
+                        let dg = (op & 0x3f).wrapping_sub(32);
+                        let next = reader.u8()?;
+                        let dr_dg = (next >> 4).wrapping_sub(8);
+                        let db_dg = (next & 0x0f).wrapping_sub(8);
+                        px[0] = px[0].wrapping_add(dg).wrapping_add(dr_dg);
+                        px[1] = px[1].wrapping_add(dg);
+                        px[2] = px[2].wrapping_add(dg).wrapping_add(db_dg);
+                    }
+                    // QOI_OP_RUN
+                    _ => run = op & 0x3f,
+                },
+            }
+            let [r, g, b, a] = px.map(usize::from);
+            index[(r * 3 + g * 5 + b * 7 + a * 11) % 64] = px;
+        }
+        pixels.push(BltPixel::new(px[0], px[1], px[2]));
+        alpha.push(px[3]);
+    }
+
+    let image = Image::new(width, height, pixels);
+    Ok(if channels == 4 {
+        image.with_alpha(alpha)
+    } else {
+        image
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        #[rustfmt::skip]
+        let data = [
+            b'q', b'o', b'i', b'f', 0, 0, 0, 4, 0, 0, 0, 2, 4, 0,
+            // RGBA (10, 20, 30, 40)
+            OP_RGBA, 10, 20, 30, 40,
+            // Run of 3
+            0xc2,
+            // Diff: +1, -2, +0
+            0x40 | (3 << 4) | 2,
+            // Luma: dg = +4, dr - dg = -1, db - dg = +2
+            0x80 | 36, (7 << 4) | 10,
+            // Index of the second color: (11 * 3 + 18 * 5 + 30 * 7 + 40 * 11) % 64
+            5,
+            // RGB (1, 2, 3)
+            OP_RGB, 1, 2, 3,
+            // End marker
+            0, 0, 0, 0, 0, 0, 0, 1,
+        ];
+        let image = Image::decode(&data).unwrap();
+        assert_eq!((image.width(), image.height()), (4, 2));
+        assert_eq!(
+            image.pixels(),
+            [
+                BltPixel::new(10, 20, 30),
+                BltPixel::new(10, 20, 30),
+                BltPixel::new(10, 20, 30),
+                BltPixel::new(10, 20, 30),
+                BltPixel::new(11, 18, 30),
+                BltPixel::new(14, 22, 36),
+                BltPixel::new(11, 18, 30),
+                BltPixel::new(1, 2, 3),
+            ]
+        );
+        assert_eq!(image.alpha().unwrap(), [40; 8]);
+
+        // Pixels missing.
+        assert_eq!(
+            Image::decode(&data[..24]),
+            Err(ImageDecodeError::UnexpectedEnd)
+        );
+    }
+}
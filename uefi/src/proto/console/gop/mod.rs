@@ -51,7 +51,31 @@
 //! the graphics card will re-draw the buffer at around the monitor's refresh rate.
 //! You will have to implement your own double buffering if you want to
 //! avoid tearing with animations.
-
+//!
+//! With the `alloc` feature, the `Canvas` type provides such a back buffer,
+//! along with basic drawing operations and decoding of images.
+//!
+//! # Text output
+//!
+//! On systems without a usable text console, the [`GraphicsConsole`] can be
+//! used to render text directly to the frame buffer.
+
+#[cfg(feature = "alloc")]
+mod canvas;
+mod console;
+mod font;
+#[cfg(feature = "alloc")]
+mod image;
+mod pixel;
+
+#[cfg(feature = "alloc")]
+pub use canvas::{Canvas, Rect};
+pub use console::GraphicsConsole;
+#[cfg(feature = "alloc")]
+pub use image::{Image, ImageDecodeError};
+
+use crate::proto::console::edid::{self, Edid};
+use crate::proto::console::text::Color;
 use crate::proto::unsafe_protocol;
 use crate::util::usize_from_u32;
 use crate::{Result, StatusExt, boot};
@@ -115,6 +139,17 @@ impl GraphicsOutput {
         }
     }
 
+    /// Returns the mode that best matches the display described by `edid`.
+    ///
+    /// This is the mode with the resolution of the display's preferred
+    /// timing, which is the native resolution of flat panels. If there is no
+    /// such mode, it is the largest mode supported by the display. `None` is
+    /// returned if the display supports none of the modes.
+    #[must_use]
+    pub fn preferred_mode(&self, edid: &Edid<'_>) -> Option<Mode> {
+        edid::select_mode(self.modes(), |mode| mode.info().resolution(), edid)
+    }
+
     /// Sets the video device into the specified mode, clearing visible portions
     /// of the output display to black.
     ///
@@ -255,6 +290,55 @@ impl GraphicsOutput {
         }
     }
 
+    /// Reads the contents of the whole screen into an [`Image`], using
+    /// [`BltOp::VideoToBltBuffer`].
+    ///
+    /// This works in all graphics modes. The image can be encoded as a BMP or
+    /// PNG file, for example to store a screenshot:
+    ///
+    /// ```no_run
+    /// use uefi::fs::FileSystem;
+    /// use uefi::proto::console::gop::GraphicsOutput;
+    /// use uefi::{boot, cstr16};
+    ///
+    /// # fn main() -> Result<(), Box<dyn core::error::Error>> {
+    /// let handle = boot::get_handle_for_protocol::<GraphicsOutput>()?;
+    /// let mut gop = boot::open_protocol_exclusive::<GraphicsOutput>(handle)?;
+    /// let screenshot = gop.capture()?.encode_png();
+    ///
+    /// let sfs = boot::get_image_file_system(boot::image_handle())?;
+    /// let mut fs = FileSystem::new(sfs);
+    /// fs.write(cstr16!("screenshot.png"), screenshot)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn capture(&mut self) -> Result<Image> {
+        let (width, height) = self.current_mode_info().resolution();
+        self.capture_rect(Rect::new(0, 0, width, height))
+    }
+
+    /// Reads the contents of `rect` on the screen into an [`Image`].
+    ///
+    /// See [`capture`] for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rect` is not within the screen.
+    ///
+    /// [`capture`]: Self::capture
+    #[cfg(feature = "alloc")]
+    pub fn capture_rect(&mut self, rect: Rect) -> Result<Image> {
+        let mut pixels = alloc::vec![BltPixel::new(0, 0, 0); rect.width * rect.height];
+        self.blt(BltOp::VideoToBltBuffer {
+            buffer: &mut pixels,
+            src: (rect.x, rect.y),
+            dest: BltRegion::Full,
+            dims: (rect.width, rect.height),
+        })?;
+        Ok(Image::new(rect.width, rect.height, pixels))
+    }
+
     /// Memory-safety check for accessing a region of the framebuffer
     fn check_framebuffer_region(&self, coords: (usize, usize), dims: (usize, usize)) {
         let (width, height) = self.current_mode_info().resolution();
@@ -454,7 +538,7 @@ impl ExactSizeIterator for ModeIter<'_> {}
 ///
 /// This is a BGR 24-bit format with an 8-bit padding, to keep each pixel 32-bit in size.
 #[allow(missing_docs)]
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 #[repr(C)]
 pub struct BltPixel {
     pub blue: u8,
@@ -487,6 +571,31 @@ impl From<u32> for BltPixel {
     }
 }
 
+impl From<Color> for BltPixel {
+    /// Converts a text mode color to the pixel value used for it by the
+    /// graphics console of EDK2.
+    fn from(color: Color) -> Self {
+        match color {
+            Color::Black => Self::new(0x00, 0x00, 0x00),
+            Color::Blue => Self::new(0x00, 0x00, 0x98),
+            Color::Green => Self::new(0x00, 0x98, 0x00),
+            Color::Cyan => Self::new(0x00, 0x98, 0x98),
+            Color::Red => Self::new(0x98, 0x00, 0x00),
+            Color::Magenta => Self::new(0x98, 0x00, 0x98),
+            Color::Brown => Self::new(0x98, 0x98, 0x00),
+            Color::LightGray => Self::new(0x98, 0x98, 0x98),
+            Color::DarkGray => Self::new(0x30, 0x30, 0x30),
+            Color::LightBlue => Self::new(0x00, 0x00, 0xff),
+            Color::LightGreen => Self::new(0x00, 0xff, 0x00),
+            Color::LightCyan => Self::new(0x00, 0xff, 0xff),
+            Color::LightRed => Self::new(0xff, 0x00, 0x00),
+            Color::LightMagenta => Self::new(0xff, 0x00, 0xff),
+            Color::Yellow => Self::new(0xff, 0xff, 0x00),
+            Color::White => Self::new(0xff, 0xff, 0xff),
+        }
+    }
+}
+
 /// Region of the `BltBuffer` which we are operating on
 ///
 /// Some `Blt` operations can operate on either the full `BltBuffer` or a
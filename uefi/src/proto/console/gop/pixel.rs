@@ -0,0 +1,272 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Direct frame buffer access in any of the pixel formats that support it.
+
+use super::{BltPixel, ModeInfo, PixelBitmask, PixelFormat};
+use crate::{Result, Status};
+use core::ptr;
+
+/// Frame buffer of a graphics mode, accessed through a raw pointer.
+///
+/// All accesses are clipped to the screen.
+#[derive(Debug)]
+pub(super) struct RawFrameBuffer {
+    base: *mut u8,
+    encoding: PixelEncoding,
+    /// Size of a pixel in bytes.
+    bytes_per_pixel: usize,
+    /// Size of a scan line in bytes.
+    pitch: usize,
+    width: usize,
+    height: usize,
+}
+
+impl RawFrameBuffer {
+    /// Creates an accessor for a frame buffer of `size` bytes at `base`,
+    /// laid out as described by `info`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: `info` does not allow direct frame buffer
+    ///   access.
+    /// * [`Status::BAD_BUFFER_SIZE`]: `size` is too small for the mode.
+    ///
+    /// # Safety
+    ///
+    /// `base` must be valid for reads and writes of `size` bytes for the
+    /// whole lifetime of the returned value.
+    pub(super) unsafe fn new(base: *mut u8, size: usize, info: &ModeInfo) -> Result<Self> {
+        let encoding = PixelEncoding::new(info).ok_or(Status::UNSUPPORTED)?;
+        let bytes_per_pixel = encoding.bytes_per_pixel();
+        let (width, height) = info.resolution();
+        let pitch = info.stride() * bytes_per_pixel;
+        let required = pitch * height.saturating_sub(1) + width * bytes_per_pixel;
+        if info.stride() < width || size < required {
+            return Err(Status::BAD_BUFFER_SIZE.into());
+        }
+
+        Ok(Self {
+            base,
+            encoding,
+            bytes_per_pixel,
+            pitch,
+            width,
+            height,
+        })
+    }
+
+    /// Returns the (width, height) of the screen in pixels.
+    pub(super) const fn resolution(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Converts `pixel` to the pixel format of the frame buffer.
+    pub(super) fn encode(&self, pixel: BltPixel) -> u32 {
+        self.encoding.encode(pixel)
+    }
+
+    /// Writes the encoded pixel `value` at (`x`, `y`).
+    pub(super) fn write(&mut self, x: usize, y: usize, value: u32) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        let offset = y * self.pitch + x * self.bytes_per_pixel;
+        let bytes = value.to_le_bytes();
+        for (i, byte) in bytes[..self.bytes_per_pixel].iter().enumerate() {
+            // SAFETY: the pixel is within the screen, which was checked to
+            // fit the frame buffer on creation.
+            unsafe { self.base.add(offset + i).write_volatile(*byte) };
+        }
+    }
+
+    /// Fills the rectangle at (`x`, `y`) of size `dims` with `value`.
+    pub(super) fn fill(&mut self, (x, y): (usize, usize), dims: (usize, usize), value: u32) {
+        let (x_end, y_end) = self.clip((x, y), dims);
+        for y in y..y_end {
+            for x in x..x_end {
+                self.write(x, y, value);
+            }
+        }
+    }
+
+    /// Inverts all bits of the pixels in the rectangle at (`x`, `y`) of size
+    /// `dims`.
+    pub(super) fn invert(&mut self, (x, y): (usize, usize), dims: (usize, usize)) {
+        let (x_end, y_end) = self.clip((x, y), dims);
+        for y in y..y_end {
+            let line = y * self.pitch;
+            for i in line + x * self.bytes_per_pixel..line + x_end * self.bytes_per_pixel {
+                // SAFETY: the byte is within the screen.
+                unsafe {
+                    let byte = self.base.add(i);
+                    byte.write_volatile(!byte.read_volatile());
+                }
+            }
+        }
+    }
+
+    /// Copies `count` scan lines starting at `src` to `dest`. The ranges may
+    /// overlap.
+    pub(super) fn copy_lines(&mut self, src: usize, dest: usize, count: usize) {
+        let count = count.min(self.height.saturating_sub(src.max(dest)));
+        if count == 0 {
+            return;
+        }
+        let len = self.pitch * (count - 1) + self.width * self.bytes_per_pixel;
+        // SAFETY: both ranges are within the screen. Frame buffer memory has
+        // no side effects, so a non-volatile copy is fine here and much
+        // faster than copying pixel by pixel.
+        unsafe {
+            ptr::copy(
+                self.base.add(src * self.pitch),
+                self.base.add(dest * self.pitch),
+                len,
+            )
+        };
+    }
+
+    /// Returns the exclusive end coordinates of the rectangle at `pos` of
+    /// size `dims`, clipped to the screen.
+    fn clip(&self, (x, y): (usize, usize), (width, height): (usize, usize)) -> (usize, usize) {
+        (
+            x.saturating_add(width).min(self.width),
+            y.saturating_add(height).min(self.height),
+        )
+    }
+}
+
+/// Conversion of [`BltPixel`]s to the pixel format of the frame buffer.
+#[derive(Clone, Copy, Debug)]
+enum PixelEncoding {
+    Rgb,
+    Bgr,
+    Bitmask(PixelBitmask),
+}
+
+impl PixelEncoding {
+    /// Returns the encoding for `info`, or `None` if the frame buffer can't
+    /// be accessed directly.
+    fn new(info: &ModeInfo) -> Option<Self> {
+        match info.pixel_format() {
+            PixelFormat::Rgb => Some(Self::Rgb),
+            PixelFormat::Bgr => Some(Self::Bgr),
+            PixelFormat::Bitmask => {
+                let mask = info.pixel_bitmask()?;
+                (mask.red | mask.green | mask.blue != 0).then_some(Self::Bitmask(mask))
+            }
+            PixelFormat::BltOnly => None,
+        }
+    }
+
+    /// Returns the size of a pixel in bytes.
+    const fn bytes_per_pixel(self) -> usize {
+        match self {
+            Self::Rgb | Self::Bgr => 4,
+            Self::Bitmask(mask) => {
+                let bits = 32 - (mask.red | mask.green | mask.blue | mask.reserved).leading_zeros();
+                bits.div_ceil(8) as usize
+            }
+        }
+    }
+
+    /// Returns the pixel value for `pixel`, in native byte order.
+    fn encode(self, pixel: BltPixel) -> u32 {
+        let (red, green, blue) = (
+            u32::from(pixel.red),
+            u32::from(pixel.green),
+            u32::from(pixel.blue),
+        );
+        match self {
+            Self::Rgb => red | (green << 8) | (blue << 16),
+            Self::Bgr => blue | (green << 8) | (red << 16),
+            Self::Bitmask(mask) => {
+                scale(pixel.red, mask.red)
+                    | scale(pixel.green, mask.green)
+                    | scale(pixel.blue, mask.blue)
+            }
+        }
+    }
+}
+
+/// Scales the 8-bit channel `value` to the bits set in `mask`.
+fn scale(value: u8, mask: u32) -> u32 {
+    if mask == 0 {
+        return 0;
+    }
+    let shift = mask.trailing_zeros();
+    let max = u64::from(mask >> shift);
+    let scaled = (u64::from(value) * max + 127) / 255;
+    ((scaled as u32) << shift) & mask
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use uefi_raw::protocol::console::{GraphicsOutputModeInformation, GraphicsPixelFormat};
+
+    /// Returns the mode info for a screen of `resolution` pixels.
+    pub(in super::super) fn mode_info(
+        resolution: (usize, usize),
+        stride: usize,
+        format: GraphicsPixelFormat,
+        mask: PixelBitmask,
+    ) -> ModeInfo {
+        ModeInfo(GraphicsOutputModeInformation {
+            version: 0,
+            horizontal_resolution: resolution.0 as u32,
+            vertical_resolution: resolution.1 as u32,
+            pixel_format: format,
+            pixel_information: mask,
+            pixels_per_scan_line: stride as u32,
+        })
+    }
+
+    #[test]
+    fn test_bitmask_encoding() {
+        let mask = PixelBitmask {
+            red: 0xf800,
+            green: 0x07e0,
+            blue: 0x001f,
+            reserved: 0,
+        };
+        let info = mode_info((4, 4), 4, GraphicsPixelFormat::PIXEL_BIT_MASK, mask);
+        let encoding = PixelEncoding::new(&info).unwrap();
+        assert_eq!(encoding.bytes_per_pixel(), 2);
+        assert_eq!(encoding.encode(BltPixel::new(0xff, 0xff, 0xff)), 0xffff);
+        assert_eq!(encoding.encode(BltPixel::new(0xff, 0, 0)), 0xf800);
+        assert_eq!(encoding.encode(BltPixel::new(0, 0x80, 0)), 0x0400);
+
+        let mut buf = [0u16; 16];
+        let mut fb = unsafe { RawFrameBuffer::new(buf.as_mut_ptr().cast(), 32, &info) }.unwrap();
+        fb.fill((3, 3), (2, 2), 0x1234);
+        assert_eq!(buf[15], 0x1234);
+        assert_eq!(buf[14], 0);
+
+        let info = mode_info(
+            (4, 4),
+            4,
+            GraphicsPixelFormat::PIXEL_BIT_MASK,
+            PixelBitmask::default(),
+        );
+        assert!(PixelEncoding::new(&info).is_none());
+    }
+
+    #[test]
+    fn test_buffer_size() {
+        let info = mode_info(
+            (4, 4),
+            5,
+            GraphicsPixelFormat::PIXEL_RED_GREEN_BLUE_RESERVED_8_BIT_PER_COLOR,
+            PixelBitmask::default(),
+        );
+        let mut buf = [0u32; 19];
+        // The padding after the last scan line is not required.
+        assert!(unsafe { RawFrameBuffer::new(buf.as_mut_ptr().cast(), 76, &info) }.is_ok());
+        assert_eq!(
+            unsafe { RawFrameBuffer::new(buf.as_mut_ptr().cast(), 75, &info) }
+                .unwrap_err()
+                .status(),
+            Status::BAD_BUFFER_SIZE
+        );
+    }
+}
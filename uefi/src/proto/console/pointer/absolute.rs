@@ -0,0 +1,188 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::proto::unsafe_protocol;
+use crate::{Event, Result, Status, StatusExt};
+use core::mem::MaybeUninit;
+use uefi_raw::protocol::console::AbsolutePointerProtocol;
+
+pub use uefi_raw::protocol::console::{AbsolutePointerMode, AbsolutePointerModeAttributes};
+
+/// Absolute Pointer [`Protocol`]. Provides information about a pointer device
+/// that reports absolute positions, like a touchscreen or a pen tablet.
+///
+/// # Example
+///
+/// Mapping touches to pixels of the screen, for example to select an entry
+/// of a boot menu:
+///
+/// ```
+/// use uefi::proto::console::gop::GraphicsOutput;
+/// use uefi::proto::console::pointer::AbsolutePointer;
+/// use uefi::{Result, ResultExt, boot};
+///
+/// fn wait_for_touch(
+///     pointer: &mut AbsolutePointer,
+///     gop: &GraphicsOutput,
+/// ) -> Result<(usize, usize)> {
+///     let resolution = gop.current_mode_info().resolution();
+///     let mut events = [pointer.wait_for_input_event().unwrap()];
+///     loop {
+///         boot::wait_for_event(&mut events).discard_errdata()?;
+///         if let Some(state) = pointer.read_state()? {
+///             if state.touch_active {
+///                 return Ok(pointer.screen_position(&state, resolution));
+///             }
+///         }
+///     }
+/// }
+/// ```
+///
+/// [`Protocol`]: uefi::proto::Protocol
+#[derive(Debug)]
+#[repr(transparent)]
+#[unsafe_protocol(AbsolutePointerProtocol::GUID)]
+pub struct AbsolutePointer(AbsolutePointerProtocol);
+
+impl AbsolutePointer {
+    /// Resets the pointer device hardware.
+    ///
+    /// # Arguments
+    /// The `extended_verification` parameter is used to request that UEFI
+    /// performs an extended check and reset of the input device.
+    ///
+    /// # Errors
+    /// - `DeviceError` if the device is malfunctioning and cannot be reset.
+    pub fn reset(&mut self, extended_verification: bool) -> Result {
+        unsafe { (self.0.reset)(&mut self.0, extended_verification.into()) }.to_result()
+    }
+
+    /// Retrieves the pointer device's current state, if a state change occurred
+    /// since the last time this function was called.
+    ///
+    /// Use `wait_for_input_event()` with the [`boot::wait_for_event`]
+    /// interface in order to wait for input from the pointer device.
+    ///
+    /// # Errors
+    /// - `DeviceError` if there was an issue with the pointer device.
+    ///
+    /// [`boot::wait_for_event`]: crate::boot::wait_for_event
+    pub fn read_state(&mut self) -> Result<Option<AbsolutePointerState>> {
+        let mut state = MaybeUninit::uninit();
+        match unsafe { (self.0.get_state)(&self.0, state.as_mut_ptr()) } {
+            Status::NOT_READY => Ok(None),
+            other => other.to_result_with_val(|| {
+                let state = unsafe { state.assume_init() };
+                Some(AbsolutePointerState {
+                    position: [state.current_x, state.current_y, state.current_z],
+                    touch_active: state.active_buttons & TOUCH_ACTIVE != 0,
+                    alt_active: state.active_buttons & ALT_ACTIVE != 0,
+                })
+            }),
+        }
+    }
+
+    /// Event to be used with [`boot::wait_for_event`] in order to wait
+    /// for input from the pointer device
+    ///
+    /// [`boot::wait_for_event`]: crate::boot::wait_for_event
+    #[must_use]
+    pub fn wait_for_input_event(&self) -> Option<Event> {
+        unsafe { Event::from_ptr(self.0.wait_for_input) }
+    }
+
+    /// Returns a reference to the pointer device information, including the
+    /// range of the coordinates.
+    #[must_use]
+    pub const fn mode(&self) -> &AbsolutePointerMode {
+        unsafe { &*self.0.mode.cast_const() }
+    }
+
+    /// Translates the position of `state` to the pixel of a screen with the
+    /// given `resolution`, for example [`ModeInfo::resolution`].
+    ///
+    /// Positions outside of the range reported by [`mode`] are clamped to the
+    /// edges of the screen.
+    ///
+    /// [`ModeInfo::resolution`]: crate::proto::console::gop::ModeInfo::resolution
+    /// [`mode`]: Self::mode
+    #[must_use]
+    pub const fn screen_position(
+        &self,
+        state: &AbsolutePointerState,
+        resolution: (usize, usize),
+    ) -> (usize, usize) {
+        let mode = self.mode();
+        (
+            scale(
+                state.position[0],
+                mode.absolute_min_x,
+                mode.absolute_max_x,
+                resolution.0,
+            ),
+            scale(
+                state.position[1],
+                mode.absolute_min_y,
+                mode.absolute_max_y,
+                resolution.1,
+            ),
+        )
+    }
+}
+
+/// `EFI_ABSP_TouchActive`
+const TOUCH_ACTIVE: u32 = 0x1;
+/// `EFI_ABS_AltActive`
+const ALT_ACTIVE: u32 = 0x2;
+
+/// The state of an absolute pointer device.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct AbsolutePointerState {
+    /// The position on the X/Y/Z axis, within the ranges reported by
+    /// [`AbsolutePointer::mode`].
+    ///
+    /// If [`AbsolutePointerModeAttributes::SUPPORTS_PRESSURE_AS_Z`] is set,
+    /// the Z position is the pressure.
+    pub position: [u64; 3],
+    /// Whether the device is being touched, or its primary button is
+    /// pressed.
+    pub touch_active: bool,
+    /// Whether the alternate button is pressed. Only supported if
+    /// [`AbsolutePointerModeAttributes::SUPPORTS_ALT_ACTIVE`] is set.
+    pub alt_active: bool,
+}
+
+/// Maps `value` in `min..=max` to `0..size`.
+const fn scale(value: u64, min: u64, max: u64, size: usize) -> usize {
+    if max <= min || size == 0 {
+        return 0;
+    }
+    let value = if value < min {
+        0
+    } else if value > max {
+        max - min
+    } else {
+        value - min
+    };
+    (value as u128 * (size as u128 - 1) / (max - min) as u128) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scale() {
+        assert_eq!(scale(0, 0, 1000, 1024), 0);
+        assert_eq!(scale(500, 0, 1000, 1025), 512);
+        assert_eq!(scale(1000, 0, 1000, 1024), 1023);
+        assert_eq!(scale(150, 100, 200, 11), 5);
+
+        // Out of range positions are clamped.
+        assert_eq!(scale(50, 100, 200, 11), 0);
+        assert_eq!(scale(300, 100, 200, 11), 10);
+
+        // Invalid ranges.
+        assert_eq!(scale(5, 0, 0, 11), 0);
+        assert_eq!(scale(5, 0, 10, 0), 0);
+    }
+}
@@ -6,6 +6,11 @@ use crate::proto::unsafe_protocol;
 use crate::{Event, Result, Status, StatusExt};
 use uefi_raw::protocol::console::SimplePointerProtocol;
 
+mod absolute;
+pub use absolute::{
+    AbsolutePointer, AbsolutePointerMode, AbsolutePointerModeAttributes, AbsolutePointerState,
+};
+
 /// Simple Pointer [`Protocol`]. Provides information about a pointer device.
 ///
 /// Pointer devices are mouses, touchpads, and touchscreens.
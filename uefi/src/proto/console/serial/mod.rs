@@ -7,6 +7,9 @@ use crate::{Result, StatusExt};
 use core::fmt::Write;
 use uefi_raw::protocol::console::serial::SerialIoProtocol;
 
+mod terminal;
+pub use terminal::Terminal;
+
 pub use uefi_raw::protocol::console::serial::{
     ControlBits, Parity, SerialIoMode as IoMode, StopBits,
 };
@@ -0,0 +1,448 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use super::Serial;
+use crate::proto::console::text::{Color, Key, ScanCode};
+use crate::{Char16, Result, Status};
+use core::fmt::{self, Write};
+use core::time::Duration;
+
+/// Time to wait for the rest of an escape sequence before treating the
+/// escape byte as the escape key.
+const ESCAPE_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// VT100-compatible terminal connected to a [`Serial`] device.
+///
+/// This provides the functionality of [`Output`] and [`Input`] on headless
+/// machines: text is written with [`core::fmt::Write`], the cursor and
+/// colors are controlled with ANSI escape sequences, and escape sequences
+/// sent by the terminal are translated to [`Key`]s.
+///
+/// # Example
+///
+/// ```
+/// use core::fmt::Write;
+/// use core::time::Duration;
+/// use uefi::Result;
+/// use uefi::proto::console::serial::{Serial, Terminal};
+/// use uefi::proto::console::text::{Color, Key, ScanCode};
+///
+/// fn confirm(serial: &mut Serial) -> Result<bool> {
+///     let mut terminal = Terminal::new(serial);
+///     terminal.clear()?;
+///     terminal.set_color(Color::Yellow, Color::Black)?;
+///     writeln!(terminal, "Press enter to continue, escape to abort").unwrap();
+///     loop {
+///         match terminal.read_key(Some(Duration::from_secs(10)))? {
+///             Some(Key::Special(ScanCode::ESCAPE)) | None => return Ok(false),
+///             Some(Key::Printable(ch)) if ch == '\r' => return Ok(true),
+///             _ => {}
+///         }
+///     }
+/// }
+/// ```
+///
+/// [`Input`]: crate::proto::console::text::Input
+/// [`Output`]: crate::proto::console::text::Output
+#[derive(Debug)]
+pub struct Terminal<'a> {
+    serial: &'a mut Serial,
+    parser: KeyParser,
+}
+
+impl<'a> Terminal<'a> {
+    /// Creates a terminal using `serial`.
+    pub const fn new(serial: &'a mut Serial) -> Self {
+        Self {
+            serial,
+            parser: KeyParser::new(),
+        }
+    }
+
+    /// Returns the serial device.
+    pub const fn serial(&mut self) -> &mut Serial {
+        self.serial
+    }
+
+    /// Clears the screen, resets the colors, and moves the cursor to the
+    /// top left corner.
+    pub fn clear(&mut self) -> Result {
+        self.send(format_args!("\x1b[0m\x1b[2J\x1b[H"))
+    }
+
+    /// Moves the cursor to `column` and `row`, starting at zero.
+    pub fn set_cursor_position(&mut self, column: usize, row: usize) -> Result {
+        self.send(format_args!("\x1b[{};{}H", row + 1, column + 1))
+    }
+
+    /// Shows or hides the cursor.
+    pub fn enable_cursor(&mut self, visible: bool) -> Result {
+        let mode = if visible { 'h' } else { 'l' };
+        self.send(format_args!("\x1b[?25{mode}"))
+    }
+
+    /// Sets the colors of the text written afterwards.
+    pub fn set_color(&mut self, foreground: Color, background: Color) -> Result {
+        self.send(format_args!(
+            "\x1b[{};{}m",
+            ansi_color(foreground),
+            ansi_color(background) + 10
+        ))
+    }
+
+    /// Resets the colors to the defaults of the terminal.
+    pub fn reset_color(&mut self) -> Result {
+        self.send(format_args!("\x1b[0m"))
+    }
+
+    /// Reads a key, waiting up to `timeout` for it, or forever if `timeout`
+    /// is `None`. Returns `None` if no key was pressed.
+    ///
+    /// The timeout is set with the [`timeout`] of the serial device's
+    /// [`IoMode`], so its accuracy depends on the device, and the previous
+    /// value is not restored.
+    ///
+    /// Escape sequences for the arrow, editing and function keys are
+    /// translated to [`Key::Special`]. The delete character sent by most
+    /// terminals for backspace is translated to `\x08`, as with [`Input`].
+    /// UTF-8 encoded characters outside of the Basic Multilingual Plane are
+    /// ignored.
+    ///
+    /// # Errors
+    ///
+    /// - [`Status::DEVICE_ERROR`] if the serial device reported an error
+    ///
+    /// [`Input`]: crate::proto::console::text::Input
+    /// [`IoMode`]: super::IoMode
+    /// [`timeout`]: super::IoMode::timeout
+    pub fn read_key(&mut self, timeout: Option<Duration>) -> Result<Option<Key>> {
+        loop {
+            if let Some(key) = self.parser.next_key() {
+                return Ok(Some(key));
+            }
+            let byte_timeout = if self.parser.is_empty() {
+                timeout
+            } else {
+                Some(ESCAPE_TIMEOUT)
+            };
+            match self.read_byte(byte_timeout)? {
+                Some(byte) => self.parser.push(byte),
+                None => return Ok(self.parser.flush()),
+            }
+        }
+    }
+
+    fn read_byte(&mut self, timeout: Option<Duration>) -> Result<Option<u8>> {
+        // A timeout of zero selects the device's default timeout, so wait at
+        // least one microsecond.
+        let micros = timeout.map_or(u32::MAX, |timeout| {
+            u32::try_from(timeout.as_micros())
+                .unwrap_or(u32::MAX)
+                .max(1)
+        });
+        if self.serial.io_mode().timeout != micros {
+            let mut mode = *self.serial.io_mode();
+            mode.timeout = micros;
+            self.serial.set_attributes(&mode)?;
+        }
+
+        let mut byte = [0];
+        loop {
+            match self.serial.read(&mut byte) {
+                Ok(()) => return Ok(Some(byte[0])),
+                Err(err) if err.status() == Status::TIMEOUT => {
+                    if timeout.is_some() {
+                        return Ok(None);
+                    }
+                }
+                Err(err) => return Err(err.status().into()),
+            }
+        }
+    }
+
+    fn send(&mut self, args: fmt::Arguments<'_>) -> Result {
+        self.write_fmt(args)
+            .map_err(|_| Status::DEVICE_ERROR.into())
+    }
+}
+
+impl Write for Terminal<'_> {
+    /// Writes `s`, translating line feeds to carriage return and line feed.
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for (i, line) in s.split('\n').enumerate() {
+            if i > 0 {
+                self.serial.write(b"\r\n").map_err(|_| fmt::Error)?;
+            }
+            if !line.is_empty() {
+                self.serial.write(line.as_bytes()).map_err(|_| fmt::Error)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the ANSI foreground color code for `color`.
+const fn ansi_color(color: Color) -> u8 {
+    match color {
+        Color::Black => 30,
+        Color::Red => 31,
+        Color::Green => 32,
+        Color::Brown => 33,
+        Color::Blue => 34,
+        Color::Magenta => 35,
+        Color::Cyan => 36,
+        Color::LightGray => 37,
+        Color::DarkGray => 90,
+        Color::LightRed => 91,
+        Color::LightGreen => 92,
+        Color::Yellow => 93,
+        Color::LightBlue => 94,
+        Color::LightMagenta => 95,
+        Color::LightCyan => 96,
+        Color::White => 97,
+    }
+}
+
+/// Result of parsing the start of the input.
+#[derive(Debug, Eq, PartialEq)]
+enum Parsed {
+    /// A key and the number of bytes it was encoded with.
+    Key(Key, usize),
+    /// More bytes are needed.
+    Incomplete,
+    /// The given number of bytes should be skipped.
+    Invalid(usize),
+}
+
+/// Translates bytes received from a terminal to keys.
+#[derive(Debug)]
+struct KeyParser {
+    buf: [u8; 16],
+    len: usize,
+}
+
+impl KeyParser {
+    const fn new() -> Self {
+        Self {
+            buf: [0; 16],
+            len: 0,
+        }
+    }
+
+    const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    const fn push(&mut self, byte: u8) {
+        if self.len == self.buf.len() {
+            // Only unknown escape sequences can be this long.
+            self.len = 0;
+        }
+        self.buf[self.len] = byte;
+        self.len += 1;
+    }
+
+    /// Returns the next complete key.
+    fn next_key(&mut self) -> Option<Key> {
+        loop {
+            let (key, len) = match parse(&self.buf[..self.len]) {
+                Parsed::Key(key, len) => (Some(key), len),
+                Parsed::Invalid(len) => (None, len),
+                Parsed::Incomplete => return None,
+            };
+            self.buf.copy_within(len..self.len, 0);
+            self.len -= len;
+            if key.is_some() {
+                return key;
+            }
+        }
+    }
+
+    /// Handles a timeout while waiting for the rest of a key. A lone escape
+    /// byte is the escape key, anything else is discarded.
+    const fn flush(&mut self) -> Option<Key> {
+        let is_escape = self.len == 1 && self.buf[0] == ESC;
+        self.len = 0;
+        if is_escape {
+            Some(Key::Special(ScanCode::ESCAPE))
+        } else {
+            None
+        }
+    }
+}
+
+const ESC: u8 = 0x1b;
+
+fn parse(buf: &[u8]) -> Parsed {
+    let printable = |ch: char, len| match Char16::try_from(ch) {
+        Ok(ch) => Parsed::Key(Key::Printable(ch), len),
+        Err(_) => Parsed::Invalid(len),
+    };
+    match buf {
+        [] | [ESC] | [ESC, b'O'] => Parsed::Incomplete,
+        [ESC, b'[', rest @ ..] => parse_csi(rest),
+        [ESC, b'O', code, ..] => match ss3_key(*code) {
+            Some(code) => Parsed::Key(Key::Special(code), 3),
+            None => Parsed::Invalid(3),
+        },
+        // Escape followed by anything else is the escape key followed by
+        // another key.
+        [ESC, ..] => Parsed::Key(Key::Special(ScanCode::ESCAPE), 1),
+        [0x7f, ..] => printable('\x08', 1),
+        [byte, ..] if byte.is_ascii() => printable(char::from(*byte), 1),
+        [byte, ..] => {
+            let len = match byte.leading_ones() {
+                2 => 2,
+                3 => 3,
+                4 => 4,
+                _ => return Parsed::Invalid(1),
+            };
+            let Some(bytes) = buf.get(..len) else {
+                return Parsed::Incomplete;
+            };
+            match core::str::from_utf8(bytes) {
+                Ok(s) => printable(s.chars().next().unwrap(), len),
+                Err(_) => Parsed::Invalid(1),
+            }
+        }
+    }
+}
+
+/// Parses a control sequence after `ESC [`, like `ESC [ 1 ; 5 A` or
+/// `ESC [ 3 ~`. Modifiers are ignored.
+fn parse_csi(rest: &[u8]) -> Parsed {
+    let Some(end) = rest.iter().position(|byte| (0x40..=0x7e).contains(byte)) else {
+        return Parsed::Incomplete;
+    };
+    let len = end + 3;
+    let params = &rest[..end];
+    let code = match rest[end] {
+        b'~' => {
+            let number = params
+                .iter()
+                .take_while(|byte| byte.is_ascii_digit())
+                .fold(0u32, |n, byte| {
+                    n.saturating_mul(10).saturating_add(u32::from(byte - b'0'))
+                });
+            tilde_key(number)
+        }
+        code => ss3_key(code),
+    };
+    match code {
+        Some(code) => Parsed::Key(Key::Special(code), len),
+        None => Parsed::Invalid(len),
+    }
+}
+
+/// Keys of the form `ESC [ <code>` and `ESC O <code>`.
+const fn ss3_key(code: u8) -> Option<ScanCode> {
+    Some(match code {
+        b'A' => ScanCode::UP,
+        b'B' => ScanCode::DOWN,
+        b'C' => ScanCode::RIGHT,
+        b'D' => ScanCode::LEFT,
+        b'H' => ScanCode::HOME,
+        b'F' => ScanCode::END,
+        b'P' => ScanCode::FUNCTION_1,
+        b'Q' => ScanCode::FUNCTION_2,
+        b'R' => ScanCode::FUNCTION_3,
+        b'S' => ScanCode::FUNCTION_4,
+        _ => return None,
+    })
+}
+
+/// Keys of the form `ESC [ <number> ~`.
+const fn tilde_key(number: u32) -> Option<ScanCode> {
+    Some(match number {
+        1 | 7 => ScanCode::HOME,
+        2 => ScanCode::INSERT,
+        3 => ScanCode::DELETE,
+        4 | 8 => ScanCode::END,
+        5 => ScanCode::PAGE_UP,
+        6 => ScanCode::PAGE_DOWN,
+        11 => ScanCode::FUNCTION_1,
+        12 => ScanCode::FUNCTION_2,
+        13 => ScanCode::FUNCTION_3,
+        14 => ScanCode::FUNCTION_4,
+        15 => ScanCode::FUNCTION_5,
+        17 => ScanCode::FUNCTION_6,
+        18 => ScanCode::FUNCTION_7,
+        19 => ScanCode::FUNCTION_8,
+        20 => ScanCode::FUNCTION_9,
+        21 => ScanCode::FUNCTION_10,
+        23 => ScanCode::FUNCTION_11,
+        24 => ScanCode::FUNCTION_12,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    fn parse_all(bytes: &[u8]) -> Vec<Key> {
+        let mut parser = KeyParser::new();
+        let mut keys = Vec::new();
+        for &byte in bytes {
+            parser.push(byte);
+            while let Some(key) = parser.next_key() {
+                keys.push(key);
+            }
+        }
+        keys.extend(parser.flush());
+        keys
+    }
+
+    fn printable(ch: char) -> Key {
+        Key::Printable(Char16::try_from(ch).unwrap())
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(
+            parse_all(b"a\x1b[A\x1bOB\x1b[1;5C\x1b[3~\x1b[24~\x1bOP\x7f\r"),
+            [
+                printable('a'),
+                Key::Special(ScanCode::UP),
+                Key::Special(ScanCode::DOWN),
+                Key::Special(ScanCode::RIGHT),
+                Key::Special(ScanCode::DELETE),
+                Key::Special(ScanCode::FUNCTION_12),
+                Key::Special(ScanCode::FUNCTION_1),
+                printable('\x08'),
+                printable('\r'),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_escape() {
+        assert_eq!(parse_all(b"\x1b"), [Key::Special(ScanCode::ESCAPE)]);
+        assert_eq!(
+            parse_all(b"\x1bx"),
+            [Key::Special(ScanCode::ESCAPE), printable('x')]
+        );
+        // Unknown and incomplete sequences are dropped.
+        assert_eq!(parse_all(b"\x1b[99~z\x1b[1;"), [printable('z')]);
+        // Numbers too large for a `u32`.
+        assert_eq!(
+            parse_all(b"\x1b[99999999999~\x1b[4294967299~z"),
+            [printable('z')]
+        );
+    }
+
+    #[test]
+    fn test_parse_utf8() {
+        assert_eq!(
+            parse_all("é€😀!".as_bytes()),
+            [printable('é'), printable('€'), printable('!')]
+        );
+        assert_eq!(parse_all(b"\xff\x80a"), [printable('a')]);
+    }
+
+    #[test]
+    fn test_ansi_color() {
+        assert_eq!(ansi_color(Color::Brown), 33);
+        assert_eq!(ansi_color(Color::LightBlue), 94);
+    }
+}
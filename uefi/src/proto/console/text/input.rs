@@ -90,6 +90,20 @@ impl Input {
     }
 }
 
+/// Waits until a key is pressed on `input` and returns it.
+#[cfg(feature = "alloc")]
+pub(super) fn wait_for_key(input: &mut Input) -> Result<Key> {
+    use crate::ResultExt;
+
+    let mut events = [input.wait_for_key_event().ok_or(Status::UNSUPPORTED)?];
+    loop {
+        if let Some(key) = input.read_key()? {
+            return Ok(key);
+        }
+        crate::boot::wait_for_event(&mut events).discard_errdata()?;
+    }
+}
+
 /// A key read from the console (high-level version)
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum Key {
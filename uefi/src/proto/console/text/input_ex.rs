@@ -0,0 +1,356 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use super::Key;
+use crate::proto::unsafe_protocol;
+use crate::{Event, Result, Status, StatusExt};
+use core::mem::MaybeUninit;
+use core::ptr;
+use uefi_raw::protocol::console::{InputKey, KeyData, SimpleTextInputExProtocol};
+
+#[cfg(feature = "alloc")]
+use {
+    crate::util::Lock,
+    alloc::boxed::Box,
+    core::ffi::c_void,
+    core::marker::PhantomData,
+    uefi_raw::protocol::console::{KeyNotifyFunction, KeyState},
+};
+
+pub use uefi_raw::protocol::console::{KeyShiftState, KeyToggleState};
+
+/// Simple Text Input Ex [`Protocol`]. Extended interface for text-based input
+/// devices, which also reports the state of modifier and toggle keys.
+///
+/// Unlike [`Input`], most methods of this protocol take `&self`, so that keys can
+/// still be read while a [`KeyNotification`] borrows the protocol.
+///
+/// [`Input`]: super::Input
+/// [`Protocol`]: uefi::proto::Protocol
+#[derive(Debug)]
+#[repr(transparent)]
+#[unsafe_protocol(SimpleTextInputExProtocol::GUID)]
+pub struct InputEx(SimpleTextInputExProtocol);
+
+impl InputEx {
+    /// Resets the input device hardware.
+    ///
+    /// The `extended_verification` parameter is used to request that UEFI
+    /// performs an extended check and reset of the input device.
+    ///
+    /// # Errors
+    ///
+    /// - [`Status::DEVICE_ERROR`] if the device is malfunctioning and cannot
+    ///   be reset.
+    pub fn reset(&mut self, extended_verification: bool) -> Result {
+        unsafe { (self.0.reset)(&mut self.0, extended_verification.into()) }.to_result()
+    }
+
+    /// Reads the next keystroke from the input device, if any, along with the
+    /// state of the modifier and toggle keys.
+    ///
+    /// If [`KeyToggleState::KEY_STATE_EXPOSED`] is set, keystrokes without a
+    /// key, like pressing shift on its own, are reported with
+    /// [`KeyStroke::key`] set to `None`.
+    ///
+    /// # Errors
+    ///
+    /// - [`Status::DEVICE_ERROR`] if there was an issue with the input device
+    /// - [`Status::UNSUPPORTED`] if the keystroke is not supported by the
+    ///   device
+    pub fn read_key_stroke(&self) -> Result<Option<KeyStroke>> {
+        let mut data = MaybeUninit::<KeyData>::uninit();
+        match unsafe { (self.0.read_key_stroke_ex)(self.this(), data.as_mut_ptr()) } {
+            Status::NOT_READY => Ok(None),
+            other => other.to_result_with_val(|| Some(unsafe { data.assume_init() }.into())),
+        }
+    }
+
+    /// Event to be used with [`boot::wait_for_event`] in order to wait for a
+    /// key to be available.
+    ///
+    /// [`boot::wait_for_event`]: crate::boot::wait_for_event
+    #[must_use]
+    pub fn wait_for_key_event(&self) -> Option<Event> {
+        unsafe { Event::from_ptr(self.0.wait_for_key_ex) }
+    }
+
+    /// Sets the state of the toggle keys, like caps lock, and whether partial
+    /// keystrokes are reported. [`KeyToggleState::TOGGLE_STATE_VALID`] is
+    /// added automatically.
+    ///
+    /// # Errors
+    ///
+    /// - [`Status::DEVICE_ERROR`] if the device is not functioning correctly
+    /// - [`Status::UNSUPPORTED`] if the device does not support setting the
+    ///   state
+    pub fn set_state(&self, state: KeyToggleState) -> Result {
+        let state = state | KeyToggleState::TOGGLE_STATE_VALID;
+        unsafe { (self.0.set_state)(self.this(), &state) }.to_result()
+    }
+
+    /// Registers `callback` to be called when `key` is pressed.
+    ///
+    /// If `shift_state` or `toggle_state` is `Some`, the callback is only
+    /// called if the state of the modifier or toggle keys matches as well.
+    /// The callback runs in the notification function of the keyboard driver,
+    /// so it must not block. The key is still returned by
+    /// [`read_key_stroke`].
+    ///
+    /// The callback is unregistered when the returned [`KeyNotification`] is
+    /// dropped. At most [`MAX_KEY_NOTIFICATIONS`] callbacks can be registered
+    /// at the same time, across all devices.
+    ///
+    /// # Errors
+    ///
+    /// - [`Status::OUT_OF_RESOURCES`] if too many callbacks are registered
+    /// - [`Status::OUT_OF_RESOURCES`] if the firmware could not allocate its
+    ///   data structures
+    ///
+    /// [`MAX_KEY_NOTIFICATIONS`]: Self::MAX_KEY_NOTIFICATIONS
+    /// [`read_key_stroke`]: Self::read_key_stroke
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use core::sync::atomic::{AtomicBool, Ordering};
+    /// use uefi::proto::console::text::{InputEx, Key, KeyShiftState, ScanCode};
+    /// use uefi::Result;
+    ///
+    /// static HELP_REQUESTED: AtomicBool = AtomicBool::new(false);
+    ///
+    /// fn register_help_key(input: &InputEx) -> Result {
+    ///     let notification = input.register_key_notify(
+    ///         Key::Special(ScanCode::FUNCTION_1),
+    ///         Some(KeyShiftState::empty()),
+    ///         None,
+    ///         |_| HELP_REQUESTED.store(true, Ordering::Relaxed),
+    ///     )?;
+    ///
+    ///     // ... do something while F1 is registered ...
+    ///
+    ///     drop(notification);
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn register_key_notify(
+        &self,
+        key: Key,
+        shift_state: Option<KeyShiftState>,
+        toggle_state: Option<KeyToggleState>,
+        callback: impl FnMut(KeyStroke) + Send + 'static,
+    ) -> Result<KeyNotification<'_>> {
+        let mut callback = Some(Box::new(callback) as Box<KeyCallback>);
+        let slot = NOTIFY_SLOTS.iter().position(|slot| {
+            slot.try_lock(|slot| {
+                if slot.is_none() {
+                    *slot = callback.take();
+                }
+                callback.is_none()
+            }) == Some(true)
+        });
+        let slot = slot.ok_or(Status::OUT_OF_RESOURCES)?;
+
+        let data = KeyData {
+            key: key.into(),
+            key_state: KeyState {
+                key_shift_state: shift_state.map_or(KeyShiftState::empty(), |state| {
+                    state | KeyShiftState::SHIFT_STATE_VALID
+                }),
+                key_toggle_state: toggle_state.map_or(KeyToggleState::empty(), |state| {
+                    state | KeyToggleState::TOGGLE_STATE_VALID
+                }),
+            },
+        };
+        let mut handle = ptr::null_mut();
+        let status = unsafe {
+            (self.0.register_key_notify)(self.this(), &data, NOTIFY_FUNCTIONS[slot], &mut handle)
+        };
+        if let Err(err) = status.to_result() {
+            NOTIFY_SLOTS[slot].lock(|slot| *slot = None);
+            return Err(err);
+        }
+        Ok(KeyNotification {
+            protocol: self.this(),
+            handle,
+            slot,
+            _input: PhantomData,
+        })
+    }
+
+    /// Maximum number of callbacks registered with [`register_key_notify`] at
+    /// the same time.
+    ///
+    /// [`register_key_notify`]: Self::register_key_notify
+    #[cfg(feature = "alloc")]
+    pub const MAX_KEY_NOTIFICATIONS: usize = NOTIFY_FUNCTIONS.len();
+
+    const fn this(&self) -> *mut SimpleTextInputExProtocol {
+        ptr::from_ref(&self.0).cast_mut()
+    }
+}
+
+/// A keystroke read with [`InputEx`], including the state of the modifier
+/// and toggle keys.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct KeyStroke {
+    /// The key, or `None` for a partial keystroke like pressing shift on its
+    /// own.
+    pub key: Option<Key>,
+
+    /// State of the modifier keys. Only valid if
+    /// [`KeyShiftState::SHIFT_STATE_VALID`] is set.
+    pub shift_state: KeyShiftState,
+
+    /// State of the toggle keys. Only valid if
+    /// [`KeyToggleState::TOGGLE_STATE_VALID`] is set.
+    pub toggle_state: KeyToggleState,
+}
+
+impl KeyStroke {
+    /// Returns whether either shift key is pressed.
+    #[must_use]
+    pub const fn shift(&self) -> bool {
+        self.shift_state
+            .intersects(KeyShiftState::LEFT_SHIFT_PRESSED.union(KeyShiftState::RIGHT_SHIFT_PRESSED))
+    }
+
+    /// Returns whether either control key is pressed.
+    #[must_use]
+    pub const fn control(&self) -> bool {
+        self.shift_state.intersects(
+            KeyShiftState::LEFT_CONTROL_PRESSED.union(KeyShiftState::RIGHT_CONTROL_PRESSED),
+        )
+    }
+
+    /// Returns whether either alt key is pressed.
+    #[must_use]
+    pub const fn alt(&self) -> bool {
+        self.shift_state
+            .intersects(KeyShiftState::LEFT_ALT_PRESSED.union(KeyShiftState::RIGHT_ALT_PRESSED))
+    }
+}
+
+impl From<KeyData> for KeyStroke {
+    fn from(data: KeyData) -> Self {
+        let is_partial = data.key.scan_code == 0 && data.key.unicode_char == 0;
+        Self {
+            key: (!is_partial).then(|| data.key.into()),
+            shift_state: data.key_state.key_shift_state,
+            toggle_state: data.key_state.key_toggle_state,
+        }
+    }
+}
+
+impl From<Key> for InputKey {
+    fn from(key: Key) -> Self {
+        match key {
+            Key::Printable(ch) => Self {
+                scan_code: 0,
+                unicode_char: ch.into(),
+            },
+            Key::Special(code) => Self {
+                scan_code: code.0,
+                unicode_char: 0,
+            },
+        }
+    }
+}
+
+/// Callback registered with [`InputEx::register_key_notify`]. It is
+/// unregistered when this is dropped.
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+pub struct KeyNotification<'a> {
+    protocol: *mut SimpleTextInputExProtocol,
+    handle: *mut c_void,
+    slot: usize,
+    _input: PhantomData<&'a InputEx>,
+}
+
+#[cfg(feature = "alloc")]
+impl Drop for KeyNotification<'_> {
+    fn drop(&mut self) {
+        // The protocol is borrowed for the lifetime of `self`, so the
+        // pointer is still valid.
+        let status =
+            unsafe { ((*self.protocol).unregister_key_notify)(self.protocol, self.handle) };
+        if status.is_error() {
+            log::warn!("failed to unregister key notification: {status}");
+        }
+        // Wait for a running callback to return before freeing it.
+        NOTIFY_SLOTS[self.slot].lock(|slot| *slot = None);
+    }
+}
+
+#[cfg(feature = "alloc")]
+type KeyCallback = dyn FnMut(KeyStroke) + Send;
+
+/// Registered callbacks. The notification function of the firmware only
+/// receives the key, so each slot has its own function in
+/// `NOTIFY_FUNCTIONS`.
+#[cfg(feature = "alloc")]
+static NOTIFY_SLOTS: [Lock<Option<Box<KeyCallback>>>; 8] = [const { Lock::new(None) }; 8];
+
+#[cfg(feature = "alloc")]
+const NOTIFY_FUNCTIONS: [KeyNotifyFunction; 8] = [
+    notify::<0>,
+    notify::<1>,
+    notify::<2>,
+    notify::<3>,
+    notify::<4>,
+    notify::<5>,
+    notify::<6>,
+    notify::<7>,
+];
+
+#[cfg(feature = "alloc")]
+unsafe extern "efiapi" fn notify<const SLOT: usize>(key_data: *mut KeyData) -> Status {
+    let Some(&data) = (unsafe { key_data.as_ref() }) else {
+        return Status::INVALID_PARAMETER;
+    };
+    // The slot is locked while it is being registered or freed, in which case
+    // the key is ignored.
+    NOTIFY_SLOTS[SLOT].try_lock(|callback| {
+        if let Some(callback) = callback {
+            callback(data.into());
+        }
+    });
+    Status::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Char16;
+    use crate::proto::console::text::ScanCode;
+    use uefi_raw::protocol::console::KeyState;
+
+    #[test]
+    fn test_key_stroke() {
+        let data = KeyData {
+            key: InputKey {
+                scan_code: 0,
+                unicode_char: u16::from(b'a'),
+            },
+            key_state: KeyState {
+                key_shift_state: KeyShiftState::SHIFT_STATE_VALID
+                    | KeyShiftState::LEFT_CONTROL_PRESSED,
+                key_toggle_state: KeyToggleState::CAPS_LOCK_ACTIVE,
+            },
+        };
+        let stroke = KeyStroke::from(data);
+        let key = Key::Printable(Char16::try_from('a').unwrap());
+        assert_eq!(stroke.key, Some(key));
+        assert!(stroke.control());
+        assert!(!stroke.shift());
+        assert!(!stroke.alt());
+        assert_eq!(InputKey::from(key), data.key);
+
+        let partial = KeyStroke::from(KeyData::default());
+        assert_eq!(partial.key, None);
+
+        let escape = InputKey::from(Key::Special(ScanCode::ESCAPE));
+        assert_eq!((escape.scan_code, escape.unicode_char), (0x17, 0));
+    }
+}
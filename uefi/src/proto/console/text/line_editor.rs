@@ -0,0 +1,584 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Readline-style line editing on top of [`Input`] and [`Output`].
+
+use super::{Input, Key, Output, ScanCode, wait_for_key};
+use crate::{CString16, Result, Status};
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+
+/// Result of a completion callback.
+///
+/// The word starting at byte offset `start` of the line and ending at the
+/// cursor is replaced by the completion.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Completion {
+    /// Byte offset of the start of the word being completed.
+    pub start: usize,
+    /// Possible replacements for the word. Candidates that should be
+    /// followed by a separator, like a space, must include it.
+    pub candidates: Vec<String>,
+}
+
+type CompleteFn = dyn FnMut(&str, usize) -> Completion;
+
+/// Reads lines of text from the console, with editing, history and tab
+/// completion.
+///
+/// The following keys are supported:
+/// - Left, right, home and end move the cursor.
+/// - Backspace and delete remove characters.
+/// - Up and down browse the history.
+/// - Tab completes the word before the cursor. If there are several
+///   candidates, the longest common prefix is inserted, and pressing tab a
+///   second time lists the candidates.
+/// - Escape clears the line.
+/// - Enter finishes the line.
+///
+/// # Example
+///
+/// ```no_run
+/// use uefi::proto::console::text::{Completion, Input, LineEditor, Output};
+/// use uefi::{Result, boot};
+///
+/// fn shell() -> Result {
+///     let input = boot::get_handle_for_protocol::<Input>()?;
+///     let output = boot::get_handle_for_protocol::<Output>()?;
+///     let mut input = boot::open_protocol_exclusive::<Input>(input)?;
+///     let mut output = boot::open_protocol_exclusive::<Output>(output)?;
+///
+///     const COMMANDS: [&str; 3] = ["help", "reboot", "exit"];
+///     let mut editor = LineEditor::new().with_completer(|line: &str, cursor: usize| {
+///         let start = line[..cursor].rfind(' ').map_or(0, |i| i + 1);
+///         let word = &line[start..cursor];
+///         let candidates = COMMANDS
+///             .iter()
+///             .filter(|command| command.starts_with(word))
+///             .map(|command| format!("{command} "))
+///             .collect();
+///         Completion { start, candidates }
+///     });
+///
+///     loop {
+///         let line = editor.read_line(&mut input, &mut output, "> ")?;
+///         if line.trim() == "exit" {
+///             return Ok(());
+///         }
+///         editor.add_history(&line);
+///     }
+/// }
+/// ```
+pub struct LineEditor {
+    history: Vec<String>,
+    history_size: usize,
+    completer: Option<Box<CompleteFn>>,
+}
+
+impl LineEditor {
+    /// Default maximum number of history entries.
+    pub const DEFAULT_HISTORY_SIZE: usize = 100;
+
+    /// Creates a line editor with an empty history and no completion.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            history: Vec::new(),
+            history_size: Self::DEFAULT_HISTORY_SIZE,
+            completer: None,
+        }
+    }
+
+    /// Limits the history to `size` entries. The oldest entries are dropped
+    /// first.
+    #[must_use]
+    pub fn with_history_size(mut self, size: usize) -> Self {
+        self.history_size = size;
+        self.trim_history();
+        self
+    }
+
+    /// Sets the tab completion callback.
+    ///
+    /// The callback receives the line and the byte offset of the cursor, and
+    /// returns the candidates for the word before the cursor.
+    #[must_use]
+    pub fn with_completer(
+        mut self,
+        completer: impl FnMut(&str, usize) -> Completion + 'static,
+    ) -> Self {
+        self.completer = Some(Box::new(completer));
+        self
+    }
+
+    /// Returns the history, oldest entry first.
+    #[must_use]
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    /// Adds a line to the history.
+    ///
+    /// Empty lines and lines equal to the most recent entry are ignored.
+    pub fn add_history(&mut self, line: &str) {
+        if line.is_empty() || self.history.last().is_some_and(|last| last == line) {
+            return;
+        }
+        self.history.push(String::from(line));
+        self.trim_history();
+    }
+
+    /// Removes all history entries.
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+    }
+
+    /// Prints `prompt` and reads a line, which is returned without the line
+    /// break.
+    ///
+    /// The line is not added to the history automatically; use
+    /// [`add_history`] for that.
+    ///
+    /// [`add_history`]: Self::add_history
+    pub fn read_line(
+        &mut self,
+        input: &mut Input,
+        output: &mut Output,
+        prompt: &str,
+    ) -> Result<String> {
+        let mut state = LineState::new(false);
+        self.run(&mut state, input, output, prompt)?;
+        Ok(state.line())
+    }
+
+    /// Like [`read_line`], but returns the line as a [`CString16`].
+    ///
+    /// # Errors
+    ///
+    /// Besides the errors of [`read_line`], [`Status::INVALID_PARAMETER`] is
+    /// returned if a completion inserted characters that can't be represented
+    /// in UCS-2.
+    ///
+    /// [`read_line`]: Self::read_line
+    pub fn read_line_cstr16(
+        &mut self,
+        input: &mut Input,
+        output: &mut Output,
+        prompt: &str,
+    ) -> Result<CString16> {
+        let line = self.read_line(input, output, prompt)?;
+        CString16::try_from(line.as_str()).map_err(|_| Status::INVALID_PARAMETER.into())
+    }
+
+    /// Prints `prompt` and reads a password, showing `*` for each character.
+    ///
+    /// The history and completion are not used.
+    pub fn read_password(
+        &mut self,
+        input: &mut Input,
+        output: &mut Output,
+        prompt: &str,
+    ) -> Result<String> {
+        let mut state = LineState::new(true);
+        self.run(&mut state, input, output, prompt)?;
+        Ok(state.line())
+    }
+
+    fn run(
+        &mut self,
+        state: &mut LineState,
+        input: &mut Input,
+        output: &mut Output,
+        prompt: &str,
+    ) -> Result {
+        let mut view = View::start(output, prompt)?;
+        loop {
+            let key = wait_for_key(input)?;
+            let step = state.handle_key(key, &self.history, self.completer.as_deref_mut());
+            match step {
+                Step::Continue => view.draw(output, state)?,
+                Step::Submit => {
+                    view.finish(output, state)?;
+                    return Ok(());
+                }
+                Step::ShowCandidates(candidates) => {
+                    view.finish(output, state)?;
+                    write_output(output, format_args!("{}\r\n", candidates.join("  ")))?;
+                    view = View::start(output, prompt)?;
+                    view.draw(output, state)?;
+                }
+            }
+        }
+    }
+
+    fn trim_history(&mut self) {
+        let excess = self.history.len().saturating_sub(self.history_size);
+        self.history.drain(..excess);
+    }
+}
+
+impl Default for LineEditor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for LineEditor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LineEditor")
+            .field("history", &self.history)
+            .field("history_size", &self.history_size)
+            .field("completer", &self.completer.is_some())
+            .finish()
+    }
+}
+
+/// What the editor should do after a key press.
+#[derive(Debug, Eq, PartialEq)]
+enum Step {
+    Continue,
+    Submit,
+    ShowCandidates(Vec<String>),
+}
+
+/// Contents of the line being edited, independent of the console.
+#[derive(Debug)]
+struct LineState {
+    chars: Vec<char>,
+    cursor: usize,
+    masked: bool,
+    /// Index of the history entry being shown, if any.
+    history_index: Option<usize>,
+    /// Line that was being edited before browsing the history.
+    draft: Vec<char>,
+    /// Whether the previous key was an ambiguous tab completion.
+    after_tab: bool,
+}
+
+impl LineState {
+    const fn new(masked: bool) -> Self {
+        Self {
+            chars: Vec::new(),
+            cursor: 0,
+            masked,
+            history_index: None,
+            draft: Vec::new(),
+            after_tab: false,
+        }
+    }
+
+    fn line(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    fn set_line(&mut self, chars: Vec<char>) {
+        self.chars = chars;
+        self.cursor = self.chars.len();
+    }
+
+    /// Returns the characters to display.
+    fn display(&self) -> impl Iterator<Item = char> + '_ {
+        self.chars
+            .iter()
+            .map(|&ch| if self.masked { '*' } else { ch })
+    }
+
+    fn handle_key(
+        &mut self,
+        key: Key,
+        history: &[String],
+        completer: Option<&mut CompleteFn>,
+    ) -> Step {
+        let after_tab = core::mem::take(&mut self.after_tab);
+        match key {
+            Key::Special(ScanCode::LEFT) => self.cursor = self.cursor.saturating_sub(1),
+            Key::Special(ScanCode::RIGHT) => self.cursor = (self.cursor + 1).min(self.chars.len()),
+            Key::Special(ScanCode::HOME) => self.cursor = 0,
+            Key::Special(ScanCode::END) => self.cursor = self.chars.len(),
+            Key::Special(ScanCode::DELETE) => {
+                if self.cursor < self.chars.len() {
+                    self.chars.remove(self.cursor);
+                }
+            }
+            Key::Special(ScanCode::UP) if !self.masked => self.history_prev(history),
+            Key::Special(ScanCode::DOWN) if !self.masked => self.history_next(history),
+            Key::Special(ScanCode::ESCAPE) => self.set_line(Vec::new()),
+            Key::Printable(ch) => match char::from(ch) {
+                '\r' => return Step::Submit,
+                '\x08' => {
+                    if self.cursor > 0 {
+                        self.cursor -= 1;
+                        self.chars.remove(self.cursor);
+                    }
+                }
+                '\t' => {
+                    if let Some(completer) = completer.filter(|_| !self.masked) {
+                        return self.complete(completer, after_tab);
+                    }
+                }
+                ch if !ch.is_control() => {
+                    self.chars.insert(self.cursor, ch);
+                    self.cursor += 1;
+                }
+                _ => {}
+            },
+            Key::Special(_) => {}
+        }
+        Step::Continue
+    }
+
+    fn history_prev(&mut self, history: &[String]) {
+        let index = match self.history_index {
+            Some(index) => index.saturating_sub(1),
+            None if history.is_empty() => return,
+            None => {
+                self.draft = self.chars.clone();
+                history.len() - 1
+            }
+        };
+        self.history_index = Some(index);
+        self.set_line(history[index].chars().collect());
+    }
+
+    fn history_next(&mut self, history: &[String]) {
+        let Some(index) = self.history_index else {
+            return;
+        };
+        if index + 1 < history.len() {
+            self.history_index = Some(index + 1);
+            self.set_line(history[index + 1].chars().collect());
+        } else {
+            self.history_index = None;
+            let draft = core::mem::take(&mut self.draft);
+            self.set_line(draft);
+        }
+    }
+
+    fn complete(&mut self, completer: &mut CompleteFn, after_tab: bool) -> Step {
+        let line = self.line();
+        let cursor: usize = self.chars[..self.cursor]
+            .iter()
+            .map(|ch| ch.len_utf8())
+            .sum();
+        let completion = completer(&line, cursor);
+        if completion.start > cursor || !line.is_char_boundary(completion.start) {
+            return Step::Continue;
+        }
+        let start = line[..completion.start].chars().count();
+        let word = &self.chars[start..self.cursor];
+
+        let replacement: Vec<char> = match completion.candidates.as_slice() {
+            [] => return Step::Continue,
+            [candidate] => candidate.chars().collect(),
+            [first, rest @ ..] => {
+                let mut prefix: Vec<char> = first.chars().collect();
+                for candidate in rest {
+                    let common = prefix
+                        .iter()
+                        .zip(candidate.chars())
+                        .take_while(|(a, b)| **a == *b)
+                        .count();
+                    prefix.truncate(common);
+                }
+                if prefix.len() <= word.len() {
+                    if after_tab {
+                        return Step::ShowCandidates(completion.candidates);
+                    }
+                    self.after_tab = true;
+                    return Step::Continue;
+                }
+                self.after_tab = true;
+                prefix
+            }
+        };
+        let len = replacement.len();
+        self.chars.splice(start..self.cursor, replacement);
+        self.cursor = start + len;
+        Step::Continue
+    }
+}
+
+/// Position of the line on the console.
+struct View {
+    /// Column and row of the first character after the prompt.
+    start: (usize, usize),
+    /// Number of characters currently displayed.
+    displayed: usize,
+}
+
+impl View {
+    /// Prints the prompt and returns a view for the line after it.
+    fn start(output: &mut Output, prompt: &str) -> Result<Self> {
+        write_output(output, format_args!("{prompt}"))?;
+        Ok(Self {
+            start: output.cursor_position(),
+            displayed: 0,
+        })
+    }
+
+    /// Redraws the line and places the cursor.
+    fn draw(&mut self, output: &mut Output, state: &LineState) -> Result {
+        let columns = output
+            .current_mode()?
+            .map_or(80, |mode| mode.columns())
+            .max(1);
+        self.write(output, state)?;
+
+        // Writing may have scrolled the screen, so derive the start row from
+        // where the output ended.
+        let end = self.start.0 + self.displayed.max(state.chars.len());
+        let (_, row) = output.cursor_position();
+        self.start.1 = row.saturating_sub(end / columns);
+
+        let cursor = self.start.0 + state.cursor;
+        output.set_cursor_position(cursor % columns, self.start.1 + cursor / columns)
+    }
+
+    /// Redraws the line and moves to the next one.
+    fn finish(&mut self, output: &mut Output, state: &LineState) -> Result {
+        self.write(output, state)?;
+        write_output(output, format_args!("\r\n"))
+    }
+
+    /// Writes the line at the start position, erasing leftover characters.
+    fn write(&mut self, output: &mut Output, state: &LineState) -> Result {
+        output.set_cursor_position(self.start.0, self.start.1)?;
+        let mut text: String = state.display().collect();
+        let len = state.chars.len();
+        text.extend(core::iter::repeat_n(
+            ' ',
+            self.displayed.saturating_sub(len),
+        ));
+        write_output(output, format_args!("{text}"))?;
+        self.displayed = self.displayed.max(len);
+        Ok(())
+    }
+}
+
+fn write_output(output: &mut Output, args: fmt::Arguments<'_>) -> Result {
+    output
+        .write_fmt(args)
+        .map_err(|_| Status::DEVICE_ERROR.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Char16;
+    use alloc::vec;
+
+    fn key(ch: char) -> Key {
+        Key::Printable(Char16::try_from(ch).unwrap())
+    }
+
+    fn type_str(state: &mut LineState, s: &str) {
+        for ch in s.chars() {
+            assert_eq!(state.handle_key(key(ch), &[], None), Step::Continue);
+        }
+    }
+
+    fn special(state: &mut LineState, history: &[String], code: ScanCode) {
+        assert_eq!(
+            state.handle_key(Key::Special(code), history, None),
+            Step::Continue
+        );
+    }
+
+    #[test]
+    fn test_editing() {
+        let mut state = LineState::new(false);
+        type_str(&mut state, "hllo");
+        special(&mut state, &[], ScanCode::HOME);
+        special(&mut state, &[], ScanCode::RIGHT);
+        type_str(&mut state, "e");
+        assert_eq!((state.line().as_str(), state.cursor), ("hello", 2));
+
+        special(&mut state, &[], ScanCode::DELETE);
+        type_str(&mut state, "\x08\x08");
+        assert_eq!((state.line().as_str(), state.cursor), ("lo", 0));
+
+        special(&mut state, &[], ScanCode::END);
+        type_str(&mut state, " world");
+        assert_eq!(state.line(), "lo world");
+        assert_eq!(state.handle_key(key('\r'), &[], None), Step::Submit);
+
+        special(&mut state, &[], ScanCode::ESCAPE);
+        assert_eq!((state.line().as_str(), state.cursor), ("", 0));
+    }
+
+    #[test]
+    fn test_history() {
+        let mut editor = LineEditor::new().with_history_size(3);
+        for line in ["one", "two", "two", "", "three", "four"] {
+            editor.add_history(line);
+        }
+        assert_eq!(editor.history(), ["two", "three", "four"]);
+        let history = editor.history();
+
+        let mut state = LineState::new(false);
+        type_str(&mut state, "draft");
+        special(&mut state, history, ScanCode::UP);
+        assert_eq!(state.line(), "four");
+        special(&mut state, history, ScanCode::UP);
+        special(&mut state, history, ScanCode::UP);
+        special(&mut state, history, ScanCode::UP);
+        assert_eq!((state.line().as_str(), state.cursor), ("two", 3));
+        special(&mut state, history, ScanCode::DOWN);
+        assert_eq!(state.line(), "three");
+        special(&mut state, history, ScanCode::DOWN);
+        special(&mut state, history, ScanCode::DOWN);
+        assert_eq!(state.line(), "draft");
+        special(&mut state, history, ScanCode::DOWN);
+        assert_eq!(state.line(), "draft");
+
+        // Passwords don't use the history.
+        let mut state = LineState::new(true);
+        special(&mut state, history, ScanCode::UP);
+        assert_eq!(state.line(), "");
+    }
+
+    #[test]
+    fn test_completion() {
+        let mut completer = |line: &str, cursor: usize| {
+            let start = line[..cursor].rfind(' ').map_or(0, |i| i + 1);
+            let candidates = ["reboot", "reset", "help"]
+                .iter()
+                .filter(|word| word.starts_with(&line[start..cursor]))
+                .map(|&word| String::from(word))
+                .collect();
+            Completion { start, candidates }
+        };
+        let mut tab =
+            |state: &mut LineState| state.handle_key(key('\t'), &[], Some(&mut completer));
+
+        let mut state = LineState::new(false);
+        type_str(&mut state, "x h");
+        assert_eq!(tab(&mut state), Step::Continue);
+        assert_eq!((state.line().as_str(), state.cursor), ("x help", 6));
+
+        // The common prefix is inserted first, then the candidates are shown.
+        let mut state = LineState::new(false);
+        type_str(&mut state, "r");
+        assert_eq!(tab(&mut state), Step::Continue);
+        assert_eq!(state.line(), "re");
+        assert_eq!(
+            tab(&mut state),
+            Step::ShowCandidates(vec![String::from("reboot"), String::from("reset")])
+        );
+
+        // Completion replaces the word before the cursor only.
+        let mut state = LineState::new(false);
+        type_str(&mut state, "hx");
+        special(&mut state, &[], ScanCode::LEFT);
+        tab(&mut state);
+        assert_eq!((state.line().as_str(), state.cursor), ("helpx", 4));
+
+        // No completion in password mode.
+        let mut state = LineState::new(true);
+        type_str(&mut state, "h");
+        tab(&mut state);
+        assert_eq!(state.line(), "h");
+        assert_eq!(state.display().collect::<String>(), "*");
+    }
+}
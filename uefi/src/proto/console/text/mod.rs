@@ -3,7 +3,22 @@
 //! Text I/O.
 
 mod input;
+#[cfg(feature = "alloc")]
+use input::wait_for_key;
 pub use input::{Input, Key, ScanCode};
 
+mod input_ex;
+#[cfg(feature = "alloc")]
+pub use input_ex::KeyNotification;
+pub use input_ex::{InputEx, KeyShiftState, KeyStroke, KeyToggleState};
+
+#[cfg(feature = "alloc")]
+mod line_editor;
+#[cfg(feature = "alloc")]
+pub use line_editor::{Completion, LineEditor};
+
 mod output;
 pub use output::{Color, Output, OutputMode};
+
+#[cfg(feature = "alloc")]
+pub mod tui;
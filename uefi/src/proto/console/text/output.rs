@@ -282,7 +282,7 @@ impl Iterator for OutputModeIter<'_> {
 /// All colors can be used as foreground colors.
 /// The first 8 colors can also be used as background colors.
 #[allow(missing_docs)]
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum Color {
     Black = 0,
     Blue,
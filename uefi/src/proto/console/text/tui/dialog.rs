@@ -0,0 +1,156 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use super::{Action, Screen, Theme, centered, draw_window, is_char};
+use crate::proto::console::text::{Key, ScanCode};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Window with a message and a row of buttons, centered on the screen.
+///
+/// The left and right arrow keys and tab select a button. Enter submits and
+/// escape cancels.
+#[derive(Clone, Debug)]
+pub struct Dialog {
+    title: String,
+    message: String,
+    buttons: Vec<String>,
+    selected: usize,
+}
+
+impl Dialog {
+    /// Creates a dialog with a single "OK" button. The message may contain
+    /// multiple lines.
+    #[must_use]
+    pub fn new(title: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            message: message.into(),
+            buttons: alloc::vec![String::from("OK")],
+            selected: 0,
+        }
+    }
+
+    /// Replaces the buttons of the dialog.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buttons` is empty.
+    #[must_use]
+    pub fn with_buttons<I, S>(mut self, buttons: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.buttons = buttons.into_iter().map(Into::into).collect();
+        assert!(!self.buttons.is_empty(), "a dialog needs a button");
+        self.selected = 0;
+        self
+    }
+
+    /// Returns the index of the selected button.
+    #[must_use]
+    pub const fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Processes a key press.
+    pub fn handle_key(&mut self, key: Key) -> Action {
+        let count = self.buttons.len();
+        match key {
+            Key::Special(ScanCode::LEFT) => self.selected = self.selected.saturating_sub(1),
+            Key::Special(ScanCode::RIGHT) => self.selected = (self.selected + 1).min(count - 1),
+            Key::Special(ScanCode::ESCAPE) => return Action::Cancel,
+            key if is_char(key, '\t') => self.selected = (self.selected + 1) % count,
+            key if is_char(key, '\r') => return Action::Submit,
+            _ => {}
+        }
+        Action::Continue
+    }
+
+    /// Draws the dialog in the center of `screen`, on top of its current
+    /// contents. Lines of the message that are too long are cut off.
+    pub fn draw(&self, screen: &mut Screen, theme: &Theme) {
+        let lines: Vec<&str> = self.message.lines().collect();
+        let buttons_width: usize = self
+            .buttons
+            .iter()
+            .map(|button| button.chars().count() + 4)
+            .sum::<usize>()
+            + self.buttons.len()
+            - 1;
+        let content_width = lines
+            .iter()
+            .map(|line| line.chars().count())
+            .chain([buttons_width, self.title.chars().count() + 2])
+            .max()
+            .unwrap_or(0);
+        let width = (content_width + 4).min(screen.columns());
+        let height = (lines.len() + 5).min(screen.rows());
+
+        let (x, y) = centered(screen, (width, height));
+        draw_window(screen, (x, y), (width, height), &self.title, theme);
+        for (row, line) in lines.iter().enumerate().take(height.saturating_sub(5)) {
+            let line: String = line.chars().take(width.saturating_sub(4)).collect();
+            screen.print((x + 2, y + 2 + row), &line, theme.window);
+        }
+
+        let mut column = x + width.saturating_sub(buttons_width) / 2;
+        let row = y + height.saturating_sub(2);
+        for (index, button) in self.buttons.iter().enumerate() {
+            let style = if index == self.selected {
+                theme.selected
+            } else {
+                theme.window
+            };
+            column += screen.print((column, row), &alloc::format!("< {button} >"), style) + 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::console::text::tui::screen::tests::{key, row_text};
+
+    #[test]
+    fn test_buttons() {
+        let mut dialog = Dialog::new("Title", "Message").with_buttons(["Yes", "No", "Cancel"]);
+        dialog.handle_key(Key::Special(ScanCode::LEFT));
+        assert_eq!(dialog.selected(), 0);
+        dialog.handle_key(Key::Special(ScanCode::RIGHT));
+        dialog.handle_key(Key::Special(ScanCode::RIGHT));
+        dialog.handle_key(Key::Special(ScanCode::RIGHT));
+        assert_eq!(dialog.selected(), 2);
+        assert_eq!(dialog.handle_key(key('\t')), Action::Continue);
+        assert_eq!(dialog.selected(), 0);
+        assert_eq!(dialog.handle_key(key('\r')), Action::Submit);
+        assert_eq!(
+            dialog.handle_key(Key::Special(ScanCode::ESCAPE)),
+            Action::Cancel
+        );
+    }
+
+    #[test]
+    fn test_draw() {
+        let theme = Theme::default();
+        let mut screen = Screen::new(24, 8, theme.normal);
+        let dialog = Dialog::new("Info", "Hello\nworld!").with_buttons(["Yes", "No"]);
+        dialog.draw(&mut screen, &theme);
+        let rows: Vec<_> = (0..8).map(|row| row_text(&screen, row)).collect();
+        assert_eq!(
+            rows,
+            [
+                "   ┌───── Info ─────┐   ",
+                "   │                │   ",
+                "   │ Hello          │   ",
+                "   │ world!         │   ",
+                "   │                │   ",
+                "   │ < Yes > < No > │   ",
+                "   └────────────────┘   ",
+                "                        ",
+            ]
+        );
+        assert_eq!(screen.get(5, 5).unwrap().1, theme.selected);
+        assert_eq!(screen.get(13, 5).unwrap().1, theme.window);
+    }
+}
@@ -0,0 +1,192 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use super::screen::{ARROW_DOWN, ARROW_UP};
+use super::{Action, Screen, Theme, is_char};
+use crate::proto::console::text::{Key, ScanCode};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Scrollable list of items, one of which is selected.
+///
+/// The selection is moved with the arrow keys, page up and down, home and
+/// end. Enter submits and escape cancels.
+#[derive(Clone, Debug)]
+pub struct Menu {
+    items: Vec<String>,
+    selected: usize,
+    scroll: usize,
+    page_size: usize,
+}
+
+impl Menu {
+    /// Creates a menu with the first item selected.
+    #[must_use]
+    pub fn new<I, S>(items: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            items: items.into_iter().map(Into::into).collect(),
+            selected: 0,
+            scroll: 0,
+            page_size: 1,
+        }
+    }
+
+    /// Returns the items of the menu.
+    #[must_use]
+    pub fn items(&self) -> &[String] {
+        &self.items
+    }
+
+    /// Returns the index of the selected item.
+    #[must_use]
+    pub const fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Selects the item at `index`, or the last item if `index` is too large.
+    pub fn select(&mut self, index: usize) {
+        self.selected = index.min(self.items.len().saturating_sub(1));
+    }
+
+    /// Processes a key press.
+    pub fn handle_key(&mut self, key: Key) -> Action {
+        let last = self.items.len().saturating_sub(1);
+        match key {
+            Key::Special(ScanCode::UP) => self.select(self.selected.saturating_sub(1)),
+            Key::Special(ScanCode::DOWN) => self.select(self.selected + 1),
+            Key::Special(ScanCode::PAGE_UP) => {
+                self.select(self.selected.saturating_sub(self.page_size));
+            }
+            Key::Special(ScanCode::PAGE_DOWN) => self.select(self.selected + self.page_size),
+            Key::Special(ScanCode::HOME) => self.select(0),
+            Key::Special(ScanCode::END) => self.select(last),
+            Key::Special(ScanCode::ESCAPE) => return Action::Cancel,
+            key if is_char(key, '\r') && !self.items.is_empty() => return Action::Submit,
+            _ => {}
+        }
+        Action::Continue
+    }
+
+    /// Draws the menu into the rectangle at `pos` of size `dims`, scrolling
+    /// it so that the selected item is visible.
+    ///
+    /// The last column shows arrows if there are more items above or below.
+    pub fn draw(
+        &mut self,
+        screen: &mut Screen,
+        pos: (usize, usize),
+        dims: (usize, usize),
+        theme: &Theme,
+    ) {
+        let (width, height) = dims;
+        // The items are drawn after a margin column, and one column before
+        // the column of the arrows.
+        if width < 3 || height == 0 {
+            return;
+        }
+        self.page_size = height;
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + height {
+            self.scroll = self.selected + 1 - height;
+        }
+        self.scroll = self.scroll.min(self.items.len().saturating_sub(height));
+
+        screen.fill(pos, dims, ' ', theme.normal);
+        for (row, (index, item)) in self
+            .items
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(height)
+            .enumerate()
+        {
+            let style = if index == self.selected {
+                theme.selected
+            } else {
+                theme.normal
+            };
+            let y = pos.1 + row;
+            screen.fill((pos.0, y), (width - 1, 1), ' ', style);
+            let text: String = item.chars().take(width - 3).collect();
+            screen.print((pos.0 + 1, y), &text, style);
+        }
+
+        let arrow_column = pos.0 + width - 1;
+        if self.scroll > 0 {
+            screen.fill((arrow_column, pos.1), (1, 1), ARROW_UP, theme.normal);
+        }
+        if self.scroll + height < self.items.len() {
+            screen.fill(
+                (arrow_column, pos.1 + height - 1),
+                (1, 1),
+                ARROW_DOWN,
+                theme.normal,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::console::text::tui::screen::tests::{key, row_text};
+
+    #[test]
+    fn test_navigation() {
+        let mut menu = Menu::new(["a", "b", "c", "d", "e"]);
+        assert_eq!(
+            menu.handle_key(Key::Special(ScanCode::UP)),
+            Action::Continue
+        );
+        assert_eq!(menu.selected(), 0);
+        menu.handle_key(Key::Special(ScanCode::DOWN));
+        menu.handle_key(Key::Special(ScanCode::DOWN));
+        assert_eq!(menu.selected(), 2);
+        menu.handle_key(Key::Special(ScanCode::END));
+        assert_eq!(menu.selected(), 4);
+        menu.handle_key(Key::Special(ScanCode::DOWN));
+        assert_eq!(menu.selected(), 4);
+        menu.handle_key(Key::Special(ScanCode::HOME));
+        assert_eq!(menu.selected(), 0);
+
+        assert_eq!(menu.handle_key(key('\r')), Action::Submit);
+        assert_eq!(
+            menu.handle_key(Key::Special(ScanCode::ESCAPE)),
+            Action::Cancel
+        );
+        assert_eq!(
+            Menu::new::<_, String>([]).handle_key(key('\r')),
+            Action::Continue
+        );
+    }
+
+    #[test]
+    fn test_scrolling() {
+        let theme = Theme::default();
+        let mut screen = Screen::new(6, 3, theme.normal);
+        let mut menu = Menu::new(["one", "two", "three", "four", "five"]);
+        menu.draw(&mut screen, (0, 0), (6, 3), &theme);
+        assert_eq!(row_text(&screen, 0), " one  ");
+        assert_eq!(row_text(&screen, 2), " thr ↓");
+        assert_eq!(screen.get(1, 0).unwrap().1, theme.selected);
+
+        // Page down uses the height of the last drawn menu.
+        menu.handle_key(Key::Special(ScanCode::PAGE_DOWN));
+        menu.handle_key(Key::Special(ScanCode::DOWN));
+        assert_eq!(menu.selected(), 4);
+        menu.draw(&mut screen, (0, 0), (6, 3), &theme);
+        assert_eq!(row_text(&screen, 0), " thr ↑");
+        assert_eq!(row_text(&screen, 2), " fiv  ");
+        assert_eq!(screen.get(1, 2).unwrap().1, theme.selected);
+        assert_eq!(screen.get(1, 1).unwrap().1, theme.normal);
+
+        // Too narrow to show anything.
+        let mut screen = Screen::new(2, 3, theme.normal);
+        menu.draw(&mut screen, (0, 0), (2, 3), &theme);
+        assert_eq!(row_text(&screen, 0), "  ");
+    }
+}
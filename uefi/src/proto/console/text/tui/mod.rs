@@ -0,0 +1,444 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Text-mode user interface toolkit.
+//!
+//! This module provides a small set of widgets for building interactive text
+//! interfaces like boot menus on top of the [`Output`] and [`Input`]
+//! protocols:
+//!
+//! * [`Menu`]: a scrollable list of selectable items.
+//! * [`Dialog`]: a message box with one or more buttons.
+//! * [`TextInput`]: a single-line text field.
+//! * [`ProgressBar`]: a horizontal progress indicator.
+//!
+//! Widgets draw into a [`Screen`], an off-screen buffer that only writes
+//! changed characters to the console. They process keys with their
+//! `handle_key` method, which returns an [`Action`] telling the caller when
+//! the user confirmed or cancelled.
+//!
+//! [`Tui`] ties everything together and provides ready-made event loops for
+//! the common cases:
+//!
+//! ```no_run
+//! use core::time::Duration;
+//! use uefi::proto::console::text::tui::{Menu, Tui};
+//! use uefi::system;
+//!
+//! # fn main() -> uefi::Result {
+//! let choice = system::with_stdin(|input| {
+//!     system::with_stdout(|output| -> uefi::Result<Option<usize>> {
+//!         let mut tui = Tui::new(output, input)?;
+//!         let mut menu = Menu::new(["Boot Linux", "Boot Windows", "Firmware setup"]);
+//!         // Boot the first entry if the user doesn't press a key in time.
+//!         let choice = tui.run_menu("Boot menu", &mut menu, Some(Duration::from_secs(5)))?;
+//!         if choice == Some(2) && !tui.confirm("Setup", "Reboot into the firmware setup?")? {
+//!             return Ok(None);
+//!         }
+//!         Ok(choice)
+//!     })
+//! })?;
+//! # Ok(())
+//! # }
+//! ```
+
+mod dialog;
+mod menu;
+mod progress;
+mod screen;
+mod text_input;
+
+pub use dialog::Dialog;
+pub use menu::Menu;
+pub use progress::ProgressBar;
+pub use screen::Screen;
+pub use text_input::TextInput;
+
+use crate::boot::{self, EventType, TimerTrigger, Tpl};
+use crate::proto::console::text::{Color, Input, Key, Output, OutputMode};
+use crate::{Result, ResultExt, Status};
+use alloc::string::String;
+use core::time::Duration;
+
+/// Foreground and background color of text.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Style {
+    /// Text color.
+    pub foreground: Color,
+    /// Background color. Must be one of the first 8 colors, see
+    /// [`Output::set_color`].
+    pub background: Color,
+}
+
+impl Style {
+    /// Light gray text on a black background, the default of most consoles.
+    pub const DEFAULT: Self = Self::new(Color::LightGray, Color::Black);
+
+    /// Creates a new style.
+    #[must_use]
+    pub const fn new(foreground: Color, background: Color) -> Self {
+        Self {
+            foreground,
+            background,
+        }
+    }
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Styles used by the widgets.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Theme {
+    /// Screen background and unselected items.
+    pub normal: Style,
+    /// Selected items and buttons.
+    pub selected: Style,
+    /// Dialog windows.
+    pub window: Style,
+    /// Titles of screens and windows.
+    pub title: Style,
+    /// Text input fields.
+    pub input: Style,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            normal: Style::DEFAULT,
+            selected: Style::new(Color::Black, Color::LightGray),
+            window: Style::new(Color::White, Color::Blue),
+            title: Style::new(Color::Yellow, Color::Blue),
+            input: Style::new(Color::White, Color::Black),
+        }
+    }
+}
+
+/// Result of passing a key to a widget.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Action {
+    /// The key was ignored, or changed the state of the widget.
+    Continue,
+    /// The user confirmed, usually with the enter key.
+    Submit,
+    /// The user cancelled, usually with the escape key.
+    Cancel,
+}
+
+/// Text-mode user interface running on an [`Output`] and an [`Input`].
+///
+/// See the [module-level documentation](self) for an example.
+#[derive(Debug)]
+pub struct Tui<'a> {
+    output: &'a mut Output,
+    input: &'a mut Input,
+    screen: Screen,
+    theme: Theme,
+}
+
+impl<'a> Tui<'a> {
+    /// Creates a user interface covering the whole `output`, and hides the
+    /// cursor.
+    pub fn new(output: &'a mut Output, input: &'a mut Input) -> Result<Self> {
+        let theme = Theme::default();
+        let (columns, rows) = match output.current_mode()? {
+            Some(mode) => (mode.columns(), mode.rows()),
+            // Every console must support 80x25.
+            None => (80, 25),
+        };
+        ignore_unsupported(output.enable_cursor(false))?;
+        Ok(Self {
+            output,
+            input,
+            screen: Screen::new(columns, rows, theme.normal),
+            theme,
+        })
+    }
+
+    /// Returns the current theme.
+    #[must_use]
+    pub const fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    /// Changes the theme used by the widgets.
+    pub const fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    /// Returns the screen buffer, for drawing custom content. Changes become
+    /// visible with the next [`render`].
+    ///
+    /// [`render`]: Self::render
+    pub const fn screen(&mut self) -> &mut Screen {
+        &mut self.screen
+    }
+
+    /// Returns the text modes supported by the output.
+    pub fn modes(&mut self) -> impl Iterator<Item = OutputMode> + '_ {
+        self.output.modes()
+    }
+
+    /// Changes the text mode of the output, and resizes and clears the
+    /// screen accordingly.
+    pub fn set_mode(&mut self, mode: OutputMode) -> Result {
+        self.output.set_mode(mode)?;
+        self.screen
+            .resize(mode.columns(), mode.rows(), self.theme.normal);
+        ignore_unsupported(self.output.enable_cursor(false))
+    }
+
+    /// Switches to the text mode with the most characters.
+    pub fn set_largest_mode(&mut self) -> Result {
+        let mode = self
+            .modes()
+            .max_by_key(|mode| mode.columns() * mode.rows())
+            .ok_or(Status::UNSUPPORTED)?;
+        self.set_mode(mode)
+    }
+
+    /// Writes the changes to the screen to the output.
+    pub fn render(&mut self) -> Result {
+        self.screen.render(self.output)
+    }
+
+    /// Waits for a key press for at most `timeout`, or forever if `timeout`
+    /// is `None`.
+    ///
+    /// Returns `None` if the timeout expired.
+    pub fn read_key(&mut self, timeout: Option<Duration>) -> Result<Option<Key>> {
+        if let Some(key) = self.input.read_key()? {
+            return Ok(Some(key));
+        }
+        let key_event = self.input.wait_for_key_event().ok_or(Status::UNSUPPORTED)?;
+
+        let Some(timeout) = timeout else {
+            let mut events = [key_event];
+            loop {
+                boot::wait_for_event(&mut events).discard_errdata()?;
+                if let Some(key) = self.input.read_key()? {
+                    return Ok(Some(key));
+                }
+            }
+        };
+
+        let timer = unsafe { boot::create_event(EventType::TIMER, Tpl::CALLBACK, None, None) }?;
+        // The timer uses units of 100 ns.
+        let ticks = u64::try_from(timeout.as_nanos() / 100).unwrap_or(u64::MAX);
+        let result = boot::set_timer(&timer, TimerTrigger::Relative(ticks)).and_then(|()| {
+            let mut events = [key_event, unsafe { timer.unsafe_clone() }];
+            loop {
+                if boot::wait_for_event(&mut events).discard_errdata()? == 1 {
+                    return Ok(None);
+                }
+                if let Some(key) = self.input.read_key()? {
+                    return Ok(Some(key));
+                }
+            }
+        });
+        boot::close_event(timer)?;
+        result
+    }
+
+    /// Shows `menu` below `title` until the user selects an item or presses
+    /// escape.
+    ///
+    /// If `timeout` is set, the remaining time is shown, and the selected item
+    /// is chosen when it expires. Any key press stops the countdown.
+    ///
+    /// Returns the index of the chosen item, or `None` if the user cancelled.
+    pub fn run_menu(
+        &mut self,
+        title: &str,
+        menu: &mut Menu,
+        mut timeout: Option<Duration>,
+    ) -> Result<Option<usize>> {
+        const TICK: Duration = Duration::from_secs(1);
+        loop {
+            let theme = self.theme;
+            let (columns, rows) = (self.screen.columns(), self.screen.rows());
+            self.screen.clear(theme.normal);
+            self.screen.fill((0, 0), (columns, 1), ' ', theme.title);
+            self.screen.print((1, 0), title, theme.title);
+            menu.draw(
+                &mut self.screen,
+                (2, 2),
+                (columns.saturating_sub(4), rows.saturating_sub(5)),
+                &theme,
+            );
+            let help = match timeout {
+                Some(timeout) => {
+                    let seconds = timeout.as_secs() + u64::from(timeout.subsec_nanos() > 0);
+                    alloc::format!("Selecting automatically in {seconds}s")
+                }
+                None => String::from("\u{2191}/\u{2193}: Select  Enter: Confirm  Esc: Cancel"),
+            };
+            self.screen
+                .print((2, rows.saturating_sub(2)), &help, theme.normal);
+            self.screen.set_cursor(None);
+            self.render()?;
+
+            let wait = timeout.map(|timeout| timeout.min(TICK));
+            match self.read_key(wait)? {
+                Some(key) => {
+                    timeout = None;
+                    match menu.handle_key(key) {
+                        Action::Continue => {}
+                        Action::Submit => return Ok(Some(menu.selected())),
+                        Action::Cancel => return Ok(None),
+                    }
+                }
+                None => {
+                    let remaining = timeout.and_then(|timeout| timeout.checked_sub(TICK));
+                    match remaining {
+                        Some(remaining) if !remaining.is_zero() => timeout = Some(remaining),
+                        _ => return Ok(Some(menu.selected())),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Shows `dialog` on top of the current screen until the user presses a
+    /// button or escape.
+    ///
+    /// Returns the index of the pressed button, or `None` if the user
+    /// cancelled.
+    pub fn run_dialog(&mut self, dialog: &mut Dialog) -> Result<Option<usize>> {
+        loop {
+            let theme = self.theme;
+            dialog.draw(&mut self.screen, &theme);
+            self.screen.set_cursor(None);
+            self.render()?;
+            if let Some(key) = self.read_key(None)? {
+                match dialog.handle_key(key) {
+                    Action::Continue => {}
+                    Action::Submit => return Ok(Some(dialog.selected())),
+                    Action::Cancel => return Ok(None),
+                }
+            }
+        }
+    }
+
+    /// Shows a message until the user confirms it.
+    pub fn message(&mut self, title: &str, message: &str) -> Result {
+        self.run_dialog(&mut Dialog::new(title, message))?;
+        Ok(())
+    }
+
+    /// Asks a yes or no question, and returns whether the user answered yes.
+    pub fn confirm(&mut self, title: &str, message: &str) -> Result<bool> {
+        let mut dialog = Dialog::new(title, message).with_buttons(["Yes", "No"]);
+        Ok(self.run_dialog(&mut dialog)? == Some(0))
+    }
+
+    /// Shows `input` with a `label` in a window until the user presses enter
+    /// or escape.
+    ///
+    /// Returns the entered text, or `None` if the user cancelled.
+    pub fn prompt(
+        &mut self,
+        title: &str,
+        label: &str,
+        input: &mut TextInput,
+    ) -> Result<Option<String>> {
+        loop {
+            let theme = self.theme;
+            let width = self.screen.columns().saturating_sub(4).min(60);
+            let (x, y) = centered(&self.screen, (width, 6));
+            draw_window(&mut self.screen, (x, y), (width, 6), title, &theme);
+            self.screen.print((x + 2, y + 2), label, theme.window);
+            input.draw(
+                &mut self.screen,
+                (x + 2, y + 3),
+                width.saturating_sub(4),
+                theme.input,
+            );
+            self.render()?;
+
+            if let Some(key) = self.read_key(None)? {
+                match input.handle_key(key) {
+                    Action::Continue => {}
+                    Action::Submit => {
+                        self.screen.set_cursor(None);
+                        return Ok(Some(input.value()));
+                    }
+                    Action::Cancel => {
+                        self.screen.set_cursor(None);
+                        return Ok(None);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Shows `bar` with a `title` in a window, without waiting for input.
+    ///
+    /// Call this again after updating the progress.
+    pub fn show_progress(&mut self, title: &str, bar: &ProgressBar) -> Result {
+        let theme = self.theme;
+        let width = self.screen.columns().saturating_sub(4).min(60);
+        let (x, y) = centered(&self.screen, (width, 5));
+        draw_window(&mut self.screen, (x, y), (width, 5), title, &theme);
+        bar.draw(
+            &mut self.screen,
+            (x + 2, y + 2),
+            width.saturating_sub(4),
+            &theme,
+        );
+        self.screen.set_cursor(None);
+        self.render()
+    }
+}
+
+/// Returns the position of a window of size `dims` centered on `screen`.
+const fn centered(screen: &Screen, dims: (usize, usize)) -> (usize, usize) {
+    (
+        screen.columns().saturating_sub(dims.0) / 2,
+        screen.rows().saturating_sub(dims.1) / 2,
+    )
+}
+
+/// Draws a framed window with `title` in its top border.
+fn draw_window(
+    screen: &mut Screen,
+    pos: (usize, usize),
+    dims: (usize, usize),
+    title: &str,
+    theme: &Theme,
+) {
+    screen.draw_box(pos, dims, theme.window);
+    let title: String = title.chars().take(dims.0.saturating_sub(4)).collect();
+    if title.is_empty() {
+        return;
+    }
+    let x = pos.0 + (dims.0 - title.chars().count()) / 2;
+    screen.print((x - 1, pos.1), " ", theme.window);
+    let len = screen.print((x, pos.1), &title, theme.title);
+    screen.print((x + len, pos.1), " ", theme.window);
+}
+
+/// Returns the character of a key, if it's printable.
+fn printable(key: Key) -> Option<char> {
+    match key {
+        Key::Printable(ch) => Some(char::from(ch)).filter(|ch| !ch.is_control()),
+        Key::Special(_) => None,
+    }
+}
+
+/// Returns whether `key` is the printable key `ch`, for control characters
+/// like enter.
+fn is_char(key: Key, ch: char) -> bool {
+    matches!(key, Key::Printable(key) if char::from(key) == ch)
+}
+
+/// Treats [`Status::UNSUPPORTED`] as success, for optional operations like
+/// hiding the cursor.
+fn ignore_unsupported(result: Result) -> Result {
+    match result {
+        Err(err) if err.status() == Status::UNSUPPORTED => Ok(()),
+        other => other,
+    }
+}
@@ -0,0 +1,89 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use super::Theme;
+use super::screen::{FULL_BLOCK, LIGHT_SHADE, Screen};
+
+/// Horizontal bar showing the progress of an operation, followed by the
+/// percentage.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ProgressBar {
+    value: u64,
+    total: u64,
+}
+
+impl ProgressBar {
+    /// Creates a progress bar for an operation with `total` steps.
+    #[must_use]
+    pub const fn new(total: u64) -> Self {
+        Self { value: 0, total }
+    }
+
+    /// Returns the number of completed steps.
+    #[must_use]
+    pub const fn value(&self) -> u64 {
+        self.value
+    }
+
+    /// Returns the total number of steps.
+    #[must_use]
+    pub const fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Sets the number of completed steps, limited to the total.
+    pub fn set_value(&mut self, value: u64) {
+        self.value = value.min(self.total);
+    }
+
+    /// Returns the progress in percent.
+    #[must_use]
+    pub const fn percent(&self) -> u8 {
+        if self.total == 0 {
+            100
+        } else {
+            (self.value as u128 * 100 / self.total as u128) as u8
+        }
+    }
+
+    /// Draws the progress bar at `pos` with the given `width`, including the
+    /// percentage.
+    pub fn draw(&self, screen: &mut Screen, pos: (usize, usize), width: usize, theme: &Theme) {
+        let label = alloc::format!("{:>4}%", self.percent());
+        let bar_width = width.saturating_sub(label.len());
+        let filled = bar_width * usize::from(self.percent()) / 100;
+        screen.fill(pos, (filled, 1), FULL_BLOCK, theme.window);
+        screen.fill(
+            (pos.0 + filled, pos.1),
+            (bar_width - filled, 1),
+            LIGHT_SHADE,
+            theme.window,
+        );
+        screen.print((pos.0 + bar_width, pos.1), &label, theme.window);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::console::text::tui::screen::tests::row_text;
+
+    #[test]
+    fn test_progress() {
+        let theme = Theme::default();
+        let mut screen = Screen::new(15, 1, theme.normal);
+        let mut bar = ProgressBar::new(3);
+        bar.draw(&mut screen, (0, 0), 15, &theme);
+        assert_eq!(row_text(&screen, 0), "░░░░░░░░░░   0%");
+
+        bar.set_value(2);
+        bar.draw(&mut screen, (0, 0), 15, &theme);
+        assert_eq!(row_text(&screen, 0), "██████░░░░  66%");
+
+        bar.set_value(5);
+        assert_eq!(bar.value(), 3);
+        bar.draw(&mut screen, (0, 0), 15, &theme);
+        assert_eq!(row_text(&screen, 0), "██████████ 100%");
+
+        assert_eq!(ProgressBar::new(0).percent(), 100);
+    }
+}
@@ -0,0 +1,353 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use super::Style;
+use crate::proto::console::text::Output;
+use crate::{CStr16, Char16, Result};
+use alloc::vec;
+use alloc::vec::Vec;
+
+pub(super) const HORIZONTAL: char = '\u{2500}';
+pub(super) const VERTICAL: char = '\u{2502}';
+pub(super) const DOWN_RIGHT: char = '\u{250c}';
+pub(super) const DOWN_LEFT: char = '\u{2510}';
+pub(super) const UP_RIGHT: char = '\u{2514}';
+pub(super) const UP_LEFT: char = '\u{2518}';
+pub(super) const FULL_BLOCK: char = '\u{2588}';
+pub(super) const LIGHT_SHADE: char = '\u{2591}';
+pub(super) const ARROW_UP: char = '\u{2191}';
+pub(super) const ARROW_DOWN: char = '\u{2193}';
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Cell {
+    ch: u16,
+    style: Style,
+}
+
+impl Cell {
+    /// Marks a cell whose contents on the output are unknown.
+    const UNKNOWN: Self = Self {
+        ch: 0,
+        style: Style::DEFAULT,
+    };
+}
+
+/// Sequence of changed cells in the same row and style.
+#[derive(Debug, Eq, PartialEq)]
+struct Run {
+    column: usize,
+    row: usize,
+    style: Style,
+    text: Vec<u16>,
+}
+
+/// Off-screen buffer holding the characters and colors of a text console.
+///
+/// Drawing operations only modify the buffer and are clipped to the screen.
+/// [`render`] then writes the cells that changed since the last call to the
+/// [`Output`], which avoids flickering.
+///
+/// The cell in the bottom right corner is never written, because printing a
+/// character there makes many consoles scroll.
+///
+/// [`render`]: Self::render
+#[derive(Clone, Debug)]
+pub struct Screen {
+    columns: usize,
+    rows: usize,
+    cells: Vec<Cell>,
+    shown: Vec<Cell>,
+    redraw: bool,
+    cursor: Option<(usize, usize)>,
+}
+
+impl Screen {
+    /// Creates a screen of the given size, filled with spaces in `style`.
+    #[must_use]
+    pub fn new(columns: usize, rows: usize, style: Style) -> Self {
+        let blank = Cell {
+            ch: u16::from(b' '),
+            style,
+        };
+        Self {
+            columns,
+            rows,
+            cells: vec![blank; columns * rows],
+            shown: vec![Cell::UNKNOWN; columns * rows],
+            redraw: true,
+            cursor: None,
+        }
+    }
+
+    /// Returns the width of the screen in characters.
+    #[must_use]
+    pub const fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// Returns the height of the screen in characters.
+    #[must_use]
+    pub const fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Changes the size of the screen, which clears it.
+    ///
+    /// This must be called after changing the mode of the [`Output`].
+    pub fn resize(&mut self, columns: usize, rows: usize, style: Style) {
+        *self = Self::new(columns, rows, style);
+    }
+
+    /// Makes the next [`render`] redraw the whole screen, for example if
+    /// something else wrote to the [`Output`].
+    ///
+    /// [`render`]: Self::render
+    pub const fn invalidate(&mut self) {
+        self.redraw = true;
+    }
+
+    /// Returns the character and style at (`column`, `row`).
+    #[must_use]
+    pub fn get(&self, column: usize, row: usize) -> Option<(char, Style)> {
+        let cell = self.cell(column, row)?;
+        Some((char::from(Char16::try_from(cell.ch).ok()?), cell.style))
+    }
+
+    /// Fills the whole screen with spaces in `style`.
+    pub fn clear(&mut self, style: Style) {
+        self.fill((0, 0), (self.columns, self.rows), ' ', style);
+    }
+
+    /// Fills the rectangle at `pos` of size `dims` with `ch`.
+    pub fn fill(&mut self, pos: (usize, usize), dims: (usize, usize), ch: char, style: Style) {
+        let ch = encode(ch);
+        let column_end = pos.0.saturating_add(dims.0).min(self.columns);
+        let row_end = pos.1.saturating_add(dims.1).min(self.rows);
+        for row in pos.1..row_end {
+            for column in pos.0..column_end {
+                self.cells[row * self.columns + column] = Cell { ch, style };
+            }
+        }
+    }
+
+    /// Prints `text` at `pos`, cutting it off at the right edge of the
+    /// screen, and returns the number of columns printed.
+    ///
+    /// Control characters and characters that can't be represented in UCS-2
+    /// are replaced by `?`.
+    pub fn print(&mut self, pos: (usize, usize), text: &str, style: Style) -> usize {
+        let mut column = pos.0;
+        for ch in text.chars() {
+            let Some(cell) = self.cell_mut(column, pos.1) else {
+                break;
+            };
+            *cell = Cell {
+                ch: encode(ch),
+                style,
+            };
+            column += 1;
+        }
+        column.saturating_sub(pos.0)
+    }
+
+    /// Draws a frame around the rectangle at `pos` of size `dims`, and
+    /// fills its inside with spaces.
+    pub fn draw_box(&mut self, pos: (usize, usize), dims: (usize, usize), style: Style) {
+        let (width, height) = dims;
+        if width < 2 || height < 2 {
+            return;
+        }
+        let (right, bottom) = (pos.0 + width - 1, pos.1 + height - 1);
+        self.fill(pos, (width, 1), HORIZONTAL, style);
+        self.fill((pos.0, bottom), (width, 1), HORIZONTAL, style);
+        self.fill((pos.0, pos.1 + 1), (1, height - 2), VERTICAL, style);
+        self.fill((right, pos.1 + 1), (1, height - 2), VERTICAL, style);
+        self.fill((pos.0 + 1, pos.1 + 1), (width - 2, height - 2), ' ', style);
+        self.fill(pos, (1, 1), DOWN_RIGHT, style);
+        self.fill((right, pos.1), (1, 1), DOWN_LEFT, style);
+        self.fill((pos.0, bottom), (1, 1), UP_RIGHT, style);
+        self.fill((right, bottom), (1, 1), UP_LEFT, style);
+    }
+
+    /// Returns the position of the cursor, or `None` if it's hidden.
+    #[must_use]
+    pub const fn cursor(&self) -> Option<(usize, usize)> {
+        self.cursor
+    }
+
+    /// Shows the cursor at (`column`, `row`) after rendering, or hides it if
+    /// `None`.
+    pub fn set_cursor(&mut self, position: Option<(usize, usize)>) {
+        self.cursor = position.filter(|&(column, row)| column < self.columns && row < self.rows);
+    }
+
+    /// Writes the changed cells to `output`, and updates the cursor.
+    pub fn render(&mut self, output: &mut Output) -> Result {
+        if self.redraw {
+            // Clearing the output also covers the bottom right cell.
+            if let Some(last) = self.cells.last() {
+                output.set_color(last.style.foreground, last.style.background)?;
+            }
+            output.clear()?;
+            self.shown.fill(Cell::UNKNOWN);
+            self.redraw = false;
+        }
+
+        let mut current_style = None;
+        for mut run in self.runs() {
+            output.set_cursor_position(run.column, run.row)?;
+            if current_style != Some(run.style) {
+                output.set_color(run.style.foreground, run.style.background)?;
+                current_style = Some(run.style);
+            }
+            run.text.push(0);
+            let text = CStr16::from_u16_with_nul(&run.text).expect("text contains no null");
+            output.output_string_lossy(text)?;
+        }
+        self.shown.copy_from_slice(&self.cells);
+
+        match self.cursor {
+            Some((column, row)) => {
+                output.set_cursor_position(column, row)?;
+                super::ignore_unsupported(output.enable_cursor(true))
+            }
+            None => super::ignore_unsupported(output.enable_cursor(false)),
+        }
+    }
+
+    /// Returns the changed cells, grouped by row and style.
+    fn runs(&self) -> Vec<Run> {
+        let last = self.cells.len().saturating_sub(1);
+        let changed = |index: usize| index != last && self.cells[index] != self.shown[index];
+
+        let mut runs = Vec::new();
+        for row in 0..self.rows {
+            let mut column = 0;
+            while column < self.columns {
+                let index = row * self.columns + column;
+                if !changed(index) {
+                    column += 1;
+                    continue;
+                }
+                let style = self.cells[index].style;
+                let start = column;
+                let mut text = Vec::new();
+                while column < self.columns {
+                    let index = row * self.columns + column;
+                    if !changed(index) || self.cells[index].style != style {
+                        break;
+                    }
+                    text.push(self.cells[index].ch);
+                    column += 1;
+                }
+                runs.push(Run {
+                    column: start,
+                    row,
+                    style,
+                    text,
+                });
+            }
+        }
+        runs
+    }
+
+    fn cell(&self, column: usize, row: usize) -> Option<&Cell> {
+        (column < self.columns && row < self.rows).then(|| &self.cells[row * self.columns + column])
+    }
+
+    fn cell_mut(&mut self, column: usize, row: usize) -> Option<&mut Cell> {
+        (column < self.columns && row < self.rows)
+            .then(|| &mut self.cells[row * self.columns + column])
+    }
+}
+
+/// Converts `ch` to a printable UCS-2 character.
+fn encode(ch: char) -> u16 {
+    match Char16::try_from(ch) {
+        Ok(ch) if !char::from(ch).is_control() => u16::from(ch),
+        _ => u16::from(b'?'),
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use crate::proto::console::text::{Color, Key};
+    use alloc::string::String;
+
+    const NORMAL: Style = Style::new(Color::LightGray, Color::Black);
+    const INVERSE: Style = Style::new(Color::Black, Color::LightGray);
+
+    /// Returns the key for the character `ch`.
+    pub(in super::super) fn key(ch: char) -> Key {
+        Key::Printable(Char16::try_from(ch).unwrap())
+    }
+
+    /// Returns the characters of a row.
+    pub(in super::super) fn row_text(screen: &Screen, row: usize) -> String {
+        (0..screen.columns())
+            .map(|column| screen.get(column, row).unwrap().0)
+            .collect()
+    }
+
+    #[test]
+    fn test_print() {
+        let mut screen = Screen::new(8, 2, NORMAL);
+        assert_eq!(screen.print((5, 0), "hello", INVERSE), 3);
+        assert_eq!(screen.print((0, 1), "a\tb\u{1f600}", NORMAL), 4);
+        assert_eq!(screen.print((0, 2), "x", NORMAL), 0);
+        assert_eq!(row_text(&screen, 0), "     hel");
+        assert_eq!(row_text(&screen, 1), "a?b?    ");
+        assert_eq!(screen.get(4, 0), Some((' ', NORMAL)));
+        assert_eq!(screen.get(5, 0), Some(('h', INVERSE)));
+        assert_eq!(screen.get(8, 0), None);
+    }
+
+    #[test]
+    fn test_draw_box() {
+        let mut screen = Screen::new(5, 4, NORMAL);
+        screen.print((0, 1), "xxxxx", NORMAL);
+        screen.draw_box((1, 0), (4, 3), INVERSE);
+        assert_eq!(row_text(&screen, 0), " ┌──┐");
+        assert_eq!(row_text(&screen, 1), "x│  │");
+        assert_eq!(row_text(&screen, 2), " └──┘");
+        assert_eq!(row_text(&screen, 3), "     ");
+    }
+
+    #[test]
+    fn test_runs() {
+        let mut screen = Screen::new(6, 2, NORMAL);
+        let runs = screen.runs();
+        assert_eq!(runs.len(), 2);
+        assert_eq!((runs[1].column, runs[1].text.len()), (0, 5));
+
+        // Only changed cells are written, and the last cell never.
+        screen.shown.copy_from_slice(&screen.cells);
+        screen.print((1, 0), "ab", NORMAL);
+        screen.print((3, 0), "c", INVERSE);
+        screen.print((4, 1), "de", NORMAL);
+        let text = |s: &str| s.encode_utf16().collect::<Vec<_>>();
+        assert_eq!(
+            screen.runs(),
+            [
+                Run {
+                    column: 1,
+                    row: 0,
+                    style: NORMAL,
+                    text: text("ab"),
+                },
+                Run {
+                    column: 3,
+                    row: 0,
+                    style: INVERSE,
+                    text: text("c"),
+                },
+                Run {
+                    column: 4,
+                    row: 1,
+                    style: NORMAL,
+                    text: text("d"),
+                },
+            ]
+        );
+    }
+}
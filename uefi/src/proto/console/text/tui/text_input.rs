@@ -0,0 +1,169 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use super::{Action, Screen, Style, is_char, printable};
+use crate::proto::console::text::{Key, ScanCode};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Single-line text field.
+///
+/// Supports moving the cursor with the arrow keys, home and end, and
+/// deleting with backspace and delete. Enter submits and escape cancels.
+#[derive(Clone, Debug, Default)]
+pub struct TextInput {
+    chars: Vec<char>,
+    cursor: usize,
+    max_len: Option<usize>,
+    masked: bool,
+}
+
+impl TextInput {
+    /// Creates an empty text field.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the initial text, with the cursor at its end.
+    #[must_use]
+    pub fn with_value(mut self, value: &str) -> Self {
+        self.chars = value.chars().collect();
+        self.cursor = self.chars.len();
+        self
+    }
+
+    /// Limits the text to `max_len` characters.
+    #[must_use]
+    pub const fn with_max_len(mut self, max_len: usize) -> Self {
+        self.max_len = Some(max_len);
+        self
+    }
+
+    /// Shows `*` instead of the characters, for passwords.
+    #[must_use]
+    pub const fn masked(mut self) -> Self {
+        self.masked = true;
+        self
+    }
+
+    /// Returns the entered text.
+    #[must_use]
+    pub fn value(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    /// Returns the cursor position in characters.
+    #[must_use]
+    pub const fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Processes a key press.
+    pub fn handle_key(&mut self, key: Key) -> Action {
+        match key {
+            Key::Special(ScanCode::LEFT) => self.cursor = self.cursor.saturating_sub(1),
+            Key::Special(ScanCode::RIGHT) => self.cursor = (self.cursor + 1).min(self.chars.len()),
+            Key::Special(ScanCode::HOME) => self.cursor = 0,
+            Key::Special(ScanCode::END) => self.cursor = self.chars.len(),
+            Key::Special(ScanCode::DELETE) => {
+                if self.cursor < self.chars.len() {
+                    self.chars.remove(self.cursor);
+                }
+            }
+            Key::Special(ScanCode::ESCAPE) => return Action::Cancel,
+            key if is_char(key, '\r') => return Action::Submit,
+            key if is_char(key, '\x08') => {
+                if self.cursor > 0 {
+                    self.cursor -= 1;
+                    self.chars.remove(self.cursor);
+                }
+            }
+            key => {
+                if let Some(ch) = printable(key) {
+                    if self
+                        .max_len
+                        .is_none_or(|max_len| self.chars.len() < max_len)
+                    {
+                        self.chars.insert(self.cursor, ch);
+                        self.cursor += 1;
+                    }
+                }
+            }
+        }
+        Action::Continue
+    }
+
+    /// Draws the text field at `pos` with the given `width`, and places the
+    /// screen's cursor at the input position.
+    ///
+    /// If the text is too long, it is scrolled so that the cursor is visible.
+    pub fn draw(&self, screen: &mut Screen, pos: (usize, usize), width: usize, style: Style) {
+        if width == 0 {
+            return;
+        }
+        // Keep one column free for the cursor at the end of the text.
+        let scroll = (self.cursor + 1).saturating_sub(width);
+        let text: String = self.chars[scroll..]
+            .iter()
+            .take(width)
+            .map(|&ch| if self.masked { '*' } else { ch })
+            .collect();
+        screen.fill(pos, (width, 1), ' ', style);
+        screen.print(pos, &text, style);
+        screen.set_cursor(Some((pos.0 + self.cursor - scroll, pos.1)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::console::text::tui::screen::tests::{key, row_text};
+
+    fn type_str(input: &mut TextInput, s: &str) {
+        for ch in s.chars() {
+            assert_eq!(input.handle_key(key(ch)), Action::Continue);
+        }
+    }
+
+    #[test]
+    fn test_editing() {
+        let mut input = TextInput::new().with_max_len(8);
+        type_str(&mut input, "hllo");
+        input.handle_key(Key::Special(ScanCode::HOME));
+        input.handle_key(Key::Special(ScanCode::RIGHT));
+        type_str(&mut input, "e");
+        assert_eq!((input.value().as_str(), input.cursor()), ("hello", 2));
+
+        input.handle_key(Key::Special(ScanCode::DELETE));
+        type_str(&mut input, "\x08\x08");
+        assert_eq!((input.value().as_str(), input.cursor()), ("lo", 0));
+
+        input.handle_key(Key::Special(ScanCode::END));
+        type_str(&mut input, " world!");
+        assert_eq!(input.value(), "lo world");
+        assert_eq!(input.handle_key(key('\r')), Action::Submit);
+        assert_eq!(
+            input.handle_key(Key::Special(ScanCode::ESCAPE)),
+            Action::Cancel
+        );
+    }
+
+    #[test]
+    fn test_draw() {
+        let style = Style::default();
+        let mut screen = Screen::new(8, 1, style);
+        let mut input = TextInput::new().with_value("abcdefgh");
+        input.draw(&mut screen, (1, 0), 5, style);
+        assert_eq!(row_text(&screen, 0), " efgh   ");
+        assert_eq!(screen.cursor(), Some((5, 0)));
+
+        input.handle_key(Key::Special(ScanCode::HOME));
+        input.draw(&mut screen, (1, 0), 5, style);
+        assert_eq!(row_text(&screen, 0), " abcde  ");
+        assert_eq!(screen.cursor(), Some((1, 0)));
+
+        let input = TextInput::new().with_value("secret").masked();
+        input.draw(&mut screen, (0, 0), 8, style);
+        assert_eq!(row_text(&screen, 0), "******  ");
+    }
+}
@@ -10,13 +10,13 @@
 pub union SystemContext {
     ebc: *mut SystemContextEBC,
     riscv_32: *mut SystemContextRiscV32,
-    riscv_64: *mut SystemContextRiscV64,
+    pub(super) riscv_64: *mut SystemContextRiscV64,
     riscv_128: *mut SystemContextRiscV128,
     ia32: *mut SystemContextIA32,
-    x64: *mut SystemContextX64,
+    pub(super) x64: *mut SystemContextX64,
     ipf: *mut SystemContextIPF,
     arm: *mut SystemContextARM,
-    aarch64: *mut SystemContextAARCH64,
+    pub(super) aarch64: *mut SystemContextAARCH64,
 }
 
 /// System context for virtual EBC processors
@@ -107,75 +107,140 @@ pub struct SystemContextRiscV32 {
     ft11: u128,
 }
 
+/// System context for RISC-V 64-bit processors
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
 pub struct SystemContextRiscV64 {
     // Integer registers
-    zero: u64,
-    ra: u64,
-    sp: u64,
-    gp: u64,
-    tp: u64,
-    t0: u64,
-    t1: u64,
-    t2: u64,
-    s0fp: u64,
-    s1: u64,
-    a0: u64,
-    a1: u64,
-    a2: u64,
-    a3: u64,
-    a4: u64,
-    a5: u64,
-    a6: u64,
-    a7: u64,
-    s2: u64,
-    s3: u64,
-    s4: u64,
-    s5: u64,
-    s6: u64,
-    s7: u64,
-    s8: u64,
-    s9: u64,
-    s10: u64,
-    s11: u64,
-    t3: u64,
-    t4: u64,
-    t5: u64,
-    t6: u64,
+    /// `zero` register.
+    pub zero: u64,
+    /// `ra` register.
+    pub ra: u64,
+    /// `sp` register.
+    pub sp: u64,
+    /// `gp` register.
+    pub gp: u64,
+    /// `tp` register.
+    pub tp: u64,
+    /// `t0` register.
+    pub t0: u64,
+    /// `t1` register.
+    pub t1: u64,
+    /// `t2` register.
+    pub t2: u64,
+    /// `s0` register, the frame pointer.
+    pub s0fp: u64,
+    /// `s1` register.
+    pub s1: u64,
+    /// `a0` register.
+    pub a0: u64,
+    /// `a1` register.
+    pub a1: u64,
+    /// `a2` register.
+    pub a2: u64,
+    /// `a3` register.
+    pub a3: u64,
+    /// `a4` register.
+    pub a4: u64,
+    /// `a5` register.
+    pub a5: u64,
+    /// `a6` register.
+    pub a6: u64,
+    /// `a7` register.
+    pub a7: u64,
+    /// `s2` register.
+    pub s2: u64,
+    /// `s3` register.
+    pub s3: u64,
+    /// `s4` register.
+    pub s4: u64,
+    /// `s5` register.
+    pub s5: u64,
+    /// `s6` register.
+    pub s6: u64,
+    /// `s7` register.
+    pub s7: u64,
+    /// `s8` register.
+    pub s8: u64,
+    /// `s9` register.
+    pub s9: u64,
+    /// `s10` register.
+    pub s10: u64,
+    /// `s11` register.
+    pub s11: u64,
+    /// `t3` register.
+    pub t3: u64,
+    /// `t4` register.
+    pub t4: u64,
+    /// `t5` register.
+    pub t5: u64,
+    /// `t6` register.
+    pub t6: u64,
     // Floating registers for F, D, and Q Standard Extensions
-    ft0: u128,
-    ft1: u128,
-    ft2: u128,
-    ft3: u128,
-    ft4: u128,
-    ft5: u128,
-    ft6: u128,
-    ft7: u128,
-    fs0: u128,
-    fs1: u128,
-    fa0: u128,
-    fa1: u128,
-    fa2: u128,
-    fa3: u128,
-    fa4: u128,
-    fa5: u128,
-    fa6: u128,
-    fa7: u128,
-    fs2: u128,
-    fs3: u128,
-    fs4: u128,
-    fs5: u128,
-    fs6: u128,
-    fs7: u128,
-    fs8: u128,
-    fs9: u128,
-    fs10: u128,
-    fs11: u128,
-    ft8: u128,
-    ft9: u128,
-    ft10: u128,
-    ft11: u128,
+    /// `ft0` register.
+    pub ft0: u128,
+    /// `ft1` register.
+    pub ft1: u128,
+    /// `ft2` register.
+    pub ft2: u128,
+    /// `ft3` register.
+    pub ft3: u128,
+    /// `ft4` register.
+    pub ft4: u128,
+    /// `ft5` register.
+    pub ft5: u128,
+    /// `ft6` register.
+    pub ft6: u128,
+    /// `ft7` register.
+    pub ft7: u128,
+    /// `fs0` register.
+    pub fs0: u128,
+    /// `fs1` register.
+    pub fs1: u128,
+    /// `fa0` register.
+    pub fa0: u128,
+    /// `fa1` register.
+    pub fa1: u128,
+    /// `fa2` register.
+    pub fa2: u128,
+    /// `fa3` register.
+    pub fa3: u128,
+    /// `fa4` register.
+    pub fa4: u128,
+    /// `fa5` register.
+    pub fa5: u128,
+    /// `fa6` register.
+    pub fa6: u128,
+    /// `fa7` register.
+    pub fa7: u128,
+    /// `fs2` register.
+    pub fs2: u128,
+    /// `fs3` register.
+    pub fs3: u128,
+    /// `fs4` register.
+    pub fs4: u128,
+    /// `fs5` register.
+    pub fs5: u128,
+    /// `fs6` register.
+    pub fs6: u128,
+    /// `fs7` register.
+    pub fs7: u128,
+    /// `fs8` register.
+    pub fs8: u128,
+    /// `fs9` register.
+    pub fs9: u128,
+    /// `fs10` register.
+    pub fs10: u128,
+    /// `fs11` register.
+    pub fs11: u128,
+    /// `ft8` register.
+    pub ft8: u128,
+    /// `ft9` register.
+    pub ft9: u128,
+    /// `ft10` register.
+    pub ft10: u128,
+    /// `ft11` register.
+    pub ft11: u128,
 }
 
 #[repr(C)]
@@ -333,48 +398,90 @@ pub struct FxSaveStateIA32 {
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
 pub struct SystemContextX64 {
-    exception_data: u64, // additional data pushed on the stack by some types of exceptions
-    fx_save_state: FxSaveStateX64,
-    dr0: u64,
-    dr1: u64,
-    dr2: u64,
-    dr3: u64,
-    dr6: u64,
-    dr7: u64,
-    cr0: u64,
-    cr1: u64, // Noted as "Reserved" in the UEFI Specification
-    cr2: u64,
-    cr3: u64,
-    cr4: u64,
-    cr8: u64,
-    rflags: u64,
-    ldtr: u64,
-    tr: u64,
-    gdtr: [u64; 2],
-    idtr: [u64; 2],
-    rip: u64,
-    gs: u64,
-    fs: u64,
-    es: u64,
-    ds: u64,
-    cs: u64,
-    ss: u64,
-    rdi: u64,
-    rsi: u64,
-    rbp: u64,
-    rsp: u64,
-    rbx: u64,
-    rdx: u64,
-    rcx: u64,
-    rax: u64,
-    r8: u64,
-    r9: u64,
-    r10: u64,
-    r11: u64,
-    r12: u64,
-    r13: u64,
-    r14: u64,
-    r15: u64,
+    /// Additional data pushed on the stack by some types of exceptions.
+    pub exception_data: u64,
+    /// FP, MMX and XMM registers.
+    pub fx_save_state: FxSaveStateX64,
+    /// `dr0` register.
+    pub dr0: u64,
+    /// `dr1` register.
+    pub dr1: u64,
+    /// `dr2` register.
+    pub dr2: u64,
+    /// `dr3` register.
+    pub dr3: u64,
+    /// `dr6` register.
+    pub dr6: u64,
+    /// `dr7` register.
+    pub dr7: u64,
+    /// `cr0` register.
+    pub cr0: u64,
+    /// Reserved.
+    pub cr1: u64,
+    /// `cr2` register.
+    pub cr2: u64,
+    /// `cr3` register.
+    pub cr3: u64,
+    /// `cr4` register.
+    pub cr4: u64,
+    /// `cr8` register.
+    pub cr8: u64,
+    /// `rflags` register.
+    pub rflags: u64,
+    /// Local descriptor table register.
+    pub ldtr: u64,
+    /// Task register.
+    pub tr: u64,
+    /// Global descriptor table register.
+    pub gdtr: [u64; 2],
+    /// Interrupt descriptor table register.
+    pub idtr: [u64; 2],
+    /// `rip` register.
+    pub rip: u64,
+    /// `gs` register.
+    pub gs: u64,
+    /// `fs` register.
+    pub fs: u64,
+    /// `es` register.
+    pub es: u64,
+    /// `ds` register.
+    pub ds: u64,
+    /// `cs` register.
+    pub cs: u64,
+    /// `ss` register.
+    pub ss: u64,
+    /// `rdi` register.
+    pub rdi: u64,
+    /// `rsi` register.
+    pub rsi: u64,
+    /// `rbp` register.
+    pub rbp: u64,
+    /// `rsp` register.
+    pub rsp: u64,
+    /// `rbx` register.
+    pub rbx: u64,
+    /// `rdx` register.
+    pub rdx: u64,
+    /// `rcx` register.
+    pub rcx: u64,
+    /// `rax` register.
+    pub rax: u64,
+    /// `r8` register.
+    pub r8: u64,
+    /// `r9` register.
+    pub r9: u64,
+    /// `r10` register.
+    pub r10: u64,
+    /// `r11` register.
+    pub r11: u64,
+    /// `r12` register.
+    pub r12: u64,
+    /// `r13` register.
+    pub r13: u64,
+    /// `r14` register.
+    pub r14: u64,
+    /// `r15` register.
+    pub r15: u64,
 }
 
 /// FP / MMX / XMM registers for X64
@@ -574,74 +681,143 @@ pub struct SystemContextARM {
 #[derive(Debug, Clone, Copy)]
 pub struct SystemContextAARCH64 {
     // General Purpose Registers
-    x0: u64,
-    x1: u64,
-    x2: u64,
-    x3: u64,
-    x4: u64,
-    x5: u64,
-    x6: u64,
-    x7: u64,
-    x8: u64,
-    x9: u64,
-    x10: u64,
-    x11: u64,
-    x12: u64,
-    x13: u64,
-    x14: u64,
-    x15: u64,
-    x16: u64,
-    x17: u64,
-    x18: u64,
-    x19: u64,
-    x20: u64,
-    x21: u64,
-    x22: u64,
-    x23: u64,
-    x24: u64,
-    x25: u64,
-    x26: u64,
-    x27: u64,
-    x28: u64,
-    fp: u64, // x29 - Frame Pointer
-    lr: u64, // x30 - Link Register
-    sp: u64, // x31 - Stack Pointer
+    /// `x0` register.
+    pub x0: u64,
+    /// `x1` register.
+    pub x1: u64,
+    /// `x2` register.
+    pub x2: u64,
+    /// `x3` register.
+    pub x3: u64,
+    /// `x4` register.
+    pub x4: u64,
+    /// `x5` register.
+    pub x5: u64,
+    /// `x6` register.
+    pub x6: u64,
+    /// `x7` register.
+    pub x7: u64,
+    /// `x8` register.
+    pub x8: u64,
+    /// `x9` register.
+    pub x9: u64,
+    /// `x10` register.
+    pub x10: u64,
+    /// `x11` register.
+    pub x11: u64,
+    /// `x12` register.
+    pub x12: u64,
+    /// `x13` register.
+    pub x13: u64,
+    /// `x14` register.
+    pub x14: u64,
+    /// `x15` register.
+    pub x15: u64,
+    /// `x16` register.
+    pub x16: u64,
+    /// `x17` register.
+    pub x17: u64,
+    /// `x18` register.
+    pub x18: u64,
+    /// `x19` register.
+    pub x19: u64,
+    /// `x20` register.
+    pub x20: u64,
+    /// `x21` register.
+    pub x21: u64,
+    /// `x22` register.
+    pub x22: u64,
+    /// `x23` register.
+    pub x23: u64,
+    /// `x24` register.
+    pub x24: u64,
+    /// `x25` register.
+    pub x25: u64,
+    /// `x26` register.
+    pub x26: u64,
+    /// `x27` register.
+    pub x27: u64,
+    /// `x28` register.
+    pub x28: u64,
+    /// `x29` register, the frame pointer.
+    pub fp: u64,
+    /// `x30` register, the link register.
+    pub lr: u64,
+    /// Stack pointer.
+    pub sp: u64,
     // FP/SIMD Registers
-    v0: [u64; 2],
-    v1: [u64; 2],
-    v2: [u64; 2],
-    v3: [u64; 2],
-    v4: [u64; 2],
-    v5: [u64; 2],
-    v6: [u64; 2],
-    v7: [u64; 2],
-    v8: [u64; 2],
-    v9: [u64; 2],
-    v10: [u64; 2],
-    v11: [u64; 2],
-    v12: [u64; 2],
-    v13: [u64; 2],
-    v14: [u64; 2],
-    v15: [u64; 2],
-    v16: [u64; 2],
-    v17: [u64; 2],
-    v18: [u64; 2],
-    v19: [u64; 2],
-    v20: [u64; 2],
-    v21: [u64; 2],
-    v22: [u64; 2],
-    v23: [u64; 2],
-    v24: [u64; 2],
-    v25: [u64; 2],
-    v26: [u64; 2],
-    v27: [u64; 2],
-    v28: [u64; 2],
-    v29: [u64; 2],
-    v30: [u64; 2],
-    v31: [u64; 2],
-    elr: u64,  // Exception Link Register
-    spsr: u64, // Saved Processor Status Register
-    fpsr: u64, // Floating Point Status Register
-    esr: u64,  // Exception Syndrome Register
-    far: u64,  // Fault Address Register
+    /// `v0` register.
+    pub v0: [u64; 2],
+    /// `v1` register.
+    pub v1: [u64; 2],
+    /// `v2` register.
+    pub v2: [u64; 2],
+    /// `v3` register.
+    pub v3: [u64; 2],
+    /// `v4` register.
+    pub v4: [u64; 2],
+    /// `v5` register.
+    pub v5: [u64; 2],
+    /// `v6` register.
+    pub v6: [u64; 2],
+    /// `v7` register.
+    pub v7: [u64; 2],
+    /// `v8` register.
+    pub v8: [u64; 2],
+    /// `v9` register.
+    pub v9: [u64; 2],
+    /// `v10` register.
+    pub v10: [u64; 2],
+    /// `v11` register.
+    pub v11: [u64; 2],
+    /// `v12` register.
+    pub v12: [u64; 2],
+    /// `v13` register.
+    pub v13: [u64; 2],
+    /// `v14` register.
+    pub v14: [u64; 2],
+    /// `v15` register.
+    pub v15: [u64; 2],
+    /// `v16` register.
+    pub v16: [u64; 2],
+    /// `v17` register.
+    pub v17: [u64; 2],
+    /// `v18` register.
+    pub v18: [u64; 2],
+    /// `v19` register.
+    pub v19: [u64; 2],
+    /// `v20` register.
+    pub v20: [u64; 2],
+    /// `v21` register.
+    pub v21: [u64; 2],
+    /// `v22` register.
+    pub v22: [u64; 2],
+    /// `v23` register.
+    pub v23: [u64; 2],
+    /// `v24` register.
+    pub v24: [u64; 2],
+    /// `v25` register.
+    pub v25: [u64; 2],
+    /// `v26` register.
+    pub v26: [u64; 2],
+    /// `v27` register.
+    pub v27: [u64; 2],
+    /// `v28` register.
+    pub v28: [u64; 2],
+    /// `v29` register.
+    pub v29: [u64; 2],
+    /// `v30` register.
+    pub v30: [u64; 2],
+    /// `v31` register.
+    pub v31: [u64; 2],
+    /// Exception Link Register.
+    pub elr: u64,
+    /// Saved Processor Status Register.
+    pub spsr: u64,
+    /// Floating Point Status Register.
+    pub fpsr: u64,
+    /// Exception Syndrome Register.
+    pub esr: u64,
+    /// Fault Address Register.
+    pub far: u64,
 }
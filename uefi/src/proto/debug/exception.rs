@@ -2,10 +2,23 @@
 
 /// Represents supported CPU exceptions.
 #[repr(C)]
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct ExceptionType(isize);
 
 impl ExceptionType {
+    /// Returns the raw value of the exception type.
+    #[cfg(all(
+        feature = "alloc",
+        any(
+            target_arch = "x86_64",
+            target_arch = "aarch64",
+            target_arch = "riscv64"
+        )
+    ))]
+    pub(super) const fn value(self) -> isize {
+        self.0
+    }
+
     /// Undefined Exception
     pub const EXCEPT_EBC_UNDEFINED: Self = Self(0);
     /// Divide-by-zero Error
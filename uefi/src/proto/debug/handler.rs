@@ -0,0 +1,525 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Exception handlers implemented as Rust closures.
+
+use super::{DebugSupport, ExceptionType, ProcessorArch, SystemContext};
+use crate::util::Lock;
+use crate::{Result, Status, StatusExt};
+use alloc::boxed::Box;
+use core::fmt::{self, Display, Formatter};
+use core::marker::PhantomData;
+use core::ptr;
+
+cfg_if::cfg_if! {
+    if #[cfg(target_arch = "x86_64")] {
+        /// System context of the processor the image runs on, passed to the
+        /// handlers registered with
+        /// [`DebugSupport::register_exception_handler`].
+        pub type ArchSystemContext = super::SystemContextX64;
+        const NATIVE_ARCH: ProcessorArch = ProcessorArch::X86_64;
+    } else if #[cfg(target_arch = "aarch64")] {
+        /// System context of the processor the image runs on, passed to the
+        /// handlers registered with
+        /// [`DebugSupport::register_exception_handler`].
+        pub type ArchSystemContext = super::SystemContextAARCH64;
+        const NATIVE_ARCH: ProcessorArch = ProcessorArch::AARCH_64;
+    } else {
+        /// System context of the processor the image runs on, passed to the
+        /// handlers registered with
+        /// [`DebugSupport::register_exception_handler`].
+        pub type ArchSystemContext = super::SystemContextRiscV64;
+        const NATIVE_ARCH: ProcessorArch = ProcessorArch::RISCV_64;
+    }
+}
+
+/// Maximum number of frames logged by [`log_exception`].
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+const MAX_FRAMES: usize = 32;
+
+/// Maximum distance between the stack pointer and the frame pointer for the
+/// frame pointer to be followed by [`log_exception`].
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+const MAX_STACK_DEPTH: u64 = 1024 * 1024;
+
+impl DebugSupport {
+    /// Registers `handler` to be called when an exception of type
+    /// `exception_type` occurs on the processor `processor_index`.
+    ///
+    /// The handler receives the saved context of the processor, and changes
+    /// made to it are restored when the handler returns. For example, the
+    /// instruction pointer can be advanced past the faulting instruction.
+    ///
+    /// The handler is unregistered when the returned [`ExceptionHandler`] is
+    /// dropped.
+    ///
+    /// The handler runs in exception context: it must not call UEFI services
+    /// or allocate memory. Exceptions raised while the handler runs, or while
+    /// it is being registered or unregistered, are not passed to it.
+    ///
+    /// Note: Applications built with EDK2 (such as OVMF) ignore the
+    /// `processor_index` parameter, so only one handler can be registered
+    /// for each exception type.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the protocol is not the one of the processor
+    ///   the image runs on, e.g. it is the one of the EBC interpreter.
+    /// * [`Status::INVALID_PARAMETER`]: `processor_index` exceeds
+    ///   [`get_maximum_processor_index`], or `exception_type` is out of range.
+    /// * [`Status::ALREADY_STARTED`]: a handler is already registered for
+    ///   `exception_type`.
+    /// * Errors of the firmware's `RegisterExceptionCallback`.
+    ///
+    /// [`get_maximum_processor_index`]: Self::get_maximum_processor_index
+    pub fn register_exception_handler(
+        &self,
+        processor_index: usize,
+        exception_type: ExceptionType,
+        handler: impl FnMut(ExceptionType, &mut ArchSystemContext) + Send + 'static,
+    ) -> Result<ExceptionHandler<'_>> {
+        if self.arch() != NATIVE_ARCH {
+            return Err(Status::UNSUPPORTED.into());
+        }
+        let slot = usize::try_from(exception_type.value())
+            .ok()
+            .filter(|&slot| slot < HANDLER_SLOTS.len())
+            .ok_or(Status::INVALID_PARAMETER)?;
+        let mut max_processor_index = usize::MAX;
+        let _ = (self.get_maximum_processor_index)(self.this(), &mut max_processor_index);
+        if processor_index > max_processor_index {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+
+        let mut handler = Some(Box::new(handler) as Box<Handler>);
+        let registered = HANDLER_SLOTS[slot].try_lock(|slot| {
+            if slot.is_none() {
+                *slot = handler.take();
+            }
+            handler.is_none()
+        }) == Some(true);
+        if !registered {
+            return Err(Status::ALREADY_STARTED.into());
+        }
+
+        let status = unsafe {
+            (self.register_exception_callback)(
+                self.this(),
+                processor_index,
+                Some(dispatch),
+                exception_type,
+            )
+        };
+        if let Err(err) = status.to_result() {
+            HANDLER_SLOTS[slot].lock(|slot| *slot = None);
+            return Err(err);
+        }
+        Ok(ExceptionHandler {
+            protocol: self.this(),
+            processor_index,
+            exception_type,
+            slot,
+            _debug_support: PhantomData,
+        })
+    }
+
+    const fn this(&self) -> *mut Self {
+        ptr::from_ref(self).cast_mut()
+    }
+}
+
+/// Exception handler registered with
+/// [`DebugSupport::register_exception_handler`].
+///
+/// The handler is unregistered when this is dropped.
+#[must_use]
+pub struct ExceptionHandler<'a> {
+    protocol: *mut DebugSupport,
+    processor_index: usize,
+    exception_type: ExceptionType,
+    slot: usize,
+    _debug_support: PhantomData<&'a DebugSupport>,
+}
+
+impl ExceptionHandler<'_> {
+    /// Returns the type of the exceptions passed to the handler.
+    #[must_use]
+    pub const fn exception_type(&self) -> ExceptionType {
+        self.exception_type
+    }
+}
+
+impl fmt::Debug for ExceptionHandler<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExceptionHandler")
+            .field("processor_index", &self.processor_index)
+            .field("exception_type", &self.exception_type)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for ExceptionHandler<'_> {
+    fn drop(&mut self) {
+        // The protocol is borrowed for the lifetime of `self`, so the
+        // pointer is still valid.
+        let status = unsafe {
+            ((*self.protocol).register_exception_callback)(
+                self.protocol,
+                self.processor_index,
+                None,
+                self.exception_type,
+            )
+        };
+        if status.is_error() {
+            log::warn!("failed to unregister exception handler: {status}");
+        }
+        // Wait for a running handler to return before freeing it.
+        HANDLER_SLOTS[self.slot].lock(|slot| *slot = None);
+    }
+}
+
+type Handler = dyn FnMut(ExceptionType, &mut ArchSystemContext) + Send;
+
+/// Registered handlers, indexed by exception type. The exception types of
+/// all supported architectures are below 32.
+static HANDLER_SLOTS: [Lock<Option<Box<Handler>>>; 32] = [const { Lock::new(None) }; 32];
+
+/// Exception callback registered with the firmware, which passes the
+/// exception to the handler registered for its type.
+unsafe extern "efiapi" fn dispatch(exception_type: ExceptionType, context: SystemContext) {
+    let Some(slot) = usize::try_from(exception_type.value())
+        .ok()
+        .and_then(|slot| HANDLER_SLOTS.get(slot))
+    else {
+        return;
+    };
+    // SAFETY: the callback is only registered with the protocol of the
+    // native processor, so the context has the native layout.
+    #[cfg(target_arch = "x86_64")]
+    let context = unsafe { context.x64 };
+    #[cfg(target_arch = "aarch64")]
+    let context = unsafe { context.aarch64 };
+    #[cfg(target_arch = "riscv64")]
+    let context = unsafe { context.riscv_64 };
+    let Some(context) = (unsafe { context.as_mut() }) else {
+        return;
+    };
+    // The slot is locked while it is being registered or freed, in which
+    // case the exception is ignored.
+    slot.try_lock(|handler| {
+        if let Some(handler) = handler {
+            handler(exception_type, context);
+        }
+    });
+}
+
+/// Logs an exception with [`log::error!`]: its type, the registers of the
+/// processor and a backtrace.
+///
+/// This can be passed directly to
+/// [`DebugSupport::register_exception_handler`]. The logger must be usable
+/// in exception context, e.g. a serial or debugcon logger.
+///
+/// The backtrace follows the chain of frame pointers, and only produces
+/// meaningful results if the interrupted code was compiled with frame
+/// pointers; see [`helpers::backtrace`]. The chain is only followed if the
+/// frame pointer points into the stack. On RISC-V, only the return address
+/// is logged.
+///
+/// [`helpers::backtrace`]: crate::helpers::backtrace
+pub fn log_exception(exception_type: ExceptionType, context: &mut ArchSystemContext) {
+    log::error!("exception: {exception_type:?}");
+    for line in registers(context).chunks(4) {
+        log::error!("{}", RegisterLine(line));
+    }
+    log_backtrace(context);
+}
+
+#[cfg(target_arch = "x86_64")]
+const fn registers(c: &ArchSystemContext) -> [(&'static str, u64); 28] {
+    [
+        ("rip", c.rip),
+        ("rsp", c.rsp),
+        ("rbp", c.rbp),
+        ("rflags", c.rflags),
+        ("rax", c.rax),
+        ("rbx", c.rbx),
+        ("rcx", c.rcx),
+        ("rdx", c.rdx),
+        ("rsi", c.rsi),
+        ("rdi", c.rdi),
+        ("r8", c.r8),
+        ("r9", c.r9),
+        ("r10", c.r10),
+        ("r11", c.r11),
+        ("r12", c.r12),
+        ("r13", c.r13),
+        ("r14", c.r14),
+        ("r15", c.r15),
+        ("cs", c.cs),
+        ("ss", c.ss),
+        ("cr0", c.cr0),
+        ("cr2", c.cr2),
+        ("cr3", c.cr3),
+        ("cr4", c.cr4),
+        ("dr6", c.dr6),
+        ("dr7", c.dr7),
+        ("error", c.exception_data),
+        ("cr8", c.cr8),
+    ]
+}
+
+#[cfg(target_arch = "aarch64")]
+const fn registers(c: &ArchSystemContext) -> [(&'static str, u64); 37] {
+    [
+        ("elr", c.elr),
+        ("sp", c.sp),
+        ("fp", c.fp),
+        ("lr", c.lr),
+        ("spsr", c.spsr),
+        ("esr", c.esr),
+        ("far", c.far),
+        ("fpsr", c.fpsr),
+        ("x0", c.x0),
+        ("x1", c.x1),
+        ("x2", c.x2),
+        ("x3", c.x3),
+        ("x4", c.x4),
+        ("x5", c.x5),
+        ("x6", c.x6),
+        ("x7", c.x7),
+        ("x8", c.x8),
+        ("x9", c.x9),
+        ("x10", c.x10),
+        ("x11", c.x11),
+        ("x12", c.x12),
+        ("x13", c.x13),
+        ("x14", c.x14),
+        ("x15", c.x15),
+        ("x16", c.x16),
+        ("x17", c.x17),
+        ("x18", c.x18),
+        ("x19", c.x19),
+        ("x20", c.x20),
+        ("x21", c.x21),
+        ("x22", c.x22),
+        ("x23", c.x23),
+        ("x24", c.x24),
+        ("x25", c.x25),
+        ("x26", c.x26),
+        ("x27", c.x27),
+        ("x28", c.x28),
+    ]
+}
+
+#[cfg(target_arch = "riscv64")]
+const fn registers(c: &ArchSystemContext) -> [(&'static str, u64); 32] {
+    [
+        ("zero", c.zero),
+        ("ra", c.ra),
+        ("sp", c.sp),
+        ("gp", c.gp),
+        ("tp", c.tp),
+        ("t0", c.t0),
+        ("t1", c.t1),
+        ("t2", c.t2),
+        ("s0", c.s0fp),
+        ("s1", c.s1),
+        ("a0", c.a0),
+        ("a1", c.a1),
+        ("a2", c.a2),
+        ("a3", c.a3),
+        ("a4", c.a4),
+        ("a5", c.a5),
+        ("a6", c.a6),
+        ("a7", c.a7),
+        ("s2", c.s2),
+        ("s3", c.s3),
+        ("s4", c.s4),
+        ("s5", c.s5),
+        ("s6", c.s6),
+        ("s7", c.s7),
+        ("s8", c.s8),
+        ("s9", c.s9),
+        ("s10", c.s10),
+        ("s11", c.s11),
+        ("t3", c.t3),
+        ("t4", c.t4),
+        ("t5", c.t5),
+        ("t6", c.t6),
+    ]
+}
+
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+fn log_backtrace(context: &ArchSystemContext) {
+    #[cfg(target_arch = "x86_64")]
+    let (ip, sp, fp) = (context.rip, context.rsp, context.rbp);
+    #[cfg(target_arch = "aarch64")]
+    let (ip, sp, fp) = (context.elr, context.sp, context.fp);
+
+    log::error!("backtrace:");
+    log::error!("  {ip:#018x}");
+    if fp < sp || fp - sp >= MAX_STACK_DEPTH {
+        return;
+    }
+    // SAFETY: the frame pointer points into the stack of the interrupted
+    // code, and the iterator stops at implausible frame records.
+    let frames = unsafe { crate::helpers::backtrace::FramePointerIter::new(fp as usize) };
+    for return_addr in frames.take(MAX_FRAMES) {
+        log::error!("  {return_addr:#018x}");
+    }
+}
+
+#[cfg(target_arch = "riscv64")]
+fn log_backtrace(context: &ArchSystemContext) {
+    log::error!("return address: {:#018x}", context.ra);
+}
+
+/// Formats registers as `name=value` pairs.
+struct RegisterLine<'a>(&'a [(&'static str, u64)]);
+
+impl Display for RegisterLine<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, (name, value)) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{name:>6}={value:016x}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, target_arch = "x86_64"))]
+mod tests {
+    use super::*;
+    use core::ffi::c_void;
+    use core::mem::MaybeUninit;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    type Callback = unsafe extern "efiapi" fn(ExceptionType, SystemContext);
+
+    static CALLBACK: Lock<Option<Callback>> = Lock::new(None);
+
+    const extern "efiapi" fn get_maximum_processor_index(
+        _this: *mut DebugSupport,
+        max_processor_index: &mut usize,
+    ) -> Status {
+        *max_processor_index = 0;
+        Status::SUCCESS
+    }
+
+    const unsafe extern "efiapi" fn register_periodic_callback(
+        _this: *mut DebugSupport,
+        _processor_index: usize,
+        _periodic_callback: Option<unsafe extern "efiapi" fn(SystemContext)>,
+    ) -> Status {
+        Status::UNSUPPORTED
+    }
+
+    unsafe extern "efiapi" fn register_exception_callback(
+        _this: *mut DebugSupport,
+        _processor_index: usize,
+        exception_callback: Option<Callback>,
+        _exception_type: ExceptionType,
+    ) -> Status {
+        CALLBACK.lock(|callback| *callback = exception_callback);
+        Status::SUCCESS
+    }
+
+    const unsafe extern "efiapi" fn invalidate_instruction_cache(
+        _this: *mut DebugSupport,
+        _processor_index: usize,
+        _start: *mut c_void,
+        _length: u64,
+    ) -> Status {
+        Status::SUCCESS
+    }
+
+    fn fake_debug_support(isa: ProcessorArch) -> DebugSupport {
+        DebugSupport {
+            isa,
+            get_maximum_processor_index,
+            register_periodic_callback,
+            register_exception_callback,
+            invalidate_instruction_cache,
+        }
+    }
+
+    #[test]
+    fn test_exception_handler() {
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        let ebc = fake_debug_support(ProcessorArch::EBC);
+        assert_eq!(
+            ebc.register_exception_handler(0, ExceptionType::EXCEPT_X64_BREAKPOINT, |_, _| {})
+                .unwrap_err()
+                .status(),
+            Status::UNSUPPORTED
+        );
+
+        let debug_support = fake_debug_support(ProcessorArch::X86_64);
+        assert_eq!(
+            debug_support
+                .register_exception_handler(1, ExceptionType::EXCEPT_X64_BREAKPOINT, |_, _| {})
+                .unwrap_err()
+                .status(),
+            Status::INVALID_PARAMETER
+        );
+
+        let handler = debug_support
+            .register_exception_handler(
+                0,
+                ExceptionType::EXCEPT_X64_BREAKPOINT,
+                |exception_type, context| {
+                    assert_eq!(exception_type, ExceptionType::EXCEPT_X64_BREAKPOINT);
+                    CALLS.fetch_add(1, Ordering::Relaxed);
+                    context.rip += 1;
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            handler.exception_type(),
+            ExceptionType::EXCEPT_X64_BREAKPOINT
+        );
+        assert_eq!(
+            debug_support
+                .register_exception_handler(0, ExceptionType::EXCEPT_X64_BREAKPOINT, |_, _| {})
+                .unwrap_err()
+                .status(),
+            Status::ALREADY_STARTED
+        );
+
+        // SAFETY: all fields of the context are integers.
+        let mut context: ArchSystemContext = unsafe { MaybeUninit::zeroed().assume_init() };
+        context.rip = 0x1000;
+        let callback = CALLBACK.lock(|callback| callback.unwrap());
+        unsafe {
+            callback(
+                ExceptionType::EXCEPT_X64_BREAKPOINT,
+                SystemContext { x64: &mut context },
+            )
+        };
+        assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+        assert_eq!(context.rip, 0x1001);
+
+        drop(handler);
+        assert!(CALLBACK.lock(|callback| callback.is_none()));
+
+        // Exceptions without a handler are ignored.
+        unsafe {
+            callback(
+                ExceptionType::EXCEPT_X64_BREAKPOINT,
+                SystemContext { x64: &mut context },
+            )
+        };
+        assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_register_line() {
+        use alloc::string::ToString;
+
+        let line = RegisterLine(&[("rip", 0x1000), ("rflags", 0x246)]).to_string();
+        assert_eq!(line, "   rip=0000000000001000 rflags=0000000000000246");
+    }
+}
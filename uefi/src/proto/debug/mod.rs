@@ -17,11 +17,31 @@ use crate::proto::unsafe_protocol;
 use crate::{Result, Status, StatusExt};
 
 // re-export for ease of use
-pub use context::SystemContext;
+pub use context::{
+    FxSaveStateX64, SystemContext, SystemContextAARCH64, SystemContextRiscV64, SystemContextX64,
+};
 pub use exception::ExceptionType;
+#[cfg(all(
+    feature = "alloc",
+    any(
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        target_arch = "riscv64"
+    )
+))]
+pub use handler::{ArchSystemContext, ExceptionHandler, log_exception};
 
 mod context;
 mod exception;
+#[cfg(all(
+    feature = "alloc",
+    any(
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        target_arch = "riscv64"
+    )
+))]
+mod handler;
 
 /// Debug support [`Protocol`].
 ///
@@ -41,20 +61,20 @@ mod exception;
 pub struct DebugSupport {
     isa: ProcessorArch,
     get_maximum_processor_index:
-        extern "efiapi" fn(this: &mut DebugSupport, max_processor_index: &mut usize) -> Status,
+        extern "efiapi" fn(this: *mut DebugSupport, max_processor_index: &mut usize) -> Status,
     register_periodic_callback: unsafe extern "efiapi" fn(
-        this: &mut DebugSupport,
+        this: *mut DebugSupport,
         processor_index: usize,
         periodic_callback: Option<unsafe extern "efiapi" fn(SystemContext)>,
     ) -> Status,
     register_exception_callback: unsafe extern "efiapi" fn(
-        this: &mut DebugSupport,
+        this: *mut DebugSupport,
         processor_index: usize,
         exception_callback: Option<unsafe extern "efiapi" fn(ExceptionType, SystemContext)>,
         exception_type: ExceptionType,
     ) -> Status,
     invalidate_instruction_cache: unsafe extern "efiapi" fn(
-        this: &mut DebugSupport,
+        this: *mut DebugSupport,
         processor_index: usize,
         start: *mut c_void,
         length: u64,
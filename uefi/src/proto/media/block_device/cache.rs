@@ -0,0 +1,386 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use super::{BlockDevice, check_range};
+use crate::proto::media::block::Lba;
+use crate::{Result, Status};
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Write-back cache of the blocks of a [`BlockDevice`], with reads and writes
+/// at any byte offset.
+///
+/// Up to `capacity` blocks are kept in memory. Writes only modify the cached
+/// blocks, which are written to the device when they are evicted, when
+/// [`flush`] is called, or when the cache is dropped. When the cache is full,
+/// the least recently used block is evicted.
+///
+/// The cache is a [`BlockDevice`] itself, so it can be used in place of the
+/// device.
+///
+/// [`flush`]: Self::flush
+#[derive(Debug)]
+pub struct BlockCache<D: BlockDevice> {
+    device: D,
+    capacity: usize,
+    blocks: BTreeMap<Lba, CachedBlock>,
+    /// Incremented on each access, to find the least recently used block.
+    clock: u64,
+    stats: CacheStats,
+}
+
+#[derive(Debug)]
+struct CachedBlock {
+    data: Box<[u8]>,
+    dirty: bool,
+    last_used: u64,
+}
+
+/// Statistics of a [`BlockCache`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CacheStats {
+    /// Number of block accesses served from the cache.
+    pub hits: u64,
+    /// Number of block accesses that required reading the block from the
+    /// device, or allocating it for a write of the whole block.
+    pub misses: u64,
+    /// Number of dirty blocks written to the device.
+    pub writebacks: u64,
+}
+
+impl<D: BlockDevice> BlockCache<D> {
+    /// Creates a cache of up to `capacity` blocks of `device`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    #[must_use]
+    pub fn new(device: D, capacity: usize) -> Self {
+        assert!(capacity > 0, "cache capacity must not be zero");
+        Self {
+            device,
+            capacity,
+            blocks: BTreeMap::new(),
+            clock: 0,
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// Returns the device.
+    #[must_use]
+    pub const fn device(&self) -> &D {
+        &self.device
+    }
+
+    /// Returns the cache statistics.
+    #[must_use]
+    pub const fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    /// Returns the size of the device in bytes.
+    #[must_use]
+    pub fn size(&self) -> u64 {
+        self.device.block_count() * self.device.block_size() as u64
+    }
+
+    /// Reads `buffer.len()` bytes starting at byte `offset`.
+    ///
+    /// # Errors
+    ///
+    /// - [`Status::INVALID_PARAMETER`] if the range is past the end of the
+    ///   device
+    /// - Errors of [`BlockDevice::read_blocks`] and
+    ///   [`BlockDevice::write_blocks`], which is used to evict dirty blocks
+    pub fn read(&mut self, offset: u64, buffer: &mut [u8]) -> Result {
+        self.check_bytes(offset, buffer.len())?;
+        let mut done = 0;
+        while done < buffer.len() {
+            let (lba, start, len) = self.split(offset + done as u64, buffer.len() - done);
+            let block = self.block(lba, false)?;
+            buffer[done..done + len].copy_from_slice(&block.data[start..start + len]);
+            done += len;
+        }
+        Ok(())
+    }
+
+    /// Writes `buffer` starting at byte `offset`.
+    ///
+    /// Blocks that are only partially written are read from the device
+    /// first, unless they are cached already.
+    ///
+    /// # Errors
+    ///
+    /// - [`Status::INVALID_PARAMETER`] if the range is past the end of the
+    ///   device
+    /// - Errors of [`BlockDevice::read_blocks`] and
+    ///   [`BlockDevice::write_blocks`]
+    pub fn write(&mut self, offset: u64, buffer: &[u8]) -> Result {
+        self.check_bytes(offset, buffer.len())?;
+        let mut done = 0;
+        while done < buffer.len() {
+            let (lba, start, len) = self.split(offset + done as u64, buffer.len() - done);
+            let whole = len == self.device.block_size();
+            let block = self.block(lba, whole)?;
+            block.data[start..start + len].copy_from_slice(&buffer[done..done + len]);
+            block.dirty = true;
+            done += len;
+        }
+        Ok(())
+    }
+
+    /// Writes all dirty blocks to the device, then flushes the device.
+    ///
+    /// Consecutive dirty blocks are written with a single request.
+    pub fn flush(&mut self) -> Result {
+        let mut run: Vec<u8> = Vec::new();
+        let mut run_start = 0;
+        let mut run_blocks = Vec::new();
+        let dirty: Vec<Lba> = self
+            .blocks
+            .iter()
+            .filter(|(_, block)| block.dirty)
+            .map(|(&lba, _)| lba)
+            .collect();
+        for (i, &lba) in dirty.iter().enumerate() {
+            if run_blocks.is_empty() {
+                run_start = lba;
+            }
+            run.extend_from_slice(&self.blocks[&lba].data);
+            run_blocks.push(lba);
+            if dirty.get(i + 1) != Some(&(lba + 1)) {
+                self.device.write_blocks(run_start, &run)?;
+                for lba in run_blocks.drain(..) {
+                    self.blocks.get_mut(&lba).unwrap().dirty = false;
+                    self.stats.writebacks += 1;
+                }
+                run.clear();
+            }
+        }
+        self.device.flush()
+    }
+
+    /// Removes all blocks from the cache, discarding unwritten changes.
+    pub fn discard(&mut self) {
+        self.blocks.clear();
+    }
+
+    fn check_bytes(&self, offset: u64, len: usize) -> Result {
+        if offset
+            .checked_add(len as u64)
+            .is_none_or(|end| end > self.size())
+        {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+        Ok(())
+    }
+
+    /// Returns the block containing `offset`, the offset within the block,
+    /// and the number of bytes of the block to access.
+    fn split(&self, offset: u64, len: usize) -> (Lba, usize, usize) {
+        let block_size = self.device.block_size();
+        let start = (offset % block_size as u64) as usize;
+        (
+            offset / block_size as u64,
+            start,
+            len.min(block_size - start),
+        )
+    }
+
+    /// Returns the cached block `lba`, loading it unless `overwrite` is
+    /// true, in which case the caller replaces all of its contents.
+    fn block(&mut self, lba: Lba, overwrite: bool) -> Result<&mut CachedBlock> {
+        self.clock += 1;
+        if self.blocks.contains_key(&lba) {
+            self.stats.hits += 1;
+        } else {
+            self.stats.misses += 1;
+            if self.blocks.len() >= self.capacity {
+                self.evict()?;
+            }
+            let mut data = vec![0; self.device.block_size()].into_boxed_slice();
+            if !overwrite {
+                self.device.read_blocks(lba, &mut data)?;
+            }
+            self.blocks.insert(
+                lba,
+                CachedBlock {
+                    data,
+                    dirty: false,
+                    last_used: 0,
+                },
+            );
+        }
+        let block = self.blocks.get_mut(&lba).unwrap();
+        block.last_used = self.clock;
+        Ok(block)
+    }
+
+    /// Removes the least recently used block, writing it if it is dirty.
+    fn evict(&mut self) -> Result {
+        let Some((&lba, block)) = self.blocks.iter().min_by_key(|(_, block)| block.last_used)
+        else {
+            return Ok(());
+        };
+        if block.dirty {
+            self.device.write_blocks(lba, &block.data)?;
+            self.stats.writebacks += 1;
+        }
+        self.blocks.remove(&lba);
+        Ok(())
+    }
+}
+
+impl<D: BlockDevice> BlockDevice for BlockCache<D> {
+    fn block_size(&self) -> usize {
+        self.device.block_size()
+    }
+
+    fn block_count(&self) -> u64 {
+        self.device.block_count()
+    }
+
+    fn read_blocks(&mut self, lba: Lba, buffer: &mut [u8]) -> Result {
+        let offset = check_range(self, lba, buffer.len())?;
+        self.read(offset, buffer)
+    }
+
+    fn write_blocks(&mut self, lba: Lba, buffer: &[u8]) -> Result {
+        let offset = check_range(self, lba, buffer.len())?;
+        self.write(offset, buffer)
+    }
+
+    fn flush(&mut self) -> Result {
+        Self::flush(self)
+    }
+}
+
+impl<D: BlockDevice> Drop for BlockCache<D> {
+    fn drop(&mut self) {
+        if let Err(err) = self.flush() {
+            log::warn!("failed to flush block cache: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::media::block_device::MemoryBlockDevice;
+
+    /// Block device that counts the requests.
+    struct CountingDevice {
+        inner: MemoryBlockDevice<Vec<u8>>,
+        reads: usize,
+        writes: usize,
+    }
+
+    impl BlockDevice for CountingDevice {
+        fn block_size(&self) -> usize {
+            self.inner.block_size()
+        }
+
+        fn block_count(&self) -> u64 {
+            self.inner.block_count()
+        }
+
+        fn read_blocks(&mut self, lba: Lba, buffer: &mut [u8]) -> Result {
+            self.reads += 1;
+            self.inner.read_blocks(lba, buffer)
+        }
+
+        fn write_blocks(&mut self, lba: Lba, buffer: &[u8]) -> Result {
+            self.writes += 1;
+            self.inner.write_blocks(lba, buffer)
+        }
+
+        fn flush(&mut self) -> Result {
+            Ok(())
+        }
+    }
+
+    fn device() -> CountingDevice {
+        let data = (0..64).collect();
+        CountingDevice {
+            inner: MemoryBlockDevice::new(data, 8),
+            reads: 0,
+            writes: 0,
+        }
+    }
+
+    #[test]
+    fn test_read_write() {
+        let mut cache = BlockCache::new(device(), 4);
+        let mut buffer = [0; 10];
+        cache.read(5, &mut buffer).unwrap();
+        assert_eq!(buffer, [5, 6, 7, 8, 9, 10, 11, 12, 13, 14]);
+
+        // Partial write of a cached block, and a whole block write.
+        cache.write(14, &[0xff; 10]).unwrap();
+        assert_eq!(cache.device().reads, 2);
+        assert_eq!(cache.device().writes, 0);
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 1,
+                misses: 3,
+                writebacks: 0
+            }
+        );
+
+        cache.read(12, &mut buffer).unwrap();
+        assert_eq!(
+            buffer,
+            [12, 13, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]
+        );
+
+        // Blocks 1 and 2 are written with a single request.
+        cache.flush().unwrap();
+        assert_eq!(cache.device().writes, 1);
+        assert_eq!(cache.stats().writebacks, 2);
+        assert_eq!(&cache.device().inner.as_bytes()[12..26], {
+            let mut expected: [u8; 14] = core::array::from_fn(|i| 12 + i as u8);
+            expected[2..12].fill(0xff);
+            expected
+        });
+
+        let err = cache.read(60, &mut buffer).unwrap_err();
+        assert_eq!(err.status(), Status::INVALID_PARAMETER);
+    }
+
+    #[test]
+    fn test_eviction() {
+        let mut cache = BlockCache::new(device(), 2);
+        cache.write(0, &[1]).unwrap();
+        cache.read(8, &mut [0]).unwrap();
+        // Block 0 is used again, so block 1 is evicted next.
+        cache.read(0, &mut [0]).unwrap();
+        cache.read(16, &mut [0]).unwrap();
+        assert_eq!(cache.device().writes, 0);
+        assert_eq!(cache.blocks.keys().copied().collect::<Vec<_>>(), [0, 2]);
+
+        // The dirty block 0 is written when evicted.
+        cache.read(24, &mut [0]).unwrap();
+        cache.read(32, &mut [0]).unwrap();
+        assert_eq!(cache.device().writes, 1);
+        assert_eq!(cache.device().inner.as_bytes()[..2], [1, 1]);
+
+        // Unwritten changes are discarded.
+        cache.write(40, &[0xff]).unwrap();
+        cache.discard();
+        cache.flush().unwrap();
+        assert_eq!(cache.device().inner.as_bytes()[40], 40);
+    }
+
+    #[test]
+    fn test_block_device() {
+        let mut cache = BlockCache::new(device(), 2);
+        BlockDevice::write_blocks(&mut cache, 7, &[0xaa; 8]).unwrap();
+        let mut buffer = [0; 16];
+        BlockDevice::read_blocks(&mut cache, 6, &mut buffer).unwrap();
+        assert_eq!(buffer[..8], [48, 49, 50, 51, 52, 53, 54, 55]);
+        assert_eq!(buffer[8..], [0xaa; 8]);
+        drop(cache);
+    }
+}
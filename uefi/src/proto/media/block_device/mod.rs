@@ -0,0 +1,417 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Device-independent access to block devices.
+//!
+//! The [`BlockDevice`] trait abstracts over the protocols and buffers that
+//! provide fixed-size blocks, so that code like partition table or file
+//! system parsers can be written once, and tested on the host with a
+//! [`MemoryBlockDevice`]. It is implemented for:
+//! - [`BlockIO`]
+//! - [`DiskIo`], with [`DiskIoDevice`]
+//! - in-memory buffers, with [`MemoryBlockDevice`]
+//!
+//! With the `alloc` feature, [`BlockCache`] adds a write-back cache with
+//! byte-granular reads and writes on top of any block device.
+//!
+//! # Example
+//!
+//! ```
+//! use uefi::Result;
+//! use uefi::proto::media::block_device::{BlockCache, BlockDevice};
+//!
+//! /// Reads the signature of an MBR partition table.
+//! fn has_mbr<D: BlockDevice>(device: D) -> Result<bool> {
+//!     let mut cache = BlockCache::new(device, 16);
+//!     let mut signature = [0; 2];
+//!     cache.read(510, &mut signature)?;
+//!     Ok(signature == [0x55, 0xaa])
+//! }
+//! ```
+
+#[cfg(feature = "alloc")]
+mod cache;
+
+#[cfg(feature = "alloc")]
+pub use cache::{BlockCache, CacheStats};
+
+use super::block::{BlockIO, BlockIOMedia, Lba};
+use super::disk::DiskIo;
+use crate::{Result, Status};
+
+/// A device made of fixed-size blocks.
+///
+/// The buffers passed to [`read_blocks`] and [`write_blocks`] must be a
+/// multiple of the block size. Otherwise, [`Status::BAD_BUFFER_SIZE`] is
+/// returned. Accessing blocks past the end of the device returns
+/// [`Status::INVALID_PARAMETER`].
+///
+/// [`read_blocks`]: Self::read_blocks
+/// [`write_blocks`]: Self::write_blocks
+pub trait BlockDevice {
+    /// Returns the size of a block in bytes.
+    fn block_size(&self) -> usize;
+
+    /// Returns the number of blocks.
+    fn block_count(&self) -> u64;
+
+    /// Reads blocks starting at `lba` into `buffer`.
+    fn read_blocks(&mut self, lba: Lba, buffer: &mut [u8]) -> Result;
+
+    /// Writes `buffer` to the blocks starting at `lba`.
+    fn write_blocks(&mut self, lba: Lba, buffer: &[u8]) -> Result;
+
+    /// Writes data cached by the device to the medium.
+    fn flush(&mut self) -> Result;
+}
+
+impl<D: BlockDevice + ?Sized> BlockDevice for &mut D {
+    fn block_size(&self) -> usize {
+        (**self).block_size()
+    }
+
+    fn block_count(&self) -> u64 {
+        (**self).block_count()
+    }
+
+    fn read_blocks(&mut self, lba: Lba, buffer: &mut [u8]) -> Result {
+        (**self).read_blocks(lba, buffer)
+    }
+
+    fn write_blocks(&mut self, lba: Lba, buffer: &[u8]) -> Result {
+        (**self).write_blocks(lba, buffer)
+    }
+
+    fn flush(&mut self) -> Result {
+        (**self).flush()
+    }
+}
+
+/// Checks that `len` bytes starting at `lba` are whole blocks within
+/// `device`, and returns the byte offset of `lba`.
+pub(crate) fn check_range(
+    device: &(impl BlockDevice + ?Sized),
+    lba: Lba,
+    len: usize,
+) -> Result<u64> {
+    let block_size = device.block_size();
+    if len % block_size != 0 {
+        return Err(Status::BAD_BUFFER_SIZE.into());
+    }
+    let blocks = (len / block_size) as u64;
+    if lba
+        .checked_add(blocks)
+        .is_none_or(|end| end > device.block_count())
+    {
+        return Err(Status::INVALID_PARAMETER.into());
+    }
+    Ok(lba * block_size as u64)
+}
+
+/// Uses the current media of the device.
+///
+/// If the buffers are not aligned to [`BlockIOMedia::io_align`], the data is
+/// copied through an aligned buffer, which requires the `alloc` feature.
+/// Without it, [`Status::INVALID_PARAMETER`] is returned for unaligned
+/// buffers.
+impl BlockDevice for BlockIO {
+    fn block_size(&self) -> usize {
+        self.media().block_size() as usize
+    }
+
+    fn block_count(&self) -> u64 {
+        self.media().last_block() + 1
+    }
+
+    fn read_blocks(&mut self, lba: Lba, buffer: &mut [u8]) -> Result {
+        let media_id = self.media().media_id();
+        if is_aligned(buffer, self.media()) {
+            return Self::read_blocks(self, media_id, lba, buffer);
+        }
+        #[cfg(feature = "alloc")]
+        {
+            let mut bounce = aligned_buffer(buffer.len(), self.media())?;
+            Self::read_blocks(self, media_id, lba, bounce.as_mut())?;
+            buffer.copy_from_slice(bounce.as_mut());
+            Ok(())
+        }
+        #[cfg(not(feature = "alloc"))]
+        Err(Status::INVALID_PARAMETER.into())
+    }
+
+    fn write_blocks(&mut self, lba: Lba, buffer: &[u8]) -> Result {
+        let media_id = self.media().media_id();
+        if is_aligned(buffer, self.media()) {
+            return Self::write_blocks(self, media_id, lba, buffer);
+        }
+        #[cfg(feature = "alloc")]
+        {
+            let mut bounce = aligned_buffer(buffer.len(), self.media())?;
+            bounce.as_mut().copy_from_slice(buffer);
+            Self::write_blocks(self, media_id, lba, bounce.as_mut())
+        }
+        #[cfg(not(feature = "alloc"))]
+        Err(Status::INVALID_PARAMETER.into())
+    }
+
+    fn flush(&mut self) -> Result {
+        self.flush_blocks()
+    }
+}
+
+fn is_aligned(buffer: &[u8], media: &BlockIOMedia) -> bool {
+    let align = media.io_align().max(1) as usize;
+    buffer.as_ptr() as usize % align == 0
+}
+
+#[cfg(feature = "alloc")]
+fn aligned_buffer(len: usize, media: &BlockIOMedia) -> Result<BounceBuffer> {
+    let align = media.io_align().max(1) as usize;
+    let mut buffer = crate::mem::AlignedBuffer::from_size_align(len.max(1), align)
+        .map_err(|_| Status::INVALID_PARAMETER)?;
+    // SAFETY: the buffer is at least `len` bytes long.
+    unsafe { buffer.ptr_mut().write_bytes(0, len) };
+    Ok(BounceBuffer { buffer, len })
+}
+
+/// Aligned buffer for [`BlockIO`] transfers.
+#[cfg(feature = "alloc")]
+struct BounceBuffer {
+    buffer: crate::mem::AlignedBuffer,
+    len: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl BounceBuffer {
+    const fn as_mut(&mut self) -> &mut [u8] {
+        // SAFETY: the buffer is at least `len` bytes long, and was zeroed by
+        // `aligned_buffer`.
+        unsafe { core::slice::from_raw_parts_mut(self.buffer.ptr_mut(), self.len) }
+    }
+}
+
+/// [`BlockDevice`] using the [`DiskIo`] protocol.
+///
+/// [`DiskIo`] has no information about the device, so the block size and
+/// count are taken from the [`BlockIOMedia`] of the same handle.
+#[derive(Debug)]
+pub struct DiskIoDevice<'a> {
+    disk: &'a mut DiskIo,
+    media_id: u32,
+    block_size: usize,
+    block_count: u64,
+}
+
+impl<'a> DiskIoDevice<'a> {
+    /// Creates a block device accessing `disk` with the geometry of `media`.
+    #[must_use]
+    pub const fn new(disk: &'a mut DiskIo, media: &BlockIOMedia) -> Self {
+        Self {
+            disk,
+            media_id: media.media_id(),
+            block_size: media.block_size() as usize,
+            block_count: media.last_block() + 1,
+        }
+    }
+}
+
+impl BlockDevice for DiskIoDevice<'_> {
+    fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    fn block_count(&self) -> u64 {
+        self.block_count
+    }
+
+    fn read_blocks(&mut self, lba: Lba, buffer: &mut [u8]) -> Result {
+        let offset = check_range(self, lba, buffer.len())?;
+        self.disk.read_disk(self.media_id, offset, buffer)
+    }
+
+    fn write_blocks(&mut self, lba: Lba, buffer: &[u8]) -> Result {
+        let offset = check_range(self, lba, buffer.len())?;
+        self.disk.write_disk(self.media_id, offset, buffer)
+    }
+
+    /// [`DiskIo`] has no flush operation, so this does nothing.
+    fn flush(&mut self) -> Result {
+        Ok(())
+    }
+}
+
+/// [`BlockDevice`] stored in memory, for example a disk image or a test
+/// fixture.
+///
+/// The buffer can be anything that dereferences to a byte slice, like a
+/// `&mut [u8]` or a `Vec<u8>`.
+#[derive(Debug)]
+pub struct MemoryBlockDevice<B> {
+    buffer: B,
+    block_size: usize,
+}
+
+impl<B: AsRef<[u8]> + AsMut<[u8]>> MemoryBlockDevice<B> {
+    /// Creates a block device storing its blocks in `buffer`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_size` is zero or the buffer size is not a multiple of
+    /// it.
+    #[must_use]
+    pub fn new(buffer: B, block_size: usize) -> Self {
+        assert!(
+            block_size != 0 && buffer.as_ref().len() % block_size == 0,
+            "buffer size must be a multiple of the block size"
+        );
+        Self { buffer, block_size }
+    }
+
+    /// Returns the contents of the device.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        self.buffer.as_ref()
+    }
+
+    /// Returns the buffer.
+    #[must_use]
+    pub fn into_inner(self) -> B {
+        self.buffer
+    }
+}
+
+impl<B: AsRef<[u8]> + AsMut<[u8]>> BlockDevice for MemoryBlockDevice<B> {
+    fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    fn block_count(&self) -> u64 {
+        (self.buffer.as_ref().len() / self.block_size) as u64
+    }
+
+    fn read_blocks(&mut self, lba: Lba, buffer: &mut [u8]) -> Result {
+        let offset = check_range(self, lba, buffer.len())? as usize;
+        buffer.copy_from_slice(&self.buffer.as_ref()[offset..offset + buffer.len()]);
+        Ok(())
+    }
+
+    fn write_blocks(&mut self, lba: Lba, buffer: &[u8]) -> Result {
+        let offset = check_range(self, lba, buffer.len())? as usize;
+        self.buffer.as_mut()[offset..offset + buffer.len()].copy_from_slice(buffer);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_device() {
+        let mut data = [0u8; 64];
+        let mut device = MemoryBlockDevice::new(&mut data[..], 16);
+        assert_eq!((device.block_size(), device.block_count()), (16, 4));
+
+        device.write_blocks(1, &[1; 32]).unwrap();
+        let mut buffer = [0; 16];
+        device.read_blocks(2, &mut buffer).unwrap();
+        assert_eq!(buffer, [1; 16]);
+
+        let err = |result: Result| result.unwrap_err().status();
+        assert_eq!(
+            err(device.read_blocks(0, &mut [0; 8])),
+            Status::BAD_BUFFER_SIZE
+        );
+        assert_eq!(
+            err(device.read_blocks(3, &mut [0; 32])),
+            Status::INVALID_PARAMETER
+        );
+        assert_eq!(
+            err(device.write_blocks(u64::MAX, &[0; 16])),
+            Status::INVALID_PARAMETER
+        );
+
+        assert_eq!(&data[..16], [0; 16]);
+        assert_eq!(&data[16..48], [1; 32]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_block_io_unaligned_read() {
+        use core::ffi::c_void;
+        use core::ptr;
+        use uefi_raw::Boolean;
+        use uefi_raw::protocol::block::{BlockIoMedia, BlockIoProtocol};
+
+        unsafe extern "efiapi" fn reset(_: *mut BlockIoProtocol, _: Boolean) -> uefi_raw::Status {
+            uefi_raw::Status::SUCCESS
+        }
+        unsafe extern "efiapi" fn read_blocks(
+            _: *const BlockIoProtocol,
+            _: u32,
+            lba: Lba,
+            buffer_size: usize,
+            buffer: *mut c_void,
+        ) -> uefi_raw::Status {
+            assert_eq!(buffer as usize % 16, 0);
+            let buffer =
+                unsafe { core::slice::from_raw_parts_mut(buffer.cast::<u8>(), buffer_size) };
+            for (i, byte) in buffer.iter_mut().enumerate() {
+                *byte = lba as u8 + i as u8;
+            }
+            uefi_raw::Status::SUCCESS
+        }
+        unsafe extern "efiapi" fn write_blocks(
+            _: *mut BlockIoProtocol,
+            _: u32,
+            _: Lba,
+            _: usize,
+            _: *const c_void,
+        ) -> uefi_raw::Status {
+            uefi_raw::Status::SUCCESS
+        }
+        unsafe extern "efiapi" fn flush_blocks(_: *mut BlockIoProtocol) -> uefi_raw::Status {
+            uefi_raw::Status::SUCCESS
+        }
+
+        let media = BlockIoMedia {
+            media_id: 0,
+            removable_media: Boolean::FALSE,
+            media_present: Boolean::TRUE,
+            logical_partition: Boolean::FALSE,
+            read_only: Boolean::FALSE,
+            write_caching: Boolean::FALSE,
+            block_size: 16,
+            io_align: 16,
+            last_block: 3,
+            lowest_aligned_lba: 0,
+            logical_blocks_per_physical_block: 1,
+            optimal_transfer_length_granularity: 0,
+        };
+        let mut protocol = BlockIoProtocol {
+            revision: 0,
+            media: &media,
+            reset,
+            read_blocks,
+            write_blocks,
+            flush_blocks,
+        };
+        // SAFETY: `BlockIO` is a transparent wrapper of the raw protocol.
+        let block_io = unsafe { &mut *ptr::from_mut(&mut protocol).cast::<BlockIO>() };
+
+        #[repr(align(16))]
+        struct Aligned([u8; 33]);
+        let mut data = Aligned([0; 33]);
+        let buffer = &mut data.0[1..];
+        BlockDevice::read_blocks(block_io, 2, buffer).unwrap();
+        assert!(
+            buffer
+                .iter()
+                .enumerate()
+                .all(|(i, &byte)| byte == 2 + i as u8)
+        );
+    }
+}
@@ -9,8 +9,10 @@
 pub mod file;
 
 pub mod block;
+pub mod block_device;
 pub mod disk;
 pub mod disk_info;
 pub mod fs;
 pub mod load_file;
 pub mod partition;
+pub mod storage_security;
@@ -0,0 +1,210 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Storage Security Command protocol.
+//!
+//! The [`StorageSecurityCommand`] protocol sends the SECURITY PROTOCOL IN
+//! and OUT commands of ATA, SCSI and NVMe devices, which are used by
+//! self-encrypting drives. The [`opal`] module parses the TCG discovery data
+//! of such drives.
+
+pub mod opal;
+
+use crate::proto::unsafe_protocol;
+use crate::{Result, StatusExt};
+use core::time::Duration;
+use opal::Level0Discovery;
+use uefi_raw::protocol::media::StorageSecurityCommandProtocol;
+
+/// Security protocol returning information about the security protocols
+/// supported by the device.
+pub const SECURITY_PROTOCOL_INFORMATION: u8 = 0x00;
+
+/// First of the security protocols defined by the TCG, used by Opal drives.
+pub const SECURITY_PROTOCOL_TCG: u8 = 0x01;
+
+/// Storage Security Command [`Protocol`].
+///
+/// Sends security protocol commands to a storage device, for example to
+/// unlock a self-encrypting drive before booting. The protocol is installed
+/// on the handles of the block devices supporting the commands.
+///
+/// The `media_id` arguments are the [`BlockIOMedia::media_id`] of the device.
+/// The `timeout` arguments are rounded to 100 ns units, and
+/// [`Duration::ZERO`] waits indefinitely.
+///
+/// The `protocol_specific` arguments are the Security Protocol Specific
+/// field of the commands, like the ComID for the TCG protocols. They are
+/// byte-swapped before being passed to the driver, as expected by the EDK2
+/// drivers.
+///
+/// # Example
+///
+/// ```
+/// use uefi::Result;
+/// use uefi::proto::media::storage_security::StorageSecurityCommand;
+///
+/// fn is_locked(storage: &mut StorageSecurityCommand, media_id: u32) -> Result<bool> {
+///     let mut buffer = [0; 2048];
+///     let discovery = storage.tcg_discovery(media_id, &mut buffer)?;
+///     Ok(discovery.is_locked())
+/// }
+/// ```
+///
+/// [`BlockIOMedia::media_id`]: crate::proto::media::block::BlockIOMedia::media_id
+/// [`Protocol`]: uefi::proto::Protocol
+#[derive(Debug)]
+#[repr(transparent)]
+#[unsafe_protocol(StorageSecurityCommandProtocol::GUID)]
+pub struct StorageSecurityCommand(StorageSecurityCommandProtocol);
+
+impl StorageSecurityCommand {
+    /// Receives data of `protocol_id` from the device into `buffer`, and
+    /// returns the number of bytes received.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the device does not support the security
+    ///   protocol commands.
+    /// * [`Status::DEVICE_ERROR`]: the command failed.
+    /// * [`Status::NO_MEDIA`]: there is no medium in the device.
+    /// * [`Status::MEDIA_CHANGED`]: `media_id` is not for the current medium.
+    /// * [`Status::WARN_BUFFER_TOO_SMALL`]: `buffer` is too small for the
+    ///   data.
+    /// * [`Status::TIMEOUT`]: the command timed out.
+    ///
+    /// [`Status::UNSUPPORTED`]: crate::Status::UNSUPPORTED
+    /// [`Status::DEVICE_ERROR`]: crate::Status::DEVICE_ERROR
+    /// [`Status::NO_MEDIA`]: crate::Status::NO_MEDIA
+    /// [`Status::MEDIA_CHANGED`]: crate::Status::MEDIA_CHANGED
+    /// [`Status::WARN_BUFFER_TOO_SMALL`]: crate::Status::WARN_BUFFER_TOO_SMALL
+    /// [`Status::TIMEOUT`]: crate::Status::TIMEOUT
+    pub fn receive_data(
+        &mut self,
+        media_id: u32,
+        timeout: Duration,
+        protocol_id: u8,
+        protocol_specific: u16,
+        buffer: &mut [u8],
+    ) -> Result<usize> {
+        let mut transfer_size = 0;
+        unsafe {
+            (self.0.receive_data)(
+                &mut self.0,
+                media_id,
+                timeout_100ns(timeout),
+                protocol_id,
+                protocol_specific.swap_bytes(),
+                buffer.len(),
+                buffer.as_mut_ptr().cast(),
+                &mut transfer_size,
+            )
+        }
+        .to_result_with_val(|| transfer_size)
+    }
+
+    /// Sends `data` of `protocol_id` to the device.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the device does not support the security
+    ///   protocol commands.
+    /// * [`Status::DEVICE_ERROR`]: the command failed.
+    /// * [`Status::NO_MEDIA`]: there is no medium in the device.
+    /// * [`Status::MEDIA_CHANGED`]: `media_id` is not for the current medium.
+    /// * [`Status::TIMEOUT`]: the command timed out.
+    ///
+    /// [`Status::UNSUPPORTED`]: crate::Status::UNSUPPORTED
+    /// [`Status::DEVICE_ERROR`]: crate::Status::DEVICE_ERROR
+    /// [`Status::NO_MEDIA`]: crate::Status::NO_MEDIA
+    /// [`Status::MEDIA_CHANGED`]: crate::Status::MEDIA_CHANGED
+    /// [`Status::TIMEOUT`]: crate::Status::TIMEOUT
+    pub fn send_data(
+        &mut self,
+        media_id: u32,
+        timeout: Duration,
+        protocol_id: u8,
+        protocol_specific: u16,
+        data: &[u8],
+    ) -> Result {
+        unsafe {
+            (self.0.send_data)(
+                &mut self.0,
+                media_id,
+                timeout_100ns(timeout),
+                protocol_id,
+                protocol_specific.swap_bytes(),
+                data.len(),
+                data.as_ptr().cast(),
+            )
+        }
+        .to_result()
+    }
+
+    /// Returns the IDs of the security protocols supported by the device,
+    /// read into `buffer`.
+    pub fn supported_protocols<'buf>(
+        &mut self,
+        media_id: u32,
+        buffer: &'buf mut [u8],
+    ) -> Result<&'buf [u8]> {
+        let len = self.receive_data(
+            media_id,
+            DEFAULT_TIMEOUT,
+            SECURITY_PROTOCOL_INFORMATION,
+            0,
+            buffer,
+        )?;
+        let data = &buffer[..len.min(buffer.len())];
+        // The list follows a 6 byte reserved field and its length.
+        let count = match data {
+            [_, _, _, _, _, _, high, low, ..] => usize::from(u16::from_be_bytes([*high, *low])),
+            _ => 0,
+        };
+        Ok(data
+            .get(8..)
+            .map_or(&[], |list| &list[..count.min(list.len())]))
+    }
+
+    /// Reads the TCG Level 0 Discovery data into `buffer`, and parses it.
+    ///
+    /// `buffer` should be 2048 bytes long. Some drivers require a multiple of
+    /// the block size.
+    ///
+    /// # Errors
+    ///
+    /// * Errors of [`receive_data`].
+    /// * [`Status::UNSUPPORTED`] if the data is not valid discovery data,
+    ///   for example because the drive does not implement a TCG storage
+    ///   specification.
+    ///
+    /// [`receive_data`]: Self::receive_data
+    /// [`Status::UNSUPPORTED`]: crate::Status::UNSUPPORTED
+    pub fn tcg_discovery<'buf>(
+        &mut self,
+        media_id: u32,
+        buffer: &'buf mut [u8],
+    ) -> Result<Level0Discovery<'buf>> {
+        let len = self.receive_data(
+            media_id,
+            DEFAULT_TIMEOUT,
+            SECURITY_PROTOCOL_TCG,
+            opal::LEVEL0_DISCOVERY_COM_ID,
+            buffer,
+        )?;
+        Level0Discovery::parse(&buffer[..len.min(buffer.len())])
+            .ok_or_else(|| crate::Status::UNSUPPORTED.into())
+    }
+}
+
+/// Timeout of the helper commands.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Converts `timeout` to 100 ns units.
+const fn timeout_100ns(timeout: Duration) -> u64 {
+    let units = timeout.as_nanos().div_ceil(100);
+    if units > u64::MAX as u128 {
+        u64::MAX
+    } else {
+        units as u64
+    }
+}
@@ -0,0 +1,508 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! TCG Level 0 Discovery.
+//!
+//! Drives implementing a TCG storage specification, like Opal, describe the
+//! features they support in the Level 0 Discovery data. It is read with
+//! [`StorageSecurityCommand::tcg_discovery`] and tells whether the drive
+//! supports locking, whether locking is enabled and whether a locking range
+//! is currently locked.
+//!
+//! Reading or changing the locking ranges themselves requires a session with
+//! the drive, which is not implemented here.
+//!
+//! [`StorageSecurityCommand::tcg_discovery`]: super::StorageSecurityCommand::tcg_discovery
+
+use bitflags::bitflags;
+use core::fmt::{self, Debug, Formatter};
+
+/// ComID to read the Level 0 Discovery data from.
+pub const LEVEL0_DISCOVERY_COM_ID: u16 = 0x0001;
+
+/// Size of the Level 0 Discovery header.
+const HEADER_SIZE: usize = 48;
+
+/// Size of the header of a feature descriptor.
+const DESCRIPTOR_HEADER_SIZE: usize = 4;
+
+/// Feature codes of the Level 0 Discovery data.
+pub mod feature_code {
+    /// TPer feature.
+    pub const TPER: u16 = 0x0001;
+    /// Locking feature.
+    pub const LOCKING: u16 = 0x0002;
+    /// Geometry Reporting feature.
+    pub const GEOMETRY: u16 = 0x0003;
+    /// Enterprise SSC.
+    pub const ENTERPRISE: u16 = 0x0100;
+    /// Opal SSC V1.00.
+    pub const OPAL_V1: u16 = 0x0200;
+    /// Single User Mode feature.
+    pub const SINGLE_USER_MODE: u16 = 0x0201;
+    /// Opal SSC V2.00.
+    pub const OPAL_V2: u16 = 0x0203;
+    /// Opalite SSC.
+    pub const OPALITE: u16 = 0x0301;
+    /// Pyrite SSC V1.00.
+    pub const PYRITE_V1: u16 = 0x0302;
+    /// Pyrite SSC V2.00.
+    pub const PYRITE_V2: u16 = 0x0303;
+    /// Ruby SSC.
+    pub const RUBY: u16 = 0x0304;
+}
+
+/// Parsed TCG Level 0 Discovery data.
+///
+/// The data is borrowed from the buffer it was read into.
+#[derive(Clone, Copy)]
+pub struct Level0Discovery<'a> {
+    revision: u32,
+    features: &'a [u8],
+}
+
+impl<'a> Level0Discovery<'a> {
+    /// Parses the discovery data in `data`.
+    ///
+    /// Returns `None` if `data` is too short for the header, or if the data
+    /// is empty, as returned by drives without a TCG storage specification.
+    #[must_use]
+    pub fn parse(data: &'a [u8]) -> Option<Self> {
+        if data.len() < HEADER_SIZE {
+            return None;
+        }
+        // The length does not include the length field itself.
+        let len = usize::try_from(be_u32(data, 0)).ok()?.saturating_add(4);
+        if len < HEADER_SIZE {
+            return None;
+        }
+        Some(Self {
+            revision: be_u32(data, 4),
+            features: &data[HEADER_SIZE..len.min(data.len())],
+        })
+    }
+
+    /// Revision of the discovery data structure.
+    #[must_use]
+    pub const fn revision(&self) -> u32 {
+        self.revision
+    }
+
+    /// Returns an iterator over the feature descriptors.
+    #[must_use]
+    pub const fn features(&self) -> FeatureIter<'a> {
+        FeatureIter {
+            data: self.features,
+        }
+    }
+
+    /// Returns the descriptor of the feature `code`, if present.
+    #[must_use]
+    pub fn feature(&self, code: u16) -> Option<FeatureDescriptor<'a>> {
+        self.features().find(|feature| feature.code == code)
+    }
+
+    /// Returns the TPer feature.
+    #[must_use]
+    pub fn tper(&self) -> Option<TperFeature> {
+        let feature = self.feature(feature_code::TPER)?;
+        Some(TperFeature::from_bits_retain(feature.byte(0)))
+    }
+
+    /// Returns the Locking feature.
+    #[must_use]
+    pub fn locking(&self) -> Option<LockingFeature> {
+        let feature = self.feature(feature_code::LOCKING)?;
+        Some(LockingFeature::from_bits_retain(feature.byte(0)))
+    }
+
+    /// Returns whether locking is enabled and a locking range is locked.
+    #[must_use]
+    pub fn is_locked(&self) -> bool {
+        self.locking().is_some_and(|locking| {
+            locking.contains(LockingFeature::ENABLED | LockingFeature::LOCKED)
+        })
+    }
+
+    /// Returns the Geometry Reporting feature.
+    #[must_use]
+    pub fn geometry(&self) -> Option<Geometry> {
+        let feature = self.feature(feature_code::GEOMETRY)?;
+        Some(Geometry {
+            align_required: feature.byte(0) & 1 != 0,
+            logical_block_size: feature.be_u32(8),
+            alignment_granularity: feature.be_u64(12),
+            lowest_aligned_lba: feature.be_u64(20),
+        })
+    }
+
+    /// Returns the first Security Subsystem Class supported by the drive.
+    #[must_use]
+    pub fn ssc(&self) -> Option<Ssc> {
+        self.features().find_map(|feature| {
+            let kind = match feature.code {
+                feature_code::ENTERPRISE => SscKind::Enterprise,
+                feature_code::OPAL_V1 => SscKind::OpalV1,
+                feature_code::OPAL_V2 => SscKind::OpalV2,
+                feature_code::OPALITE => SscKind::Opalite,
+                feature_code::PYRITE_V1 | feature_code::PYRITE_V2 => SscKind::Pyrite,
+                feature_code::RUBY => SscKind::Ruby,
+                _ => return None,
+            };
+            Some(Ssc {
+                kind,
+                base_com_id: feature.be_u16(0),
+                num_com_ids: feature.be_u16(2),
+            })
+        })
+    }
+
+    /// Returns the Opal SSC V2.00 feature.
+    #[must_use]
+    pub fn opal_v2(&self) -> Option<OpalV2> {
+        let feature = self.feature(feature_code::OPAL_V2)?;
+        Some(OpalV2 {
+            base_com_id: feature.be_u16(0),
+            num_com_ids: feature.be_u16(2),
+            range_crossing: feature.byte(4) & 1 != 0,
+            num_admin_authorities: feature.be_u16(5),
+            num_user_authorities: feature.be_u16(7),
+        })
+    }
+
+    /// Returns the Single User Mode feature.
+    #[must_use]
+    pub fn single_user_mode(&self) -> Option<SingleUserMode> {
+        let feature = self.feature(feature_code::SINGLE_USER_MODE)?;
+        let flags = feature.byte(4);
+        Some(SingleUserMode {
+            num_locking_objects: feature.be_u32(0),
+            any: flags & (1 << 0) != 0,
+            all: flags & (1 << 1) != 0,
+            policy: flags & (1 << 2) != 0,
+        })
+    }
+}
+
+impl Debug for Level0Discovery<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Level0Discovery")
+            .field("revision", &self.revision)
+            .field("features", &self.features())
+            .finish()
+    }
+}
+
+impl Debug for FeatureIter<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+/// Descriptor of a feature in the Level 0 Discovery data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeatureDescriptor<'a> {
+    /// Feature code, one of the [`feature_code`] constants for the
+    /// features defined by the TCG.
+    pub code: u16,
+    /// Version of the descriptor.
+    pub version: u8,
+    /// Feature-specific data, following the descriptor header.
+    pub data: &'a [u8],
+}
+
+impl FeatureDescriptor<'_> {
+    fn byte(&self, offset: usize) -> u8 {
+        self.data.get(offset).copied().unwrap_or(0)
+    }
+
+    fn be_u16(&self, offset: usize) -> u16 {
+        u16::from_be_bytes([self.byte(offset), self.byte(offset + 1)])
+    }
+
+    fn be_u32(&self, offset: usize) -> u32 {
+        u32::from_be_bytes(core::array::from_fn(|i| self.byte(offset + i)))
+    }
+
+    fn be_u64(&self, offset: usize) -> u64 {
+        u64::from_be_bytes(core::array::from_fn(|i| self.byte(offset + i)))
+    }
+}
+
+/// Iterator over the feature descriptors of the Level 0 Discovery data.
+///
+/// Returned by [`Level0Discovery::features`]. A truncated descriptor ends the
+/// iteration.
+#[derive(Clone)]
+pub struct FeatureIter<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Iterator for FeatureIter<'a> {
+    type Item = FeatureDescriptor<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.len() < DESCRIPTOR_HEADER_SIZE {
+            return None;
+        }
+        let len = DESCRIPTOR_HEADER_SIZE + usize::from(self.data[3]);
+        let Some(data) = self.data.get(DESCRIPTOR_HEADER_SIZE..len) else {
+            self.data = &[];
+            return None;
+        };
+        let feature = FeatureDescriptor {
+            code: u16::from_be_bytes([self.data[0], self.data[1]]),
+            version: self.data[2] >> 4,
+            data,
+        };
+        self.data = &self.data[len..];
+        Some(feature)
+    }
+}
+
+bitflags! {
+    /// TPer feature of the Level 0 Discovery data.
+    #[repr(transparent)]
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct TperFeature: u8 {
+        /// Synchronous communication.
+        const SYNC = 1 << 0;
+        /// Asynchronous communication.
+        const ASYNC = 1 << 1;
+        /// ACK/NAK of the packets.
+        const ACK_NAK = 1 << 2;
+        /// Buffer management.
+        const BUFFER_MANAGEMENT = 1 << 3;
+        /// Streaming.
+        const STREAMING = 1 << 4;
+        /// ComID management.
+        const COM_ID_MANAGEMENT = 1 << 6;
+    }
+}
+
+bitflags! {
+    /// Locking feature of the Level 0 Discovery data.
+    #[repr(transparent)]
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct LockingFeature: u8 {
+        /// The drive supports locking.
+        const SUPPORTED = 1 << 0;
+        /// Locking is enabled: the locking SP is activated.
+        const ENABLED = 1 << 1;
+        /// A locking range is locked.
+        const LOCKED = 1 << 2;
+        /// The drive encrypts the user data.
+        const MEDIA_ENCRYPTION = 1 << 3;
+        /// The shadow MBR is enabled.
+        const MBR_ENABLED = 1 << 4;
+        /// The shadow MBR is done: the drive no longer shows it in place of
+        /// the start of the user data.
+        const MBR_DONE = 1 << 5;
+    }
+}
+
+/// Geometry Reporting feature of the Level 0 Discovery data.
+///
+/// The start and length of the locking ranges should be aligned as
+/// described by this feature.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Geometry {
+    /// The locking ranges must be aligned.
+    pub align_required: bool,
+    /// Size of a logical block in bytes.
+    pub logical_block_size: u32,
+    /// Alignment of the locking ranges, in logical blocks.
+    pub alignment_granularity: u64,
+    /// First logical block aligned to the alignment granularity.
+    pub lowest_aligned_lba: u64,
+}
+
+/// Security Subsystem Class of a drive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SscKind {
+    /// Enterprise SSC.
+    Enterprise,
+    /// Opal SSC V1.00.
+    OpalV1,
+    /// Opal SSC V2.00.
+    OpalV2,
+    /// Opalite SSC.
+    Opalite,
+    /// Pyrite SSC, which has no encryption.
+    Pyrite,
+    /// Ruby SSC.
+    Ruby,
+}
+
+/// Security Subsystem Class feature of the Level 0 Discovery data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ssc {
+    /// Kind of the SSC.
+    pub kind: SscKind,
+    /// First ComID to use for the sessions.
+    pub base_com_id: u16,
+    /// Number of ComIDs starting from `base_com_id`.
+    pub num_com_ids: u16,
+}
+
+/// Opal SSC V2.00 feature of the Level 0 Discovery data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OpalV2 {
+    /// First ComID to use for the sessions.
+    pub base_com_id: u16,
+    /// Number of ComIDs starting from `base_com_id`.
+    pub num_com_ids: u16,
+    /// Commands accessing several locking ranges are rejected.
+    pub range_crossing: bool,
+    /// Number of admin authorities of the locking SP.
+    pub num_admin_authorities: u16,
+    /// Number of user authorities of the locking SP.
+    pub num_user_authorities: u16,
+}
+
+/// Single User Mode feature of the Level 0 Discovery data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SingleUserMode {
+    /// Number of locking objects, i.e. the global range and the other
+    /// locking ranges, supported by the drive.
+    pub num_locking_objects: u32,
+    /// At least one locking object is in single user mode.
+    pub any: bool,
+    /// All the locking objects are in single user mode.
+    pub all: bool,
+    /// The admins, not the users, own the policy of the locking objects.
+    pub policy: bool,
+}
+
+fn be_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    /// Builds discovery data with the `features` descriptors.
+    fn discovery(features: &[(u16, &[u8])]) -> Vec<u8> {
+        let mut data = alloc::vec![0; HEADER_SIZE];
+        data[4..8].copy_from_slice(&1u32.to_be_bytes());
+        for (code, body) in features {
+            data.extend_from_slice(&code.to_be_bytes());
+            data.push(0x10);
+            data.push(body.len() as u8);
+            data.extend_from_slice(body);
+        }
+        let len = (data.len() - 4) as u32;
+        data[..4].copy_from_slice(&len.to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn test_parse_opal_v2() {
+        let mut geometry = [0; 28];
+        geometry[0] = 1;
+        geometry[8..12].copy_from_slice(&512u32.to_be_bytes());
+        geometry[12..20].copy_from_slice(&8u64.to_be_bytes());
+        let opal = [
+            0x10, 0x00, 0x00, 0x01, 0x01, 0x00, 0x04, 0x00, 0x08, 0, 0, 0, 0, 0, 0, 0,
+        ];
+        let mut sum = [0; 12];
+        sum[..4].copy_from_slice(&9u32.to_be_bytes());
+        sum[4] = 0b001;
+
+        let data = discovery(&[
+            (feature_code::TPER, &[0x11, 0, 0, 0]),
+            (feature_code::LOCKING, &[0x0f, 0, 0, 0]),
+            (feature_code::GEOMETRY, &geometry),
+            (feature_code::OPAL_V2, &opal),
+            (feature_code::SINGLE_USER_MODE, &sum),
+            (0xc000, &[]),
+        ]);
+        let discovery = Level0Discovery::parse(&data).unwrap();
+
+        assert_eq!(discovery.revision(), 1);
+        assert_eq!(discovery.features().count(), 6);
+        assert_eq!(discovery.feature(0xc000).unwrap().version, 1);
+        assert_eq!(
+            discovery.tper(),
+            Some(TperFeature::SYNC | TperFeature::STREAMING)
+        );
+        assert_eq!(
+            discovery.locking(),
+            Some(
+                LockingFeature::SUPPORTED
+                    | LockingFeature::ENABLED
+                    | LockingFeature::LOCKED
+                    | LockingFeature::MEDIA_ENCRYPTION
+            )
+        );
+        assert!(discovery.is_locked());
+        assert_eq!(
+            discovery.geometry(),
+            Some(Geometry {
+                align_required: true,
+                logical_block_size: 512,
+                alignment_granularity: 8,
+                lowest_aligned_lba: 0,
+            })
+        );
+        assert_eq!(
+            discovery.ssc(),
+            Some(Ssc {
+                kind: SscKind::OpalV2,
+                base_com_id: 0x1000,
+                num_com_ids: 1,
+            })
+        );
+        assert_eq!(
+            discovery.opal_v2(),
+            Some(OpalV2 {
+                base_com_id: 0x1000,
+                num_com_ids: 1,
+                range_crossing: true,
+                num_admin_authorities: 4,
+                num_user_authorities: 8,
+            })
+        );
+        assert_eq!(
+            discovery.single_user_mode(),
+            Some(SingleUserMode {
+                num_locking_objects: 9,
+                any: true,
+                all: false,
+                policy: false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_unlocked_pyrite() {
+        let data = discovery(&[
+            (feature_code::LOCKING, &[0x01, 0, 0, 0]),
+            (feature_code::PYRITE_V2, &[0x07, 0xfe, 0x00, 0x01]),
+        ]);
+        let discovery = Level0Discovery::parse(&data).unwrap();
+
+        assert!(!discovery.is_locked());
+        assert_eq!(discovery.ssc().unwrap().kind, SscKind::Pyrite);
+        assert_eq!(discovery.ssc().unwrap().base_com_id, 0x07fe);
+        assert!(discovery.opal_v2().is_none());
+        assert!(discovery.geometry().is_none());
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(Level0Discovery::parse(&[0; 16]).is_none());
+        // Drives without a TCG specification return zeroes.
+        assert!(Level0Discovery::parse(&[0; 512]).is_none());
+
+        // Truncated descriptors end the iteration.
+        let mut data = discovery(&[(feature_code::TPER, &[0x01, 0, 0, 0])]);
+        data.extend_from_slice(&[0x00, 0x02, 0x10, 0x0c, 0x01]);
+        let len = (data.len() - 4) as u32;
+        data[..4].copy_from_slice(&len.to_be_bytes());
+        let discovery = Level0Discovery::parse(&data).unwrap();
+        assert_eq!(discovery.features().count(), 1);
+        assert!(discovery.locking().is_none());
+    }
+}
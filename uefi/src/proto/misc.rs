@@ -32,6 +32,13 @@ impl Timestamp {
         unsafe { (self.0.get_timestamp)() }
     }
 
+    /// Get the raw `get_timestamp` function. Unlike the protocol itself, the
+    /// function does not take a `this` pointer, so it can be stored.
+    #[cfg(all(feature = "logger", feature = "alloc"))]
+    pub(crate) fn get_timestamp_fn(&self) -> unsafe extern "efiapi" fn() -> u64 {
+        self.0.get_timestamp
+    }
+
     /// Get the properties of the timestamp counter.
     pub fn get_properties(&self) -> Result<TimestampProperties> {
         let mut properties = TimestampProperties::default();
@@ -0,0 +1,923 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Typed NVMe admin commands.
+//!
+//! The helpers in this module are methods of [`NvmeNamespace`]. Commands
+//! concerning the whole controller, like [`identify_controller`] or the
+//! firmware commands, are sent to [`NvmePassThru::controller`]. Commands
+//! concerning a namespace, like [`identify_namespace`] or [`format_nvm`],
+//! are sent to the namespace, from [`NvmePassThru::iter_namespaces`].
+//!
+//! If the controller completes a command with an error, the pass thru
+//! protocol returns [`Status::DEVICE_ERROR`].
+//!
+//! # Example
+//!
+//! ```
+//! use uefi::Result;
+//! use uefi::proto::nvme::pass_thru::NvmePassThru;
+//!
+//! fn print_health(nvme: &NvmePassThru) -> Result {
+//!     let mut controller = nvme.controller();
+//!     let identify = controller.identify_controller()?;
+//!     let smart = controller.smart_log()?;
+//!     log::info!(
+//!         "{}: {}% used, {} K",
+//!         identify.model_number,
+//!         smart.percentage_used,
+//!         smart.temperature
+//!     );
+//!     Ok(())
+//! }
+//! ```
+//!
+//! [`NvmePassThru::controller`]: super::pass_thru::NvmePassThru::controller
+//! [`NvmePassThru::iter_namespaces`]: super::pass_thru::NvmePassThru::iter_namespaces
+//! [`format_nvm`]: NvmeNamespace::format_nvm
+//! [`identify_controller`]: NvmeNamespace::identify_controller
+//! [`identify_namespace`]: NvmeNamespace::identify_namespace
+
+use super::pass_thru::NvmeNamespace;
+use super::{NvmeQueueType, NvmeRequestBuilder};
+use crate::mem::AlignedBuffer;
+use crate::{Result, Status};
+use alloc::string::String;
+use alloc::vec::Vec;
+use bitflags::bitflags;
+
+/// Admin command opcodes.
+mod opcode {
+    pub const GET_LOG_PAGE: u8 = 0x02;
+    pub const IDENTIFY: u8 = 0x06;
+    pub const FIRMWARE_COMMIT: u8 = 0x10;
+    pub const FIRMWARE_DOWNLOAD: u8 = 0x11;
+    pub const FORMAT_NVM: u8 = 0x80;
+    pub const SANITIZE: u8 = 0x84;
+}
+
+/// Size of the Identify data structures.
+const IDENTIFY_SIZE: usize = 4096;
+
+/// Log page identifiers.
+pub mod log_id {
+    /// Error Information.
+    pub const ERROR: u8 = 0x01;
+    /// SMART / Health Information.
+    pub const SMART: u8 = 0x02;
+    /// Firmware Slot Information.
+    pub const FIRMWARE_SLOT: u8 = 0x03;
+    /// Sanitize Status.
+    pub const SANITIZE_STATUS: u8 = 0x81;
+}
+
+impl NvmeNamespace<'_> {
+    /// Sends an admin command, transferring `data_len` bytes from the
+    /// controller, and returns the transferred data.
+    fn admin_read(&mut self, opcode: u8, cdws: [u32; 4], data_len: usize) -> Result<Vec<u8>> {
+        let request = NvmeRequestBuilder::new(self.io_align(), opcode, NvmeQueueType::ADMIN)
+            .with_cdw10(cdws[0])
+            .with_cdw11(cdws[1])
+            .with_cdw12(cdws[2])
+            .with_cdw13(cdws[3]);
+        let request = if data_len == 0 {
+            request
+        } else {
+            request
+                .with_transfer_buffer(data_len)
+                .map_err(|_| Status::INVALID_PARAMETER)?
+        };
+        let response = self.execute_command(request.build())?;
+        Ok(response.transfer_buffer().unwrap_or_default().to_vec())
+    }
+
+    /// Reads the Identify Controller data structure.
+    pub fn identify_controller(&mut self) -> Result<IdentifyController> {
+        let data = self.admin_read(opcode::IDENTIFY, [1, 0, 0, 0], IDENTIFY_SIZE)?;
+        IdentifyController::parse(&data).ok_or_else(|| Status::BAD_BUFFER_SIZE.into())
+    }
+
+    /// Reads the Identify Namespace data structure of this namespace.
+    pub fn identify_namespace(&mut self) -> Result<IdentifyNamespace> {
+        let data = self.admin_read(opcode::IDENTIFY, [0, 0, 0, 0], IDENTIFY_SIZE)?;
+        IdentifyNamespace::parse(&data).ok_or_else(|| Status::BAD_BUFFER_SIZE.into())
+    }
+
+    /// Reads `buffer.len()` bytes of the log page `log_id`, starting at byte
+    /// `offset`. Constants for the standard log pages are in [`log_id`].
+    ///
+    /// # Errors
+    ///
+    /// [`Status::INVALID_PARAMETER`] if the length or the offset is not a
+    /// multiple of 4, or if the buffer is empty, and [`Status::DEVICE_ERROR`]
+    /// if the controller returned fewer bytes than requested.
+    pub fn get_log_page(&mut self, log_id: u8, offset: u64, buffer: &mut [u8]) -> Result {
+        if buffer.is_empty() || buffer.len() % 4 != 0 || offset % 4 != 0 {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+        let dwords = (buffer.len() / 4 - 1) as u32;
+        let cdw10 = u32::from(log_id) | (dwords << 16);
+        let cdw11 = dwords >> 16;
+        let data = self.admin_read(
+            opcode::GET_LOG_PAGE,
+            [cdw10, cdw11, offset as u32, (offset >> 32) as u32],
+            buffer.len(),
+        )?;
+        if data.len() != buffer.len() {
+            return Err(Status::DEVICE_ERROR.into());
+        }
+        buffer.copy_from_slice(&data);
+        Ok(())
+    }
+
+    /// Reads the SMART / Health Information log page.
+    ///
+    /// Sent to the controller, this returns the information of the whole
+    /// controller.
+    pub fn smart_log(&mut self) -> Result<SmartLog> {
+        let mut data = [0; 512];
+        self.get_log_page(log_id::SMART, 0, &mut data)?;
+        SmartLog::parse(&data).ok_or_else(|| Status::BAD_BUFFER_SIZE.into())
+    }
+
+    /// Reads the valid entries of the Error Information log page, newest
+    /// first.
+    ///
+    /// `max_entries` is the number of entries to read. The number of entries
+    /// supported by the controller is
+    /// [`IdentifyController::error_log_entries`].
+    pub fn error_log(&mut self, max_entries: usize) -> Result<Vec<ErrorLogEntry>> {
+        let mut data = alloc::vec![0; max_entries * ErrorLogEntry::SIZE];
+        if !data.is_empty() {
+            self.get_log_page(log_id::ERROR, 0, &mut data)?;
+        }
+        Ok(data
+            .chunks_exact(ErrorLogEntry::SIZE)
+            .filter_map(ErrorLogEntry::parse)
+            .filter(|entry| entry.error_count != 0)
+            .collect())
+    }
+
+    /// Reads the Firmware Slot Information log page.
+    pub fn firmware_slot_log(&mut self) -> Result<FirmwareSlotLog> {
+        let mut data = [0; 512];
+        self.get_log_page(log_id::FIRMWARE_SLOT, 0, &mut data)?;
+        FirmwareSlotLog::parse(&data).ok_or_else(|| Status::BAD_BUFFER_SIZE.into())
+    }
+
+    /// Reads the Sanitize Status log page, to follow the progress of a
+    /// [`sanitize`] operation.
+    ///
+    /// [`sanitize`]: Self::sanitize
+    pub fn sanitize_status(&mut self) -> Result<SanitizeStatus> {
+        let mut data = [0; 512];
+        self.get_log_page(log_id::SANITIZE_STATUS, 0, &mut data)?;
+        SanitizeStatus::parse(&data).ok_or_else(|| Status::BAD_BUFFER_SIZE.into())
+    }
+
+    /// Formats this namespace with the LBA format at index `lba_format` of
+    /// [`IdentifyNamespace::lba_formats`].
+    ///
+    /// **All data of the namespace is lost.**
+    pub fn format_nvm(&mut self, lba_format: u8, secure_erase: SecureErase) -> Result {
+        let lbaf = u32::from(lba_format);
+        let cdw10 = (lbaf & 0xf) | ((secure_erase as u32) << 9) | ((lbaf >> 4 & 0x3) << 12);
+        self.admin_read(opcode::FORMAT_NVM, [cdw10, 0, 0, 0], 0)
+            .map(|_| ())
+    }
+
+    /// Starts a sanitize operation, which erases all user data of the NVM
+    /// subsystem, and all caches.
+    ///
+    /// The operation continues in the background after this returns. Use
+    /// [`sanitize_status`] to wait for its completion. If
+    /// `allow_unrestricted_exit` is false and the operation fails, the
+    /// controller stays in a failure mode until a successful sanitize, or
+    /// until [`SanitizeAction::ExitFailureMode`].
+    ///
+    /// **All user data is lost.**
+    ///
+    /// [`sanitize_status`]: Self::sanitize_status
+    pub fn sanitize(&mut self, action: SanitizeAction, allow_unrestricted_exit: bool) -> Result {
+        let (cdw10, cdw11) = action.cdws();
+        let cdw10 = cdw10 | (u32::from(allow_unrestricted_exit) << 3);
+        self.admin_read(opcode::SANITIZE, [cdw10, cdw11, 0, 0], 0)
+            .map(|_| ())
+    }
+
+    /// Transfers a part of a firmware image to the controller, to be
+    /// installed with [`firmware_commit`].
+    ///
+    /// `offset` is the position of `data` within the image. Large images
+    /// must be split into several transfers of at most the maximum data
+    /// transfer size of the controller, and of a multiple of its firmware
+    /// update granularity.
+    ///
+    /// # Errors
+    ///
+    /// [`Status::INVALID_PARAMETER`] if `data` is empty, or if its length or
+    /// `offset` is not a multiple of 4.
+    ///
+    /// [`firmware_commit`]: Self::firmware_commit
+    pub fn firmware_download(&mut self, offset: u32, data: &[u8]) -> Result {
+        if data.is_empty() || data.len() % 4 != 0 || offset % 4 != 0 {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+        let mut buffer = AlignedBuffer::from_size_align(data.len(), self.io_align() as usize)
+            .map_err(|_| Status::INVALID_PARAMETER)?;
+        buffer.copy_from_slice(data);
+        let request = NvmeRequestBuilder::new(
+            self.io_align(),
+            opcode::FIRMWARE_DOWNLOAD,
+            NvmeQueueType::ADMIN,
+        )
+        .with_cdw10((data.len() / 4 - 1) as u32)
+        .with_cdw11(offset / 4)
+        .use_transfer_buffer(&mut buffer)
+        .map_err(|_| Status::INVALID_PARAMETER)?
+        .build();
+        self.execute_command(request).map(|_| ())
+    }
+
+    /// Commits the image transferred with [`firmware_download`] to firmware
+    /// `slot`, or activates the image of `slot`, depending on `action`.
+    ///
+    /// A `slot` of 0 lets the controller choose the slot. Controllers may
+    /// complete the command with an error status meaning that a reset is
+    /// required to activate the firmware, which is reported as
+    /// [`Status::DEVICE_ERROR`].
+    ///
+    /// [`firmware_download`]: Self::firmware_download
+    pub fn firmware_commit(&mut self, slot: u8, action: FirmwareCommitAction) -> Result {
+        let cdw10 = u32::from(slot & 0x7) | ((action as u32) << 3);
+        self.admin_read(opcode::FIRMWARE_COMMIT, [cdw10, 0, 0, 0], 0)
+            .map(|_| ())
+    }
+}
+
+/// Reads `N` bytes at `offset`.
+fn bytes<const N: usize>(data: &[u8], offset: usize) -> [u8; N] {
+    data[offset..offset + N].try_into().unwrap()
+}
+
+fn u16_at(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(bytes(data, offset))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes(data, offset))
+}
+
+fn u64_at(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes(data, offset))
+}
+
+fn u128_at(data: &[u8], offset: usize) -> u128 {
+    u128::from_le_bytes(bytes(data, offset))
+}
+
+/// Reads an ASCII string padded with spaces.
+fn ascii_at(data: &[u8], offset: usize, len: usize) -> String {
+    String::from_utf8_lossy(&data[offset..offset + len])
+        .trim_end_matches([' ', '\0'])
+        .into()
+}
+
+bitflags! {
+    /// Optional admin commands supported by a controller.
+    #[repr(transparent)]
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct OptionalAdminCommands: u16 {
+        /// Security Send and Security Receive.
+        const SECURITY = 1 << 0;
+        /// Format NVM.
+        const FORMAT_NVM = 1 << 1;
+        /// Firmware Commit and Firmware Image Download.
+        const FIRMWARE = 1 << 2;
+        /// Namespace Management.
+        const NAMESPACE_MANAGEMENT = 1 << 3;
+        /// Device Self-test.
+        const SELF_TEST = 1 << 4;
+        /// Directives.
+        const DIRECTIVES = 1 << 5;
+        /// NVMe-MI Send and NVMe-MI Receive.
+        const NVME_MI = 1 << 6;
+        /// Virtualization Management.
+        const VIRTUALIZATION_MANAGEMENT = 1 << 7;
+        /// Doorbell Buffer Config.
+        const DOORBELL_BUFFER_CONFIG = 1 << 8;
+        /// Get LBA Status.
+        const GET_LBA_STATUS = 1 << 9;
+    }
+}
+
+bitflags! {
+    /// Sanitize operations supported by a controller.
+    #[repr(transparent)]
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct SanitizeCapabilities: u32 {
+        /// [`SanitizeAction::CryptoErase`].
+        const CRYPTO_ERASE = 1 << 0;
+        /// [`SanitizeAction::BlockErase`].
+        const BLOCK_ERASE = 1 << 1;
+        /// [`SanitizeAction::Overwrite`].
+        const OVERWRITE = 1 << 2;
+    }
+}
+
+bitflags! {
+    /// Critical warnings of the [`SmartLog`].
+    #[repr(transparent)]
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct CriticalWarning: u8 {
+        /// The available spare capacity is below the threshold.
+        const AVAILABLE_SPARE = 1 << 0;
+        /// The temperature is outside of the thresholds.
+        const TEMPERATURE = 1 << 1;
+        /// The reliability is degraded by media or internal errors.
+        const RELIABILITY = 1 << 2;
+        /// The media is read-only.
+        const READ_ONLY = 1 << 3;
+        /// The volatile memory backup device has failed.
+        const VOLATILE_MEMORY_BACKUP = 1 << 4;
+        /// The persistent memory region is read-only or unreliable.
+        const PERSISTENT_MEMORY_REGION = 1 << 5;
+    }
+}
+
+/// Identify Controller data structure.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IdentifyController {
+    /// PCI vendor ID.
+    pub vendor_id: u16,
+    /// PCI subsystem vendor ID.
+    pub subsystem_vendor_id: u16,
+    /// Serial number.
+    pub serial_number: String,
+    /// Model number.
+    pub model_number: String,
+    /// Revision of the active firmware.
+    pub firmware_revision: String,
+    /// Maximum data transfer size, as a power of two of the minimum memory
+    /// page size. Zero means no limit.
+    pub max_data_transfer_size: u8,
+    /// Controller ID.
+    pub controller_id: u16,
+    /// Supported NVMe version, with the major version in bits 16 to 31,
+    /// the minor version in bits 8 to 15 and the tertiary version in bits
+    /// 0 to 7. Zero for controllers implementing NVMe 1.1 or older.
+    pub version: u32,
+    /// Optional admin commands supported by the controller.
+    pub optional_admin_commands: OptionalAdminCommands,
+    /// Firmware updates field. Bit 0 is set if slot 1 is read-only, and bits
+    /// 1 to 3 are the number of firmware slots.
+    pub firmware_updates: u8,
+    /// Number of entries of the Error Information log page.
+    pub error_log_entries: u16,
+    /// Supported sanitize operations.
+    pub sanitize_capabilities: SanitizeCapabilities,
+    /// Total capacity of the NVM subsystem in bytes, if reported.
+    pub total_capacity: u128,
+    /// Unallocated capacity of the NVM subsystem in bytes, if reported.
+    pub unallocated_capacity: u128,
+    /// Maximum namespace ID.
+    pub namespace_count: u32,
+    /// Whether a volatile write cache is present.
+    pub volatile_write_cache: bool,
+    /// NVM subsystem NVMe Qualified Name.
+    pub subsystem_nqn: String,
+}
+
+impl IdentifyController {
+    /// Parses the Identify Controller data structure. Returns `None` if
+    /// `data` is shorter than 4096 bytes.
+    #[must_use]
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < IDENTIFY_SIZE {
+            return None;
+        }
+        Some(Self {
+            vendor_id: u16_at(data, 0),
+            subsystem_vendor_id: u16_at(data, 2),
+            serial_number: ascii_at(data, 4, 20),
+            model_number: ascii_at(data, 24, 40),
+            firmware_revision: ascii_at(data, 64, 8),
+            max_data_transfer_size: data[77],
+            controller_id: u16_at(data, 78),
+            version: u32_at(data, 80),
+            optional_admin_commands: OptionalAdminCommands::from_bits_retain(u16_at(data, 256)),
+            firmware_updates: data[260],
+            error_log_entries: u16::from(data[262]) + 1,
+            total_capacity: u128_at(data, 280),
+            unallocated_capacity: u128_at(data, 296),
+            sanitize_capabilities: SanitizeCapabilities::from_bits_retain(u32_at(data, 328)),
+            namespace_count: u32_at(data, 516),
+            volatile_write_cache: data[525] & 1 != 0,
+            subsystem_nqn: ascii_at(data, 768, 256),
+        })
+    }
+
+    /// Returns the number of firmware slots.
+    #[must_use]
+    pub const fn firmware_slots(&self) -> u8 {
+        (self.firmware_updates >> 1) & 0x7
+    }
+}
+
+/// Format of the logical blocks of a namespace.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct LbaFormat {
+    /// Number of metadata bytes per block.
+    pub metadata_size: u16,
+    /// Number of data bytes per block.
+    pub data_size: u32,
+    /// Relative performance, from 0 (best) to 3 (degraded).
+    pub relative_performance: u8,
+}
+
+/// Identify Namespace data structure.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IdentifyNamespace {
+    /// Total size of the namespace, in logical blocks.
+    pub size: u64,
+    /// Maximum number of logical blocks that may be allocated.
+    pub capacity: u64,
+    /// Number of logical blocks currently allocated.
+    pub utilization: u64,
+    /// Supported LBA formats.
+    pub lba_formats: Vec<LbaFormat>,
+    /// Index of the current format in [`lba_formats`].
+    ///
+    /// [`lba_formats`]: Self::lba_formats
+    pub current_lba_format: u8,
+    /// Size of the namespace in bytes, if reported.
+    pub nvm_capacity: u128,
+    /// Namespace globally unique identifier, or zero if not supported.
+    pub nguid: [u8; 16],
+    /// IEEE Extended Unique Identifier, or zero if not supported.
+    pub eui64: [u8; 8],
+}
+
+impl IdentifyNamespace {
+    /// Parses the Identify Namespace data structure. Returns `None` if
+    /// `data` is shorter than 4096 bytes.
+    #[must_use]
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < IDENTIFY_SIZE {
+            return None;
+        }
+        let count = usize::from(data[25]) + 1;
+        let lba_formats = (0..count)
+            .map(|i| {
+                let format = u32_at(data, 128 + i * 4);
+                LbaFormat {
+                    metadata_size: format as u16,
+                    data_size: 1 << ((format >> 16) & 0xff).min(31),
+                    relative_performance: (format >> 24) as u8 & 0x3,
+                }
+            })
+            .collect();
+        let flbas = data[26];
+        Some(Self {
+            size: u64_at(data, 0),
+            capacity: u64_at(data, 8),
+            utilization: u64_at(data, 16),
+            lba_formats,
+            current_lba_format: (flbas & 0xf) | ((flbas >> 5 & 0x3) << 4),
+            nvm_capacity: u128_at(data, 48),
+            nguid: bytes(data, 104),
+            eui64: bytes(data, 120),
+        })
+    }
+
+    /// Returns the current format of the logical blocks.
+    #[must_use]
+    pub fn lba_format(&self) -> Option<&LbaFormat> {
+        self.lba_formats.get(usize::from(self.current_lba_format))
+    }
+
+    /// Returns the size of the logical blocks in bytes.
+    #[must_use]
+    pub fn block_size(&self) -> Option<u32> {
+        self.lba_format().map(|format| format.data_size)
+    }
+}
+
+/// SMART / Health Information log page.
+///
+/// Data units are thousands of 512-byte units. Times are in minutes, except
+/// [`power_on_hours`].
+///
+/// [`power_on_hours`]: Self::power_on_hours
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SmartLog {
+    /// Critical warnings.
+    pub critical_warning: CriticalWarning,
+    /// Composite temperature, in Kelvin.
+    pub temperature: u16,
+    /// Remaining spare capacity, in percent.
+    pub available_spare: u8,
+    /// Threshold of [`available_spare`] below which a critical warning is
+    /// raised, in percent.
+    ///
+    /// [`available_spare`]: Self::available_spare
+    pub available_spare_threshold: u8,
+    /// Estimate of the used life of the device, in percent. May exceed 100.
+    pub percentage_used: u8,
+    /// Number of data units read by the host.
+    pub data_units_read: u128,
+    /// Number of data units written by the host.
+    pub data_units_written: u128,
+    /// Number of read commands completed.
+    pub host_read_commands: u128,
+    /// Number of write commands completed.
+    pub host_write_commands: u128,
+    /// Time the controller was busy with I/O commands.
+    pub controller_busy_time: u128,
+    /// Number of power cycles.
+    pub power_cycles: u128,
+    /// Number of power-on hours.
+    pub power_on_hours: u128,
+    /// Number of unsafe shutdowns.
+    pub unsafe_shutdowns: u128,
+    /// Number of unrecovered data integrity errors.
+    pub media_errors: u128,
+    /// Number of Error Information log entries over the life of the
+    /// controller.
+    pub error_log_entries: u128,
+    /// Time spent above the warning temperature threshold.
+    pub warning_temperature_time: u32,
+    /// Time spent above the critical temperature threshold.
+    pub critical_temperature_time: u32,
+    /// Temperatures of the sensors, in Kelvin, or zero if not implemented.
+    pub temperature_sensors: [u16; 8],
+}
+
+impl SmartLog {
+    /// Parses the log page. Returns `None` if `data` is shorter than 512
+    /// bytes.
+    #[must_use]
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < 512 {
+            return None;
+        }
+        Some(Self {
+            critical_warning: CriticalWarning::from_bits_retain(data[0]),
+            temperature: u16_at(data, 1),
+            available_spare: data[3],
+            available_spare_threshold: data[4],
+            percentage_used: data[5],
+            data_units_read: u128_at(data, 32),
+            data_units_written: u128_at(data, 48),
+            host_read_commands: u128_at(data, 64),
+            host_write_commands: u128_at(data, 80),
+            controller_busy_time: u128_at(data, 96),
+            power_cycles: u128_at(data, 112),
+            power_on_hours: u128_at(data, 128),
+            unsafe_shutdowns: u128_at(data, 144),
+            media_errors: u128_at(data, 160),
+            error_log_entries: u128_at(data, 176),
+            warning_temperature_time: u32_at(data, 192),
+            critical_temperature_time: u32_at(data, 196),
+            temperature_sensors: core::array::from_fn(|i| u16_at(data, 200 + i * 2)),
+        })
+    }
+}
+
+/// Entry of the Error Information log page.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ErrorLogEntry {
+    /// Unique, incrementing identifier of the error.
+    pub error_count: u64,
+    /// Submission queue of the failed command.
+    pub submission_queue_id: u16,
+    /// Identifier of the failed command.
+    pub command_id: u16,
+    /// Status field of the completion of the failed command.
+    pub status: u16,
+    /// Byte and bit of the command parameter that caused the error.
+    pub parameter_error_location: u16,
+    /// First LBA of the failed command.
+    pub lba: u64,
+    /// Namespace of the failed command.
+    pub namespace_id: u32,
+    /// Command specific information.
+    pub command_specific: u64,
+}
+
+impl ErrorLogEntry {
+    const SIZE: usize = 64;
+
+    /// Parses an entry. Returns `None` if `data` is shorter than 64
+    /// bytes.
+    #[must_use]
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < Self::SIZE {
+            return None;
+        }
+        Some(Self {
+            error_count: u64_at(data, 0),
+            submission_queue_id: u16_at(data, 8),
+            command_id: u16_at(data, 10),
+            // Bit 0 is the phase tag.
+            status: u16_at(data, 12) >> 1,
+            parameter_error_location: u16_at(data, 14),
+            lba: u64_at(data, 16),
+            namespace_id: u32_at(data, 24),
+            command_specific: u64_at(data, 32),
+        })
+    }
+}
+
+/// Firmware Slot Information log page.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FirmwareSlotLog {
+    /// Slot of the running firmware.
+    pub active_slot: u8,
+    /// Slot of the firmware activated at the next reset, if it differs from
+    /// the active slot.
+    pub next_slot: Option<u8>,
+    /// Revisions of the firmware in slots 1 to 7. `None` for empty or
+    /// unsupported slots.
+    pub revisions: [Option<String>; 7],
+}
+
+impl FirmwareSlotLog {
+    /// Parses the log page. Returns `None` if `data` is shorter than 64
+    /// bytes.
+    #[must_use]
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < 64 {
+            return None;
+        }
+        let next_slot = (data[0] >> 4) & 0x7;
+        Some(Self {
+            active_slot: data[0] & 0x7,
+            next_slot: (next_slot != 0).then_some(next_slot),
+            revisions: core::array::from_fn(|i| {
+                let revision = ascii_at(data, 8 + i * 8, 8);
+                (!revision.is_empty()).then_some(revision)
+            }),
+        })
+    }
+
+    /// Returns the firmware revision in `slot`, from 1 to 7.
+    #[must_use]
+    pub fn revision(&self, slot: u8) -> Option<&str> {
+        let index = usize::from(slot).checked_sub(1)?;
+        self.revisions.get(index)?.as_deref()
+    }
+}
+
+/// Sanitize Status log page.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SanitizeStatus {
+    /// Progress of the running sanitize operation, from 0 to 65535.
+    pub progress: u16,
+    /// Status of the most recent sanitize operation, in bits 0 to 2:
+    /// - 0: never sanitized
+    /// - 1: completed successfully
+    /// - 2: in progress
+    /// - 3: failed
+    pub status: u16,
+}
+
+impl SanitizeStatus {
+    /// Parses the log page. Returns `None` if `data` is shorter than 4
+    /// bytes.
+    #[must_use]
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < 4 {
+            return None;
+        }
+        Some(Self {
+            progress: u16_at(data, 0),
+            status: u16_at(data, 2),
+        })
+    }
+
+    /// Returns whether a sanitize operation is in progress.
+    #[must_use]
+    pub const fn in_progress(&self) -> bool {
+        self.status & 0x7 == 2
+    }
+}
+
+/// Secure erase setting of [`NvmeNamespace::format_nvm`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[repr(u8)]
+pub enum SecureErase {
+    /// No secure erase.
+    #[default]
+    None = 0,
+    /// Erase all user data.
+    UserData = 1,
+    /// Erase all user data by deleting the encryption key.
+    Cryptographic = 2,
+}
+
+/// Operation of [`NvmeNamespace::sanitize`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SanitizeAction {
+    /// Exits the failure mode of a failed sanitize operation.
+    ExitFailureMode,
+    /// Erases the media with a low-level block erase.
+    BlockErase,
+    /// Overwrites the media with `pattern`, `passes` times (1 to 16).
+    Overwrite {
+        /// Data pattern.
+        pattern: u32,
+        /// Number of passes.
+        passes: u8,
+        /// Whether the pattern is inverted between passes.
+        invert: bool,
+    },
+    /// Deletes the media encryption keys.
+    CryptoErase,
+}
+
+impl SanitizeAction {
+    /// Returns CDW10 and CDW11 of the command.
+    fn cdws(self) -> (u32, u32) {
+        match self {
+            Self::ExitFailureMode => (1, 0),
+            Self::BlockErase => (2, 0),
+            Self::Overwrite {
+                pattern,
+                passes,
+                invert,
+            } => {
+                let passes = u32::from(passes.clamp(1, 16)) & 0xf;
+                (3 | (passes << 4) | (u32::from(invert) << 8), pattern)
+            }
+            Self::CryptoErase => (4, 0),
+        }
+    }
+}
+
+/// Action of [`NvmeNamespace::firmware_commit`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u8)]
+pub enum FirmwareCommitAction {
+    /// Stores the downloaded image in the slot, without activating it.
+    Replace = 0,
+    /// Stores the downloaded image in the slot, and activates it at the next
+    /// reset.
+    ReplaceAndActivate = 1,
+    /// Activates the image already in the slot at the next reset.
+    Activate = 2,
+    /// Stores the downloaded image in the slot, and activates it
+    /// immediately.
+    ReplaceAndActivateNow = 3,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn test_identify_controller() {
+        let mut data = vec![0; IDENTIFY_SIZE];
+        data[0..2].copy_from_slice(&0x1b36u16.to_le_bytes());
+        data[4..24].copy_from_slice(b"uefi-rsNvmePassThru ");
+        data[24..64].fill(b' ');
+        data[24..34].copy_from_slice(b"QEMU NVMe ");
+        data[64..72].copy_from_slice(b"8.2.0   ");
+        data[80..84].copy_from_slice(&0x0001_0400u32.to_le_bytes());
+        data[256] = 0b1010;
+        data[260] = 0b0000_0111;
+        data[262] = 63;
+        data[328] = 0b101;
+        data[516..520].copy_from_slice(&256u32.to_le_bytes());
+        data[525] = 1;
+        data[768..775].copy_from_slice(b"nqn.foo");
+
+        let identify = IdentifyController::parse(&data).unwrap();
+        assert_eq!(identify.vendor_id, 0x1b36);
+        assert_eq!(identify.serial_number, "uefi-rsNvmePassThru");
+        assert_eq!(identify.model_number, "QEMU NVMe");
+        assert_eq!(identify.firmware_revision, "8.2.0");
+        assert_eq!(identify.version, 0x0001_0400);
+        assert_eq!(
+            identify.optional_admin_commands,
+            OptionalAdminCommands::FORMAT_NVM | OptionalAdminCommands::NAMESPACE_MANAGEMENT
+        );
+        assert_eq!(identify.firmware_slots(), 3);
+        assert_eq!(identify.error_log_entries, 64);
+        assert_eq!(
+            identify.sanitize_capabilities,
+            SanitizeCapabilities::CRYPTO_ERASE | SanitizeCapabilities::OVERWRITE
+        );
+        assert_eq!(identify.namespace_count, 256);
+        assert!(identify.volatile_write_cache);
+        assert_eq!(identify.subsystem_nqn, "nqn.foo");
+
+        assert_eq!(IdentifyController::parse(&data[..4095]), None);
+    }
+
+    #[test]
+    fn test_identify_namespace() {
+        let mut data = vec![0; IDENTIFY_SIZE];
+        data[0..8].copy_from_slice(&20480u64.to_le_bytes());
+        data[8..16].copy_from_slice(&20480u64.to_le_bytes());
+        data[25] = 1;
+        data[26] = 1;
+        // 512 bytes, no metadata; 4096 bytes with 8 bytes of metadata.
+        data[128..132].copy_from_slice(&0x0009_0000u32.to_le_bytes());
+        data[132..136].copy_from_slice(&0x020c_0008u32.to_le_bytes());
+        data[120..128].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+
+        let identify = IdentifyNamespace::parse(&data).unwrap();
+        assert_eq!(identify.size, 20480);
+        assert_eq!(
+            identify.lba_formats,
+            [
+                LbaFormat {
+                    metadata_size: 0,
+                    data_size: 512,
+                    relative_performance: 0,
+                },
+                LbaFormat {
+                    metadata_size: 8,
+                    data_size: 4096,
+                    relative_performance: 2,
+                }
+            ]
+        );
+        assert_eq!(identify.current_lba_format, 1);
+        assert_eq!(identify.block_size(), Some(4096));
+        assert_eq!(identify.eui64, [1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn test_log_pages() {
+        let mut data = [0; 512];
+        data[0] = 0b1001;
+        data[1..3].copy_from_slice(&310u16.to_le_bytes());
+        data[3] = 100;
+        data[4] = 10;
+        data[5] = 3;
+        data[128] = 42;
+        data[202..204].copy_from_slice(&305u16.to_le_bytes());
+        let smart = SmartLog::parse(&data).unwrap();
+        assert_eq!(
+            smart.critical_warning,
+            CriticalWarning::AVAILABLE_SPARE | CriticalWarning::READ_ONLY
+        );
+        assert_eq!(smart.temperature, 310);
+        assert_eq!(smart.available_spare, 100);
+        assert_eq!(smart.percentage_used, 3);
+        assert_eq!(smart.power_on_hours, 42);
+        assert_eq!(smart.temperature_sensors, [0, 305, 0, 0, 0, 0, 0, 0]);
+
+        let mut data = [0; 512];
+        data[0] = 0x21;
+        data[8..13].copy_from_slice(b"1.0  ");
+        data[16..19].copy_from_slice(b"2.0");
+        let slots = FirmwareSlotLog::parse(&data).unwrap();
+        assert_eq!(slots.active_slot, 1);
+        assert_eq!(slots.next_slot, Some(2));
+        assert_eq!(slots.revision(1), Some("1.0"));
+        assert_eq!(slots.revision(2), Some("2.0"));
+        assert_eq!(slots.revision(3), None);
+        assert_eq!(slots.revision(0), None);
+        assert_eq!(slots.revision(8), None);
+
+        let mut data = [0; 64];
+        data[0] = 7;
+        data[12..14].copy_from_slice(&((0x4002u16 << 1) | 1).to_le_bytes());
+        data[24] = 1;
+        let entry = ErrorLogEntry::parse(&data).unwrap();
+        assert_eq!(entry.error_count, 7);
+        assert_eq!(entry.status, 0x4002);
+        assert_eq!(entry.namespace_id, 1);
+
+        assert_eq!(SmartLog::parse(&data), None);
+        assert_eq!(ErrorLogEntry::parse(&data[..63]), None);
+        assert_eq!(FirmwareSlotLog::parse(&data[..63]), None);
+        assert_eq!(SanitizeStatus::parse(&data[..3]), None);
+    }
+
+    #[test]
+    fn test_sanitize_action() {
+        assert_eq!(SanitizeAction::BlockErase.cdws(), (2, 0));
+        assert_eq!(
+            SanitizeAction::Overwrite {
+                pattern: 0xdead_beef,
+                passes: 16,
+                invert: true
+            }
+            .cdws(),
+            (0x103, 0xdead_beef)
+        );
+        assert_eq!(
+            SanitizeAction::Overwrite {
+                pattern: 0,
+                passes: 2,
+                invert: false
+            }
+            .cdws(),
+            (0x23, 0)
+        );
+    }
+}
@@ -125,6 +125,11 @@ impl NvmeNamespace<'_> {
         ptr::from_ref(self.proto).cast_mut()
     }
 
+    /// Returns the alignment requirement of the controller for I/O buffers.
+    pub(super) fn io_align(&self) -> u32 {
+        unsafe { (*self.proto.mode).io_align.max(1) }
+    }
+
     /// Retrieves the namespace identifier (NSID) associated with this NVMe namespace.
     #[must_use]
     pub const fn namespace_id(&self) -> NvmeNamespaceId {
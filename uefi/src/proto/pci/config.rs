@@ -0,0 +1,1457 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Typed access to the configuration space of PCI functions.
+//!
+//! The [`ConfigSpace`] trait is implemented by the accessors of the
+//! configuration space of a function: [`FunctionConfig`], through the root
+//! bridge of the function, and [`PciIo`], by the driver of the function. It
+//! decodes the header of the function, sizes its BARs, and walks its
+//! capability lists.
+//!
+//! # Example
+//!
+//! ```
+//! use uefi::proto::pci::PciIoAddress;
+//! use uefi::proto::pci::config::ConfigSpace;
+//! use uefi::proto::pci::root_bridge::PciRootBridgeIo;
+//!
+//! fn print_function(bridge: &mut PciRootBridgeIo, address: PciIoAddress) -> uefi::Result {
+//!     let mut config = bridge.function(address);
+//!     let header = config.header()?;
+//!     log::info!(
+//!         "{:04x}:{:04x} {}",
+//!         header.common().vendor_id,
+//!         header.common().device_id,
+//!         header.common().class.name(),
+//!     );
+//!     for capability in config.capabilities() {
+//!         log::info!("capability {:#04x} at {:#04x}", capability.id, capability.offset);
+//!     }
+//!     Ok(())
+//! }
+//! ```
+//!
+//! [`FunctionConfig`]: super::root_bridge::FunctionConfig
+//! [`PciIo`]: super::io::PciIo
+
+use crate::{Result, Status};
+
+/// Offset of the command register.
+const COMMAND: u16 = 0x04;
+/// Offset of the status register.
+const STATUS: u16 = 0x06;
+/// Offset of the first BAR.
+const BAR0: u16 = 0x10;
+/// Offset of the capabilities pointer of the type 0 and 1 headers.
+const CAPABILITIES_POINTER: u16 = 0x34;
+/// Offset of the first extended capability.
+const EXTENDED_CAPABILITIES: u16 = 0x100;
+/// Size of the configuration space of PCI Express functions.
+const EXTENDED_CONFIG_SPACE_SIZE: u16 = 0x1000;
+
+/// I/O space and memory space enable bits of the command register.
+const COMMAND_DECODE: u16 = 0b11;
+/// Capabilities list bit of the status register.
+const STATUS_CAPABILITIES_LIST: u16 = 1 << 4;
+
+/// Size of the common part of the headers.
+pub const HEADER_SIZE: usize = 0x40;
+
+/// Access to the configuration space of a PCI function.
+///
+/// Implementors only provide the 32-bit accesses, the other methods are
+/// built on top of them.
+pub trait ConfigSpace {
+    /// Reads the 32-bit register at the byte offset `offset`, which is 4-byte
+    /// aligned.
+    fn read_u32(&mut self, offset: u16) -> Result<u32>;
+
+    /// Writes `value` to the 32-bit register at the byte offset `offset`,
+    /// which is 4-byte aligned.
+    fn write_u32(&mut self, offset: u16, value: u32) -> Result;
+
+    /// Reads the 16-bit register at the byte offset `offset`, which is 2-byte
+    /// aligned.
+    fn read_u16(&mut self, offset: u16) -> Result<u16> {
+        let dword = self.read_u32(offset & !0b11)?;
+        Ok((dword >> ((offset & 0b10) * 8)) as u16)
+    }
+
+    /// Reads the 8-bit register at the byte offset `offset`.
+    fn read_u8(&mut self, offset: u16) -> Result<u8> {
+        let dword = self.read_u32(offset & !0b11)?;
+        Ok((dword >> ((offset & 0b11) * 8)) as u8)
+    }
+
+    /// Fills `buffer` with the registers starting at the byte offset
+    /// `offset`, which is 4-byte aligned.
+    fn read_bytes(&mut self, offset: u16, buffer: &mut [u8]) -> Result {
+        for (i, chunk) in buffer.chunks_mut(4).enumerate() {
+            let dword = self.read_u32(offset + 4 * i as u16)?;
+            chunk.copy_from_slice(&dword.to_le_bytes()[..chunk.len()]);
+        }
+        Ok(())
+    }
+
+    /// Reads and decodes the header of the function.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: there is no function at this address.
+    /// * Errors of [`read_u32`].
+    ///
+    /// [`read_u32`]: Self::read_u32
+    fn header(&mut self) -> Result<ConfigHeader> {
+        let mut bytes = [0; HEADER_SIZE];
+        self.read_bytes(0, &mut bytes)?;
+        ConfigHeader::parse(&bytes).ok_or_else(|| Status::NOT_FOUND.into())
+    }
+
+    /// Decodes and sizes the BARs of the function.
+    ///
+    /// Functions with a type 0 header have six BARs, bridges two. The entry
+    /// of the upper half of a 64-bit BAR, and of unimplemented BARs, is
+    /// `None`.
+    ///
+    /// Sizing a BAR writes to it, so the I/O and memory decoding of the
+    /// function are disabled while the BARs are sized, and restored after.
+    /// This must not race with other accesses to the function.
+    fn bars(&mut self) -> Result<[Option<Bar>; 6]> {
+        let mut bars = [None; 6];
+        let count = match self.header()? {
+            ConfigHeader::Type0(_) => 6,
+            ConfigHeader::Type1(_) => 2,
+            ConfigHeader::Other(_) => return Ok(bars),
+        };
+
+        // The status register bits are cleared by writing ones, so only the
+        // command register is written back.
+        let command = self.read_u32(COMMAND)? & 0xffff;
+        self.write_u32(COMMAND, command & !u32::from(COMMAND_DECODE))?;
+        let result = size_bars(self, &mut bars[..count]);
+        // Restore the decoding even if sizing failed.
+        self.write_u32(COMMAND, command)?;
+        result.map(|()| bars)
+    }
+
+    /// Returns an iterator over the capabilities of the function.
+    ///
+    /// The iterator stops at the first read error.
+    fn capabilities(&mut self) -> Capabilities<'_, Self>
+    where
+        Self: Sized,
+    {
+        let has_list = self
+            .read_u16(STATUS)
+            .is_ok_and(|status| status & STATUS_CAPABILITIES_LIST != 0);
+        let next = if has_list {
+            self.read_u8(CAPABILITIES_POINTER).unwrap_or(0)
+        } else {
+            0
+        };
+        Capabilities {
+            config: self,
+            next,
+            // Each capability is at least 4 bytes.
+            remaining: (0x100 - HEADER_SIZE) / 4,
+        }
+    }
+
+    /// Returns an iterator over the PCI Express extended capabilities of the
+    /// function.
+    ///
+    /// Functions which are not PCI Express functions have none. The iterator
+    /// stops at the first read error.
+    fn extended_capabilities(&mut self) -> ExtendedCapabilities<'_, Self>
+    where
+        Self: Sized,
+    {
+        ExtendedCapabilities {
+            config: self,
+            next: EXTENDED_CAPABILITIES,
+            remaining: usize::from(EXTENDED_CONFIG_SPACE_SIZE - EXTENDED_CAPABILITIES) / 4,
+        }
+    }
+
+    /// Returns the offset of the first capability with the ID `id`, see
+    /// [`capability_id`].
+    fn find_capability(&mut self, id: u8) -> Option<u8>
+    where
+        Self: Sized,
+    {
+        self.capabilities()
+            .find(|capability| capability.id == id)
+            .map(|capability| capability.offset)
+    }
+
+    /// Returns the offset of the first extended capability with the ID `id`,
+    /// see [`extended_capability_id`].
+    fn find_extended_capability(&mut self, id: u16) -> Option<u16>
+    where
+        Self: Sized,
+    {
+        self.extended_capabilities()
+            .find(|capability| capability.id == id)
+            .map(|capability| capability.offset)
+    }
+
+    /// Reads the MSI capability of the function, if it has one.
+    fn msi(&mut self) -> Result<Option<MsiCapability>>
+    where
+        Self: Sized,
+    {
+        read_capability(self, capability_id::MSI, MsiCapability::parse)
+    }
+
+    /// Reads the MSI-X capability of the function, if it has one.
+    fn msix(&mut self) -> Result<Option<MsixCapability>>
+    where
+        Self: Sized,
+    {
+        read_capability(self, capability_id::MSIX, MsixCapability::parse)
+    }
+
+    /// Reads the PCI Express capability of the function, if it has one.
+    fn pci_express(&mut self) -> Result<Option<PciExpressCapability>>
+    where
+        Self: Sized,
+    {
+        read_capability(
+            self,
+            capability_id::PCI_EXPRESS,
+            PciExpressCapability::parse,
+        )
+    }
+
+    /// Reads the SR-IOV extended capability of the function, if it has one.
+    fn sriov(&mut self) -> Result<Option<SrIovCapability>>
+    where
+        Self: Sized,
+    {
+        let Some(offset) = self.find_extended_capability(extended_capability_id::SRIOV) else {
+            return Ok(None);
+        };
+        let mut bytes = [0; SrIovCapability::SIZE];
+        self.read_bytes(offset, &mut bytes)?;
+        Ok(SrIovCapability::parse(&bytes))
+    }
+}
+
+/// Sizes the BARs `bars`, with the decoding of the function disabled.
+fn size_bars<C: ConfigSpace + ?Sized>(config: &mut C, bars: &mut [Option<Bar>]) -> Result {
+    let mut index = 0;
+    while index < bars.len() {
+        let offset = BAR0 + 4 * index as u16;
+        let low = size_register(config, offset)?;
+
+        let is_64bit_memory = low.0 & 0b111 == 0b100;
+        let high = if is_64bit_memory && index + 1 < bars.len() {
+            Some(size_register(config, offset + 4)?)
+        } else {
+            None
+        };
+
+        bars[index] = Bar::decode(low, high);
+        index += if high.is_some() { 2 } else { 1 };
+    }
+    Ok(())
+}
+
+/// Returns the value of the BAR register at `offset`, and the value read
+/// back after writing all ones to it. The register is restored.
+fn size_register<C: ConfigSpace + ?Sized>(config: &mut C, offset: u16) -> Result<(u32, u32)> {
+    let value = config.read_u32(offset)?;
+    config.write_u32(offset, u32::MAX)?;
+    let mask = config.read_u32(offset);
+    config.write_u32(offset, value)?;
+    Ok((value, mask?))
+}
+
+/// Finds the capability `id` and parses it with `parse`.
+fn read_capability<C: ConfigSpace, T, const N: usize>(
+    config: &mut C,
+    id: u8,
+    parse: fn(&[u8; N]) -> Option<T>,
+) -> Result<Option<T>> {
+    let Some(offset) = config.find_capability(id) else {
+        return Ok(None);
+    };
+    let mut bytes = [0; N];
+    config.read_bytes(u16::from(offset), &mut bytes)?;
+    Ok(parse(&bytes))
+}
+
+const fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+const fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+/// Class code of a PCI function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ClassCode {
+    /// Base class.
+    pub class: u8,
+    /// Subclass, within the base class.
+    pub subclass: u8,
+    /// Programming interface, within the subclass.
+    pub prog_if: u8,
+}
+
+impl ClassCode {
+    /// Returns the name of the base class.
+    #[must_use]
+    pub const fn class_name(&self) -> &'static str {
+        match self.class {
+            0x00 => "Unclassified device",
+            0x01 => "Mass storage controller",
+            0x02 => "Network controller",
+            0x03 => "Display controller",
+            0x04 => "Multimedia controller",
+            0x05 => "Memory controller",
+            0x06 => "Bridge",
+            0x07 => "Communication controller",
+            0x08 => "Generic system peripheral",
+            0x09 => "Input device controller",
+            0x0a => "Docking station",
+            0x0b => "Processor",
+            0x0c => "Serial bus controller",
+            0x0d => "Wireless controller",
+            0x0e => "Intelligent controller",
+            0x0f => "Satellite communications controller",
+            0x10 => "Encryption controller",
+            0x11 => "Signal processing controller",
+            0x12 => "Processing accelerator",
+            0x13 => "Non-essential instrumentation",
+            0x40 => "Coprocessor",
+            _ => "Unassigned class",
+        }
+    }
+
+    /// Returns the most specific name of the class code: the name of the
+    /// programming interface or subclass if known, else the name of the base
+    /// class.
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        match (self.class, self.subclass, self.prog_if) {
+            (0x01, 0x00, _) => "SCSI storage controller",
+            (0x01, 0x01, _) => "IDE interface",
+            (0x01, 0x02, _) => "Floppy disk controller",
+            (0x01, 0x04, _) => "RAID bus controller",
+            (0x01, 0x05, _) => "ATA controller",
+            (0x01, 0x06, 0x01) => "SATA controller (AHCI)",
+            (0x01, 0x06, _) => "SATA controller",
+            (0x01, 0x07, _) => "Serial Attached SCSI controller",
+            (0x01, 0x08, 0x02) => "Non-Volatile memory controller (NVMe)",
+            (0x01, 0x08, _) => "Non-Volatile memory controller",
+            (0x01, 0x09, _) => "Universal Flash Storage controller",
+            (0x02, 0x00, _) => "Ethernet controller",
+            (0x02, 0x07, _) => "InfiniBand controller",
+            (0x02, 0x80, _) => "Network controller",
+            (0x03, 0x00, _) => "VGA compatible controller",
+            (0x03, 0x01, _) => "XGA compatible controller",
+            (0x03, 0x02, _) => "3D controller",
+            (0x04, 0x00, _) => "Multimedia video controller",
+            (0x04, 0x01, _) => "Multimedia audio controller",
+            (0x04, 0x03, _) => "Audio device",
+            (0x05, 0x00, _) => "RAM memory",
+            (0x05, 0x01, _) => "FLASH memory",
+            (0x06, 0x00, _) => "Host bridge",
+            (0x06, 0x01, _) => "ISA bridge",
+            (0x06, 0x02, _) => "EISA bridge",
+            (0x06, 0x04, _) => "PCI bridge",
+            (0x06, 0x05, _) => "PCMCIA bridge",
+            (0x06, 0x07, _) => "CardBus bridge",
+            (0x06, 0x09, _) => "Semi-transparent PCI-to-PCI bridge",
+            (0x07, 0x00, _) => "Serial controller",
+            (0x07, 0x01, _) => "Parallel controller",
+            (0x07, 0x03, _) => "Modem",
+            (0x08, 0x00, _) => "PIC",
+            (0x08, 0x01, _) => "DMA controller",
+            (0x08, 0x02, _) => "Timer",
+            (0x08, 0x03, _) => "RTC",
+            (0x08, 0x05, _) => "SD Host controller",
+            (0x08, 0x06, _) => "IOMMU",
+            (0x09, 0x00, _) => "Keyboard controller",
+            (0x09, 0x02, _) => "Mouse controller",
+            (0x0c, 0x00, _) => "FireWire (IEEE 1394)",
+            (0x0c, 0x03, 0x00) => "USB controller (UHCI)",
+            (0x0c, 0x03, 0x10) => "USB controller (OHCI)",
+            (0x0c, 0x03, 0x20) => "USB controller (EHCI)",
+            (0x0c, 0x03, 0x30) => "USB controller (xHCI)",
+            (0x0c, 0x03, 0x40) => "USB controller (USB4)",
+            (0x0c, 0x03, _) => "USB controller",
+            (0x0c, 0x04, _) => "Fibre Channel",
+            (0x0c, 0x05, _) => "SMBus",
+            (0x0c, 0x07, _) => "IPMI interface",
+            (0x0d, 0x11, _) => "Bluetooth",
+            (0x0d, 0x20, _) => "802.1a controller",
+            (0x0d, 0x21, _) => "802.1b controller",
+            _ => self.class_name(),
+        }
+    }
+}
+
+/// Layout of the header of a PCI function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HeaderType {
+    /// Type 0 header, of functions which are not bridges.
+    Endpoint,
+    /// Type 1 header, of PCI-to-PCI bridges.
+    PciBridge,
+    /// Type 2 header, of CardBus bridges.
+    CardBusBridge,
+    /// Unknown header layout.
+    Unknown(u8),
+}
+
+impl From<u8> for HeaderType {
+    fn from(value: u8) -> Self {
+        match value & 0x7f {
+            0 => Self::Endpoint,
+            1 => Self::PciBridge,
+            2 => Self::CardBusBridge,
+            other => Self::Unknown(other),
+        }
+    }
+}
+
+/// Part of the header common to all header types.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CommonHeader {
+    /// Vendor ID.
+    pub vendor_id: u16,
+    /// Device ID.
+    pub device_id: u16,
+    /// Command register.
+    pub command: u16,
+    /// Status register.
+    pub status: u16,
+    /// Revision ID.
+    pub revision_id: u8,
+    /// Class code.
+    pub class: ClassCode,
+    /// Cache line size, in units of 4 bytes.
+    pub cache_line_size: u8,
+    /// Latency timer.
+    pub latency_timer: u8,
+    /// Layout of the rest of the header.
+    pub header_type: HeaderType,
+    /// Whether the device has several functions. Only meaningful for
+    /// function 0.
+    pub multi_function: bool,
+    /// Built-in self test register.
+    pub bist: u8,
+}
+
+/// Header of functions which are not bridges.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Type0Header {
+    /// Common part of the header.
+    pub common: CommonHeader,
+    /// Raw values of the BARs, see [`ConfigSpace::bars`] to decode them.
+    pub bars: [u32; 6],
+    /// CardBus CIS pointer.
+    pub cardbus_cis_pointer: u32,
+    /// Subsystem vendor ID.
+    pub subsystem_vendor_id: u16,
+    /// Subsystem ID.
+    pub subsystem_id: u16,
+    /// Expansion ROM base address register.
+    pub expansion_rom_base: u32,
+    /// Offset of the first capability, if the capabilities list bit of the
+    /// status register is set.
+    pub capabilities_pointer: u8,
+    /// Interrupt line.
+    pub interrupt_line: u8,
+    /// Interrupt pin, 1 to 4 for INTA# to INTD#, or 0 if none.
+    pub interrupt_pin: u8,
+    /// Minimum burst period.
+    pub min_grant: u8,
+    /// Maximum access latency.
+    pub max_latency: u8,
+}
+
+/// Header of PCI-to-PCI bridges.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Type1Header {
+    /// Common part of the header.
+    pub common: CommonHeader,
+    /// Raw values of the BARs, see [`ConfigSpace::bars`] to decode them.
+    pub bars: [u32; 2],
+    /// Number of the bus on the primary side of the bridge.
+    pub primary_bus: u8,
+    /// Number of the bus on the secondary side of the bridge.
+    pub secondary_bus: u8,
+    /// Highest number of the buses behind the bridge.
+    pub subordinate_bus: u8,
+    /// Latency timer of the secondary bus.
+    pub secondary_latency_timer: u8,
+    /// I/O base register.
+    pub io_base: u8,
+    /// I/O limit register.
+    pub io_limit: u8,
+    /// Status register of the secondary bus.
+    pub secondary_status: u16,
+    /// Memory base register.
+    pub memory_base: u16,
+    /// Memory limit register.
+    pub memory_limit: u16,
+    /// Prefetchable memory base register.
+    pub prefetchable_memory_base: u16,
+    /// Prefetchable memory limit register.
+    pub prefetchable_memory_limit: u16,
+    /// Upper 32 bits of the prefetchable memory base.
+    pub prefetchable_base_upper: u32,
+    /// Upper 32 bits of the prefetchable memory limit.
+    pub prefetchable_limit_upper: u32,
+    /// Upper 16 bits of the I/O base.
+    pub io_base_upper: u16,
+    /// Upper 16 bits of the I/O limit.
+    pub io_limit_upper: u16,
+    /// Offset of the first capability, if the capabilities list bit of the
+    /// status register is set.
+    pub capabilities_pointer: u8,
+    /// Expansion ROM base address register.
+    pub expansion_rom_base: u32,
+    /// Interrupt line.
+    pub interrupt_line: u8,
+    /// Interrupt pin, 1 to 4 for INTA# to INTD#, or 0 if none.
+    pub interrupt_pin: u8,
+    /// Bridge control register.
+    pub bridge_control: u16,
+}
+
+/// Decoded header of a PCI function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigHeader {
+    /// Header of a function which is not a bridge.
+    Type0(Type0Header),
+    /// Header of a PCI-to-PCI bridge.
+    Type1(Type1Header),
+    /// Header of another type, of which only the common part is decoded.
+    Other(CommonHeader),
+}
+
+impl ConfigHeader {
+    /// Parses the first [`HEADER_SIZE`] bytes of the configuration space of a
+    /// function.
+    ///
+    /// Returns `None` if `bytes` is too short, or if the vendor ID is not
+    /// valid, meaning that there is no function.
+    #[must_use]
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let bytes = bytes.get(..HEADER_SIZE)?;
+        let vendor_id = u16_at(bytes, 0x00);
+        if vendor_id == 0xffff || vendor_id == 0 {
+            return None;
+        }
+
+        let common = CommonHeader {
+            vendor_id,
+            device_id: u16_at(bytes, 0x02),
+            command: u16_at(bytes, 0x04),
+            status: u16_at(bytes, 0x06),
+            revision_id: bytes[0x08],
+            class: ClassCode {
+                prog_if: bytes[0x09],
+                subclass: bytes[0x0a],
+                class: bytes[0x0b],
+            },
+            cache_line_size: bytes[0x0c],
+            latency_timer: bytes[0x0d],
+            header_type: HeaderType::from(bytes[0x0e]),
+            multi_function: bytes[0x0e] & 0x80 != 0,
+            bist: bytes[0x0f],
+        };
+        let bar = |index: usize| u32_at(bytes, usize::from(BAR0) + 4 * index);
+
+        Some(match common.header_type {
+            HeaderType::Endpoint => Self::Type0(Type0Header {
+                common,
+                bars: [bar(0), bar(1), bar(2), bar(3), bar(4), bar(5)],
+                cardbus_cis_pointer: u32_at(bytes, 0x28),
+                subsystem_vendor_id: u16_at(bytes, 0x2c),
+                subsystem_id: u16_at(bytes, 0x2e),
+                expansion_rom_base: u32_at(bytes, 0x30),
+                capabilities_pointer: bytes[0x34],
+                interrupt_line: bytes[0x3c],
+                interrupt_pin: bytes[0x3d],
+                min_grant: bytes[0x3e],
+                max_latency: bytes[0x3f],
+            }),
+            HeaderType::PciBridge => Self::Type1(Type1Header {
+                common,
+                bars: [bar(0), bar(1)],
+                primary_bus: bytes[0x18],
+                secondary_bus: bytes[0x19],
+                subordinate_bus: bytes[0x1a],
+                secondary_latency_timer: bytes[0x1b],
+                io_base: bytes[0x1c],
+                io_limit: bytes[0x1d],
+                secondary_status: u16_at(bytes, 0x1e),
+                memory_base: u16_at(bytes, 0x20),
+                memory_limit: u16_at(bytes, 0x22),
+                prefetchable_memory_base: u16_at(bytes, 0x24),
+                prefetchable_memory_limit: u16_at(bytes, 0x26),
+                prefetchable_base_upper: u32_at(bytes, 0x28),
+                prefetchable_limit_upper: u32_at(bytes, 0x2c),
+                io_base_upper: u16_at(bytes, 0x30),
+                io_limit_upper: u16_at(bytes, 0x32),
+                capabilities_pointer: bytes[0x34],
+                expansion_rom_base: u32_at(bytes, 0x38),
+                interrupt_line: bytes[0x3c],
+                interrupt_pin: bytes[0x3d],
+                bridge_control: u16_at(bytes, 0x3e),
+            }),
+            _ => Self::Other(common),
+        })
+    }
+
+    /// Returns the part of the header common to all header types.
+    #[must_use]
+    pub const fn common(&self) -> &CommonHeader {
+        match self {
+            Self::Type0(header) => &header.common,
+            Self::Type1(header) => &header.common,
+            Self::Other(common) => common,
+        }
+    }
+}
+
+/// Decoded base address register.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Bar {
+    /// Memory space BAR.
+    Memory {
+        /// Base address.
+        address: u64,
+        /// Size of the range, in bytes.
+        size: u64,
+        /// Whether the range is prefetchable.
+        prefetchable: bool,
+        /// Whether the BAR is a 64-bit BAR, which spans two registers.
+        is_64bit: bool,
+    },
+    /// I/O space BAR.
+    Io {
+        /// Base address.
+        address: u32,
+        /// Size of the range, in bytes.
+        size: u32,
+    },
+}
+
+impl Bar {
+    /// Decodes a BAR from its value and the value read back after writing all
+    /// ones to it, and the same for the next register for 64-bit BARs.
+    ///
+    /// Returns `None` if the BAR is not implemented.
+    #[must_use]
+    pub fn decode(low: (u32, u32), high: Option<(u32, u32)>) -> Option<Self> {
+        let (value, mask) = low;
+        if mask == 0 {
+            return None;
+        }
+
+        if value & 1 != 0 {
+            let mut mask = mask & !0b11;
+            // Functions may only decode 16 bits of I/O addresses, and return
+            // zeros in the upper half.
+            if mask & 0xffff_0000 == 0 {
+                mask |= 0xffff_0000;
+            }
+            let size = (!mask).wrapping_add(1);
+            return (size != 0).then_some(Self::Io {
+                address: value & !0b11,
+                size,
+            });
+        }
+
+        let (high_value, high_mask) = high.unwrap_or((0, u32::MAX));
+        let mask = (u64::from(high_mask) << 32) | u64::from(mask & !0b1111);
+        let size = (!mask).wrapping_add(1);
+        (mask != 0 && size != 0).then_some(Self::Memory {
+            address: (u64::from(high_value) << 32) | u64::from(value & !0b1111),
+            size,
+            prefetchable: value & 0b1000 != 0,
+            is_64bit: high.is_some(),
+        })
+    }
+
+    /// Returns the size of the range, in bytes.
+    #[must_use]
+    pub const fn size(&self) -> u64 {
+        match *self {
+            Self::Memory { size, .. } => size,
+            Self::Io { size, .. } => size as u64,
+        }
+    }
+}
+
+/// IDs of the capabilities, see [`ConfigSpace::capabilities`].
+pub mod capability_id {
+    /// PCI power management.
+    pub const POWER_MANAGEMENT: u8 = 0x01;
+    /// Accelerated Graphics Port.
+    pub const AGP: u8 = 0x02;
+    /// Vital product data.
+    pub const VPD: u8 = 0x03;
+    /// Slot identification.
+    pub const SLOT_ID: u8 = 0x04;
+    /// Message signaled interrupts, see [`MsiCapability`].
+    ///
+    /// [`MsiCapability`]: super::MsiCapability
+    pub const MSI: u8 = 0x05;
+    /// PCI-X.
+    pub const PCI_X: u8 = 0x07;
+    /// HyperTransport.
+    pub const HYPERTRANSPORT: u8 = 0x08;
+    /// Vendor specific.
+    pub const VENDOR_SPECIFIC: u8 = 0x09;
+    /// Debug port.
+    pub const DEBUG_PORT: u8 = 0x0a;
+    /// PCI hot-plug.
+    pub const HOT_PLUG: u8 = 0x0c;
+    /// Subsystem vendor ID of bridges.
+    pub const BRIDGE_SUBSYSTEM_VENDOR_ID: u8 = 0x0d;
+    /// PCI Express, see [`PciExpressCapability`].
+    ///
+    /// [`PciExpressCapability`]: super::PciExpressCapability
+    pub const PCI_EXPRESS: u8 = 0x10;
+    /// MSI-X, see [`MsixCapability`].
+    ///
+    /// [`MsixCapability`]: super::MsixCapability
+    pub const MSIX: u8 = 0x11;
+    /// Serial ATA data/index configuration.
+    pub const SATA: u8 = 0x12;
+    /// Advanced features.
+    pub const ADVANCED_FEATURES: u8 = 0x13;
+    /// Enhanced allocation.
+    pub const ENHANCED_ALLOCATION: u8 = 0x14;
+}
+
+/// IDs of the PCI Express extended capabilities, see
+/// [`ConfigSpace::extended_capabilities`].
+pub mod extended_capability_id {
+    /// Advanced error reporting.
+    pub const ADVANCED_ERROR_REPORTING: u16 = 0x0001;
+    /// Virtual channel.
+    pub const VIRTUAL_CHANNEL: u16 = 0x0002;
+    /// Device serial number.
+    pub const DEVICE_SERIAL_NUMBER: u16 = 0x0003;
+    /// Power budgeting.
+    pub const POWER_BUDGETING: u16 = 0x0004;
+    /// Vendor specific.
+    pub const VENDOR_SPECIFIC: u16 = 0x000b;
+    /// Access control services.
+    pub const ACCESS_CONTROL_SERVICES: u16 = 0x000d;
+    /// Alternative routing-ID interpretation.
+    pub const ARI: u16 = 0x000e;
+    /// Address translation services.
+    pub const ATS: u16 = 0x000f;
+    /// Single root I/O virtualization, see [`SrIovCapability`].
+    ///
+    /// [`SrIovCapability`]: super::SrIovCapability
+    pub const SRIOV: u16 = 0x0010;
+    /// Resizable BAR.
+    pub const RESIZABLE_BAR: u16 = 0x0015;
+    /// Latency tolerance reporting.
+    pub const LTR: u16 = 0x0018;
+    /// Secondary PCI Express.
+    pub const SECONDARY_PCI_EXPRESS: u16 = 0x0019;
+    /// Process address space ID.
+    pub const PASID: u16 = 0x001b;
+    /// L1 PM substates.
+    pub const L1_PM_SUBSTATES: u16 = 0x001e;
+    /// Data link feature.
+    pub const DATA_LINK_FEATURE: u16 = 0x0025;
+    /// Physical layer 16.0 GT/s.
+    pub const PHYSICAL_LAYER_16: u16 = 0x0026;
+}
+
+/// Capability found by [`ConfigSpace::capabilities`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Capability {
+    /// ID of the capability, see [`capability_id`].
+    pub id: u8,
+    /// Offset of the capability in the configuration space.
+    pub offset: u8,
+}
+
+/// Iterator over the capabilities of a function, returned by
+/// [`ConfigSpace::capabilities`].
+#[derive(Debug)]
+pub struct Capabilities<'a, C> {
+    config: &'a mut C,
+    next: u8,
+    remaining: usize,
+}
+
+impl<C: ConfigSpace> Iterator for Capabilities<'_, C> {
+    type Item = Capability;
+
+    fn next(&mut self) -> Option<Capability> {
+        let offset = self.next & !0b11;
+        // The capabilities follow the header. The bound protects against
+        // loops in the list.
+        if usize::from(offset) < HEADER_SIZE || self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        let Ok(header) = self.config.read_u16(u16::from(offset)) else {
+            self.next = 0;
+            return None;
+        };
+        let [id, next] = header.to_le_bytes();
+        self.next = next;
+        Some(Capability { id, offset })
+    }
+}
+
+/// Extended capability found by [`ConfigSpace::extended_capabilities`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ExtendedCapability {
+    /// ID of the capability, see [`extended_capability_id`].
+    pub id: u16,
+    /// Version of the capability.
+    pub version: u8,
+    /// Offset of the capability in the configuration space.
+    pub offset: u16,
+}
+
+/// Iterator over the extended capabilities of a function, returned by
+/// [`ConfigSpace::extended_capabilities`].
+#[derive(Debug)]
+pub struct ExtendedCapabilities<'a, C> {
+    config: &'a mut C,
+    next: u16,
+    remaining: usize,
+}
+
+impl<C: ConfigSpace> Iterator for ExtendedCapabilities<'_, C> {
+    type Item = ExtendedCapability;
+
+    fn next(&mut self) -> Option<ExtendedCapability> {
+        let offset = self.next & !0b11;
+        if !(EXTENDED_CAPABILITIES..EXTENDED_CONFIG_SPACE_SIZE).contains(&offset)
+            || self.remaining == 0
+        {
+            return None;
+        }
+        self.remaining -= 1;
+        self.next = 0;
+
+        // Functions without extended capabilities have a zero header at the
+        // start of the extended space, and conventional functions cannot
+        // access it, reading all ones.
+        let header = self.config.read_u32(offset).ok()?;
+        if header == 0 || header == u32::MAX {
+            return None;
+        }
+        self.next = (header >> 20) as u16;
+        Some(ExtendedCapability {
+            id: header as u16,
+            version: ((header >> 16) & 0xf) as u8,
+            offset,
+        })
+    }
+}
+
+/// Message signaled interrupts capability.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MsiCapability {
+    /// Message control register.
+    pub control: u16,
+    /// Message address.
+    pub message_address: u64,
+    /// Message data.
+    pub message_data: u16,
+}
+
+impl MsiCapability {
+    /// Parses the capability, starting at its header.
+    #[must_use]
+    pub const fn parse(bytes: &[u8; 0x18]) -> Option<Self> {
+        let control = u16::from_le_bytes([bytes[2], bytes[3]]);
+        let address_low = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as u64;
+        let (message_address, data_offset) = if control & (1 << 7) != 0 {
+            let high = u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as u64;
+            ((high << 32) | address_low, 0x0c)
+        } else {
+            (address_low, 0x08)
+        };
+        Some(Self {
+            control,
+            message_address,
+            message_data: u16::from_le_bytes([bytes[data_offset], bytes[data_offset + 1]]),
+        })
+    }
+
+    /// Returns whether MSI is enabled.
+    #[must_use]
+    pub const fn is_enabled(&self) -> bool {
+        self.control & 1 != 0
+    }
+
+    /// Returns the number of vectors the function can use.
+    #[must_use]
+    pub const fn vectors(&self) -> u8 {
+        1 << ((self.control >> 1) & 0b111)
+    }
+
+    /// Returns the number of vectors enabled.
+    #[must_use]
+    pub const fn enabled_vectors(&self) -> u8 {
+        1 << ((self.control >> 4) & 0b111)
+    }
+
+    /// Returns whether the function supports 64-bit message addresses.
+    #[must_use]
+    pub const fn is_64bit(&self) -> bool {
+        self.control & (1 << 7) != 0
+    }
+
+    /// Returns whether the vectors can be masked individually.
+    #[must_use]
+    pub const fn per_vector_masking(&self) -> bool {
+        self.control & (1 << 8) != 0
+    }
+}
+
+/// MSI-X capability.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MsixCapability {
+    /// Message control register.
+    pub control: u16,
+    /// Table offset and BAR indicator register.
+    pub table: u32,
+    /// Pending bit array offset and BAR indicator register.
+    pub pending_bit_array: u32,
+}
+
+impl MsixCapability {
+    /// Parses the capability, starting at its header.
+    #[must_use]
+    pub const fn parse(bytes: &[u8; 0x0c]) -> Option<Self> {
+        Some(Self {
+            control: u16::from_le_bytes([bytes[2], bytes[3]]),
+            table: u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+            pending_bit_array: u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]),
+        })
+    }
+
+    /// Returns the number of entries of the table.
+    #[must_use]
+    pub const fn table_size(&self) -> u16 {
+        (self.control & 0x7ff) + 1
+    }
+
+    /// Returns whether MSI-X is enabled.
+    #[must_use]
+    pub const fn is_enabled(&self) -> bool {
+        self.control & (1 << 15) != 0
+    }
+
+    /// Returns whether all the vectors are masked.
+    #[must_use]
+    pub const fn function_mask(&self) -> bool {
+        self.control & (1 << 14) != 0
+    }
+
+    /// Returns the index of the BAR of the table.
+    #[must_use]
+    pub const fn table_bar(&self) -> u8 {
+        (self.table & 0b111) as u8
+    }
+
+    /// Returns the offset of the table in its BAR.
+    #[must_use]
+    pub const fn table_offset(&self) -> u32 {
+        self.table & !0b111
+    }
+
+    /// Returns the index of the BAR of the pending bit array.
+    #[must_use]
+    pub const fn pending_bit_array_bar(&self) -> u8 {
+        (self.pending_bit_array & 0b111) as u8
+    }
+
+    /// Returns the offset of the pending bit array in its BAR.
+    #[must_use]
+    pub const fn pending_bit_array_offset(&self) -> u32 {
+        self.pending_bit_array & !0b111
+    }
+}
+
+/// Type of a PCI Express function, see
+/// [`PciExpressCapability::device_type`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PciExpressDeviceType {
+    /// PCI Express endpoint.
+    Endpoint,
+    /// Legacy PCI Express endpoint.
+    LegacyEndpoint,
+    /// Root port of a root complex.
+    RootPort,
+    /// Upstream port of a switch.
+    UpstreamSwitchPort,
+    /// Downstream port of a switch.
+    DownstreamSwitchPort,
+    /// PCI Express to PCI/PCI-X bridge.
+    PciExpressToPciBridge,
+    /// PCI/PCI-X to PCI Express bridge.
+    PciToPciExpressBridge,
+    /// Root complex integrated endpoint.
+    RootComplexIntegratedEndpoint,
+    /// Root complex event collector.
+    RootComplexEventCollector,
+    /// Unknown type.
+    Unknown(u8),
+}
+
+/// PCI Express capability.
+///
+/// Only the device and link registers are decoded, the slot and root
+/// registers are not.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PciExpressCapability {
+    /// PCI Express capabilities register.
+    pub capabilities: u16,
+    /// Device capabilities register.
+    pub device_capabilities: u32,
+    /// Device control register.
+    pub device_control: u16,
+    /// Device status register.
+    pub device_status: u16,
+    /// Link capabilities register.
+    pub link_capabilities: u32,
+    /// Link control register.
+    pub link_control: u16,
+    /// Link status register.
+    pub link_status: u16,
+}
+
+impl PciExpressCapability {
+    /// Parses the capability, starting at its header.
+    #[must_use]
+    pub const fn parse(bytes: &[u8; 0x14]) -> Option<Self> {
+        Some(Self {
+            capabilities: u16::from_le_bytes([bytes[0x02], bytes[0x03]]),
+            device_capabilities: u32::from_le_bytes([
+                bytes[0x04],
+                bytes[0x05],
+                bytes[0x06],
+                bytes[0x07],
+            ]),
+            device_control: u16::from_le_bytes([bytes[0x08], bytes[0x09]]),
+            device_status: u16::from_le_bytes([bytes[0x0a], bytes[0x0b]]),
+            link_capabilities: u32::from_le_bytes([
+                bytes[0x0c],
+                bytes[0x0d],
+                bytes[0x0e],
+                bytes[0x0f],
+            ]),
+            link_control: u16::from_le_bytes([bytes[0x10], bytes[0x11]]),
+            link_status: u16::from_le_bytes([bytes[0x12], bytes[0x13]]),
+        })
+    }
+
+    /// Returns the version of the capability.
+    #[must_use]
+    pub const fn version(&self) -> u8 {
+        (self.capabilities & 0xf) as u8
+    }
+
+    /// Returns the type of the function.
+    #[must_use]
+    pub const fn device_type(&self) -> PciExpressDeviceType {
+        match (self.capabilities >> 4) & 0xf {
+            0x0 => PciExpressDeviceType::Endpoint,
+            0x1 => PciExpressDeviceType::LegacyEndpoint,
+            0x4 => PciExpressDeviceType::RootPort,
+            0x5 => PciExpressDeviceType::UpstreamSwitchPort,
+            0x6 => PciExpressDeviceType::DownstreamSwitchPort,
+            0x7 => PciExpressDeviceType::PciExpressToPciBridge,
+            0x8 => PciExpressDeviceType::PciToPciExpressBridge,
+            0x9 => PciExpressDeviceType::RootComplexIntegratedEndpoint,
+            0xa => PciExpressDeviceType::RootComplexEventCollector,
+            other => PciExpressDeviceType::Unknown(other as u8),
+        }
+    }
+
+    /// Returns the maximum payload size supported, in bytes.
+    #[must_use]
+    pub const fn max_payload_size_supported(&self) -> u16 {
+        128 << (self.device_capabilities & 0b111)
+    }
+
+    /// Returns the maximum link speed, as a generation: 1 for 2.5 GT/s, 2 for
+    /// 5 GT/s, 3 for 8 GT/s, 4 for 16 GT/s, and so on.
+    #[must_use]
+    pub const fn max_link_speed(&self) -> u8 {
+        (self.link_capabilities & 0xf) as u8
+    }
+
+    /// Returns the maximum link width, in lanes.
+    #[must_use]
+    pub const fn max_link_width(&self) -> u8 {
+        ((self.link_capabilities >> 4) & 0x3f) as u8
+    }
+
+    /// Returns the current link speed, as a generation, see
+    /// [`max_link_speed`].
+    ///
+    /// [`max_link_speed`]: Self::max_link_speed
+    #[must_use]
+    pub const fn current_link_speed(&self) -> u8 {
+        (self.link_status & 0xf) as u8
+    }
+
+    /// Returns the negotiated link width, in lanes.
+    #[must_use]
+    pub const fn current_link_width(&self) -> u8 {
+        ((self.link_status >> 4) & 0x3f) as u8
+    }
+}
+
+/// Single root I/O virtualization extended capability.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SrIovCapability {
+    /// SR-IOV capabilities register.
+    pub capabilities: u32,
+    /// SR-IOV control register.
+    pub control: u16,
+    /// SR-IOV status register.
+    pub status: u16,
+    /// Number of virtual functions initially associated with the physical
+    /// function.
+    pub initial_vfs: u16,
+    /// Maximum number of virtual functions.
+    pub total_vfs: u16,
+    /// Number of virtual functions enabled.
+    pub num_vfs: u16,
+    /// Routing ID offset of the first virtual function.
+    pub first_vf_offset: u16,
+    /// Routing ID distance between consecutive virtual functions.
+    pub vf_stride: u16,
+    /// Device ID of the virtual functions.
+    pub vf_device_id: u16,
+    /// Page sizes supported, bit `n` meaning `4096 << n` bytes.
+    pub supported_page_sizes: u32,
+    /// Page size used, bit `n` meaning `4096 << n` bytes.
+    pub system_page_size: u32,
+    /// Raw values of the BARs of the virtual functions.
+    pub vf_bars: [u32; 6],
+}
+
+impl SrIovCapability {
+    const SIZE: usize = 0x40;
+
+    /// Parses the capability, starting at its header.
+    #[must_use]
+    pub fn parse(bytes: &[u8; Self::SIZE]) -> Option<Self> {
+        let vf_bar = |index: usize| u32_at(bytes, 0x24 + 4 * index);
+        Some(Self {
+            capabilities: u32_at(bytes, 0x04),
+            control: u16_at(bytes, 0x08),
+            status: u16_at(bytes, 0x0a),
+            initial_vfs: u16_at(bytes, 0x0c),
+            total_vfs: u16_at(bytes, 0x0e),
+            num_vfs: u16_at(bytes, 0x10),
+            first_vf_offset: u16_at(bytes, 0x14),
+            vf_stride: u16_at(bytes, 0x16),
+            vf_device_id: u16_at(bytes, 0x1a),
+            supported_page_sizes: u32_at(bytes, 0x1c),
+            system_page_size: u32_at(bytes, 0x20),
+            vf_bars: [
+                vf_bar(0),
+                vf_bar(1),
+                vf_bar(2),
+                vf_bar(3),
+                vf_bar(4),
+                vf_bar(5),
+            ],
+        })
+    }
+
+    /// Returns whether the virtual functions are enabled.
+    #[must_use]
+    pub const fn is_enabled(&self) -> bool {
+        self.control & 1 != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Configuration space of a fake function, with the masks of the BARs.
+    struct FakeConfig {
+        bytes: [u8; EXTENDED_CONFIG_SPACE_SIZE as usize],
+        bar_masks: [u32; 6],
+    }
+
+    impl FakeConfig {
+        fn new() -> Self {
+            Self {
+                bytes: [0; EXTENDED_CONFIG_SPACE_SIZE as usize],
+                bar_masks: [0; 6],
+            }
+        }
+
+        fn set(&mut self, offset: usize, bytes: &[u8]) {
+            self.bytes[offset..offset + bytes.len()].copy_from_slice(bytes);
+        }
+    }
+
+    impl ConfigSpace for FakeConfig {
+        fn read_u32(&mut self, offset: u16) -> Result<u32> {
+            Ok(u32_at(&self.bytes, usize::from(offset)))
+        }
+
+        fn write_u32(&mut self, offset: u16, value: u32) -> Result {
+            let mut value = value;
+            if (BAR0..BAR0 + 24).contains(&offset) {
+                // The bits outside of the mask are read-only.
+                let mask = self.bar_masks[usize::from(offset - BAR0) / 4];
+                value = (value & mask) | (u32_at(&self.bytes, usize::from(offset)) & !mask);
+            }
+            self.set(usize::from(offset), &value.to_le_bytes());
+            Ok(())
+        }
+    }
+
+    fn fake_function() -> FakeConfig {
+        let mut config = FakeConfig::new();
+        // Vendor 1af4, device 1001, memory decoding, capabilities list.
+        config.set(0x00, &[0xf4, 0x1a, 0x01, 0x10, 0x06, 0x00, 0x10, 0x00]);
+        // Revision 1, SATA controller (AHCI).
+        config.set(0x08, &[0x01, 0x01, 0x06, 0x01]);
+        // BAR 0: 32-bit I/O, 32 bytes at 0xc040.
+        config.set(0x10, &0xc041_u32.to_le_bytes());
+        config.bar_masks[0] = 0xffff_ffe0;
+        // BAR 1: unimplemented.
+        // BAR 2: 64-bit prefetchable memory, 16 KiB at 0x8_0000_0000.
+        config.set(0x18, &0x0000_000c_u32.to_le_bytes());
+        config.set(0x1c, &0x8_u32.to_le_bytes());
+        config.bar_masks[2] = 0xffff_c000;
+        config.bar_masks[3] = 0xffff_ffff;
+        // BAR 4: 32-bit memory, 4 KiB at 0xfebf_1000.
+        config.set(0x20, &0xfebf_1000_u32.to_le_bytes());
+        config.bar_masks[4] = 0xffff_f000;
+        config.set(0x2c, &[0xf4, 0x1a, 0x02, 0x11]);
+        config.set(0x34, &[0x40]);
+        config.set(0x3c, &[0x0b, 0x01]);
+
+        // MSI, 64-bit, 4 vectors, then MSI-X, then PCI Express.
+        config.set(0x40, &[0x05, 0x60, 0x84, 0x00]);
+        config.set(0x44, &[0x00, 0x00, 0xe0, 0xfe, 0x00, 0x00, 0x00, 0x00]);
+        config.set(0x4c, &[0x21, 0x40]);
+        config.set(0x60, &[0x11, 0x70, 0x0f, 0x80]);
+        config.set(0x64, &0x2004_u32.to_le_bytes());
+        config.set(0x68, &0x3004_u32.to_le_bytes());
+        config.set(0x70, &[0x10, 0x00, 0x02, 0x00]);
+        config.set(0x74, &0x0000_0001_u32.to_le_bytes());
+        config.set(0x7c, &0x0000_0043_u32.to_le_bytes());
+        config.set(0x82, &0x0042_u16.to_le_bytes());
+
+        // AER, then SR-IOV.
+        config.set(0x100, &0x1401_0001_u32.to_le_bytes());
+        config.set(0x140, &0x0001_0010_u32.to_le_bytes());
+        config.set(0x14c, &[0x08, 0x00, 0x08, 0x00, 0x02, 0x00]);
+        config.set(0x154, &[0x80, 0x00, 0x01, 0x00, 0x00, 0x00, 0x0c, 0x10]);
+        config
+    }
+
+    #[test]
+    fn test_header() {
+        let mut config = fake_function();
+        let header = config.header().unwrap();
+        let common = header.common();
+        assert_eq!(common.vendor_id, 0x1af4);
+        assert_eq!(common.device_id, 0x1001);
+        assert_eq!(common.header_type, HeaderType::Endpoint);
+        assert!(!common.multi_function);
+        assert_eq!(common.class.name(), "SATA controller (AHCI)");
+        assert_eq!(common.class.class_name(), "Mass storage controller");
+        let ConfigHeader::Type0(header) = header else {
+            panic!("not a type 0 header");
+        };
+        assert_eq!(header.bars[0], 0xc041);
+        assert_eq!(header.subsystem_id, 0x1102);
+        assert_eq!(header.capabilities_pointer, 0x40);
+        assert_eq!(header.interrupt_pin, 1);
+
+        // A bridge.
+        let mut bytes = [0; HEADER_SIZE];
+        bytes[..4].copy_from_slice(&[0x86, 0x80, 0x4e, 0x24]);
+        bytes[0x0a..0x0f].copy_from_slice(&[0x04, 0x06, 0, 0, 0x81]);
+        bytes[0x18..0x1b].copy_from_slice(&[0x00, 0x01, 0x03]);
+        let Some(ConfigHeader::Type1(header)) = ConfigHeader::parse(&bytes) else {
+            panic!("not a type 1 header");
+        };
+        assert!(header.common.multi_function);
+        assert_eq!(header.common.class.name(), "PCI bridge");
+        assert_eq!(
+            (
+                header.primary_bus,
+                header.secondary_bus,
+                header.subordinate_bus
+            ),
+            (0, 1, 3)
+        );
+
+        // No function.
+        assert_eq!(ConfigHeader::parse(&[0xff; HEADER_SIZE]), None);
+        assert_eq!(ConfigHeader::parse(&[0x86, 0x80]), None);
+    }
+
+    #[test]
+    fn test_bars() {
+        let mut config = fake_function();
+        let bars = config.bars().unwrap();
+        assert_eq!(
+            bars,
+            [
+                Some(Bar::Io {
+                    address: 0xc040,
+                    size: 32
+                }),
+                None,
+                Some(Bar::Memory {
+                    address: 0x8_0000_0000,
+                    size: 0x4000,
+                    prefetchable: true,
+                    is_64bit: true,
+                }),
+                None,
+                Some(Bar::Memory {
+                    address: 0xfebf_1000,
+                    size: 0x1000,
+                    prefetchable: false,
+                    is_64bit: false,
+                }),
+                None,
+            ]
+        );
+        // The registers are restored.
+        assert_eq!(config.read_u32(0x10).unwrap(), 0xc041);
+        assert_eq!(config.read_u32(0x1c).unwrap(), 0x8);
+        assert_eq!(config.read_u16(COMMAND).unwrap(), 0x0006);
+
+        // 16-bit I/O decoding.
+        assert_eq!(
+            Bar::decode((0x1001, 0x0000_fff1), None),
+            Some(Bar::Io {
+                address: 0x1000,
+                size: 16
+            })
+        );
+    }
+
+    #[test]
+    fn test_capabilities() {
+        let mut config = fake_function();
+        let mut capabilities = config.capabilities();
+        assert_eq!(
+            capabilities.next(),
+            Some(Capability {
+                id: capability_id::MSI,
+                offset: 0x40
+            })
+        );
+        assert_eq!(
+            capabilities.next(),
+            Some(Capability {
+                id: capability_id::MSIX,
+                offset: 0x60
+            })
+        );
+        assert_eq!(
+            capabilities.next(),
+            Some(Capability {
+                id: capability_id::PCI_EXPRESS,
+                offset: 0x70
+            })
+        );
+        assert_eq!(capabilities.next(), None);
+        assert_eq!(config.find_capability(capability_id::MSIX), Some(0x60));
+        assert_eq!(config.find_capability(capability_id::SATA), None);
+
+        let msi = config.msi().unwrap().unwrap();
+        assert!(msi.is_64bit());
+        assert!(!msi.is_enabled());
+        assert_eq!(msi.vectors(), 4);
+        assert_eq!(msi.message_address, 0xfee0_0000);
+        assert_eq!(msi.message_data, 0x4021);
+
+        let msix = config.msix().unwrap().unwrap();
+        assert_eq!(msix.table_size(), 16);
+        assert!(msix.is_enabled());
+        assert_eq!((msix.table_bar(), msix.table_offset()), (4, 0x2000));
+        assert_eq!(
+            (
+                msix.pending_bit_array_bar(),
+                msix.pending_bit_array_offset()
+            ),
+            (4, 0x3000)
+        );
+
+        let pcie = config.pci_express().unwrap().unwrap();
+        assert_eq!(pcie.version(), 2);
+        assert_eq!(pcie.device_type(), PciExpressDeviceType::Endpoint);
+        assert_eq!(pcie.max_payload_size_supported(), 256);
+        assert_eq!((pcie.max_link_speed(), pcie.max_link_width()), (3, 4));
+        assert_eq!(
+            (pcie.current_link_speed(), pcie.current_link_width()),
+            (2, 4)
+        );
+
+        // Without the capabilities list bit, there are no capabilities.
+        config.set(0x06, &[0x00]);
+        assert_eq!(config.capabilities().count(), 0);
+    }
+
+    #[test]
+    fn test_extended_capabilities() {
+        let mut config = fake_function();
+        let mut capabilities = config.extended_capabilities();
+        assert_eq!(
+            capabilities.next(),
+            Some(ExtendedCapability {
+                id: extended_capability_id::ADVANCED_ERROR_REPORTING,
+                version: 1,
+                offset: 0x100,
+            })
+        );
+        assert_eq!(
+            capabilities.next(),
+            Some(ExtendedCapability {
+                id: extended_capability_id::SRIOV,
+                version: 1,
+                offset: 0x140,
+            })
+        );
+        assert_eq!(capabilities.next(), None);
+
+        let sriov = config.sriov().unwrap().unwrap();
+        assert!(!sriov.is_enabled());
+        assert_eq!((sriov.initial_vfs, sriov.total_vfs), (8, 8));
+        assert_eq!(sriov.num_vfs, 2);
+        assert_eq!((sriov.first_vf_offset, sriov.vf_stride), (0x80, 1));
+        assert_eq!(sriov.vf_device_id, 0x100c);
+
+        // A conventional function.
+        config.set(0x100, &[0xff; 4]);
+        assert_eq!(config.extended_capabilities().count(), 0);
+    }
+}
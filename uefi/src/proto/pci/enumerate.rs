@@ -0,0 +1,138 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Enumeration of the PCI functions behind a root bridge.
+//!
+//! [`PciRootBridgeIo::enumerate`] scans the buses decoded by a root bridge,
+//! following the PCI-to-PCI bridges to their secondary buses, and returns the
+//! decoded headers of the functions found.
+//!
+//! # Example
+//!
+//! ```
+//! use uefi::boot;
+//! use uefi::proto::pci::config::ConfigSpace;
+//! use uefi::proto::pci::root_bridge::PciRootBridgeIo;
+//!
+//! fn list_pci_functions() -> uefi::Result {
+//!     for handle in boot::find_handles::<PciRootBridgeIo>()? {
+//!         let mut bridge = boot::open_protocol_exclusive::<PciRootBridgeIo>(handle)?;
+//!         for function in bridge.enumerate()? {
+//!             let common = function.header.common();
+//!             log::info!(
+//!                 "{:02x}:{:02x}.{:x} {:04x}:{:04x} {}",
+//!                 function.address.bus,
+//!                 function.address.dev,
+//!                 function.address.fun,
+//!                 common.vendor_id,
+//!                 common.device_id,
+//!                 common.class.name(),
+//!             );
+//!             let bars = bridge.function(function.address).bars()?;
+//!             log::info!("{bars:x?}");
+//!         }
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use super::PciIoAddress;
+use super::config::{ConfigHeader, ConfigSpace};
+use super::root_bridge::PciRootBridgeIo;
+use crate::Result;
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+
+/// Number of devices on a bus.
+const DEVICES: u8 = 32;
+/// Number of functions of a device.
+const FUNCTIONS: u8 = 8;
+
+/// PCI function found by [`PciRootBridgeIo::enumerate`].
+#[derive(Clone, Debug)]
+pub struct PciFunction {
+    /// Segment of the root bridge.
+    pub segment: u32,
+    /// Address of the function.
+    pub address: PciIoAddress,
+    /// Decoded header of the function.
+    pub header: ConfigHeader,
+    /// Address of the PCI-to-PCI bridge the bus of the function is behind,
+    /// or `None` for the functions on the root bus.
+    pub parent: Option<PciIoAddress>,
+}
+
+impl PciRootBridgeIo {
+    /// Enumerates the functions behind the root bridge.
+    ///
+    /// The buses are scanned depth first from the first bus of
+    /// [`bus_range`], and the functions are returned in the order they are
+    /// found: each bridge is followed by the functions behind it.
+    ///
+    /// # Errors
+    ///
+    /// * Errors of [`bus_range`].
+    ///
+    /// Errors reading the configuration space of a function are not returned:
+    /// the function is skipped.
+    ///
+    /// [`bus_range`]: Self::bus_range
+    pub fn enumerate(&mut self) -> Result<Vec<PciFunction>> {
+        let buses = self.bus_range()?;
+        let root_bus = *buses.start();
+        let mut walk = Walk {
+            segment: self.segment_nr(),
+            buses,
+            scanned: [false; 256],
+            functions: Vec::new(),
+        };
+        self.scan_bus(&mut walk, root_bus, None);
+        Ok(walk.functions)
+    }
+
+    /// Scans the bus `bus`, and the buses behind its bridges.
+    ///
+    /// The secondary bus of a bridge is only followed if it is above the bus
+    /// of the bridge, so the recursion is bounded by the number of buses.
+    fn scan_bus(&mut self, walk: &mut Walk, bus: u8, parent: Option<PciIoAddress>) {
+        if !walk.buses.contains(&bus) || walk.scanned[usize::from(bus)] {
+            return;
+        }
+        walk.scanned[usize::from(bus)] = true;
+
+        for device in 0..DEVICES {
+            for function in 0..FUNCTIONS {
+                let address = PciIoAddress::new(bus, device, function);
+                let header = match self.function(address).header() {
+                    Ok(header) => header,
+                    // Devices always implement function 0.
+                    Err(_) if function == 0 => break,
+                    Err(_) => continue,
+                };
+                walk.functions.push(PciFunction {
+                    segment: walk.segment,
+                    address,
+                    header,
+                    parent,
+                });
+
+                if let ConfigHeader::Type1(bridge) = header {
+                    if bridge.secondary_bus > bus {
+                        self.scan_bus(walk, bridge.secondary_bus, Some(address));
+                    }
+                }
+                if function == 0 && !header.common().multi_function {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// State of [`PciRootBridgeIo::enumerate`].
+struct Walk {
+    segment: u32,
+    buses: RangeInclusive<u8>,
+    /// Buses already scanned, to protect against misconfigured bridges.
+    scanned: [bool; 256],
+    functions: Vec<PciFunction>,
+}
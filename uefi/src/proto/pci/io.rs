@@ -0,0 +1,763 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! PCI I/O protocol.
+
+use core::ffi::c_void;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+use core::ptr::{self, NonNull};
+use core::time::Duration;
+use core::{fmt, slice};
+
+use super::config::ConfigSpace;
+use super::{PciIoAddress, PciIoMode, PciIoUnit, encode_io_mode_and_unit};
+use crate::boot::{MemoryType, PAGE_SIZE};
+use crate::{Result, StatusExt};
+use uefi_macros::unsafe_protocol;
+use uefi_raw::protocol::pci::io::{
+    PciIoAccess, PciIoProtocol, PciIoProtocolAttributeOperation, PciIoProtocolOperation,
+    PciIoProtocolWidth,
+};
+use uefi_raw::table::boot::AllocateType;
+
+pub use uefi_raw::protocol::pci::io::PciIoAttributes;
+
+/// PCI I/O [`Protocol`].
+///
+/// Installed by the PCI bus driver on the handle of each PCI controller, this
+/// protocol is what the drivers of a PCI device use to access it: its
+/// configuration space, the memory and I/O ranges of its BARs, and DMA.
+///
+/// Drivers open the protocol by driver on the handle of the controller they
+/// manage. The offsets of the BAR accesses are relative to the start of the
+/// BAR, and the accesses fail if they fall outside of it.
+///
+/// DMA buffers returned by [`allocate_buffer`] and mappings returned by
+/// [`map`] borrow the protocol, and give access to it through their `pci`
+/// methods, so the controller can be programmed while they exist.
+///
+/// # Example
+///
+/// ```
+/// use uefi::proto::pci::io::{PciIo, PciIoAttributes};
+///
+/// fn enable_device(pci: &mut PciIo) -> uefi::Result<u32> {
+///     let supported = pci.supported_attributes()?;
+///     pci.enable_attributes(
+///         supported & (PciIoAttributes::MEMORY | PciIoAttributes::BUS_MASTER),
+///     )?;
+///     // Read the first register of the memory BAR 0.
+///     pci.mem(0).read_one::<u32>(0)
+/// }
+/// ```
+///
+/// [`Protocol`]: uefi::proto::Protocol
+/// [`allocate_buffer`]: Self::allocate_buffer
+/// [`map`]: Self::map
+#[derive(Debug)]
+#[repr(transparent)]
+#[unsafe_protocol(PciIoProtocol::GUID)]
+pub struct PciIo(PciIoProtocol);
+
+impl PciIo {
+    /// Returns the location of the controller.
+    pub fn location(&self) -> Result<PciLocation> {
+        let (mut segment, mut bus, mut device, mut function) = (0, 0, 0, 0);
+
+        unsafe {
+            (self.0.get_location)(
+                self.this(),
+                &mut segment,
+                &mut bus,
+                &mut device,
+                &mut function,
+            )
+        }
+        .to_result_with_val(|| PciLocation {
+            segment: segment as u32,
+            bus: bus as u8,
+            device: device as u8,
+            function: function as u8,
+        })
+    }
+
+    /// Accesses the configuration space of the controller.
+    #[must_use]
+    pub const fn config(&mut self) -> PciIoConfigAccess<'_> {
+        PciIoConfigAccess {
+            proto: &mut self.0,
+            _pci: PhantomData,
+        }
+    }
+
+    /// Accesses the memory range of the BAR `bar`.
+    #[must_use]
+    pub const fn mem(&mut self, bar: u8) -> PciIoBarAccess<'_> {
+        PciIoBarAccess {
+            proto: &mut self.0,
+            space: BarSpace::Memory,
+            bar,
+            _pci: PhantomData,
+        }
+    }
+
+    /// Accesses the I/O range of the BAR `bar`.
+    #[must_use]
+    pub const fn io(&mut self, bar: u8) -> PciIoBarAccess<'_> {
+        PciIoBarAccess {
+            proto: &mut self.0,
+            space: BarSpace::Io,
+            bar,
+            _pci: PhantomData,
+        }
+    }
+
+    /// Copies `count` values of type `U` from `src_offset` in the memory BAR
+    /// `src_bar` to `dest_offset` in the memory BAR `dest_bar`.
+    ///
+    /// The ranges may overlap.
+    pub fn copy_mem<U: PciIoUnit>(
+        &mut self,
+        dest_bar: u8,
+        dest_offset: u64,
+        src_bar: u8,
+        src_offset: u64,
+        count: usize,
+    ) -> Result {
+        unsafe {
+            (self.0.copy_mem)(
+                &mut self.0,
+                width::<U>(PciIoMode::Normal),
+                dest_bar,
+                dest_offset,
+                src_bar,
+                src_offset,
+                count,
+            )
+        }
+        .to_result()
+    }
+
+    /// Maps `buffer` for a DMA transfer of the controller, and returns the
+    /// mapping, which is unmapped when dropped.
+    ///
+    /// The mapping may be shorter than `buffer`, see [`PciMapping::len`].
+    /// The buffer and the protocol remain accessible through the mapping.
+    /// For [`PciIoOperation::BusMasterCommonBuffer`], use
+    /// [`PciBuffer::map`] on a buffer allocated with [`allocate_buffer`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: `buffer` cannot be mapped for a common
+    ///   buffer operation.
+    /// * [`Status::OUT_OF_RESOURCES`]: there are not enough resources to map
+    ///   `buffer`.
+    /// * [`Status::DEVICE_ERROR`]: the system hardware could not map the
+    ///   buffer.
+    ///
+    /// [`allocate_buffer`]: Self::allocate_buffer
+    /// [`Status::UNSUPPORTED`]: crate::Status::UNSUPPORTED
+    /// [`Status::OUT_OF_RESOURCES`]: crate::Status::OUT_OF_RESOURCES
+    /// [`Status::DEVICE_ERROR`]: crate::Status::DEVICE_ERROR
+    pub fn map<'a>(
+        &'a mut self,
+        operation: PciIoOperation,
+        buffer: &'a mut [u8],
+    ) -> Result<PciMapping<'a>> {
+        let mut len = buffer.len();
+        let mut device_address = 0;
+        let mut mapping = ptr::null_mut();
+
+        unsafe {
+            (self.0.map)(
+                &mut self.0,
+                operation.into(),
+                buffer.as_mut_ptr().cast(),
+                &mut len,
+                &mut device_address,
+                &mut mapping,
+            )
+        }
+        .to_result_with_val(|| PciMapping {
+            proto: self,
+            buffer,
+            device_address,
+            len,
+            mapping,
+        })
+    }
+
+    /// Allocates `pages` pages of memory suitable for a common buffer DMA
+    /// transfer of the controller, freed when the buffer is dropped.
+    ///
+    /// `memory_type` must be [`MemoryType::BOOT_SERVICES_DATA`] or
+    /// [`MemoryType::RUNTIME_SERVICES_DATA`]. `attributes` can only contain
+    /// [`PciIoAttributes::MEMORY_WRITE_COMBINE`],
+    /// [`PciIoAttributes::MEMORY_CACHED`] and
+    /// [`PciIoAttributes::DUAL_ADDRESS_CYCLE`].
+    pub fn allocate_buffer(
+        &mut self,
+        memory_type: MemoryType,
+        pages: usize,
+        attributes: PciIoAttributes,
+    ) -> Result<PciBuffer<'_>> {
+        let mut host_address = ptr::null_mut();
+
+        unsafe {
+            (self.0.allocate_buffer)(
+                &mut self.0,
+                AllocateType::ANY_PAGES,
+                memory_type,
+                pages,
+                &mut host_address,
+                attributes,
+            )
+        }
+        .to_result()?;
+
+        // The buffer cannot be null on success, but do not trust the
+        // firmware to be correct.
+        let ptr = NonNull::new(host_address.cast::<u8>()).ok_or(crate::Status::OUT_OF_RESOURCES)?;
+        Ok(PciBuffer {
+            proto: self,
+            ptr,
+            pages,
+        })
+    }
+
+    /// Flushes the posted write transactions of the controller to system
+    /// memory.
+    pub fn flush(&mut self) -> Result {
+        unsafe { (self.0.flush)(&mut self.0) }.to_result()
+    }
+
+    /// Returns the current attributes of the controller.
+    pub fn attributes(&self) -> Result<PciIoAttributes> {
+        // Getting the attributes does not modify the controller.
+        Self::attribute_operation(
+            self.this(),
+            PciIoProtocolAttributeOperation::GET,
+            PciIoAttributes::empty(),
+        )
+    }
+
+    /// Returns the attributes supported by the controller.
+    pub fn supported_attributes(&self) -> Result<PciIoAttributes> {
+        Self::attribute_operation(
+            self.this(),
+            PciIoProtocolAttributeOperation::SUPPORTED,
+            PciIoAttributes::empty(),
+        )
+    }
+
+    /// Sets the attributes of the controller to `attributes`.
+    pub fn set_attributes(&mut self, attributes: PciIoAttributes) -> Result {
+        Self::attribute_operation(
+            &mut self.0,
+            PciIoProtocolAttributeOperation::SET,
+            attributes,
+        )
+        .map(|_| ())
+    }
+
+    /// Enables `attributes` on the controller, for example memory decoding
+    /// and bus mastering.
+    pub fn enable_attributes(&mut self, attributes: PciIoAttributes) -> Result {
+        Self::attribute_operation(
+            &mut self.0,
+            PciIoProtocolAttributeOperation::ENABLE,
+            attributes,
+        )
+        .map(|_| ())
+    }
+
+    /// Disables `attributes` on the controller.
+    pub fn disable_attributes(&mut self, attributes: PciIoAttributes) -> Result {
+        Self::attribute_operation(
+            &mut self.0,
+            PciIoProtocolAttributeOperation::DISABLE,
+            attributes,
+        )
+        .map(|_| ())
+    }
+
+    fn attribute_operation(
+        this: *mut PciIoProtocol,
+        operation: PciIoProtocolAttributeOperation,
+        attributes: PciIoAttributes,
+    ) -> Result<PciIoAttributes> {
+        let mut result = PciIoAttributes::empty();
+
+        unsafe { ((*this).attributes)(this, operation, attributes, &mut result) }
+            .to_result_with_val(|| result)
+    }
+
+    /// Returns the attributes supported by the range of the BAR `bar`.
+    pub fn bar_attributes(&self, bar: u8) -> Result<PciIoAttributes> {
+        let mut supports = PciIoAttributes::empty();
+
+        unsafe { (self.0.get_bar_attributes)(self.this(), bar, &mut supports, ptr::null_mut()) }
+            .to_result_with_val(|| supports)
+    }
+
+    /// Returns the option ROM image of the controller, if it has one.
+    #[must_use]
+    pub fn rom_image(&self) -> Option<&[u8]> {
+        if self.0.rom_image.is_null() || self.0.rom_size == 0 {
+            return None;
+        }
+        let len = usize::try_from(self.0.rom_size).ok()?;
+        Some(unsafe { slice::from_raw_parts(self.0.rom_image.cast::<u8>(), len) })
+    }
+
+    /// Returns the protocol pointer for the functions that do not modify
+    /// the controller.
+    const fn this(&self) -> *mut PciIoProtocol {
+        ptr::from_ref(&self.0).cast_mut()
+    }
+}
+
+impl ConfigSpace for PciIo {
+    fn read_u32(&mut self, offset: u16) -> Result<u32> {
+        self.config().read_one(u32::from(offset))
+    }
+
+    fn write_u32(&mut self, offset: u16, value: u32) -> Result {
+        self.config().write_one(u32::from(offset), value)
+    }
+}
+
+/// Location of a PCI controller, returned by [`PciIo::location`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PciLocation {
+    /// Segment, the [`PciRootBridgeIo::segment_nr`] of the root bridge of
+    /// the controller.
+    ///
+    /// [`PciRootBridgeIo::segment_nr`]: super::root_bridge::PciRootBridgeIo::segment_nr
+    pub segment: u32,
+    /// Bus number.
+    pub bus: u8,
+    /// Device number.
+    pub device: u8,
+    /// Function number.
+    pub function: u8,
+}
+
+impl PciLocation {
+    /// Returns the address of the controller, to access it through
+    /// [`PciRootBridgeIo`].
+    ///
+    /// [`PciRootBridgeIo`]: super::root_bridge::PciRootBridgeIo
+    #[must_use]
+    pub const fn address(&self) -> PciIoAddress {
+        PciIoAddress::new(self.bus, self.device, self.function)
+    }
+}
+
+impl fmt::Display for PciLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04x}:{:02x}:{:02x}.{:x}",
+            self.segment, self.bus, self.device, self.function
+        )
+    }
+}
+
+/// Direction of a DMA transfer, for [`PciIo::map`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PciIoOperation {
+    /// The controller reads the buffer.
+    BusMasterRead,
+    /// The controller writes the buffer.
+    BusMasterWrite,
+    /// Both the processor and the controller access the buffer at the same
+    /// time.
+    BusMasterCommonBuffer,
+}
+
+impl From<PciIoOperation> for PciIoProtocolOperation {
+    fn from(operation: PciIoOperation) -> Self {
+        match operation {
+            PciIoOperation::BusMasterRead => Self::BUS_MASTER_READ,
+            PciIoOperation::BusMasterWrite => Self::BUS_MASTER_WRITE,
+            PciIoOperation::BusMasterCommonBuffer => Self::BUS_MASTER_COMMON_BUFFER,
+        }
+    }
+}
+
+/// DMA mapping of a buffer, returned by [`PciIo::map`] and
+/// [`PciBuffer::map`].
+///
+/// The buffer is unmapped when the mapping is dropped. For a
+/// [`PciIoOperation::BusMasterWrite`], the data written by the controller
+/// may only be visible in the buffer after the buffer is unmapped, and for a
+/// [`PciIoOperation::BusMasterRead`], the data written by the processor while
+/// the buffer is mapped may not be seen by the controller.
+pub struct PciMapping<'a> {
+    proto: &'a mut PciIo,
+    buffer: &'a mut [u8],
+    device_address: u64,
+    len: usize,
+    mapping: *mut c_void,
+}
+
+impl PciMapping<'_> {
+    /// Returns the protocol, to program the controller while the buffer is
+    /// mapped.
+    pub const fn pci(&mut self) -> &mut PciIo {
+        self.proto
+    }
+
+    /// Returns the mapped buffer.
+    #[must_use]
+    pub const fn buffer(&self) -> &[u8] {
+        self.buffer
+    }
+
+    /// Returns the mapped buffer.
+    pub const fn buffer_mut(&mut self) -> &mut [u8] {
+        self.buffer
+    }
+
+    /// Returns the address of the buffer for the controller.
+    #[must_use]
+    pub const fn device_address(&self) -> u64 {
+        self.device_address
+    }
+
+    /// Returns the number of bytes mapped, from the start of the buffer.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether no byte is mapped.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Unmaps the buffer, returning the error of the unmap operation if it
+    /// fails.
+    pub fn unmap(self) -> Result {
+        let status = unsafe { (self.proto.0.unmap)(&mut self.proto.0, self.mapping) };
+        core::mem::forget(self);
+        status.to_result()
+    }
+}
+
+impl fmt::Debug for PciMapping<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PciMapping")
+            .field("buffer", &self.buffer.as_ptr())
+            .field("device_address", &self.device_address)
+            .field("len", &self.len)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for PciMapping<'_> {
+    fn drop(&mut self) {
+        let status = unsafe { (self.proto.0.unmap)(&mut self.proto.0, self.mapping) };
+        if status.is_error() {
+            log::error!("Failed to unmap PCI DMA buffer: {status}");
+        }
+    }
+}
+
+/// Buffer for common buffer DMA transfers, returned by
+/// [`PciIo::allocate_buffer`].
+///
+/// The buffer is freed when dropped.
+pub struct PciBuffer<'a> {
+    proto: &'a mut PciIo,
+    ptr: NonNull<u8>,
+    pages: usize,
+}
+
+impl PciBuffer<'_> {
+    /// Returns the size of the buffer, in pages.
+    #[must_use]
+    pub const fn pages(&self) -> usize {
+        self.pages
+    }
+
+    /// Returns the protocol, to program the controller while the buffer
+    /// exists.
+    pub const fn pci(&mut self) -> &mut PciIo {
+        self.proto
+    }
+
+    /// Maps the buffer for a DMA transfer of the controller, see
+    /// [`PciIo::map`].
+    pub fn map(&mut self, operation: PciIoOperation) -> Result<PciMapping<'_>> {
+        // SAFETY: the buffer is valid for `pages` pages, and borrowed
+        // mutably together with the protocol.
+        let buffer =
+            unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.pages * PAGE_SIZE) };
+        self.proto.map(operation, buffer)
+    }
+}
+
+impl Deref for PciBuffer<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.pages * PAGE_SIZE) }
+    }
+}
+
+impl DerefMut for PciBuffer<'_> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.pages * PAGE_SIZE) }
+    }
+}
+
+impl fmt::Debug for PciBuffer<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PciBuffer")
+            .field("ptr", &self.ptr)
+            .field("pages", &self.pages)
+            .finish()
+    }
+}
+
+impl Drop for PciBuffer<'_> {
+    fn drop(&mut self) {
+        let status = unsafe {
+            (self.proto.0.free_buffer)(&mut self.proto.0, self.pages, self.ptr.as_ptr().cast())
+        };
+        if status.is_error() {
+            log::error!("Failed to free PCI DMA buffer: {status}");
+        }
+    }
+}
+
+/// Struct for accessing the configuration space of a PCI controller.
+#[derive(Debug)]
+pub struct PciIoConfigAccess<'a> {
+    proto: *mut PciIoProtocol,
+    _pci: PhantomData<&'a mut PciIo>,
+}
+
+impl PciIoConfigAccess<'_> {
+    /// Reads a single value of type `U` at the byte offset `offset`.
+    pub fn read_one<U: PciIoUnit>(&self, offset: u32) -> Result<U> {
+        let mut result = U::default();
+        self.read_raw(PciIoMode::Normal, offset, slice::from_mut(&mut result))
+            .map(|()| result)
+    }
+
+    /// Writes a single value of type `U` at the byte offset `offset`.
+    pub fn write_one<U: PciIoUnit>(&self, offset: u32, data: U) -> Result {
+        self.write_raw::<U>(PciIoMode::Normal, offset, 1, ptr::from_ref(&data).cast())
+    }
+
+    /// Reads consecutive values starting at the byte offset `offset`.
+    pub fn read<U: PciIoUnit>(&self, offset: u32, data: &mut [U]) -> Result {
+        self.read_raw(PciIoMode::Normal, offset, data)
+    }
+
+    /// Writes consecutive values starting at the byte offset `offset`.
+    pub fn write<U: PciIoUnit>(&self, offset: u32, data: &[U]) -> Result {
+        self.write_raw::<U>(PciIoMode::Normal, offset, data.len(), data.as_ptr().cast())
+    }
+
+    fn read_raw<U: PciIoUnit>(&self, mode: PciIoMode, offset: u32, data: &mut [U]) -> Result {
+        unsafe {
+            ((*self.proto).pci.read)(
+                self.proto,
+                width::<U>(mode),
+                offset,
+                data.len(),
+                data.as_mut_ptr().cast(),
+            )
+        }
+        .to_result()
+    }
+
+    fn write_raw<U: PciIoUnit>(
+        &self,
+        mode: PciIoMode,
+        offset: u32,
+        count: usize,
+        data: *const c_void,
+    ) -> Result {
+        unsafe { ((*self.proto).pci.write)(self.proto, width::<U>(mode), offset, count, data) }
+            .to_result()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BarSpace {
+    Memory,
+    Io,
+}
+
+/// Struct for accessing the memory or I/O range of a BAR of a PCI
+/// controller.
+#[derive(Debug)]
+pub struct PciIoBarAccess<'a> {
+    proto: *mut PciIoProtocol,
+    space: BarSpace,
+    bar: u8,
+    _pci: PhantomData<&'a mut PciIo>,
+}
+
+impl PciIoBarAccess<'_> {
+    /// Reads a single value of type `U` at the byte offset `offset`.
+    pub fn read_one<U: PciIoUnit>(&self, offset: u64) -> Result<U> {
+        let mut result = U::default();
+        self.read_raw(PciIoMode::Normal, offset, slice::from_mut(&mut result))
+            .map(|()| result)
+    }
+
+    /// Writes a single value of type `U` at the byte offset `offset`.
+    pub fn write_one<U: PciIoUnit>(&self, offset: u64, data: U) -> Result {
+        self.write_raw::<U>(PciIoMode::Normal, offset, 1, ptr::from_ref(&data).cast())
+    }
+
+    /// Reads consecutive values starting at the byte offset `offset`.
+    pub fn read<U: PciIoUnit>(&self, offset: u64, data: &mut [U]) -> Result {
+        self.read_raw(PciIoMode::Normal, offset, data)
+    }
+
+    /// Writes consecutive values starting at the byte offset `offset`.
+    pub fn write<U: PciIoUnit>(&self, offset: u64, data: &[U]) -> Result {
+        self.write_raw::<U>(PciIoMode::Normal, offset, data.len(), data.as_ptr().cast())
+    }
+
+    /// Writes `data` `count` times at consecutive offsets starting at the
+    /// byte offset `offset`.
+    pub fn fill_write<U: PciIoUnit>(&self, offset: u64, count: usize, data: U) -> Result {
+        self.write_raw::<U>(PciIoMode::Fill, offset, count, ptr::from_ref(&data).cast())
+    }
+
+    /// Reads the register at the byte offset `offset` repeatedly, filling
+    /// `data`, like a FIFO.
+    pub fn fifo_read<U: PciIoUnit>(&self, offset: u64, data: &mut [U]) -> Result {
+        self.read_raw(PciIoMode::Fifo, offset, data)
+    }
+
+    /// Writes the values of `data` to the register at the byte offset
+    /// `offset`, like a FIFO.
+    pub fn fifo_write<U: PciIoUnit>(&self, offset: u64, data: &[U]) -> Result {
+        self.write_raw::<U>(PciIoMode::Fifo, offset, data.len(), data.as_ptr().cast())
+    }
+
+    /// Polls the register of type `U` at the byte offset `offset` until
+    /// `register & mask == value`, or until `delay` has elapsed, and returns
+    /// the last value read.
+    ///
+    /// The delay is rounded to 100 ns units. A zero delay reads the register
+    /// once.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::TIMEOUT`]: the register did not reach the value in time.
+    ///
+    /// [`Status::TIMEOUT`]: crate::Status::TIMEOUT
+    pub fn poll<U: PciIoUnit>(
+        &self,
+        offset: u64,
+        mask: u64,
+        value: u64,
+        delay: Duration,
+    ) -> Result<u64> {
+        // SAFETY: the protocol is borrowed for the lifetime of `self`.
+        let proto = unsafe { &*self.proto };
+        let poll = match self.space {
+            BarSpace::Memory => proto.poll_mem,
+            BarSpace::Io => proto.poll_io,
+        };
+        let delay = u64::try_from(delay.as_nanos().div_ceil(100)).unwrap_or(u64::MAX);
+        let mut result = 0;
+
+        unsafe {
+            poll(
+                self.proto,
+                width::<U>(PciIoMode::Normal),
+                self.bar,
+                offset,
+                mask,
+                value,
+                delay,
+                &mut result,
+            )
+        }
+        .to_result_with_val(|| result)
+    }
+
+    fn access(&self) -> &PciIoAccess {
+        // SAFETY: the protocol is borrowed for the lifetime of `self`.
+        let proto = unsafe { &*self.proto };
+        match self.space {
+            BarSpace::Memory => &proto.mem,
+            BarSpace::Io => &proto.io,
+        }
+    }
+
+    fn read_raw<U: PciIoUnit>(&self, mode: PciIoMode, offset: u64, data: &mut [U]) -> Result {
+        let access = self.access();
+        unsafe {
+            (access.read)(
+                self.proto,
+                width::<U>(mode),
+                self.bar,
+                offset,
+                data.len(),
+                data.as_mut_ptr().cast(),
+            )
+        }
+        .to_result()
+    }
+
+    fn write_raw<U: PciIoUnit>(
+        &self,
+        mode: PciIoMode,
+        offset: u64,
+        count: usize,
+        data: *const c_void,
+    ) -> Result {
+        let access = self.access();
+        unsafe { (access.write)(self.proto, width::<U>(mode), self.bar, offset, count, data) }
+            .to_result()
+    }
+}
+
+/// Returns the protocol width of the accesses of `U` in `mode`.
+fn width<U: PciIoUnit>(mode: PciIoMode) -> PciIoProtocolWidth {
+    // The widths of the root bridge and PCI I/O protocols have the same
+    // values.
+    PciIoProtocolWidth(encode_io_mode_and_unit::<U>(mode).0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_width() {
+        assert_eq!(width::<u8>(PciIoMode::Normal), PciIoProtocolWidth::UINT8);
+        assert_eq!(
+            width::<u32>(PciIoMode::Fifo),
+            PciIoProtocolWidth::FIFO_UINT32
+        );
+        assert_eq!(
+            width::<u64>(PciIoMode::Fill),
+            PciIoProtocolWidth::FILL_UINT64
+        );
+    }
+
+    #[test]
+    fn test_location_address() {
+        let location = PciLocation {
+            segment: 0,
+            bus: 0x1f,
+            device: 3,
+            function: 1,
+        };
+        assert_eq!(location.address(), PciIoAddress::new(0x1f, 3, 1));
+    }
+}
@@ -6,6 +6,10 @@ use core::cmp::Ordering;
 
 use uefi_raw::protocol::pci::root_bridge::PciRootBridgeIoProtocolWidth;
 
+pub mod config;
+#[cfg(feature = "alloc")]
+pub mod enumerate;
+pub mod io;
 pub mod root_bridge;
 
 /// IO Address for PCI/register IO operations
@@ -2,8 +2,10 @@
 
 //! PCI Root Bridge protocol.
 
+use core::ops::RangeInclusive;
 use core::ptr;
 
+use super::config::ConfigSpace;
 use super::{PciIoAddress, PciIoUnit, encode_io_mode_and_unit};
 use crate::StatusExt;
 use uefi_macros::unsafe_protocol;
@@ -34,6 +36,57 @@ impl PciRootBridgeIo {
         }
     }
 
+    /// Access the configuration space of the function at `address` through
+    /// [`ConfigSpace`].
+    pub const fn function(&mut self, address: PciIoAddress) -> FunctionConfig<'_> {
+        FunctionConfig {
+            bridge: self,
+            address,
+        }
+    }
+
+    /// Get the range of bus numbers decoded by this PCI root bridge.
+    ///
+    /// The range is read from the bus resource descriptor of the current
+    /// configuration of the root bridge. If there is none, all the bus
+    /// numbers are returned.
+    ///
+    /// # Errors
+    /// - [`crate::Status::DEVICE_ERROR`] The configuration of the root bridge could not be retrieved.
+    pub fn bus_range(&self) -> crate::Result<RangeInclusive<u8>> {
+        let mut resources = ptr::null();
+        unsafe { (self.0.configuration)(&self.0, &mut resources) }.to_result()?;
+
+        let mut descriptor = resources.cast::<u8>();
+        if descriptor.is_null() {
+            return Ok(0..=u8::MAX);
+        }
+        // SAFETY: the configuration is a list of QWORD address space
+        // descriptors, terminated by an end tag.
+        unsafe {
+            while descriptor.read() == QWORD_ADDRESS_SPACE_DESCRIPTOR {
+                let len = usize::from(u16::from_le_bytes([
+                    descriptor.add(1).read(),
+                    descriptor.add(2).read(),
+                ]));
+                let read_u64 =
+                    |offset: usize| descriptor.add(offset).cast::<u64>().read_unaligned();
+                if descriptor.add(3).read() == RESOURCE_TYPE_BUS {
+                    let min = read_u64(14);
+                    let max = read_u64(22);
+                    let len = read_u64(38);
+                    // EDK2 sets the length of the range, but not its maximum.
+                    let max = if len != 0 { min + len - 1 } else { max };
+                    let min = u8::try_from(min).unwrap_or(u8::MAX);
+                    let max = u8::try_from(max).unwrap_or(u8::MAX);
+                    return Ok(min..=max);
+                }
+                descriptor = descriptor.add(3 + len);
+            }
+        }
+        Ok(0..=u8::MAX)
+    }
+
     /// Flush all PCI posted write transactions from a PCI host bridge to system memory.
     ///
     /// # Errors
@@ -52,6 +105,49 @@ impl PciRootBridgeIo {
     // TODO: configuration / resource settings
 }
 
+/// Tag of the ACPI QWORD address space descriptors of the configuration.
+const QWORD_ADDRESS_SPACE_DESCRIPTOR: u8 = 0x8a;
+/// Resource type of the bus number ranges.
+const RESOURCE_TYPE_BUS: u8 = 2;
+
+/// Struct for accessing the configuration space of a function behind a root
+/// bridge, returned by [`PciRootBridgeIo::function`].
+#[derive(Debug)]
+pub struct FunctionConfig<'a> {
+    bridge: &'a mut PciRootBridgeIo,
+    address: PciIoAddress,
+}
+
+impl FunctionConfig<'_> {
+    /// Get the address of the function.
+    #[must_use]
+    pub const fn address(&self) -> PciIoAddress {
+        self.address
+    }
+
+    const fn register(&self, offset: u16) -> PciIoAddress {
+        // The extended register is only used when it is not zero, so offsets
+        // in the first 256 bytes use the register.
+        if offset < 0x100 {
+            self.address.with_register(offset as u8)
+        } else {
+            self.address.with_extended_register(offset as u32)
+        }
+    }
+}
+
+impl ConfigSpace for FunctionConfig<'_> {
+    fn read_u32(&mut self, offset: u16) -> crate::Result<u32> {
+        let register = self.register(offset);
+        self.bridge.pci().read_one(register)
+    }
+
+    fn write_u32(&mut self, offset: u16, value: u32) -> crate::Result {
+        let register = self.register(offset);
+        self.bridge.pci().write_one(register, value)
+    }
+}
+
 /// Struct for performing PCI I/O operations on a root bridge.
 #[derive(Debug)]
 pub struct PciIoAccessPci<'a> {
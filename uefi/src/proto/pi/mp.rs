@@ -17,9 +17,22 @@ use crate::data_types::Event;
 use crate::proto::unsafe_protocol;
 use crate::{Result, Status, StatusExt};
 use bitflags::bitflags;
+#[cfg(all(feature = "alloc", target_arch = "x86"))]
+use core::arch::x86::{self as arch, CpuidResult};
+#[cfg(all(feature = "alloc", target_arch = "x86_64"))]
+use core::arch::x86_64::{self as arch, CpuidResult};
 use core::ffi::c_void;
 use core::ptr;
 use core::time::Duration;
+#[cfg(feature = "alloc")]
+use {
+    crate::boot::{self, EventType, Tpl},
+    alloc::boxed::Box,
+    alloc::vec::Vec,
+    core::any::Any,
+    core::cell::UnsafeCell,
+    core::marker::PhantomData,
+};
 
 /// Callback to be called on the AP.
 pub type Procedure = extern "efiapi" fn(*mut c_void);
@@ -170,10 +183,7 @@ impl MpServices {
         event: Option<Event>,
         timeout: Option<Duration>,
     ) -> Result {
-        let timeout_arg = match timeout {
-            Some(timeout) => timeout.as_micros().try_into().unwrap(),
-            None => 0,
-        };
+        let timeout_arg = timeout_arg(timeout);
 
         let event_arg = match event {
             Some(event) => event.as_ptr(),
@@ -201,10 +211,7 @@ impl MpServices {
         event: Option<Event>,
         timeout: Option<Duration>,
     ) -> Result {
-        let timeout_arg = match timeout {
-            Some(timeout) => timeout.as_micros().try_into().unwrap(),
-            None => 0,
-        };
+        let timeout_arg = timeout_arg(timeout);
 
         let event_arg = match event {
             Some(event) => event.as_ptr(),
@@ -255,4 +262,385 @@ impl MpServices {
         let mut processor_number: usize = 0;
         (self.who_am_i)(self, &mut processor_number).to_result_with_val(|| processor_number)
     }
+
+    /// Runs `f` on all the enabled APs, and returns the values it returned,
+    /// indexed by processor number.
+    ///
+    /// `f` is called with the number of the processor it runs on. If
+    /// `single_thread` is true, the APs run `f` one after the other, else
+    /// simultaneously. The entries of the BSP and of the disabled APs are
+    /// `None`.
+    ///
+    /// `f` runs on the APs, so it must not use the boot services, including
+    /// allocating memory and logging, nor panic.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_STARTED`]: there are no enabled APs.
+    /// * [`Status::TIMEOUT`]: `timeout` expired before all the APs returned.
+    ///   The APs which had not returned are terminated.
+    /// * Errors of [`get_number_of_processors`].
+    ///
+    /// [`get_number_of_processors`]: Self::get_number_of_processors
+    #[cfg(feature = "alloc")]
+    pub fn run_on_aps<F, R>(
+        &self,
+        single_thread: bool,
+        timeout: Option<Duration>,
+        f: F,
+    ) -> Result<Vec<Option<R>>>
+    where
+        F: Fn(usize) -> R + Sync,
+        R: Send,
+    {
+        let results = result_slots(self.get_number_of_processors()?.total);
+        let context = ClosureContext::new(self, f, &results);
+
+        (self.startup_all_aps)(
+            self,
+            run_closure::<F, R>,
+            single_thread,
+            ptr::null_mut(),
+            timeout_arg(timeout),
+            ptr::from_ref(&context).cast_mut().cast(),
+            ptr::null_mut(),
+        )
+        .to_result_with_val(|| into_results(results))
+    }
+
+    /// Runs `f` on the AP `processor_number`, and returns the value it
+    /// returned.
+    ///
+    /// `f` is called with `processor_number`, and has the same restrictions
+    /// as in [`run_on_aps`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_READY`]: the AP is busy.
+    /// * [`Status::TIMEOUT`]: `timeout` expired before the AP returned. The
+    ///   AP is terminated.
+    /// * [`Status::INVALID_PARAMETER`]: the processor is the BSP or a
+    ///   disabled AP, or does not exist.
+    ///
+    /// [`run_on_aps`]: Self::run_on_aps
+    #[cfg(feature = "alloc")]
+    pub fn run_on_ap<F, R>(
+        &self,
+        processor_number: usize,
+        timeout: Option<Duration>,
+        f: F,
+    ) -> Result<R>
+    where
+        F: Fn(usize) -> R + Sync,
+        R: Send,
+    {
+        let results = result_slots(self.get_number_of_processors()?.total);
+        let context = ClosureContext::new(self, f, &results);
+
+        (self.startup_this_ap)(
+            self,
+            run_closure::<F, R>,
+            processor_number,
+            ptr::null_mut(),
+            timeout_arg(timeout),
+            ptr::from_ref(&context).cast_mut().cast(),
+            ptr::null_mut(),
+        )
+        .to_result()?;
+
+        into_results(results)
+            .into_iter()
+            .nth(processor_number)
+            .flatten()
+            .ok_or_else(|| Status::ABORTED.into())
+    }
+
+    /// Starts running `f` on all the enabled APs, without waiting for them,
+    /// and returns a task to poll or wait for the results.
+    ///
+    /// Apart from not blocking, this is the same as [`run_on_aps`]. If
+    /// `timeout` expires, the task finishes, and the APs which had not
+    /// returned are terminated: their entries in the results are `None`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_STARTED`]: there are no enabled APs.
+    /// * [`Status::NOT_READY`]: some APs are busy.
+    /// * [`Status::UNSUPPORTED`]: the non-blocking mode is not supported.
+    /// * Errors of [`get_number_of_processors`] and [`boot::create_event`].
+    ///
+    /// [`run_on_aps`]: Self::run_on_aps
+    /// [`get_number_of_processors`]: Self::get_number_of_processors
+    #[cfg(feature = "alloc")]
+    pub fn spawn_on_aps<F, R>(
+        &self,
+        single_thread: bool,
+        timeout: Option<Duration>,
+        f: F,
+    ) -> Result<ApTask<'_, R>>
+    where
+        F: Fn(usize) -> R + Send + Sync + 'static,
+        R: Send + 'static,
+    {
+        let results = result_slots(self.get_number_of_processors()?.total);
+        let context = Box::new(ClosureContext::new(self, f, &results));
+        let event = unsafe { boot::create_event(EventType::empty(), Tpl::CALLBACK, None, None) }?;
+
+        let status = (self.startup_all_aps)(
+            self,
+            run_closure::<F, R>,
+            single_thread,
+            event.as_ptr(),
+            timeout_arg(timeout),
+            ptr::from_ref(&*context).cast_mut().cast(),
+            ptr::null_mut(),
+        );
+        if status.is_error() {
+            let _ = boot::close_event(event);
+            return Err(status.into());
+        }
+
+        Ok(ApTask {
+            event: Some(event),
+            _context: context,
+            results,
+            _mp: PhantomData,
+        })
+    }
+
+    /// Runs `f` on every enabled processor, the APs first and then the BSP,
+    /// and returns the values it returned, indexed by processor number.
+    ///
+    /// This is [`run_on_aps`], completed with the value of the BSP, so the
+    /// entries of the disabled APs are `None`. On a system with a single
+    /// enabled processor, `f` only runs on the BSP.
+    ///
+    /// [`run_on_aps`]: Self::run_on_aps
+    #[cfg(feature = "alloc")]
+    pub fn run_on_each_processor<F, R>(&self, f: F) -> Result<Vec<Option<R>>>
+    where
+        F: Fn(usize) -> R + Sync,
+        R: Send,
+    {
+        let mut results = match self.run_on_aps(false, None, &f) {
+            Ok(results) => results,
+            Err(err) if err.status() == Status::NOT_STARTED => {
+                let total = self.get_number_of_processors()?.total;
+                (0..total).map(|_| None).collect()
+            }
+            Err(err) => return Err(err),
+        };
+
+        let bsp = self.who_am_i()?;
+        if let Some(entry) = results.get_mut(bsp) {
+            *entry = Some(f(bsp));
+        }
+        Ok(results)
+    }
+
+    /// Executes the `CPUID` instruction with the leaf `leaf` and the sub-leaf
+    /// `sub_leaf` on every enabled processor, see
+    /// [`run_on_each_processor`].
+    ///
+    /// [`run_on_each_processor`]: Self::run_on_each_processor
+    #[cfg(all(feature = "alloc", any(target_arch = "x86", target_arch = "x86_64")))]
+    #[allow(unused_unsafe)]
+    pub fn cpuid_on_each_processor(
+        &self,
+        leaf: u32,
+        sub_leaf: u32,
+    ) -> Result<Vec<Option<CpuidResult>>> {
+        self.run_on_each_processor(|_| unsafe { arch::__cpuid_count(leaf, sub_leaf) })
+    }
+
+    /// Reads the model-specific register `msr` on every enabled processor,
+    /// see [`run_on_each_processor`].
+    ///
+    /// # Safety
+    ///
+    /// The register must exist and be readable on every enabled processor,
+    /// reading it raises an exception otherwise.
+    ///
+    /// [`run_on_each_processor`]: Self::run_on_each_processor
+    #[cfg(all(feature = "alloc", any(target_arch = "x86", target_arch = "x86_64")))]
+    pub unsafe fn read_msr_on_each_processor(&self, msr: u32) -> Result<Vec<Option<u64>>> {
+        self.run_on_each_processor(|_| {
+            let (low, high): (u32, u32);
+            // SAFETY: the caller guarantees that the register exists.
+            unsafe {
+                core::arch::asm!(
+                    "rdmsr",
+                    in("ecx") msr,
+                    out("eax") low,
+                    out("edx") high,
+                    options(nomem, nostack, preserves_flags),
+                );
+            }
+            (u64::from(high) << 32) | u64::from(low)
+        })
+    }
+}
+
+/// Returns the timeout argument of the protocol functions: the timeout in
+/// microseconds, or zero for no timeout.
+fn timeout_arg(timeout: Option<Duration>) -> usize {
+    match timeout {
+        Some(timeout) => timeout.as_micros().try_into().unwrap(),
+        None => 0,
+    }
+}
+
+/// Result of a closure on a processor.
+#[cfg(feature = "alloc")]
+struct ResultSlot<R>(UnsafeCell<Option<R>>);
+
+// SAFETY: each processor only writes its own slot, and the slots are only
+// read once the processors are done.
+#[cfg(feature = "alloc")]
+unsafe impl<R: Send> Sync for ResultSlot<R> {}
+
+#[cfg(feature = "alloc")]
+fn result_slots<R>(count: usize) -> Box<[ResultSlot<R>]> {
+    (0..count)
+        .map(|_| ResultSlot(UnsafeCell::new(None)))
+        .collect()
+}
+
+#[cfg(feature = "alloc")]
+fn into_results<R>(slots: Box<[ResultSlot<R>]>) -> Vec<Option<R>> {
+    slots
+        .into_vec()
+        .into_iter()
+        .map(|slot| slot.0.into_inner())
+        .collect()
+}
+
+/// Argument of [`run_closure`].
+#[cfg(feature = "alloc")]
+struct ClosureContext<F, R> {
+    mp: *const MpServices,
+    f: F,
+    results: *const [ResultSlot<R>],
+}
+
+#[cfg(feature = "alloc")]
+impl<F, R> ClosureContext<F, R> {
+    const fn new(mp: &MpServices, f: F, results: &[ResultSlot<R>]) -> Self {
+        Self {
+            mp,
+            f,
+            results: ptr::from_ref(results),
+        }
+    }
+}
+
+/// Procedure running the closure of a [`ClosureContext`] on an AP, and
+/// storing its result in the slot of the AP.
+#[cfg(feature = "alloc")]
+extern "efiapi" fn run_closure<F, R>(context: *mut c_void)
+where
+    F: Fn(usize) -> R + Sync,
+    R: Send,
+{
+    // SAFETY: the context and the results outlive the APs running this.
+    let context = unsafe { &*context.cast::<ClosureContext<F, R>>() };
+    let mp = unsafe { &*context.mp };
+    let results = unsafe { &*context.results };
+
+    let Ok(processor_number) = mp.who_am_i() else {
+        return;
+    };
+    let Some(slot) = results.get(processor_number) else {
+        return;
+    };
+    let result = (context.f)(processor_number);
+    // SAFETY: only this processor accesses its slot while the APs run.
+    unsafe { *slot.0.get() = Some(result) };
+}
+
+/// Closure running on the APs without blocking, returned by
+/// [`MpServices::spawn_on_aps`].
+///
+/// Dropping the task waits for the APs to finish.
+#[cfg(feature = "alloc")]
+pub struct ApTask<'a, R> {
+    // Checking or waiting for the event clears its signaled state, so it is
+    // closed, and set to `None`, the first time it is seen signaled.
+    event: Option<Event>,
+    // Read by the APs until the task is finished.
+    _context: Box<dyn Any>,
+    results: Box<[ResultSlot<R>]>,
+    _mp: PhantomData<&'a MpServices>,
+}
+
+#[cfg(feature = "alloc")]
+impl<R> ApTask<'_, R> {
+    /// Returns whether all the APs have finished, or the timeout has expired.
+    pub fn is_finished(&mut self) -> Result<bool> {
+        let Some(event) = &self.event else {
+            return Ok(true);
+        };
+        let finished = boot::check_event(unsafe { event.unsafe_clone() })?;
+        if finished {
+            self.close_event();
+        }
+        Ok(finished)
+    }
+
+    /// Waits for the APs to finish, and returns the values the closure
+    /// returned, indexed by processor number, like
+    /// [`MpServices::run_on_aps`].
+    ///
+    /// The entries of the APs which had not returned when the timeout
+    /// expired are `None`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the current TPL is not
+    ///   [`Tpl::APPLICATION`].
+    pub fn wait(mut self) -> Result<Vec<Option<R>>> {
+        if let Some(event) = &self.event {
+            boot::wait_for_event(&mut [unsafe { event.unsafe_clone() }])
+                .map_err(|err| err.status())?;
+            self.close_event();
+        }
+        let results = core::mem::take(&mut self.results);
+        Ok(into_results(results))
+    }
+
+    /// Waits until the APs are finished, without requiring a TPL, and closes
+    /// the event.
+    fn close(&mut self) {
+        let Some(event) = &self.event else {
+            return;
+        };
+        while !boot::check_event(unsafe { event.unsafe_clone() }).unwrap_or(true) {
+            boot::stall(Duration::from_micros(10));
+        }
+        self.close_event();
+    }
+
+    /// Closes the event once it has been seen signaled.
+    fn close_event(&mut self) {
+        if let Some(event) = self.event.take() {
+            let _ = boot::close_event(event);
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<R> core::fmt::Debug for ApTask<'_, R> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ApTask")
+            .field("event", &self.event)
+            .field("processors", &self.results.len())
+            .finish()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<R> Drop for ApTask<'_, R> {
+    fn drop(&mut self) {
+        self.close();
+    }
 }
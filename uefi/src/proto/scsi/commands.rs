@@ -0,0 +1,902 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Typed SCSI commands.
+//!
+//! - [`cdb`] builds the command descriptor blocks of common commands, for
+//!   use with [`ScsiRequestBuilder::with_command_data`].
+//! - [`InquiryData`], [`Capacity`], [`ModeSenseData`] and [`SenseData`]
+//!   decode the responses.
+//! - Methods of [`ScsiDevice`], like [`ScsiDevice::inquiry`], send the
+//!   commands and check the status of the target.
+//! - [`ScsiBlockDevice`] reads and writes the blocks of a disk through the
+//!   [`BlockDevice`] trait.
+//!
+//! Commands completed with CHECK CONDITION fail with
+//! [`Status::DEVICE_ERROR`] and the [`SenseData`] reported by the device.
+//!
+//! # Example
+//!
+//! ```
+//! use uefi::proto::media::block_device::BlockDevice;
+//! use uefi::proto::scsi::commands::ScsiBlockDevice;
+//! use uefi::proto::scsi::pass_thru::ExtScsiPassThru;
+//! use uefi::{Result, ResultExt};
+//!
+//! fn read_first_block(scsi: &ExtScsiPassThru) -> Result {
+//!     for mut device in scsi.iter_devices() {
+//!         // Skip device addresses without a disk.
+//!         let Ok(inquiry) = device.inquiry() else {
+//!             continue;
+//!         };
+//!         if inquiry.peripheral_device_type != 0 {
+//!             continue;
+//!         }
+//!         let mut disk = ScsiBlockDevice::new(device).discard_errdata()?;
+//!         let mut block = vec![0; disk.block_size()];
+//!         disk.read_blocks(0, &mut block)?;
+//!         log::info!("{} {}: {:x?}", inquiry.vendor, inquiry.product, &block[..16]);
+//!     }
+//!     Ok(())
+//! }
+//! ```
+//!
+//! [`ScsiRequestBuilder::with_command_data`]: super::ScsiRequestBuilder::with_command_data
+
+use super::ScsiRequestBuilder;
+use super::pass_thru::ScsiDevice;
+use crate::proto::media::block::Lba;
+use crate::proto::media::block_device::{BlockDevice, check_range};
+use crate::{Error, Result, ResultExt, Status};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use uefi_raw::protocol::scsi::{ScsiIoHostAdapterStatus, ScsiIoTargetStatus};
+
+/// Command descriptor blocks of common SCSI commands.
+pub mod cdb {
+    use crate::proto::media::block::Lba;
+
+    /// TEST UNIT READY.
+    #[must_use]
+    pub const fn test_unit_ready() -> [u8; 6] {
+        [0x00, 0, 0, 0, 0, 0]
+    }
+
+    /// REQUEST SENSE, returning up to `allocation_length` bytes.
+    #[must_use]
+    pub const fn request_sense(allocation_length: u8) -> [u8; 6] {
+        [0x03, 0, 0, 0, allocation_length, 0]
+    }
+
+    /// INQUIRY of the standard data, returning up to `allocation_length`
+    /// bytes.
+    #[must_use]
+    pub const fn inquiry(allocation_length: u16) -> [u8; 6] {
+        let [high, low] = allocation_length.to_be_bytes();
+        [0x12, 0, 0, high, low, 0]
+    }
+
+    /// MODE SENSE(10) of page `page` and `subpage`, returning up to
+    /// `allocation_length` bytes. Page 0x3f returns all pages.
+    #[must_use]
+    pub const fn mode_sense10(page: u8, subpage: u8, allocation_length: u16) -> [u8; 10] {
+        let [high, low] = allocation_length.to_be_bytes();
+        [0x5a, 0, page & 0x3f, subpage, 0, 0, 0, high, low, 0]
+    }
+
+    /// READ CAPACITY(10).
+    #[must_use]
+    pub const fn read_capacity10() -> [u8; 10] {
+        [0x25, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+    }
+
+    /// READ CAPACITY(16), returning up to `allocation_length` bytes.
+    #[must_use]
+    pub const fn read_capacity16(allocation_length: u32) -> [u8; 16] {
+        let length = allocation_length.to_be_bytes();
+        [
+            0x9e, 0x10, 0, 0, 0, 0, 0, 0, 0, 0, length[0], length[1], length[2], length[3], 0, 0,
+        ]
+    }
+
+    /// READ(16) of `blocks` blocks starting at `lba`.
+    #[must_use]
+    pub const fn read16(lba: Lba, blocks: u32) -> [u8; 16] {
+        rw16(0x88, lba, blocks)
+    }
+
+    /// WRITE(16) of `blocks` blocks starting at `lba`.
+    #[must_use]
+    pub const fn write16(lba: Lba, blocks: u32) -> [u8; 16] {
+        rw16(0x8a, lba, blocks)
+    }
+
+    /// SYNCHRONIZE CACHE(10) of the whole medium.
+    #[must_use]
+    pub const fn synchronize_cache10() -> [u8; 10] {
+        [0x35, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+    }
+
+    const fn rw16(opcode: u8, lba: Lba, blocks: u32) -> [u8; 16] {
+        let l = lba.to_be_bytes();
+        let b = blocks.to_be_bytes();
+        [
+            opcode, 0, l[0], l[1], l[2], l[3], l[4], l[5], l[6], l[7], b[0], b[1], b[2], b[3], 0, 0,
+        ]
+    }
+}
+
+/// Size of the sense buffer of the commands.
+const SENSE_LEN: u8 = 96;
+
+/// Number of bytes requested by [`ScsiDevice::inquiry`].
+const INQUIRY_LEN: u16 = 96;
+
+/// Number of bytes requested by [`ScsiDevice::mode_sense`].
+const MODE_SENSE_LEN: u16 = 0xfffc;
+
+/// Data direction of a command.
+enum Transfer<'a> {
+    None,
+    Read(usize),
+    Write(&'a [u8]),
+}
+
+impl ScsiDevice<'_> {
+    /// Sends `cdb` and checks the status of the target. Returns the data
+    /// read from the device.
+    ///
+    /// A command failing with UNIT ATTENTION, which devices report once
+    /// after a reset or a medium change, is retried once.
+    fn command(
+        &mut self,
+        cdb: &[u8],
+        transfer: Transfer<'_>,
+    ) -> Result<Vec<u8>, Option<SenseData>> {
+        let result = self.command_once(cdb, &transfer);
+        match &result {
+            Err(err)
+                if err
+                    .data()
+                    .is_some_and(|sense| sense.error() == SenseError::UnitAttention) =>
+            {
+                self.command_once(cdb, &transfer)
+            }
+            _ => result,
+        }
+    }
+
+    fn command_once(
+        &mut self,
+        cdb: &[u8],
+        transfer: &Transfer<'_>,
+    ) -> Result<Vec<u8>, Option<SenseData>> {
+        let io_align = self.io_align();
+        let layout_error = |_| Error::new(Status::INVALID_PARAMETER, None);
+        let request = match transfer {
+            Transfer::None => ScsiRequestBuilder::read(io_align),
+            Transfer::Read(len) => ScsiRequestBuilder::read(io_align)
+                .with_read_buffer(*len)
+                .map_err(layout_error)?,
+            Transfer::Write(data) => ScsiRequestBuilder::write(io_align)
+                .with_write_data(data)
+                .map_err(layout_error)?,
+        };
+        let request = request
+            .with_command_data(cdb)
+            .map_err(layout_error)?
+            .with_sense_buffer(SENSE_LEN)
+            .map_err(layout_error)?
+            .build();
+        let response = self
+            .execute_command(request)
+            .map_err(|err| Error::new(err.status(), None))?;
+
+        if response.host_adapter_status() != ScsiIoHostAdapterStatus::OK {
+            return Err(Error::new(Status::DEVICE_ERROR, None));
+        }
+        match response.target_status() {
+            ScsiIoTargetStatus::GOOD | ScsiIoTargetStatus::CONDITION_MET => {
+                Ok(response.read_buffer().unwrap_or_default().to_vec())
+            }
+            ScsiIoTargetStatus::CHECK_CONDITION => Err(Error::new(
+                Status::DEVICE_ERROR,
+                response.sense_data().and_then(SenseData::parse),
+            )),
+            ScsiIoTargetStatus::BUSY | ScsiIoTargetStatus::QUEUE_FULL => {
+                Err(Error::new(Status::NOT_READY, None))
+            }
+            _ => Err(Error::new(Status::DEVICE_ERROR, None)),
+        }
+    }
+
+    /// Checks whether the device is ready to accept medium access
+    /// commands.
+    pub fn test_unit_ready(&mut self) -> Result<(), Option<SenseData>> {
+        self.command(&cdb::test_unit_ready(), Transfer::None)
+            .map(|_| ())
+    }
+
+    /// Reads the standard INQUIRY data.
+    ///
+    /// This is also a way to probe whether a device is present at the
+    /// address of this device.
+    pub fn inquiry(&mut self) -> Result<InquiryData, Option<SenseData>> {
+        let data = self.command(
+            &cdb::inquiry(INQUIRY_LEN),
+            Transfer::Read(INQUIRY_LEN.into()),
+        )?;
+        InquiryData::parse(&data).ok_or_else(|| Error::new(Status::NOT_FOUND, None))
+    }
+
+    /// Reads the capacity of the medium, with READ CAPACITY(10), or
+    /// READ CAPACITY(16) for media with more than 2<sup>32</sup> blocks.
+    pub fn read_capacity(&mut self) -> Result<Capacity, Option<SenseData>> {
+        let data = self.command(&cdb::read_capacity10(), Transfer::Read(8))?;
+        let capacity = Capacity::parse10(&data).ok_or(Error::new(Status::DEVICE_ERROR, None))?;
+        if capacity.last_lba != u64::from(u32::MAX) {
+            return Ok(capacity);
+        }
+        let data = self.command(&cdb::read_capacity16(32), Transfer::Read(32))?;
+        Capacity::parse16(&data).ok_or(Error::new(Status::DEVICE_ERROR, None))
+    }
+
+    /// Reads the mode page `page` and `subpage`, or all pages if `page` is
+    /// 0x3f.
+    pub fn mode_sense(
+        &mut self,
+        page: u8,
+        subpage: u8,
+    ) -> Result<ModeSenseData, Option<SenseData>> {
+        let data = self.command(
+            &cdb::mode_sense10(page, subpage, MODE_SENSE_LEN),
+            Transfer::Read(MODE_SENSE_LEN.into()),
+        )?;
+        ModeSenseData::parse10(&data).ok_or(Error::new(Status::DEVICE_ERROR, None))
+    }
+
+    /// Reads `buffer.len() / block_size` blocks starting at `lba` with
+    /// READ(16). The length of `buffer` must be a multiple of `block_size`.
+    pub fn read16(
+        &mut self,
+        lba: Lba,
+        block_size: usize,
+        buffer: &mut [u8],
+    ) -> Result<(), Option<SenseData>> {
+        let blocks = transfer_blocks(buffer.len(), block_size)?;
+        let data = self.command(&cdb::read16(lba, blocks), Transfer::Read(buffer.len()))?;
+        if data.len() != buffer.len() {
+            return Err(Error::new(Status::DEVICE_ERROR, None));
+        }
+        buffer.copy_from_slice(&data);
+        Ok(())
+    }
+
+    /// Writes `buffer` to the blocks starting at `lba` with WRITE(16). The
+    /// length of `buffer` must be a multiple of `block_size`.
+    pub fn write16(
+        &mut self,
+        lba: Lba,
+        block_size: usize,
+        buffer: &[u8],
+    ) -> Result<(), Option<SenseData>> {
+        let blocks = transfer_blocks(buffer.len(), block_size)?;
+        self.command(&cdb::write16(lba, blocks), Transfer::Write(buffer))
+            .map(|_| ())
+    }
+
+    /// Writes the volatile cache of the device to the medium.
+    pub fn synchronize_cache(&mut self) -> Result<(), Option<SenseData>> {
+        self.command(&cdb::synchronize_cache10(), Transfer::None)
+            .map(|_| ())
+    }
+}
+
+/// Returns the number of blocks in `len` bytes.
+fn transfer_blocks(len: usize, block_size: usize) -> Result<u32, Option<SenseData>> {
+    if block_size == 0 || len % block_size != 0 {
+        return Err(Error::new(Status::BAD_BUFFER_SIZE, None));
+    }
+    u32::try_from(len / block_size).map_err(|_| Error::new(Status::BAD_BUFFER_SIZE, None))
+}
+
+/// Standard INQUIRY data.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InquiryData {
+    /// Peripheral device type, like 0x00 for disks or 0x05 for CD/DVD
+    /// drives.
+    pub peripheral_device_type: u8,
+    /// Whether the medium is removable.
+    pub removable: bool,
+    /// Version of the SCSI standard implemented by the device.
+    pub version: u8,
+    /// Vendor identification.
+    pub vendor: String,
+    /// Product identification.
+    pub product: String,
+    /// Product revision level.
+    pub revision: String,
+}
+
+impl InquiryData {
+    /// Parses the standard INQUIRY data.
+    ///
+    /// Returns `None` if `data` is shorter than 36 bytes, or if no device
+    /// is connected to the logical unit.
+    #[must_use]
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < 36 || data[0] & 0xe0 == 0x60 || data[0] & 0x1f == 0x1f {
+            return None;
+        }
+        let string = |range: core::ops::Range<usize>| {
+            String::from_utf8_lossy(&data[range])
+                .trim_matches([' ', '\0'])
+                .into()
+        };
+        Some(Self {
+            peripheral_device_type: data[0] & 0x1f,
+            removable: data[1] & 0x80 != 0,
+            version: data[2],
+            vendor: string(8..16),
+            product: string(16..32),
+            revision: string(32..36),
+        })
+    }
+}
+
+/// Capacity of a medium.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Capacity {
+    /// Address of the last logical block.
+    pub last_lba: Lba,
+    /// Size of a logical block in bytes.
+    pub block_size: u32,
+    /// Number of logical blocks per physical block, as a power of two.
+    /// Always zero with READ CAPACITY(10).
+    pub logical_blocks_per_physical_block_exponent: u8,
+}
+
+impl Capacity {
+    /// Parses the data of READ CAPACITY(10). Returns `None` if `data` is
+    /// shorter than 8 bytes.
+    #[must_use]
+    pub fn parse10(data: &[u8]) -> Option<Self> {
+        Some(Self {
+            last_lba: u32::from_be_bytes(data.get(0..4)?.try_into().ok()?).into(),
+            block_size: u32::from_be_bytes(data.get(4..8)?.try_into().ok()?),
+            logical_blocks_per_physical_block_exponent: 0,
+        })
+    }
+
+    /// Parses the data of READ CAPACITY(16). Returns `None` if `data` is
+    /// shorter than 14 bytes.
+    #[must_use]
+    pub fn parse16(data: &[u8]) -> Option<Self> {
+        Some(Self {
+            last_lba: u64::from_be_bytes(data.get(0..8)?.try_into().ok()?),
+            block_size: u32::from_be_bytes(data.get(8..12)?.try_into().ok()?),
+            logical_blocks_per_physical_block_exponent: data.get(13)? & 0xf,
+        })
+    }
+
+    /// Returns the number of logical blocks, saturating at `u64::MAX`.
+    #[must_use]
+    pub const fn block_count(&self) -> u64 {
+        self.last_lba.saturating_add(1)
+    }
+
+    /// Returns the size of the medium in bytes, saturating at `u64::MAX`.
+    #[must_use]
+    pub const fn size(&self) -> u64 {
+        self.block_count().saturating_mul(self.block_size as u64)
+    }
+}
+
+/// Page of the [`ModeSenseData`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ModePage {
+    /// Page code.
+    pub code: u8,
+    /// Subpage code, 0 for pages in the page_0 format.
+    pub subpage: u8,
+    /// Parameters of the page, after the header.
+    pub data: Vec<u8>,
+}
+
+/// Data returned by MODE SENSE(10).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ModeSenseData {
+    /// Medium type.
+    pub medium_type: u8,
+    /// Whether the medium is write-protected.
+    pub write_protected: bool,
+    /// Block descriptors.
+    pub block_descriptors: Vec<u8>,
+    /// Mode pages.
+    pub pages: Vec<ModePage>,
+}
+
+/// Code of the Caching mode page.
+const CACHING_PAGE: u8 = 0x08;
+
+impl ModeSenseData {
+    /// Parses the data of MODE SENSE(10). Returns `None` if the lengths of
+    /// the data are inconsistent.
+    #[must_use]
+    pub fn parse10(data: &[u8]) -> Option<Self> {
+        let header = data.get(..8)?;
+        let mode_data_len = usize::from(u16::from_be_bytes([header[0], header[1]])) + 2;
+        let data = &data[..mode_data_len.min(data.len())];
+        let descriptors_len = usize::from(u16::from_be_bytes([header[6], header[7]]));
+        let block_descriptors = data.get(8..8 + descriptors_len)?.to_vec();
+
+        let mut pages = Vec::new();
+        let mut rest = &data[8 + descriptors_len..];
+        while let [first, ..] = *rest {
+            // Subpages have a 4 byte header with a 16-bit length.
+            let (subpage, header_len, len) = if first & 0x40 != 0 {
+                let header = rest.get(..4)?;
+                (
+                    header[1],
+                    4,
+                    usize::from(u16::from_be_bytes([header[2], header[3]])),
+                )
+            } else {
+                (0, 2, usize::from(*rest.get(1)?))
+            };
+            pages.push(ModePage {
+                code: first & 0x3f,
+                subpage,
+                data: rest.get(header_len..header_len + len)?.to_vec(),
+            });
+            rest = &rest[header_len + len..];
+        }
+
+        Some(Self {
+            medium_type: header[2],
+            write_protected: header[3] & 0x80 != 0,
+            block_descriptors,
+            pages,
+        })
+    }
+
+    /// Returns the page `code` and `subpage`.
+    #[must_use]
+    pub fn page(&self, code: u8, subpage: u8) -> Option<&ModePage> {
+        self.pages
+            .iter()
+            .find(|page| page.code == code && page.subpage == subpage)
+    }
+
+    /// Returns whether the write cache is enabled, according to the Caching
+    /// mode page.
+    #[must_use]
+    pub fn write_cache_enabled(&self) -> Option<bool> {
+        let page = self.page(CACHING_PAGE, 0)?;
+        Some(page.data.first()? & 0x04 != 0)
+    }
+}
+
+uefi_raw::newtype_enum! {
+    /// Sense key, the general category of an error.
+    pub enum SenseKey: u8 => {
+        /// No error.
+        NO_SENSE = 0x0,
+        /// The command succeeded after a recovery action.
+        RECOVERED_ERROR = 0x1,
+        /// The logical unit is not accessible.
+        NOT_READY = 0x2,
+        /// Flaw in the medium or in the recorded data.
+        MEDIUM_ERROR = 0x3,
+        /// Non-recoverable hardware failure.
+        HARDWARE_ERROR = 0x4,
+        /// Invalid command or parameter.
+        ILLEGAL_REQUEST = 0x5,
+        /// The medium was changed, or the device was reset.
+        UNIT_ATTENTION = 0x6,
+        /// The medium is write-protected.
+        DATA_PROTECT = 0x7,
+        /// Blank or non-blank medium encountered.
+        BLANK_CHECK = 0x8,
+        /// Vendor specific condition.
+        VENDOR_SPECIFIC = 0x9,
+        /// A copy command was aborted.
+        COPY_ABORTED = 0xa,
+        /// The device aborted the command.
+        ABORTED_COMMAND = 0xb,
+        /// The end of the partition was reached.
+        VOLUME_OVERFLOW = 0xd,
+        /// The source data did not match the medium.
+        MISCOMPARE = 0xe,
+        /// The command completed.
+        COMPLETED = 0xf,
+    }
+}
+
+/// Sense data reported by a device for a failed command.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SenseData {
+    /// Sense key.
+    pub sense_key: SenseKey,
+    /// Additional sense code.
+    pub asc: u8,
+    /// Additional sense code qualifier.
+    pub ascq: u8,
+    /// Information field, usually the address of the failing block.
+    pub information: Option<u64>,
+}
+
+impl SenseData {
+    /// Parses sense data in the fixed or in the descriptor format. Returns
+    /// `None` if `data` is too short or has another format.
+    #[must_use]
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        match data.first()? & 0x7f {
+            0x70 | 0x71 => {
+                let information = u32::from_be_bytes(data.get(3..7)?.try_into().ok()?);
+                Some(Self {
+                    sense_key: SenseKey(data.get(2)? & 0xf),
+                    asc: data.get(12).copied().unwrap_or(0),
+                    ascq: data.get(13).copied().unwrap_or(0),
+                    // The VALID bit tells whether the information is set.
+                    information: (data[0] & 0x80 != 0).then_some(information.into()),
+                })
+            }
+            0x72 | 0x73 => {
+                let len = usize::from(*data.get(7)?);
+                let mut descriptors = data.get(8..)?;
+                descriptors = &descriptors[..len.min(descriptors.len())];
+                let mut information = None;
+                while let [kind, len, ..] = *descriptors {
+                    let descriptor = descriptors.get(..2 + usize::from(len))?;
+                    // Information descriptor.
+                    if kind == 0 && descriptor.len() == 12 && descriptor[2] & 0x80 != 0 {
+                        information = Some(u64::from_be_bytes(descriptor[4..12].try_into().ok()?));
+                    }
+                    descriptors = &descriptors[descriptor.len()..];
+                }
+                Some(Self {
+                    sense_key: SenseKey(data.get(1)? & 0xf),
+                    asc: *data.get(2)?,
+                    ascq: *data.get(3)?,
+                    information,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Classifies the error from the sense key and additional sense code.
+    #[must_use]
+    pub const fn error(&self) -> SenseError {
+        match (self.sense_key, self.asc, self.ascq) {
+            (SenseKey::NO_SENSE | SenseKey::RECOVERED_ERROR | SenseKey::COMPLETED, _, _) => {
+                SenseError::NoError
+            }
+            (SenseKey::NOT_READY, 0x3a, _) => SenseError::MediumNotPresent,
+            (SenseKey::NOT_READY, 0x04, 0x01) => SenseError::BecomingReady,
+            (SenseKey::NOT_READY, _, _) => SenseError::NotReady,
+            (SenseKey::MEDIUM_ERROR, 0x11, _) => SenseError::UnrecoveredReadError,
+            (SenseKey::MEDIUM_ERROR, 0x0c, _) => SenseError::WriteError,
+            (SenseKey::MEDIUM_ERROR, _, _) => SenseError::MediumError,
+            (SenseKey::HARDWARE_ERROR, _, _) => SenseError::HardwareError,
+            (SenseKey::ILLEGAL_REQUEST, 0x20, _) => SenseError::InvalidCommand,
+            (SenseKey::ILLEGAL_REQUEST, 0x21, _) => SenseError::LbaOutOfRange,
+            (SenseKey::ILLEGAL_REQUEST, 0x24, _) => SenseError::InvalidField,
+            (SenseKey::ILLEGAL_REQUEST, _, _) => SenseError::IllegalRequest,
+            (SenseKey::UNIT_ATTENTION, 0x28, _) => SenseError::MediumChanged,
+            (SenseKey::UNIT_ATTENTION, _, _) => SenseError::UnitAttention,
+            (SenseKey::DATA_PROTECT, 0x27, _) => SenseError::WriteProtected,
+            (SenseKey::DATA_PROTECT, _, _) => SenseError::DataProtect,
+            (SenseKey::ABORTED_COMMAND, _, _) => SenseError::Aborted,
+            _ => SenseError::Other,
+        }
+    }
+}
+
+impl fmt::Display for SenseData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (sense key {:#x}, ASC {:#04x}, ASCQ {:#04x})",
+            self.error(),
+            self.sense_key.0,
+            self.asc,
+            self.ascq
+        )
+    }
+}
+
+/// Error classified from [`SenseData`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SenseError {
+    /// The sense data does not report an error.
+    NoError,
+    /// No medium is inserted.
+    MediumNotPresent,
+    /// The device is spinning up.
+    BecomingReady,
+    /// The device is not ready for another reason.
+    NotReady,
+    /// Data could not be read from the medium.
+    UnrecoveredReadError,
+    /// Data could not be written to the medium.
+    WriteError,
+    /// Other medium error.
+    MediumError,
+    /// Hardware failure.
+    HardwareError,
+    /// The command is not supported.
+    InvalidCommand,
+    /// The block address is past the end of the medium.
+    LbaOutOfRange,
+    /// A field of the command or of its parameters is invalid.
+    InvalidField,
+    /// The request is invalid for another reason.
+    IllegalRequest,
+    /// The medium may have been changed.
+    MediumChanged,
+    /// The device was reset, or its state changed for another reason.
+    UnitAttention,
+    /// The medium is write-protected.
+    WriteProtected,
+    /// The data is protected for another reason.
+    DataProtect,
+    /// The device aborted the command.
+    Aborted,
+    /// Any other error.
+    Other,
+}
+
+impl fmt::Display for SenseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::NoError => "no error",
+            Self::MediumNotPresent => "medium not present",
+            Self::BecomingReady => "device becoming ready",
+            Self::NotReady => "device not ready",
+            Self::UnrecoveredReadError => "unrecovered read error",
+            Self::WriteError => "write error",
+            Self::MediumError => "medium error",
+            Self::HardwareError => "hardware error",
+            Self::InvalidCommand => "invalid command",
+            Self::LbaOutOfRange => "LBA out of range",
+            Self::InvalidField => "invalid field in command",
+            Self::IllegalRequest => "illegal request",
+            Self::MediumChanged => "medium changed",
+            Self::UnitAttention => "unit attention",
+            Self::WriteProtected => "write protected",
+            Self::DataProtect => "data protect",
+            Self::Aborted => "command aborted",
+            Self::Other => "SCSI error",
+        };
+        f.write_str(s)
+    }
+}
+
+impl core::error::Error for SenseError {}
+
+/// Maximum number of bytes transferred by one command of
+/// [`ScsiBlockDevice`].
+const MAX_TRANSFER: usize = 64 * 1024;
+
+/// [`BlockDevice`] accessing a SCSI disk with READ(16) and WRITE(16).
+///
+/// Transfers are split into commands of at most 64 KiB. The sense data of
+/// failed commands is logged, as [`BlockDevice`] only reports a
+/// [`Status`].
+#[derive(Debug)]
+pub struct ScsiBlockDevice<'a> {
+    device: ScsiDevice<'a>,
+    capacity: Capacity,
+}
+
+impl<'a> ScsiBlockDevice<'a> {
+    /// Creates a block device for the medium of `device`, after checking
+    /// that the device is ready and reading its capacity.
+    pub fn new(mut device: ScsiDevice<'a>) -> Result<Self, Option<SenseData>> {
+        device.test_unit_ready()?;
+        let capacity = device.read_capacity()?;
+        if capacity.block_size == 0 {
+            return Err(Error::new(Status::DEVICE_ERROR, None));
+        }
+        Ok(Self { device, capacity })
+    }
+
+    /// Returns the capacity of the medium.
+    #[must_use]
+    pub const fn capacity(&self) -> &Capacity {
+        &self.capacity
+    }
+
+    /// Returns the device.
+    #[must_use]
+    pub const fn device(&mut self) -> &mut ScsiDevice<'a> {
+        &mut self.device
+    }
+
+    /// Returns the number of bytes per command, a multiple of the block
+    /// size.
+    fn chunk_size(&self) -> usize {
+        let block_size = self.block_size();
+        (MAX_TRANSFER / block_size).max(1) * block_size
+    }
+}
+
+/// Logs the sense data of a failed command.
+fn log_sense(err: Error<Option<SenseData>>) -> Error {
+    if let Some(sense) = err.data() {
+        log::warn!("SCSI command failed: {sense}");
+    }
+    err.status().into()
+}
+
+impl BlockDevice for ScsiBlockDevice<'_> {
+    fn block_size(&self) -> usize {
+        self.capacity.block_size as usize
+    }
+
+    fn block_count(&self) -> u64 {
+        self.capacity.block_count()
+    }
+
+    fn read_blocks(&mut self, lba: Lba, buffer: &mut [u8]) -> Result {
+        check_range(self, lba, buffer.len())?;
+        let (block_size, chunk_size) = (self.block_size(), self.chunk_size());
+        for (i, chunk) in buffer.chunks_mut(chunk_size).enumerate() {
+            let lba = lba + (i * chunk_size / block_size) as u64;
+            self.device
+                .read16(lba, block_size, chunk)
+                .map_err(log_sense)?;
+        }
+        Ok(())
+    }
+
+    fn write_blocks(&mut self, lba: Lba, buffer: &[u8]) -> Result {
+        check_range(self, lba, buffer.len())?;
+        let (block_size, chunk_size) = (self.block_size(), self.chunk_size());
+        for (i, chunk) in buffer.chunks(chunk_size).enumerate() {
+            let lba = lba + (i * chunk_size / block_size) as u64;
+            self.device
+                .write16(lba, block_size, chunk)
+                .map_err(log_sense)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result {
+        self.device.synchronize_cache().discard_errdata()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cdb() {
+        assert_eq!(cdb::inquiry(0x1ff), [0x12, 0, 0, 0x01, 0xff, 0]);
+        assert_eq!(
+            cdb::read16(0x0102_0304_0506_0708, 0x10),
+            [0x88, 0, 1, 2, 3, 4, 5, 6, 7, 8, 0, 0, 0, 0x10, 0, 0]
+        );
+        assert_eq!(cdb::write16(1, 2)[0], 0x8a);
+        assert_eq!(
+            cdb::mode_sense10(0x3f, 0xff, 0x1000),
+            [0x5a, 0, 0x3f, 0xff, 0, 0, 0, 0x10, 0, 0]
+        );
+        assert_eq!(cdb::read_capacity16(32)[..2], [0x9e, 0x10]);
+        assert_eq!(cdb::read_capacity16(32)[13], 32);
+    }
+
+    #[test]
+    fn test_inquiry() {
+        let mut data = [b' '; 36];
+        data[..8].copy_from_slice(&[0x00, 0x80, 0x05, 0x02, 31, 0, 0, 0]);
+        data[8..15].copy_from_slice(b"uefi-rs");
+        data[16..31].copy_from_slice(b"ExtScsiPassThru");
+        data[32..36].copy_from_slice(b"2.5+");
+        let inquiry = InquiryData::parse(&data).unwrap();
+        assert_eq!(
+            inquiry,
+            InquiryData {
+                peripheral_device_type: 0,
+                removable: true,
+                version: 5,
+                vendor: "uefi-rs".into(),
+                product: "ExtScsiPassThru".into(),
+                revision: "2.5+".into(),
+            }
+        );
+
+        // No device connected.
+        data[0] = 0x7f;
+        assert_eq!(InquiryData::parse(&data), None);
+        assert_eq!(InquiryData::parse(&data[..35]), None);
+    }
+
+    #[test]
+    fn test_capacity() {
+        let capacity = Capacity::parse10(&[0, 0, 0x4f, 0xff, 0, 0, 2, 0]).unwrap();
+        assert_eq!(capacity.block_count(), 0x5000);
+        assert_eq!(capacity.size(), 10 * 1024 * 1024);
+
+        let mut data = [0; 32];
+        data[..8].copy_from_slice(&0x1_0000_0000u64.to_be_bytes());
+        data[8..12].copy_from_slice(&4096u32.to_be_bytes());
+        data[13] = 3;
+        let capacity = Capacity::parse16(&data).unwrap();
+        assert_eq!(capacity.last_lba, 0x1_0000_0000);
+        assert_eq!(capacity.block_size, 4096);
+        assert_eq!(capacity.logical_blocks_per_physical_block_exponent, 3);
+        assert_eq!(Capacity::parse16(&data[..13]), None);
+
+        data[..8].copy_from_slice(&u64::MAX.to_be_bytes());
+        let capacity = Capacity::parse16(&data).unwrap();
+        assert_eq!(capacity.block_count(), u64::MAX);
+        assert_eq!(capacity.size(), u64::MAX);
+    }
+
+    #[test]
+    fn test_mode_sense() {
+        let data = [
+            // Header with a mode data length of 24, write protection, and
+            // an 8 byte block descriptor.
+            0, 24, 0, 0x80, 0, 0, 0, 8, //
+            0, 0, 0, 0, 0, 0, 2, 0, //
+            // Caching page with WCE set.
+            0x08, 2, 0x04, 0, //
+            // Subpage 1 of page 0x0a.
+            0x4a, 1, 0, 2, 0xaa, 0xbb, //
+            // Padding past the mode data length.
+            0xff, 0xff, 0xff,
+        ];
+        let mode = ModeSenseData::parse10(&data).unwrap();
+        assert!(mode.write_protected);
+        assert_eq!(mode.block_descriptors.len(), 8);
+        assert_eq!(mode.pages.len(), 2);
+        assert_eq!(mode.write_cache_enabled(), Some(true));
+        assert_eq!(mode.page(0x0a, 1).unwrap().data, [0xaa, 0xbb]);
+
+        // Truncated page.
+        assert_eq!(ModeSenseData::parse10(&data[..21]), None);
+    }
+
+    #[test]
+    fn test_sense_data() {
+        // Fixed format, with the information field.
+        let mut data = [0; 18];
+        data[0] = 0xf0;
+        data[2] = 0x03;
+        data[3..7].copy_from_slice(&0x1234u32.to_be_bytes());
+        data[12] = 0x11;
+        let sense = SenseData::parse(&data).unwrap();
+        assert_eq!(sense.sense_key, SenseKey::MEDIUM_ERROR);
+        assert_eq!(sense.information, Some(0x1234));
+        assert_eq!(sense.error(), SenseError::UnrecoveredReadError);
+
+        // Descriptor format, with an information descriptor.
+        let mut data = [0; 20];
+        data[..8].copy_from_slice(&[0x72, 0x05, 0x21, 0x00, 0, 0, 0, 12]);
+        data[8..12].copy_from_slice(&[0x00, 0x0a, 0x80, 0]);
+        data[12..20].copy_from_slice(&0x5000u64.to_be_bytes());
+        let sense = SenseData::parse(&data).unwrap();
+        assert_eq!(sense.error(), SenseError::LbaOutOfRange);
+        assert_eq!(sense.information, Some(0x5000));
+
+        let sense = SenseData {
+            sense_key: SenseKey::NOT_READY,
+            asc: 0x3a,
+            ascq: 0,
+            information: None,
+        };
+        assert_eq!(sense.error(), SenseError::MediumNotPresent);
+        assert_eq!(
+            alloc::format!("{sense}"),
+            "medium not present (sense key 0x2, ASC 0x3a, ASCQ 0x00)"
+        );
+
+        assert_eq!(SenseData::parse(&[0x00; 18]), None);
+        assert_eq!(SenseData::parse(&[]), None);
+    }
+}
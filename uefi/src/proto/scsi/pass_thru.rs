@@ -134,6 +134,11 @@ impl ScsiDevice<'_> {
         ptr::from_ref(self.proto).cast_mut()
     }
 
+    /// Returns the alignment requirement of the channel for I/O buffers.
+    pub(super) fn io_align(&self) -> u32 {
+        unsafe { (*self.proto.passthru_mode).io_align.max(1) }
+    }
+
     /// Returns the SCSI target address of the potential device.
     #[must_use]
     pub const fn target(&self) -> &ScsiTarget {
@@ -0,0 +1,758 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! USB descriptor tree.
+//!
+//! [`UsbIo`] only returns the descriptors of the active configuration, its
+//! interface and its endpoints one by one. The methods of this module read
+//! the full configuration descriptor from the device with a GET_DESCRIPTOR
+//! control transfer, and parse it into a [`Configuration`] tree: its
+//! interfaces, their alternate settings and endpoints, and the class-specific
+//! descriptors attached to each of them.
+//!
+//! # Example
+//!
+//! ```
+//! use uefi::proto::usb::descriptor::class;
+//! use uefi::proto::usb::io::UsbIo;
+//! use uefi::proto::usb::UsbTransferStatus;
+//!
+//! fn log_hid_interfaces(io: &mut UsbIo) -> uefi::Result<(), UsbTransferStatus> {
+//!     let configuration = io.active_configuration()?;
+//!     for interface in &configuration.interfaces {
+//!         for setting in &interface.alternate_settings {
+//!             if setting.descriptor.interface_class != class::HID {
+//!                 continue;
+//!             }
+//!             if let Some(hid) = setting.hid() {
+//!                 log::info!(
+//!                     "HID interface {}: {:?} byte report descriptor",
+//!                     interface.number,
+//!                     hid.report_descriptor_length()
+//!                 );
+//!             }
+//!         }
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use super::io::{ControlTransfer, UsbIo};
+use crate::{Error, Result, Status};
+use alloc::vec::Vec;
+use uefi_raw::protocol::usb::{
+    ConfigDescriptor, EndpointDescriptor, InterfaceDescriptor, UsbTransferStatus,
+};
+
+/// Descriptor types.
+pub mod descriptor_type {
+    /// Device descriptor.
+    pub const DEVICE: u8 = 0x01;
+    /// Configuration descriptor.
+    pub const CONFIGURATION: u8 = 0x02;
+    /// String descriptor.
+    pub const STRING: u8 = 0x03;
+    /// Interface descriptor.
+    pub const INTERFACE: u8 = 0x04;
+    /// Endpoint descriptor.
+    pub const ENDPOINT: u8 = 0x05;
+    /// Interface association descriptor.
+    pub const INTERFACE_ASSOCIATION: u8 = 0x0b;
+    /// HID descriptor.
+    pub const HID: u8 = 0x21;
+    /// HID report descriptor.
+    pub const HID_REPORT: u8 = 0x22;
+    /// Class-specific interface descriptor.
+    pub const CS_INTERFACE: u8 = 0x24;
+    /// Class-specific endpoint descriptor.
+    pub const CS_ENDPOINT: u8 = 0x25;
+}
+
+/// Device and interface class codes.
+pub mod class {
+    /// Audio.
+    pub const AUDIO: u8 = 0x01;
+    /// Communications Device Class, control interfaces.
+    pub const CDC: u8 = 0x02;
+    /// Human Interface Device.
+    pub const HID: u8 = 0x03;
+    /// Mass storage.
+    pub const MASS_STORAGE: u8 = 0x08;
+    /// Hub.
+    pub const HUB: u8 = 0x09;
+    /// Communications Device Class, data interfaces.
+    pub const CDC_DATA: u8 = 0x0a;
+    /// Video.
+    pub const VIDEO: u8 = 0x0e;
+    /// Miscellaneous, used by devices with interface associations.
+    pub const MISCELLANEOUS: u8 = 0xef;
+    /// Vendor-specific.
+    pub const VENDOR_SPECIFIC: u8 = 0xff;
+}
+
+/// GET_DESCRIPTOR standard request.
+const GET_DESCRIPTOR: u8 = 0x06;
+
+/// Standard request to the device.
+const STANDARD_DEVICE_REQUEST: u8 = 0x00;
+
+/// Size of the configuration descriptor header.
+const CONFIG_DESCRIPTOR_SIZE: usize = 9;
+
+/// Timeout of the GET_DESCRIPTOR requests, in milliseconds.
+const GET_DESCRIPTOR_TIMEOUT_MS: u32 = 1000;
+
+impl UsbIo {
+    /// Reads the full configuration descriptor `index` from the device,
+    /// including the interface, endpoint and class-specific descriptors
+    /// following it.
+    ///
+    /// `index` is the index of the configuration, from zero to the number of
+    /// configurations of the device descriptor, and not its
+    /// `configuration_value`.
+    ///
+    /// # Errors
+    ///
+    /// * Errors of [`control_transfer`].
+    /// * [`Status::PROTOCOL_ERROR`]: the device returned a truncated
+    ///   descriptor.
+    ///
+    /// [`control_transfer`]: Self::control_transfer
+    pub fn raw_config_descriptor(&mut self, index: u8) -> Result<Vec<u8>, UsbTransferStatus> {
+        // Read the header first for the total length.
+        let mut header = [0; CONFIG_DESCRIPTOR_SIZE];
+        self.get_config_descriptor(index, &mut header)?;
+        let total_length = usize::from(header_total_length(&header));
+        if header[1] != descriptor_type::CONFIGURATION || total_length < CONFIG_DESCRIPTOR_SIZE {
+            return Err(protocol_error());
+        }
+
+        let mut data = alloc::vec![0; total_length];
+        self.get_config_descriptor(index, &mut data)?;
+        if header_total_length(&data) != header_total_length(&header) {
+            return Err(protocol_error());
+        }
+        Ok(data)
+    }
+
+    /// Reads and parses the configuration descriptor `index`.
+    ///
+    /// # Errors
+    ///
+    /// * Errors of [`raw_config_descriptor`].
+    /// * [`Status::PROTOCOL_ERROR`]: the descriptor is malformed.
+    ///
+    /// [`raw_config_descriptor`]: Self::raw_config_descriptor
+    pub fn configuration(&mut self, index: u8) -> Result<Configuration, UsbTransferStatus> {
+        let data = self.raw_config_descriptor(index)?;
+        Configuration::parse(&data).ok_or_else(protocol_error)
+    }
+
+    /// Reads and parses the active configuration descriptor.
+    ///
+    /// # Errors
+    ///
+    /// * Errors of [`configuration`].
+    /// * [`Status::NOT_FOUND`]: none of the configurations of the device is
+    ///   the active one.
+    ///
+    /// [`configuration`]: Self::configuration
+    pub fn active_configuration(&mut self) -> Result<Configuration, UsbTransferStatus> {
+        let to_transfer_error = |err: Error| Error::new(err.status(), UsbTransferStatus::default());
+        let device = self.device_descriptor().map_err(to_transfer_error)?;
+        let active = self.config_descriptor().map_err(to_transfer_error)?;
+        for index in 0..device.num_configurations {
+            let configuration = self.configuration(index)?;
+            if configuration.descriptor.configuration_value == active.configuration_value {
+                return Ok(configuration);
+            }
+        }
+        Err(Error::new(Status::NOT_FOUND, UsbTransferStatus::default()))
+    }
+
+    fn get_config_descriptor(
+        &mut self,
+        index: u8,
+        buffer: &mut [u8],
+    ) -> Result<(), UsbTransferStatus> {
+        self.control_transfer(
+            STANDARD_DEVICE_REQUEST,
+            GET_DESCRIPTOR,
+            (u16::from(descriptor_type::CONFIGURATION) << 8) | u16::from(index),
+            0,
+            ControlTransfer::DataIn(buffer),
+            GET_DESCRIPTOR_TIMEOUT_MS,
+        )
+    }
+}
+
+const fn header_total_length(header: &[u8]) -> u16 {
+    u16::from_le_bytes([header[2], header[3]])
+}
+
+fn protocol_error() -> Error<UsbTransferStatus> {
+    Error::new(Status::PROTOCOL_ERROR, UsbTransferStatus::default())
+}
+
+/// Parsed configuration descriptor tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Configuration {
+    /// Configuration descriptor.
+    pub descriptor: ConfigDescriptor,
+    /// Interfaces, sorted by interface number.
+    pub interfaces: Vec<Interface>,
+    /// Descriptors not attached to an interface, like the interface
+    /// association descriptors.
+    pub extra: Vec<ClassDescriptor>,
+}
+
+impl Configuration {
+    /// Parses the full configuration descriptor in `data`, as returned by
+    /// [`UsbIo::raw_config_descriptor`].
+    ///
+    /// Data past the total length of the descriptor is ignored. Returns
+    /// `None` if a descriptor is truncated, or if an endpoint descriptor is
+    /// not preceded by an interface descriptor.
+    #[must_use]
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < CONFIG_DESCRIPTOR_SIZE
+            || usize::from(data[0]) < CONFIG_DESCRIPTOR_SIZE
+            || data[1] != descriptor_type::CONFIGURATION
+        {
+            return None;
+        }
+        let total_length = usize::from(header_total_length(data));
+        let data = data.get(..total_length)?;
+        // The configuration descriptor itself may be longer than the total
+        // length reported by the device.
+        let mut rest = data.get(usize::from(data[0])..)?;
+
+        let mut configuration = Self {
+            descriptor: ConfigDescriptor {
+                length: data[0],
+                descriptor_type: data[1],
+                total_length: header_total_length(data),
+                num_interfaces: data[4],
+                configuration_value: data[5],
+                configuration: data[6],
+                attributes: data[7],
+                max_power: data[8],
+            },
+            interfaces: Vec::new(),
+            extra: Vec::new(),
+        };
+
+        // The class-specific descriptors belong to the last endpoint, or to
+        // the last alternate setting if it has no endpoint yet.
+        while !rest.is_empty() {
+            let len = usize::from(rest[0]);
+            if len < 2 || len > rest.len() {
+                return None;
+            }
+            let (descriptor, tail) = rest.split_at(len);
+            rest = tail;
+
+            match descriptor[1] {
+                descriptor_type::INTERFACE => {
+                    let setting = AlternateSetting::parse(descriptor)?;
+                    let number = setting.descriptor.interface_number;
+                    let index = match configuration
+                        .interfaces
+                        .iter()
+                        .position(|interface| interface.number == number)
+                    {
+                        Some(index) => index,
+                        None => {
+                            configuration.interfaces.push(Interface {
+                                number,
+                                alternate_settings: Vec::new(),
+                            });
+                            configuration.interfaces.len() - 1
+                        }
+                    };
+                    // Move the interface to the end, so that the following
+                    // descriptors are attached to it.
+                    let interface = configuration.interfaces.remove(index);
+                    configuration.interfaces.push(interface);
+                    configuration
+                        .last_interface()?
+                        .alternate_settings
+                        .push(setting);
+                }
+                descriptor_type::ENDPOINT => {
+                    let endpoint = Endpoint::parse(descriptor)?;
+                    configuration
+                        .last_interface()?
+                        .alternate_settings
+                        .last_mut()?
+                        .endpoints
+                        .push(endpoint);
+                }
+                _ => {
+                    let class_descriptor = ClassDescriptor {
+                        descriptor_type: descriptor[1],
+                        data: descriptor[2..].to_vec(),
+                    };
+                    match configuration
+                        .interfaces
+                        .last_mut()
+                        .and_then(|interface| interface.alternate_settings.last_mut())
+                    {
+                        Some(setting) => match setting.endpoints.last_mut() {
+                            Some(endpoint) => endpoint.class_descriptors.push(class_descriptor),
+                            None => setting.class_descriptors.push(class_descriptor),
+                        },
+                        None => configuration.extra.push(class_descriptor),
+                    }
+                }
+            }
+        }
+
+        // Restore the order of the interface numbers.
+        configuration
+            .interfaces
+            .sort_by_key(|interface| interface.number);
+        Some(configuration)
+    }
+
+    fn last_interface(&mut self) -> Option<&mut Interface> {
+        self.interfaces.last_mut()
+    }
+
+    /// Returns the interface `number`.
+    #[must_use]
+    pub fn interface(&self, number: u8) -> Option<&Interface> {
+        self.interfaces
+            .iter()
+            .find(|interface| interface.number == number)
+    }
+
+    /// Returns whether the device is self-powered in this configuration.
+    #[must_use]
+    pub const fn self_powered(&self) -> bool {
+        self.descriptor.attributes & (1 << 6) != 0
+    }
+
+    /// Returns whether the device supports remote wakeup in this
+    /// configuration.
+    #[must_use]
+    pub const fn remote_wakeup(&self) -> bool {
+        self.descriptor.attributes & (1 << 5) != 0
+    }
+}
+
+/// Interface of a [`Configuration`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Interface {
+    /// Interface number.
+    pub number: u8,
+    /// Alternate settings of the interface, in the order of the descriptor.
+    /// The first one is the default setting.
+    pub alternate_settings: Vec<AlternateSetting>,
+}
+
+/// Alternate setting of an [`Interface`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AlternateSetting {
+    /// Interface descriptor of the alternate setting.
+    pub descriptor: InterfaceDescriptor,
+    /// Endpoints of the alternate setting.
+    pub endpoints: Vec<Endpoint>,
+    /// Class-specific descriptors of the alternate setting, like the HID
+    /// descriptor or the CDC functional descriptors.
+    pub class_descriptors: Vec<ClassDescriptor>,
+}
+
+impl AlternateSetting {
+    fn parse(data: &[u8]) -> Option<Self> {
+        let data = data.get(..9)?;
+        Some(Self {
+            descriptor: InterfaceDescriptor {
+                length: data[0],
+                descriptor_type: data[1],
+                interface_number: data[2],
+                alternate_setting: data[3],
+                num_endpoints: data[4],
+                interface_class: data[5],
+                interface_subclass: data[6],
+                interface_protocol: data[7],
+                interface: data[8],
+            },
+            endpoints: Vec::new(),
+            class_descriptors: Vec::new(),
+        })
+    }
+
+    /// Returns the HID descriptor of the alternate setting.
+    #[must_use]
+    pub fn hid(&self) -> Option<HidDescriptor> {
+        self.class_descriptors.iter().find_map(ClassDescriptor::hid)
+    }
+
+    /// Returns an iterator over the CDC functional descriptors of the
+    /// alternate setting.
+    ///
+    /// The descriptors are only CDC functional descriptors for interfaces of
+    /// the [`class::CDC`] class, other classes use the same descriptor type.
+    pub fn cdc(&self) -> impl Iterator<Item = CdcDescriptor> + '_ {
+        self.class_descriptors
+            .iter()
+            .filter_map(ClassDescriptor::cdc)
+    }
+}
+
+/// Endpoint of an [`AlternateSetting`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Endpoint {
+    /// Endpoint descriptor.
+    pub descriptor: EndpointDescriptor,
+    /// Class-specific descriptors of the endpoint.
+    pub class_descriptors: Vec<ClassDescriptor>,
+}
+
+impl Endpoint {
+    fn parse(data: &[u8]) -> Option<Self> {
+        let data = data.get(..7)?;
+        Some(Self {
+            descriptor: EndpointDescriptor {
+                length: data[0],
+                descriptor_type: data[1],
+                endpoint_address: data[2],
+                attributes: data[3],
+                max_packet_size: u16::from_le_bytes([data[4], data[5]]),
+                interval: data[6],
+            },
+            class_descriptors: Vec::new(),
+        })
+    }
+
+    /// Returns the endpoint address, including the direction bit.
+    #[must_use]
+    pub const fn address(&self) -> u8 {
+        self.descriptor.endpoint_address
+    }
+
+    /// Returns whether the endpoint transfers data from the device to the
+    /// host.
+    #[must_use]
+    pub const fn is_in(&self) -> bool {
+        self.descriptor.endpoint_address & 0x80 != 0
+    }
+
+    /// Returns the transfer type of the endpoint.
+    #[must_use]
+    pub const fn transfer_type(&self) -> TransferType {
+        match self.descriptor.attributes & 0b11 {
+            0 => TransferType::Control,
+            1 => TransferType::Isochronous,
+            2 => TransferType::Bulk,
+            _ => TransferType::Interrupt,
+        }
+    }
+
+    /// Returns the maximum packet size of the endpoint, in bytes.
+    #[must_use]
+    pub const fn max_packet_size(&self) -> u16 {
+        self.descriptor.max_packet_size & 0x7ff
+    }
+}
+
+/// Transfer type of an [`Endpoint`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransferType {
+    /// Control transfers.
+    Control,
+    /// Isochronous transfers.
+    Isochronous,
+    /// Bulk transfers.
+    Bulk,
+    /// Interrupt transfers.
+    Interrupt,
+}
+
+/// Descriptor which is not an interface or endpoint descriptor, usually a
+/// class-specific descriptor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClassDescriptor {
+    /// Type of the descriptor.
+    pub descriptor_type: u8,
+    /// Data of the descriptor, following the length and type.
+    pub data: Vec<u8>,
+}
+
+impl ClassDescriptor {
+    /// Parses the descriptor as a HID descriptor.
+    ///
+    /// Returns `None` if it is not a HID descriptor, or if it is truncated.
+    #[must_use]
+    pub fn hid(&self) -> Option<HidDescriptor> {
+        if self.descriptor_type != descriptor_type::HID {
+            return None;
+        }
+        let header = self.data.get(..4)?;
+        let num_descriptors = usize::from(header[3]);
+        let descriptors = self
+            .data
+            .get(4..4 + 3 * num_descriptors)?
+            .chunks_exact(3)
+            .map(|descriptor| {
+                (
+                    descriptor[0],
+                    u16::from_le_bytes([descriptor[1], descriptor[2]]),
+                )
+            })
+            .collect();
+        Some(HidDescriptor {
+            bcd_hid: u16::from_le_bytes([header[0], header[1]]),
+            country_code: header[2],
+            descriptors,
+        })
+    }
+
+    /// Parses the descriptor as a CDC functional descriptor.
+    ///
+    /// Returns `None` if it is not a class-specific interface descriptor, or
+    /// if it is truncated.
+    #[must_use]
+    pub fn cdc(&self) -> Option<CdcDescriptor> {
+        if self.descriptor_type != descriptor_type::CS_INTERFACE {
+            return None;
+        }
+        let (&subtype, data) = self.data.split_first()?;
+        let le_u16 = |offset: usize| {
+            Some(u16::from_le_bytes([
+                *data.get(offset)?,
+                *data.get(offset + 1)?,
+            ]))
+        };
+        Some(match subtype {
+            0x00 => CdcDescriptor::Header {
+                bcd_cdc: le_u16(0)?,
+            },
+            0x01 => CdcDescriptor::CallManagement {
+                capabilities: *data.first()?,
+                data_interface: *data.get(1)?,
+            },
+            0x02 => CdcDescriptor::AbstractControlManagement {
+                capabilities: *data.first()?,
+            },
+            0x06 => CdcDescriptor::Union {
+                control_interface: *data.first()?,
+                subordinate_interfaces: data[1..].to_vec(),
+            },
+            0x0f => CdcDescriptor::EthernetNetworking {
+                mac_address_index: *data.first()?,
+                statistics: u32::from_le_bytes(data.get(1..5)?.try_into().unwrap()),
+                max_segment_size: le_u16(5)?,
+                num_multicast_filters: le_u16(7)?,
+                num_power_filters: *data.get(9)?,
+            },
+            _ => CdcDescriptor::Other {
+                subtype,
+                data: data.to_vec(),
+            },
+        })
+    }
+}
+
+/// HID descriptor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HidDescriptor {
+    /// Version of the HID specification, in BCD.
+    pub bcd_hid: u16,
+    /// Country code of localized hardware, or zero.
+    pub country_code: u8,
+    /// Type and length of the class descriptors of the HID device, like the
+    /// report descriptor.
+    pub descriptors: Vec<(u8, u16)>,
+}
+
+impl HidDescriptor {
+    /// Returns the length of the report descriptor, in bytes.
+    #[must_use]
+    pub fn report_descriptor_length(&self) -> Option<u16> {
+        self.descriptors
+            .iter()
+            .find(|(descriptor_type, _)| *descriptor_type == descriptor_type::HID_REPORT)
+            .map(|(_, length)| *length)
+    }
+}
+
+/// CDC functional descriptor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CdcDescriptor {
+    /// Header functional descriptor, first of the functional descriptors.
+    Header {
+        /// Version of the CDC specification, in BCD.
+        bcd_cdc: u16,
+    },
+    /// Call management functional descriptor.
+    CallManagement {
+        /// Call management capabilities.
+        capabilities: u8,
+        /// Data interface used for call management.
+        data_interface: u8,
+    },
+    /// Abstract control management functional descriptor.
+    AbstractControlManagement {
+        /// Supported requests and notifications.
+        capabilities: u8,
+    },
+    /// Union functional descriptor, grouping the interfaces of the function.
+    Union {
+        /// Control interface of the function.
+        control_interface: u8,
+        /// Other interfaces of the function, usually the data interface.
+        subordinate_interfaces: Vec<u8>,
+    },
+    /// Ethernet networking functional descriptor.
+    EthernetNetworking {
+        /// Index of the string descriptor holding the MAC address.
+        mac_address_index: u8,
+        /// Ethernet statistics collected by the device.
+        statistics: u32,
+        /// Maximum segment size, in bytes.
+        max_segment_size: u16,
+        /// Number of multicast filters, with the imperfect filtering flag.
+        num_multicast_filters: u16,
+        /// Number of power filters.
+        num_power_filters: u8,
+    },
+    /// Other functional descriptor.
+    Other {
+        /// Subtype of the descriptor.
+        subtype: u8,
+        /// Data of the descriptor, following the subtype.
+        data: Vec<u8>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Configuration of a CDC ECM device with a HID interface.
+    #[rustfmt::skip]
+    const CONFIGURATION: &[u8] = &[
+        // Configuration.
+        9, 0x02, 99, 0, 3, 1, 0, 0xc0, 50,
+        // Interface association.
+        8, 0x0b, 0, 2, 0x02, 0x06, 0x00, 0,
+        // CDC control interface.
+        9, 0x04, 0, 0, 1, 0x02, 0x06, 0x00, 0,
+        5, 0x24, 0x00, 0x10, 0x01,
+        5, 0x24, 0x06, 0, 1,
+        13, 0x24, 0x0f, 3, 0, 0, 0, 0, 0xea, 0x05, 0, 0, 0,
+        7, 0x05, 0x81, 0x03, 16, 0, 9,
+        // CDC data interface, without and with endpoints.
+        9, 0x04, 1, 0, 0, 0x0a, 0x00, 0x00, 0,
+        // HID interface.
+        9, 0x04, 2, 0, 1, 0x03, 0x01, 0x01, 0,
+        9, 0x21, 0x11, 0x01, 0, 1, 0x22, 63, 0,
+        7, 0x05, 0x83, 0x03, 8, 0, 10,
+        // Second alternate setting of the CDC data interface.
+        9, 0x04, 1, 1, 2, 0x0a, 0x00, 0x00, 0,
+    ];
+
+    #[test]
+    fn test_parse_configuration() {
+        let mut data = CONFIGURATION.to_vec();
+        data.extend_from_slice(&[
+            7, 0x05, 0x82, 0x02, 0x00, 0x02, 0, //
+            7, 0x05, 0x02, 0x02, 0x00, 0x02, 0,
+        ]);
+        data[2] = data.len() as u8;
+        // Data past the total length is ignored.
+        data.extend_from_slice(&[0xff; 4]);
+        let configuration = Configuration::parse(&data).unwrap();
+
+        assert_eq!(configuration.descriptor.num_interfaces, 3);
+        assert!(configuration.self_powered());
+        assert!(!configuration.remote_wakeup());
+        assert_eq!(
+            configuration.extra,
+            [ClassDescriptor {
+                descriptor_type: descriptor_type::INTERFACE_ASSOCIATION,
+                data: alloc::vec![0, 2, 0x02, 0x06, 0x00, 0],
+            }]
+        );
+        let numbers: Vec<u8> = configuration
+            .interfaces
+            .iter()
+            .map(|interface| interface.number)
+            .collect();
+        assert_eq!(numbers, [0, 1, 2]);
+
+        let control = &configuration.interface(0).unwrap().alternate_settings[0];
+        assert_eq!(control.descriptor.interface_class, class::CDC);
+        assert_eq!(
+            control.cdc().collect::<Vec<_>>(),
+            [
+                CdcDescriptor::Header { bcd_cdc: 0x0110 },
+                CdcDescriptor::Union {
+                    control_interface: 0,
+                    subordinate_interfaces: alloc::vec![1],
+                },
+                CdcDescriptor::EthernetNetworking {
+                    mac_address_index: 3,
+                    statistics: 0,
+                    max_segment_size: 1514,
+                    num_multicast_filters: 0,
+                    num_power_filters: 0,
+                },
+            ]
+        );
+        assert_eq!(control.endpoints.len(), 1);
+        assert_eq!(
+            control.endpoints[0].transfer_type(),
+            TransferType::Interrupt
+        );
+
+        let data = configuration.interface(1).unwrap();
+        assert_eq!(data.alternate_settings.len(), 2);
+        assert!(data.alternate_settings[0].endpoints.is_empty());
+        let endpoints = &data.alternate_settings[1].endpoints;
+        assert_eq!(endpoints.len(), 2);
+        assert!(endpoints[0].is_in());
+        assert!(!endpoints[1].is_in());
+        assert_eq!(endpoints[1].address(), 0x02);
+        assert_eq!(endpoints[1].transfer_type(), TransferType::Bulk);
+        assert_eq!(endpoints[1].max_packet_size(), 512);
+
+        let hid = configuration.interface(2).unwrap().alternate_settings[0]
+            .hid()
+            .unwrap();
+        assert_eq!(hid.bcd_hid, 0x0111);
+        assert_eq!(hid.report_descriptor_length(), Some(63));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        // Too short for the configuration descriptor.
+        assert!(Configuration::parse(&CONFIGURATION[..8]).is_none());
+        // Total length past the end of the data.
+        assert!(Configuration::parse(&CONFIGURATION[..40]).is_none());
+
+        // Truncated descriptor.
+        let mut data = CONFIGURATION.to_vec();
+        data.push(9);
+        data[2] += 1;
+        assert!(Configuration::parse(&data).is_none());
+
+        // Endpoint without an interface.
+        let data = [
+            9, 0x02, 16, 0, 0, 1, 0, 0x80, 50, 7, 0x05, 0x81, 0x03, 8, 0, 10,
+        ];
+        assert!(Configuration::parse(&data).is_none());
+
+        // Zero length descriptor.
+        let data = [9, 0x02, 11, 0, 0, 1, 0, 0x80, 50, 0, 0x04];
+        assert!(Configuration::parse(&data).is_none());
+
+        // Configuration descriptor longer than the total length.
+        let data = [12, 0x02, 10, 0, 0, 1, 0, 0x80, 50, 0, 0, 0];
+        assert!(Configuration::parse(&data).is_none());
+        // Total length shorter than the configuration descriptor.
+        let data = [9, 0x02, 4, 0, 0, 1, 0, 0x80, 50];
+        assert!(Configuration::parse(&data).is_none());
+    }
+}
@@ -0,0 +1,127 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Enumeration of the USB devices.
+//!
+//! [`devices`] walks the handles supporting [`UsbIo`] and returns their
+//! descriptors, with the string descriptors resolved.
+//!
+//! # Example
+//!
+//! ```
+//! use uefi::proto::usb::enumerate;
+//!
+//! fn list_usb_devices() -> uefi::Result {
+//!     for device in enumerate::devices()? {
+//!         log::info!(
+//!             "{:04x}:{:04x} {} {}",
+//!             device.device.id_vendor,
+//!             device.device.id_product,
+//!             device.manufacturer.as_deref().unwrap_or("?"),
+//!             device.product.as_deref().unwrap_or("?"),
+//!         );
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use super::descriptor::Configuration;
+use super::io::UsbIo;
+use crate::boot::{self, OpenProtocolAttributes, OpenProtocolParams, SearchType};
+use crate::{Handle, Result, Status};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use uefi_raw::protocol::usb::{DeviceDescriptor, InterfaceDescriptor};
+
+/// Language of the strings if the device does not list its languages:
+/// English (United States).
+const DEFAULT_LANGUAGE: u16 = 0x0409;
+
+/// USB interface found by [`devices`].
+///
+/// The USB bus driver installs [`UsbIo`] on a handle per interface of the
+/// active configuration, so a device with several interfaces has several
+/// entries, with the same device descriptor.
+#[derive(Clone, Debug)]
+pub struct UsbDeviceInfo {
+    /// Handle supporting [`UsbIo`].
+    pub handle: Handle,
+    /// Device descriptor.
+    pub device: DeviceDescriptor,
+    /// Descriptor of the interface of the handle.
+    pub interface: InterfaceDescriptor,
+    /// Manufacturer string, if the device has one.
+    pub manufacturer: Option<String>,
+    /// Product string, if the device has one.
+    pub product: Option<String>,
+    /// Serial number string, if the device has one.
+    pub serial_number: Option<String>,
+    /// Active configuration, if it could be read and parsed.
+    pub configuration: Option<Configuration>,
+}
+
+/// Returns the USB interfaces of the system.
+///
+/// The string descriptors are read in the first language supported by the
+/// device. Strings which cannot be read are `None`.
+///
+/// # Errors
+///
+/// * [`Status::OUT_OF_RESOURCES`]: the handles could not be listed.
+/// * Errors of [`UsbIo::device_descriptor`] and
+///   [`UsbIo::interface_descriptor`].
+pub fn devices() -> Result<Vec<UsbDeviceInfo>> {
+    let handles = match boot::locate_handle_buffer(SearchType::from_proto::<UsbIo>()) {
+        Ok(handles) => handles,
+        Err(err) if err.status() == Status::NOT_FOUND => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+
+    handles.iter().map(|&handle| device_info(handle)).collect()
+}
+
+fn device_info(handle: Handle) -> Result<UsbDeviceInfo> {
+    // The USB class drivers open `UsbIo` by driver, so it cannot be opened
+    // exclusively without disconnecting them.
+    //
+    // SAFETY: the protocol is only used until the end of this function, and
+    // nothing here uninstalls it.
+    let mut io = unsafe {
+        boot::open_protocol::<UsbIo>(
+            OpenProtocolParams {
+                handle,
+                agent: boot::image_handle(),
+                controller: None,
+            },
+            OpenProtocolAttributes::GetProtocol,
+        )
+    }?;
+
+    let device = io.device_descriptor()?;
+    let interface = io.interface_descriptor()?;
+    let language = io
+        .supported_languages()
+        .ok()
+        .and_then(|languages| languages.first().copied())
+        .unwrap_or(DEFAULT_LANGUAGE);
+    let mut string = |index: u8| {
+        if index == 0 {
+            return None;
+        }
+        io.string_descriptor(language, index)
+            .ok()
+            .map(|string| string.to_string())
+    };
+    let manufacturer = string(device.str_manufacturer);
+    let product = string(device.str_product);
+    let serial_number = string(device.str_serial_number);
+
+    Ok(UsbDeviceInfo {
+        handle,
+        device,
+        interface,
+        manufacturer,
+        product,
+        serial_number,
+        configuration: io.active_configuration().ok(),
+    })
+}
@@ -0,0 +1,1112 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! USB Human Interface Device class helpers.
+//!
+//! HID devices can be driven directly through [`UsbIo`], without the
+//! firmware keyboard and mouse drivers:
+//!
+//! * The class requests, like [`UsbIo::hid_set_protocol`], configure the
+//!   device and read its reports.
+//! * [`ReportDescriptor`] parses the report descriptor of the device, which
+//!   describes the layout of its reports.
+//! * [`KeyboardReport`] and [`MouseReport`] decode the reports of the boot
+//!   protocol, and [`BootKeyboard`] turns the keyboard reports into the
+//!   [`KeyStroke`]s of [`uefi::proto::console::text`].
+//!
+//! # Example
+//!
+//! ```
+//! use uefi::proto::usb::UsbTransferStatus;
+//! use uefi::proto::usb::hid::{BootKeyboard, HidProtocol, KeyboardReport};
+//! use uefi::proto::usb::io::UsbIo;
+//!
+//! fn read_keys(
+//!     io: &mut UsbIo,
+//!     interface: u8,
+//!     endpoint: u8,
+//! ) -> uefi::Result<(), UsbTransferStatus> {
+//!     io.hid_set_protocol(interface, HidProtocol::Boot)?;
+//!     // Only report changes.
+//!     io.hid_set_idle(interface, 0, 0)?;
+//!
+//!     let mut keyboard = BootKeyboard::new();
+//!     let mut buffer = [0; 8];
+//!     loop {
+//!         let len = io.sync_interrupt_receive(endpoint, &mut buffer, 1000)?;
+//!         let Some(report) = KeyboardReport::parse(&buffer[..len]) else {
+//!             continue;
+//!         };
+//!         for stroke in keyboard.update(&report) {
+//!             log::info!("{:?}", stroke.key);
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! [`uefi::proto::console::text`]: crate::proto::console::text
+
+use super::UsbTransferStatus;
+use super::descriptor::descriptor_type;
+use super::io::{ControlTransfer, UsbIo};
+use crate::proto::console::pointer::PointerState;
+use crate::proto::console::text::{Key, KeyShiftState, KeyStroke, KeyToggleState, ScanCode};
+use crate::{Char16, Result};
+use alloc::vec::Vec;
+use bitflags::bitflags;
+
+/// Usage pages.
+pub mod usage_page {
+    /// Generic Desktop Controls.
+    pub const GENERIC_DESKTOP: u16 = 0x01;
+    /// Keyboard/Keypad.
+    pub const KEYBOARD: u16 = 0x07;
+    /// LEDs.
+    pub const LED: u16 = 0x08;
+    /// Buttons.
+    pub const BUTTON: u16 = 0x09;
+    /// Consumer devices.
+    pub const CONSUMER: u16 = 0x0c;
+}
+
+/// HID class requests.
+mod request {
+    pub const GET_REPORT: u8 = 0x01;
+    pub const SET_REPORT: u8 = 0x09;
+    pub const SET_IDLE: u8 = 0x0a;
+    pub const SET_PROTOCOL: u8 = 0x0b;
+}
+
+/// GET_DESCRIPTOR standard request.
+const GET_DESCRIPTOR: u8 = 0x06;
+
+/// Standard request to an interface.
+const STANDARD_INTERFACE_REQUEST: u8 = 0x01;
+
+/// Class request to an interface.
+const CLASS_INTERFACE_REQUEST: u8 = 0x21;
+
+/// Timeout of the class requests, in milliseconds.
+const REQUEST_TIMEOUT_MS: u32 = 1000;
+
+/// Protocol of a HID device supporting the boot protocol.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HidProtocol {
+    /// Boot protocol: the reports have the fixed layout of [`KeyboardReport`]
+    /// or [`MouseReport`].
+    Boot,
+    /// Report protocol: the reports are described by the report descriptor.
+    Report,
+}
+
+/// Type of a report.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ReportType {
+    /// Report sent by the device, like the keys pressed.
+    Input,
+    /// Report sent to the device, like the keyboard LEDs.
+    Output,
+    /// Configuration report.
+    Feature,
+}
+
+impl ReportType {
+    const fn value(self) -> u16 {
+        match self {
+            Self::Input => 1,
+            Self::Output => 2,
+            Self::Feature => 3,
+        }
+    }
+}
+
+impl UsbIo {
+    /// Reads the report descriptor of the HID interface `interface`.
+    ///
+    /// `length` is the length of the report descriptor, from
+    /// [`HidDescriptor::report_descriptor_length`].
+    ///
+    /// [`HidDescriptor::report_descriptor_length`]: super::descriptor::HidDescriptor::report_descriptor_length
+    pub fn hid_report_descriptor(
+        &mut self,
+        interface: u8,
+        length: u16,
+    ) -> Result<Vec<u8>, UsbTransferStatus> {
+        let mut descriptor = alloc::vec![0; usize::from(length)];
+        self.control_transfer(
+            STANDARD_INTERFACE_REQUEST,
+            GET_DESCRIPTOR,
+            u16::from(descriptor_type::HID_REPORT) << 8,
+            u16::from(interface),
+            ControlTransfer::DataIn(&mut descriptor),
+            REQUEST_TIMEOUT_MS,
+        )?;
+        Ok(descriptor)
+    }
+
+    /// Selects the protocol of the HID interface `interface`.
+    ///
+    /// Only the interfaces of the boot subclass support this request. They
+    /// use the report protocol after a reset.
+    pub fn hid_set_protocol(
+        &mut self,
+        interface: u8,
+        protocol: HidProtocol,
+    ) -> Result<(), UsbTransferStatus> {
+        let protocol = match protocol {
+            HidProtocol::Boot => 0,
+            HidProtocol::Report => 1,
+        };
+        self.control_transfer(
+            CLASS_INTERFACE_REQUEST,
+            request::SET_PROTOCOL,
+            protocol,
+            u16::from(interface),
+            ControlTransfer::None,
+            REQUEST_TIMEOUT_MS,
+        )
+    }
+
+    /// Sets the rate at which the HID interface `interface` repeats the
+    /// input report `report_id` if it did not change.
+    ///
+    /// `duration` is in units of 4 ms, zero only sending the report when it
+    /// changes. A `report_id` of zero applies to all the reports.
+    pub fn hid_set_idle(
+        &mut self,
+        interface: u8,
+        report_id: u8,
+        duration: u8,
+    ) -> Result<(), UsbTransferStatus> {
+        self.control_transfer(
+            CLASS_INTERFACE_REQUEST,
+            request::SET_IDLE,
+            (u16::from(duration) << 8) | u16::from(report_id),
+            u16::from(interface),
+            ControlTransfer::None,
+            REQUEST_TIMEOUT_MS,
+        )
+    }
+
+    /// Reads the report `report_id` of the HID interface `interface` into
+    /// `buffer` through the control pipe.
+    pub fn hid_get_report(
+        &mut self,
+        interface: u8,
+        report_type: ReportType,
+        report_id: u8,
+        buffer: &mut [u8],
+    ) -> Result<(), UsbTransferStatus> {
+        self.control_transfer(
+            CLASS_INTERFACE_REQUEST,
+            request::GET_REPORT,
+            (report_type.value() << 8) | u16::from(report_id),
+            u16::from(interface),
+            ControlTransfer::DataIn(buffer),
+            REQUEST_TIMEOUT_MS,
+        )
+    }
+
+    /// Sends the report `report_id` to the HID interface `interface`
+    /// through the control pipe, for example the keyboard LEDs from
+    /// [`BootKeyboard::led_report`].
+    pub fn hid_set_report(
+        &mut self,
+        interface: u8,
+        report_type: ReportType,
+        report_id: u8,
+        data: &[u8],
+    ) -> Result<(), UsbTransferStatus> {
+        self.control_transfer(
+            CLASS_INTERFACE_REQUEST,
+            request::SET_REPORT,
+            (report_type.value() << 8) | u16::from(report_id),
+            u16::from(interface),
+            ControlTransfer::DataOut(data),
+            REQUEST_TIMEOUT_MS,
+        )
+    }
+}
+
+bitflags! {
+    /// Flags of an Input, Output or Feature main item.
+    #[repr(transparent)]
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct FieldFlags: u32 {
+        /// The field is constant, usually padding.
+        const CONSTANT = 1 << 0;
+        /// Each element of the field is a value of a usage. Otherwise the
+        /// elements are arrays of usage indices, like the keys pressed.
+        const VARIABLE = 1 << 1;
+        /// The values are relative to the previous report.
+        const RELATIVE = 1 << 2;
+        /// The values wrap around.
+        const WRAP = 1 << 3;
+        /// The values are not linear.
+        const NON_LINEAR = 1 << 4;
+        /// The control has no preferred state to return to.
+        const NO_PREFERRED = 1 << 5;
+        /// The control has a null state, outside of the logical range.
+        const NULL_STATE = 1 << 6;
+        /// The value can change without host requests.
+        const VOLATILE = 1 << 7;
+        /// The field is a stream of bytes.
+        const BUFFERED_BYTES = 1 << 8;
+    }
+}
+
+/// Field of a report, created by an Input, Output or Feature main item of
+/// the report descriptor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReportField {
+    /// Type of the report containing the field.
+    pub report_type: ReportType,
+    /// ID of the report containing the field, or zero if the device does not
+    /// use report IDs.
+    pub report_id: u8,
+    /// Flags of the field.
+    pub flags: FieldFlags,
+    /// Usages of the elements, as extended usages with the usage page in
+    /// the high 16 bits.
+    ///
+    /// For an array field, these are the usages selected by the values of
+    /// the elements, starting from `logical_minimum`.
+    pub usages: Vec<u32>,
+    /// Offset of the field in the report, in bits, not counting the report
+    /// ID.
+    pub bit_offset: u32,
+    /// Size of an element, in bits.
+    pub bit_size: u32,
+    /// Number of elements.
+    pub count: u32,
+    /// Minimum value of an element.
+    pub logical_minimum: i32,
+    /// Maximum value of an element.
+    pub logical_maximum: i32,
+}
+
+impl ReportField {
+    /// Returns whether the field is an array of usage indices.
+    #[must_use]
+    pub const fn is_array(&self) -> bool {
+        !self.flags.contains(FieldFlags::VARIABLE)
+    }
+
+    /// Returns the usage of the element `index` of a variable field.
+    ///
+    /// Elements past the last usage share the last usage.
+    #[must_use]
+    pub fn usage(&self, index: u32) -> Option<u32> {
+        let index = usize::try_from(index).ok()?;
+        self.usages.get(index).or(self.usages.last()).copied()
+    }
+
+    /// Returns the value of the element `index` in `report`, which must not
+    /// start with the report ID.
+    ///
+    /// The value is sign-extended if the logical minimum is negative.
+    /// Returns `None` if `index` is out of bounds or if `report` is too
+    /// short.
+    #[must_use]
+    pub fn value(&self, report: &[u8], index: u32) -> Option<i32> {
+        if index >= self.count {
+            return None;
+        }
+        let start = self.bit_offset + index * self.bit_size;
+        let mut value = 0u32;
+        for bit in 0..self.bit_size {
+            let position = usize::try_from(start + bit).ok()?;
+            let byte = report.get(position / 8)?;
+            value |= u32::from((byte >> (position % 8)) & 1) << bit;
+        }
+        if self.logical_minimum < 0 && self.bit_size > 0 && self.bit_size < 32 {
+            let shift = 32 - self.bit_size;
+            Some(((value << shift) as i32) >> shift)
+        } else {
+            Some(value as i32)
+        }
+    }
+}
+
+/// Parsed HID report descriptor.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReportDescriptor {
+    /// Fields of the reports, in the order of the descriptor.
+    pub fields: Vec<ReportField>,
+}
+
+/// Global items of the report descriptor.
+#[derive(Clone, Copy, Default)]
+struct GlobalState {
+    usage_page: u16,
+    logical_minimum: i32,
+    logical_maximum: i32,
+    report_size: u32,
+    report_id: u8,
+    report_count: u32,
+}
+
+/// Local items of the report descriptor.
+#[derive(Default)]
+struct LocalState {
+    usages: Vec<u32>,
+    usage_minimum: Option<u32>,
+    usage_maximum: Option<u32>,
+}
+
+impl ReportDescriptor {
+    /// Parses the report descriptor in `data`.
+    ///
+    /// Returns `None` if an item is truncated, if a field is wider than 32
+    /// bits, or if the collections or the Push and Pop items are not
+    /// balanced.
+    #[must_use]
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let mut fields = Vec::new();
+        let mut global = GlobalState::default();
+        let mut stack = Vec::new();
+        let mut local = LocalState::default();
+        let mut collections = 0u32;
+        // Current size of the reports, in bits, per type and ID.
+        let mut offsets: Vec<(ReportType, u8, u32)> = Vec::new();
+
+        let mut rest = data;
+        while let Some((&prefix, tail)) = rest.split_first() {
+            // Long items are reserved, skip them.
+            if prefix == 0xfe {
+                let size = usize::from(*tail.first()?);
+                rest = tail.get(2 + size..)?;
+                continue;
+            }
+            let size = match prefix & 0b11 {
+                3 => 4,
+                size => usize::from(size),
+            };
+            let item = tail.get(..size)?;
+            rest = &tail[size..];
+            let mut bytes = [0; 4];
+            bytes[..size].copy_from_slice(item);
+            let unsigned = u32::from_le_bytes(bytes);
+            let signed = match size {
+                0 => 0,
+                1 => i32::from(item[0] as i8),
+                2 => i32::from(i16::from_le_bytes([item[0], item[1]])),
+                _ => unsigned as i32,
+            };
+
+            match (prefix >> 2) & 0b11 {
+                // Main items.
+                0 => {
+                    let report_type = match prefix >> 4 {
+                        0x8 => Some(ReportType::Input),
+                        0x9 => Some(ReportType::Output),
+                        0xb => Some(ReportType::Feature),
+                        0xa => {
+                            collections += 1;
+                            None
+                        }
+                        0xc => {
+                            collections = collections.checked_sub(1)?;
+                            None
+                        }
+                        _ => None,
+                    };
+                    if let Some(report_type) = report_type {
+                        if global.report_size > 32 {
+                            return None;
+                        }
+                        let offset = match offsets
+                            .iter_mut()
+                            .find(|(ty, id, _)| *ty == report_type && *id == global.report_id)
+                        {
+                            Some((_, _, offset)) => offset,
+                            None => {
+                                offsets.push((report_type, global.report_id, 0));
+                                &mut offsets.last_mut()?.2
+                            }
+                        };
+                        let bit_offset = *offset;
+                        *offset = offset
+                            .checked_add(global.report_size.checked_mul(global.report_count)?)?;
+                        fields.push(ReportField {
+                            report_type,
+                            report_id: global.report_id,
+                            flags: FieldFlags::from_bits_retain(unsigned),
+                            usages: local.usages(global.usage_page)?,
+                            bit_offset,
+                            bit_size: global.report_size,
+                            count: global.report_count,
+                            logical_minimum: global.logical_minimum,
+                            logical_maximum: global.logical_maximum,
+                        });
+                    }
+                    local = LocalState::default();
+                }
+                // Global items.
+                1 => match prefix >> 4 {
+                    0x0 => global.usage_page = unsigned as u16,
+                    0x1 => global.logical_minimum = signed,
+                    // The maximum is unsigned if the minimum is not negative.
+                    0x2 if global.logical_minimum >= 0 => global.logical_maximum = unsigned as i32,
+                    0x2 => global.logical_maximum = signed,
+                    0x7 => global.report_size = unsigned,
+                    0x8 => global.report_id = u8::try_from(unsigned).ok()?,
+                    0x9 => global.report_count = unsigned,
+                    0xa => stack.push(global),
+                    0xb => global = stack.pop()?,
+                    _ => {}
+                },
+                // Local items.
+                2 => match prefix >> 4 {
+                    0x0 => local.usages.push(extended_usage(unsigned, size)),
+                    0x1 => local.usage_minimum = Some(extended_usage(unsigned, size)),
+                    0x2 => local.usage_maximum = Some(extended_usage(unsigned, size)),
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+
+        if collections != 0 || !stack.is_empty() {
+            return None;
+        }
+        Some(Self { fields })
+    }
+
+    /// Returns whether the reports start with a report ID.
+    #[must_use]
+    pub fn uses_report_ids(&self) -> bool {
+        self.fields.iter().any(|field| field.report_id != 0)
+    }
+
+    /// Returns the size of the report `report_id` of type `report_type` in
+    /// bytes, not counting the report ID.
+    #[must_use]
+    pub fn report_size(&self, report_type: ReportType, report_id: u8) -> usize {
+        let bits = self
+            .fields
+            .iter()
+            .filter(|field| field.report_type == report_type && field.report_id == report_id)
+            .map(|field| field.bit_offset + field.bit_size * field.count)
+            .max()
+            .unwrap_or(0);
+        bits.div_ceil(8) as usize
+    }
+
+    /// Returns an iterator over the fields of the report `report_id` of type
+    /// `report_type`.
+    pub fn report_fields(
+        &self,
+        report_type: ReportType,
+        report_id: u8,
+    ) -> impl Iterator<Item = &ReportField> {
+        self.fields
+            .iter()
+            .filter(move |field| field.report_type == report_type && field.report_id == report_id)
+    }
+}
+
+impl LocalState {
+    /// Returns the usages of the local items, with the usage page of
+    /// `usage_page` if they do not have one.
+    fn usages(&self, usage_page: u16) -> Option<Vec<u32>> {
+        let with_page = |usage: u32| {
+            if usage >> 16 == 0 {
+                (u32::from(usage_page) << 16) | usage
+            } else {
+                usage
+            }
+        };
+        let mut usages: Vec<u32> = self.usages.iter().map(|&usage| with_page(usage)).collect();
+        if let (Some(minimum), Some(maximum)) = (self.usage_minimum, self.usage_maximum) {
+            let (minimum, maximum) = (with_page(minimum), with_page(maximum));
+            // Usage ranges cannot span usage pages.
+            if maximum < minimum || maximum - minimum > 0xffff {
+                return None;
+            }
+            usages.extend(minimum..=maximum);
+        }
+        Some(usages)
+    }
+}
+
+/// Returns the usage of a local item of `size` bytes. Only 4 byte items
+/// contain the usage page.
+const fn extended_usage(value: u32, size: usize) -> u32 {
+    if size == 4 { value } else { value & 0xffff }
+}
+
+bitflags! {
+    /// Modifier keys of a [`KeyboardReport`].
+    #[repr(transparent)]
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct Modifiers: u8 {
+        /// Left control.
+        const LEFT_CONTROL = 1 << 0;
+        /// Left shift.
+        const LEFT_SHIFT = 1 << 1;
+        /// Left alt.
+        const LEFT_ALT = 1 << 2;
+        /// Left GUI, the Windows or Command key.
+        const LEFT_GUI = 1 << 3;
+        /// Right control.
+        const RIGHT_CONTROL = 1 << 4;
+        /// Right shift.
+        const RIGHT_SHIFT = 1 << 5;
+        /// Right alt.
+        const RIGHT_ALT = 1 << 6;
+        /// Right GUI, the Windows or Command key.
+        const RIGHT_GUI = 1 << 7;
+    }
+}
+
+/// Usage of the keyboard reporting too many keys pressed.
+const ERROR_ROLL_OVER: u8 = 0x01;
+
+/// Boot protocol keyboard input report.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct KeyboardReport {
+    /// Modifier keys pressed.
+    pub modifiers: Modifiers,
+    /// Usages of the other keys pressed, in the keyboard usage page. Unused
+    /// entries are zero.
+    pub keys: [u8; 6],
+}
+
+impl KeyboardReport {
+    /// Parses a boot protocol keyboard report. Returns `None` if `data` is
+    /// shorter than 8 bytes.
+    #[must_use]
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let data = data.get(..8)?;
+        Some(Self {
+            modifiers: Modifiers::from_bits_retain(data[0]),
+            keys: data[2..8].try_into().unwrap(),
+        })
+    }
+
+    /// Returns whether the keyboard reported that too many keys are
+    /// pressed, in which case the keys are not valid.
+    #[must_use]
+    pub fn is_rollover_error(&self) -> bool {
+        self.keys.iter().all(|&key| key == ERROR_ROLL_OVER)
+    }
+
+    /// Returns an iterator over the usages of the keys pressed.
+    pub fn pressed_keys(&self) -> impl Iterator<Item = u8> + '_ {
+        self.keys
+            .iter()
+            .copied()
+            .filter(|&key| key > ERROR_ROLL_OVER)
+    }
+
+    /// Returns the modifier keys as a [`KeyShiftState`].
+    #[must_use]
+    pub const fn shift_state(&self) -> KeyShiftState {
+        let modifiers = self.modifiers;
+        let mut state = KeyShiftState::SHIFT_STATE_VALID;
+        let pairs = [
+            (Modifiers::LEFT_CONTROL, KeyShiftState::LEFT_CONTROL_PRESSED),
+            (Modifiers::LEFT_SHIFT, KeyShiftState::LEFT_SHIFT_PRESSED),
+            (Modifiers::LEFT_ALT, KeyShiftState::LEFT_ALT_PRESSED),
+            (Modifiers::LEFT_GUI, KeyShiftState::LEFT_LOGO_PRESSED),
+            (
+                Modifiers::RIGHT_CONTROL,
+                KeyShiftState::RIGHT_CONTROL_PRESSED,
+            ),
+            (Modifiers::RIGHT_SHIFT, KeyShiftState::RIGHT_SHIFT_PRESSED),
+            (Modifiers::RIGHT_ALT, KeyShiftState::RIGHT_ALT_PRESSED),
+            (Modifiers::RIGHT_GUI, KeyShiftState::RIGHT_LOGO_PRESSED),
+        ];
+        let mut i = 0;
+        while i < pairs.len() {
+            if modifiers.contains(pairs[i].0) {
+                state = state.union(pairs[i].1);
+            }
+            i += 1;
+        }
+        state
+    }
+}
+
+/// Keyboard usages of the lock keys.
+mod lock_usage {
+    pub const CAPS_LOCK: u8 = 0x39;
+    pub const SCROLL_LOCK: u8 = 0x47;
+    pub const NUM_LOCK: u8 = 0x53;
+}
+
+/// Decoder of boot protocol keyboard reports.
+///
+/// The reports list the keys currently pressed. The decoder compares each
+/// report with the previous one to find the keys newly pressed, and tracks
+/// the state of the lock keys.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BootKeyboard {
+    previous: KeyboardReport,
+    toggle_state: KeyToggleState,
+}
+
+impl BootKeyboard {
+    /// Creates a decoder with no key pressed and the lock keys off.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            previous: KeyboardReport {
+                modifiers: Modifiers::empty(),
+                keys: [0; 6],
+            },
+            toggle_state: KeyToggleState::empty(),
+        }
+    }
+
+    /// Returns the state of the lock keys.
+    #[must_use]
+    pub const fn toggle_state(&self) -> KeyToggleState {
+        self.toggle_state.union(KeyToggleState::TOGGLE_STATE_VALID)
+    }
+
+    /// Returns the output report setting the keyboard LEDs to the state of
+    /// the lock keys, to send with [`UsbIo::hid_set_report`].
+    #[must_use]
+    pub const fn led_report(&self) -> u8 {
+        let mut leds = 0;
+        if self.toggle_state.contains(KeyToggleState::NUM_LOCK_ACTIVE) {
+            leds |= 1 << 0;
+        }
+        if self.toggle_state.contains(KeyToggleState::CAPS_LOCK_ACTIVE) {
+            leds |= 1 << 1;
+        }
+        if self
+            .toggle_state
+            .contains(KeyToggleState::SCROLL_LOCK_ACTIVE)
+        {
+            leds |= 1 << 2;
+        }
+        leds
+    }
+
+    /// Decodes `report`, and returns the keystrokes of the keys pressed
+    /// since the previous report.
+    ///
+    /// The lock keys update the toggle state and are not returned, like the
+    /// keys without an equivalent [`Key`]. Reports with a rollover error are
+    /// ignored.
+    pub fn update(&mut self, report: &KeyboardReport) -> Vec<KeyStroke> {
+        if report.is_rollover_error() {
+            return Vec::new();
+        }
+
+        let mut strokes = Vec::new();
+        for usage in report.pressed_keys() {
+            if self
+                .previous
+                .pressed_keys()
+                .any(|previous| previous == usage)
+            {
+                continue;
+            }
+            let toggle = match usage {
+                lock_usage::CAPS_LOCK => KeyToggleState::CAPS_LOCK_ACTIVE,
+                lock_usage::SCROLL_LOCK => KeyToggleState::SCROLL_LOCK_ACTIVE,
+                lock_usage::NUM_LOCK => KeyToggleState::NUM_LOCK_ACTIVE,
+                _ => KeyToggleState::empty(),
+            };
+            if !toggle.is_empty() {
+                self.toggle_state.toggle(toggle);
+                continue;
+            }
+            let shift_state = report.shift_state();
+            let shift = report
+                .modifiers
+                .intersects(Modifiers::LEFT_SHIFT | Modifiers::RIGHT_SHIFT);
+            if let Some(key) = usage_to_key(usage, shift, self.toggle_state) {
+                strokes.push(KeyStroke {
+                    key: Some(key),
+                    shift_state,
+                    toggle_state: self.toggle_state(),
+                });
+            }
+        }
+        self.previous = *report;
+        strokes
+    }
+}
+
+/// Converts the keyboard usage `usage` to a [`Key`], with the US keyboard
+/// layout.
+///
+/// `shift` tells whether a shift key is pressed, and `toggle_state` is the
+/// state of the lock keys, which changes the letters and the keypad.
+/// Returns `None` for the keys without an equivalent [`Key`], like the
+/// modifier and lock keys.
+#[must_use]
+pub fn usage_to_key(usage: u8, shift: bool, toggle_state: KeyToggleState) -> Option<Key> {
+    const SHIFTED: &[(char, char); 12] = &[
+        ('-', '_'),
+        ('=', '+'),
+        ('[', '{'),
+        (']', '}'),
+        ('\\', '|'),
+        ('#', '~'),
+        (';', ':'),
+        ('\'', '"'),
+        ('`', '~'),
+        (',', '<'),
+        ('.', '>'),
+        ('/', '?'),
+    ];
+    const DIGITS_SHIFTED: &[u8; 10] = b"!@#$%^&*()";
+    const KEYPAD: &[(char, ScanCode); 10] = &[
+        ('1', ScanCode::END),
+        ('2', ScanCode::DOWN),
+        ('3', ScanCode::PAGE_DOWN),
+        ('4', ScanCode::LEFT),
+        ('5', ScanCode::NULL),
+        ('6', ScanCode::RIGHT),
+        ('7', ScanCode::HOME),
+        ('8', ScanCode::UP),
+        ('9', ScanCode::PAGE_UP),
+        ('0', ScanCode::INSERT),
+    ];
+
+    let printable = |c: char| Char16::try_from(c).ok().map(Key::Printable);
+    let special = |scan_code: ScanCode| Some(Key::Special(scan_code));
+    let num_lock = toggle_state.contains(KeyToggleState::NUM_LOCK_ACTIVE);
+
+    match usage {
+        // Letters.
+        0x04..=0x1d => {
+            let letter = char::from(b'a' + usage - 0x04);
+            let upper = shift != toggle_state.contains(KeyToggleState::CAPS_LOCK_ACTIVE);
+            printable(if upper {
+                letter.to_ascii_uppercase()
+            } else {
+                letter
+            })
+        }
+        // Digits, from 1 to 0.
+        0x1e..=0x27 => {
+            let index = usize::from(usage - 0x1e);
+            if shift {
+                printable(char::from(DIGITS_SHIFTED[index]))
+            } else {
+                printable(char::from(b"1234567890"[index]))
+            }
+        }
+        0x28 | 0x58 => printable('\r'),
+        0x29 => special(ScanCode::ESCAPE),
+        0x2a => printable('\u{8}'),
+        0x2b => printable('\t'),
+        0x2c => printable(' '),
+        // Punctuation, with the non-US hash key at 0x32.
+        0x2d..=0x38 => {
+            let (normal, shifted) = SHIFTED[usize::from(usage - 0x2d)];
+            printable(if shift { shifted } else { normal })
+        }
+        0x3a..=0x45 => special(ScanCode(ScanCode::FUNCTION_1.0 + u16::from(usage - 0x3a))),
+        0x49 => special(ScanCode::INSERT),
+        0x4a => special(ScanCode::HOME),
+        0x4b => special(ScanCode::PAGE_UP),
+        0x4c => special(ScanCode::DELETE),
+        0x4d => special(ScanCode::END),
+        0x4e => special(ScanCode::PAGE_DOWN),
+        0x4f => special(ScanCode::RIGHT),
+        0x50 => special(ScanCode::LEFT),
+        0x51 => special(ScanCode::DOWN),
+        0x52 => special(ScanCode::UP),
+        0x54 => printable('/'),
+        0x55 => printable('*'),
+        0x56 => printable('-'),
+        0x57 => printable('+'),
+        // Keypad digits, which are navigation keys without num lock.
+        0x59..=0x62 => {
+            let (digit, scan_code) = KEYPAD[usize::from(usage - 0x59)];
+            if num_lock {
+                printable(digit)
+            } else if scan_code == ScanCode::NULL {
+                None
+            } else {
+                special(scan_code)
+            }
+        }
+        0x63 if num_lock => printable('.'),
+        0x63 => special(ScanCode::DELETE),
+        0x68..=0x73 => special(ScanCode(ScanCode::FUNCTION_13.0 + u16::from(usage - 0x68))),
+        0x7f => special(ScanCode::MUTE),
+        0x80 => special(ScanCode::VOLUME_UP),
+        0x81 => special(ScanCode::VOLUME_DOWN),
+        _ => None,
+    }
+}
+
+bitflags! {
+    /// Buttons of a [`MouseReport`].
+    #[repr(transparent)]
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct MouseButtons: u8 {
+        /// Left button.
+        const LEFT = 1 << 0;
+        /// Right button.
+        const RIGHT = 1 << 1;
+        /// Middle button.
+        const MIDDLE = 1 << 2;
+    }
+}
+
+/// Boot protocol mouse input report.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MouseReport {
+    /// Buttons pressed.
+    pub buttons: MouseButtons,
+    /// Relative movement on the X axis.
+    pub x: i8,
+    /// Relative movement on the Y axis.
+    pub y: i8,
+    /// Relative movement of the wheel, zero if the mouse has no wheel. The
+    /// wheel is not part of the boot protocol, but most mice report it.
+    pub wheel: i8,
+}
+
+impl MouseReport {
+    /// Parses a boot protocol mouse report. Returns `None` if `data` is
+    /// shorter than 3 bytes.
+    #[must_use]
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let [buttons, x, y, rest @ ..] = data else {
+            return None;
+        };
+        Some(Self {
+            buttons: MouseButtons::from_bits_retain(*buttons),
+            x: *x as i8,
+            y: *y as i8,
+            wheel: rest.first().map_or(0, |&wheel| wheel as i8),
+        })
+    }
+}
+
+impl From<MouseReport> for PointerState {
+    fn from(report: MouseReport) -> Self {
+        Self {
+            relative_movement: [
+                i32::from(report.x),
+                i32::from(report.y),
+                i32::from(report.wheel),
+            ],
+            button: [
+                report.buttons.contains(MouseButtons::LEFT),
+                report.buttons.contains(MouseButtons::RIGHT),
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::String;
+
+    /// Boot keyboard report descriptor, from appendix B.1 of the HID
+    /// specification.
+    const KEYBOARD: &[u8] = &[
+        0x05, 0x01, 0x09, 0x06, 0xa1, 0x01, 0x05, 0x07, 0x19, 0xe0, 0x29, 0xe7, 0x15, 0x00, 0x25,
+        0x01, 0x75, 0x01, 0x95, 0x08, 0x81, 0x02, 0x95, 0x01, 0x75, 0x08, 0x81, 0x01, 0x95, 0x05,
+        0x75, 0x01, 0x05, 0x08, 0x19, 0x01, 0x29, 0x05, 0x91, 0x02, 0x95, 0x01, 0x75, 0x03, 0x91,
+        0x01, 0x95, 0x06, 0x75, 0x08, 0x15, 0x00, 0x25, 0x65, 0x05, 0x07, 0x19, 0x00, 0x29, 0x65,
+        0x81, 0x00, 0xc0,
+    ];
+
+    /// Boot mouse report descriptor, from appendix B.2 of the HID
+    /// specification, with a report ID.
+    const MOUSE: &[u8] = &[
+        0x05, 0x01, 0x09, 0x02, 0xa1, 0x01, 0x85, 0x02, 0x09, 0x01, 0xa1, 0x00, 0x05, 0x09, 0x19,
+        0x01, 0x29, 0x03, 0x15, 0x00, 0x25, 0x01, 0x95, 0x03, 0x75, 0x01, 0x81, 0x02, 0x95, 0x01,
+        0x75, 0x05, 0x81, 0x01, 0x05, 0x01, 0x09, 0x30, 0x09, 0x31, 0x15, 0x81, 0x25, 0x7f, 0x75,
+        0x08, 0x95, 0x02, 0x81, 0x06, 0xc0, 0xc0,
+    ];
+
+    #[test]
+    fn test_parse_keyboard_descriptor() {
+        let descriptor = ReportDescriptor::parse(KEYBOARD).unwrap();
+
+        assert!(!descriptor.uses_report_ids());
+        assert_eq!(descriptor.report_size(ReportType::Input, 0), 8);
+        assert_eq!(descriptor.report_size(ReportType::Output, 0), 1);
+        assert_eq!(descriptor.report_fields(ReportType::Input, 0).count(), 3);
+
+        let modifiers = &descriptor.fields[0];
+        assert_eq!(modifiers.flags, FieldFlags::VARIABLE);
+        assert_eq!(modifiers.usage(0), Some(0x0007_00e0));
+        assert_eq!(modifiers.usage(7), Some(0x0007_00e7));
+        assert_eq!((modifiers.bit_offset, modifiers.bit_size), (0, 1));
+
+        let leds = &descriptor.fields[2];
+        assert_eq!(leds.report_type, ReportType::Output);
+        assert_eq!(
+            leds.usages,
+            [
+                0x0008_0001,
+                0x0008_0002,
+                0x0008_0003,
+                0x0008_0004,
+                0x0008_0005
+            ]
+        );
+
+        let keys = &descriptor.fields[4];
+        assert!(keys.is_array());
+        assert_eq!((keys.bit_offset, keys.bit_size, keys.count), (16, 8, 6));
+        assert_eq!(keys.usages.len(), 0x66);
+        assert_eq!((keys.logical_minimum, keys.logical_maximum), (0, 0x65));
+
+        let report = [0x02, 0, 0x04, 0x05, 0, 0, 0, 0];
+        assert_eq!(modifiers.value(&report, 1), Some(1));
+        assert_eq!(keys.value(&report, 0), Some(0x04));
+        assert_eq!(keys.value(&report, 1), Some(0x05));
+        assert_eq!(keys.value(&report, 6), None);
+    }
+
+    #[test]
+    fn test_parse_mouse_descriptor() {
+        let descriptor = ReportDescriptor::parse(MOUSE).unwrap();
+
+        assert!(descriptor.uses_report_ids());
+        assert_eq!(descriptor.report_size(ReportType::Input, 2), 3);
+        let axes = &descriptor.fields[2];
+        assert_eq!(axes.report_id, 2);
+        assert_eq!(axes.flags, FieldFlags::VARIABLE | FieldFlags::RELATIVE);
+        assert_eq!(axes.usages, [0x0001_0030, 0x0001_0031]);
+        assert_eq!((axes.logical_minimum, axes.logical_maximum), (-127, 127));
+
+        // The report ID is not part of the data.
+        let report = [0b101, 0xfe, 0x03];
+        assert_eq!(descriptor.fields[0].value(&report, 2), Some(1));
+        assert_eq!(axes.value(&report, 0), Some(-2));
+        assert_eq!(axes.value(&report, 1), Some(3));
+        assert_eq!(axes.value(&report[..2], 1), None);
+
+        // Empty signed field.
+        let descriptor =
+            ReportDescriptor::parse(&[0x15, 0xff, 0x75, 0x00, 0x95, 0x01, 0x81, 0x02]).unwrap();
+        assert_eq!(descriptor.fields[0].value(&[], 0), Some(0));
+    }
+
+    #[test]
+    fn test_parse_invalid_descriptor() {
+        // Truncated item.
+        assert!(ReportDescriptor::parse(&KEYBOARD[..KEYBOARD.len() - 2]).is_none());
+        // Unbalanced collections.
+        assert!(ReportDescriptor::parse(&[0xc0]).is_none());
+        // Pop without push.
+        assert!(ReportDescriptor::parse(&[0xb4]).is_none());
+        // Fields larger than 32 bits.
+        assert!(ReportDescriptor::parse(&[0x75, 0x40, 0x95, 0x01, 0x81, 0x02]).is_none());
+    }
+
+    #[test]
+    fn test_boot_keyboard() {
+        let mut keyboard = BootKeyboard::new();
+        let report = |modifiers, keys: &[u8]| {
+            let mut data = [0; 8];
+            data[0] = modifiers;
+            data[2..2 + keys.len()].copy_from_slice(keys);
+            KeyboardReport::parse(&data).unwrap()
+        };
+        let keys = |strokes: Vec<KeyStroke>| {
+            strokes
+                .into_iter()
+                .map(|stroke| stroke.key.unwrap())
+                .collect::<Vec<_>>()
+        };
+        let printable = |s: &str| {
+            s.chars()
+                .map(|c| Key::Printable(Char16::try_from(c).unwrap()))
+                .collect::<Vec<_>>()
+        };
+
+        // 'h', then 'i' while 'h' is still pressed.
+        assert_eq!(keys(keyboard.update(&report(0, &[0x0b]))), printable("h"));
+        assert_eq!(
+            keys(keyboard.update(&report(0, &[0x0b, 0x0c]))),
+            printable("i")
+        );
+        assert!(keyboard.update(&report(0, &[])).is_empty());
+
+        // Shift.
+        let strokes = keyboard.update(&report(0x20, &[0x1e, 0x2d]));
+        assert!(strokes[0].shift());
+        assert_eq!(keys(strokes), printable("!_"));
+        assert!(keyboard.update(&report(0x01, &[0x01; 6])).is_empty());
+
+        // Caps lock.
+        assert!(keyboard.update(&report(0, &[0x39])).is_empty());
+        assert_eq!(keyboard.led_report(), 0b010);
+        let strokes = keyboard.update(&report(0x02, &[0x04, 0x3a]));
+        assert!(
+            strokes[0]
+                .toggle_state
+                .contains(KeyToggleState::CAPS_LOCK_ACTIVE)
+        );
+        assert_eq!(
+            keys(strokes),
+            [
+                Key::Printable(Char16::try_from('a').unwrap()),
+                Key::Special(ScanCode::FUNCTION_1)
+            ]
+        );
+        assert!(keyboard.update(&report(0, &[])).is_empty());
+        assert!(keyboard.update(&report(0, &[0x39])).is_empty());
+        assert_eq!(keyboard.toggle_state(), KeyToggleState::TOGGLE_STATE_VALID);
+    }
+
+    #[test]
+    fn test_usage_to_key() {
+        let none = KeyToggleState::empty();
+        let text: String = [0x17, 0x08, 0x0f, 0x0f, 0x12, 0x2c, 0x34, 0x31, 0x32, 0x38]
+            .into_iter()
+            .filter_map(|usage| match usage_to_key(usage, false, none) {
+                Some(Key::Printable(c)) => Some(char::from(c)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(text, "tello '\\#/");
+
+        assert_eq!(
+            usage_to_key(0x28, false, none),
+            Some(Key::Printable(Char16::try_from('\r').unwrap()))
+        );
+        assert_eq!(
+            usage_to_key(0x45, false, none),
+            Some(Key::Special(ScanCode::FUNCTION_12))
+        );
+        assert_eq!(
+            usage_to_key(0x73, false, none),
+            Some(Key::Special(ScanCode::FUNCTION_24))
+        );
+        assert_eq!(
+            usage_to_key(0x5a, false, none),
+            Some(Key::Special(ScanCode::DOWN))
+        );
+        assert_eq!(usage_to_key(0x5d, false, none), None);
+        assert_eq!(
+            usage_to_key(0x5a, false, KeyToggleState::NUM_LOCK_ACTIVE),
+            Some(Key::Printable(Char16::try_from('2').unwrap()))
+        );
+        assert_eq!(usage_to_key(0xe1, true, none), None);
+    }
+
+    #[test]
+    fn test_mouse_report() {
+        assert!(MouseReport::parse(&[1, 2]).is_none());
+
+        let report = MouseReport::parse(&[0b011, 0xff, 0x05]).unwrap();
+        assert_eq!(report.buttons, MouseButtons::LEFT | MouseButtons::RIGHT);
+        assert_eq!(
+            PointerState::from(report),
+            PointerState {
+                relative_movement: [-1, 5, 0],
+                button: [true, true],
+            }
+        );
+        assert_eq!(MouseReport::parse(&[0, 0, 0, 0xfe]).unwrap().wheel, -2);
+    }
+}
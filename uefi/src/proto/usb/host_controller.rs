@@ -0,0 +1,515 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! USB2 Host Controller protocol.
+
+use core::ffi;
+use core::ptr;
+
+use uefi_macros::unsafe_protocol;
+use uefi_raw::Boolean;
+use uefi_raw::protocol::usb::host_controller::Usb2HostControllerProtocol;
+use uefi_raw::protocol::usb::{DataDirection, DeviceRequest};
+
+use super::io::ControlTransfer;
+use super::{AsyncUsbTransferCallback, UsbTransferStatus};
+use crate::{Error, Result, Status, StatusExt};
+
+pub use uefi_raw::protocol::usb::host_controller::{
+    HostControllerState, PortChangeStatus, PortFeature, PortStatus, ResetAttributes, Speed,
+    TransactionTranslator, UsbPortStatus,
+};
+
+/// USB2 Host Controller [`Protocol`].
+///
+/// Gives access to a USB host controller and its root hub. The transfer
+/// methods address a device by its bus address, which is assigned by the USB
+/// bus driver, so they are mostly useful to diagnose the devices and ports
+/// of the controller. Drivers of a USB device should use [`UsbIo`] instead.
+///
+/// The timeouts of the transfers are in milliseconds, zero waiting
+/// indefinitely.
+///
+/// [`Protocol`]: uefi::proto::Protocol
+/// [`UsbIo`]: super::io::UsbIo
+#[derive(Debug)]
+#[repr(transparent)]
+#[unsafe_protocol(Usb2HostControllerProtocol::GUID)]
+pub struct Usb2HostController(Usb2HostControllerProtocol);
+
+impl Usb2HostController {
+    /// Returns the revision of the USB specification implemented by the
+    /// controller, as a (major, minor) pair.
+    #[must_use]
+    pub const fn revision(&self) -> (u16, u16) {
+        (self.0.major_revision, self.0.minor_revision)
+    }
+
+    /// Returns the capabilities of the controller.
+    pub fn capability(&self) -> Result<Capability> {
+        let mut max_speed = Speed::FULL;
+        let mut num_ports = 0;
+        let mut is_64_bit_capable = 0;
+
+        unsafe {
+            (self.0.get_capability)(
+                &self.0,
+                &mut max_speed,
+                &mut num_ports,
+                &mut is_64_bit_capable,
+            )
+        }
+        .to_result_with_val(|| Capability {
+            max_speed,
+            num_ports,
+            is_64_bit_capable: is_64_bit_capable != 0,
+        })
+    }
+
+    /// Resets the controller or the bus, depending on `attributes`.
+    ///
+    /// The devices connected to the controller have to be enumerated again
+    /// after a reset.
+    pub fn reset(&mut self, attributes: ResetAttributes) -> Result {
+        unsafe { (self.0.reset)(&mut self.0, attributes) }.to_result()
+    }
+
+    /// Returns the state of the controller.
+    pub fn state(&mut self) -> Result<HostControllerState> {
+        let mut state = HostControllerState::HALT;
+
+        unsafe { (self.0.get_state)(&mut self.0, &mut state) }.to_result_with_val(|| state)
+    }
+
+    /// Sets the state of the controller.
+    pub fn set_state(&mut self, state: HostControllerState) -> Result {
+        unsafe { (self.0.set_state)(&mut self.0, state) }.to_result()
+    }
+
+    /// Returns the status of the root hub port `port`, numbered from zero.
+    pub fn port_status(&mut self, port: u8) -> Result<UsbPortStatus> {
+        let mut status = UsbPortStatus {
+            port_status: PortStatus::empty(),
+            port_change_status: PortChangeStatus::empty(),
+        };
+
+        unsafe { (self.0.get_root_hub_port_status)(&mut self.0, port, &mut status) }
+            .to_result_with_val(|| status)
+    }
+
+    /// Sets `feature` on the root hub port `port`, for example to reset or
+    /// to power it.
+    pub fn set_port_feature(&mut self, port: u8, feature: PortFeature) -> Result {
+        unsafe { (self.0.set_root_hub_port_feature)(&mut self.0, port, feature) }.to_result()
+    }
+
+    /// Clears `feature` on the root hub port `port`, for example to
+    /// acknowledge a change of the port status.
+    pub fn clear_port_feature(&mut self, port: u8, feature: PortFeature) -> Result {
+        unsafe { (self.0.clear_root_hub_port_feature)(&mut self.0, port, feature) }.to_result()
+    }
+
+    /// Performs a control transfer to the default endpoint of `target`.
+    ///
+    /// Returns the number of bytes transferred in the data phase.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: the data buffer is longer than
+    ///   65535 bytes, the maximum length of a control transfer.
+    /// * Errors of the firmware, with the [`UsbTransferStatus`] of the
+    ///   transfer.
+    #[allow(clippy::too_many_arguments)]
+    pub fn control_transfer(
+        &mut self,
+        target: &TransferTarget,
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        transfer: ControlTransfer,
+        timeout: usize,
+    ) -> Result<usize, UsbTransferStatus> {
+        let (direction, buffer_ptr, mut length) = match transfer {
+            ControlTransfer::None => (DataDirection::NO_DATA, ptr::null_mut(), 0),
+            ControlTransfer::DataIn(buffer) => {
+                (DataDirection::DATA_IN, buffer.as_mut_ptr(), buffer.len())
+            }
+            ControlTransfer::DataOut(buffer) => (
+                DataDirection::DATA_OUT,
+                buffer.as_ptr().cast_mut(),
+                buffer.len(),
+            ),
+        };
+
+        let request_type = if direction == DataDirection::DATA_IN {
+            request_type | 0x80
+        } else if direction == DataDirection::DATA_OUT {
+            request_type & !0x80
+        } else {
+            request_type
+        };
+
+        let device_request = DeviceRequest {
+            request_type,
+            request,
+            value,
+            index,
+            length: u16::try_from(length)
+                .map_err(|_| Error::new(Status::INVALID_PARAMETER, UsbTransferStatus::default()))?,
+        };
+        let translator = target.translator();
+        let mut status = UsbTransferStatus::default();
+
+        unsafe {
+            (self.0.control_transfer)(
+                &mut self.0,
+                target.device_address,
+                target.speed,
+                target.max_packet_length,
+                &device_request,
+                direction,
+                buffer_ptr.cast::<ffi::c_void>(),
+                &mut length,
+                timeout,
+                &translator,
+                &mut status,
+            )
+        }
+        .to_result_with_err(|_| status)
+        .map(|()| length)
+    }
+
+    /// Sends `buffer` to the bulk endpoint `endpoint` of `target`.
+    ///
+    /// `data_toggle` is the data toggle of the endpoint, updated by the
+    /// transfer. Returns the number of bytes sent.
+    pub fn sync_bulk_send(
+        &mut self,
+        target: &TransferTarget,
+        endpoint: u8,
+        buffer: &[u8],
+        data_toggle: &mut u8,
+        timeout: usize,
+    ) -> Result<usize, UsbTransferStatus> {
+        self.bulk_transfer(
+            target,
+            endpoint & !0x80,
+            buffer.as_ptr().cast_mut(),
+            buffer.len(),
+            data_toggle,
+            timeout,
+        )
+    }
+
+    /// Fills `buffer` with data from the bulk endpoint `endpoint` of
+    /// `target`.
+    ///
+    /// `data_toggle` is the data toggle of the endpoint, updated by the
+    /// transfer. Returns the number of bytes received.
+    pub fn sync_bulk_receive(
+        &mut self,
+        target: &TransferTarget,
+        endpoint: u8,
+        buffer: &mut [u8],
+        data_toggle: &mut u8,
+        timeout: usize,
+    ) -> Result<usize, UsbTransferStatus> {
+        self.bulk_transfer(
+            target,
+            endpoint | 0x80,
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            data_toggle,
+            timeout,
+        )
+    }
+
+    fn bulk_transfer(
+        &mut self,
+        target: &TransferTarget,
+        endpoint: u8,
+        buffer: *mut u8,
+        mut length: usize,
+        data_toggle: &mut u8,
+        timeout: usize,
+    ) -> Result<usize, UsbTransferStatus> {
+        let buffers = [buffer.cast_const().cast::<ffi::c_void>()];
+        let translator = target.translator();
+        let mut status = UsbTransferStatus::default();
+
+        unsafe {
+            (self.0.bulk_transfer)(
+                &mut self.0,
+                target.device_address,
+                endpoint,
+                target.speed,
+                target.max_packet_length,
+                buffers.len() as u8,
+                buffers.as_ptr(),
+                &mut length,
+                data_toggle,
+                timeout,
+                &translator,
+                &mut status,
+            )
+        }
+        .to_result_with_err(|_| status)
+        .map(|()| length)
+    }
+
+    /// Sends `buffer` to the interrupt endpoint `endpoint` of `target`.
+    ///
+    /// `data_toggle` is the data toggle of the endpoint, updated by the
+    /// transfer. Returns the number of bytes sent.
+    pub fn sync_interrupt_send(
+        &mut self,
+        target: &TransferTarget,
+        endpoint: u8,
+        buffer: &[u8],
+        data_toggle: &mut u8,
+        timeout: usize,
+    ) -> Result<usize, UsbTransferStatus> {
+        self.sync_interrupt_transfer(
+            target,
+            endpoint & !0x80,
+            buffer.as_ptr().cast_mut(),
+            buffer.len(),
+            data_toggle,
+            timeout,
+        )
+    }
+
+    /// Fills `buffer` with data from the interrupt endpoint `endpoint` of
+    /// `target`.
+    ///
+    /// `data_toggle` is the data toggle of the endpoint, updated by the
+    /// transfer. Returns the number of bytes received.
+    pub fn sync_interrupt_receive(
+        &mut self,
+        target: &TransferTarget,
+        endpoint: u8,
+        buffer: &mut [u8],
+        data_toggle: &mut u8,
+        timeout: usize,
+    ) -> Result<usize, UsbTransferStatus> {
+        self.sync_interrupt_transfer(
+            target,
+            endpoint | 0x80,
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            data_toggle,
+            timeout,
+        )
+    }
+
+    fn sync_interrupt_transfer(
+        &mut self,
+        target: &TransferTarget,
+        endpoint: u8,
+        buffer: *mut u8,
+        mut length: usize,
+        data_toggle: &mut u8,
+        timeout: usize,
+    ) -> Result<usize, UsbTransferStatus> {
+        let translator = target.translator();
+        let mut status = UsbTransferStatus::default();
+
+        unsafe {
+            (self.0.sync_interrupt_transfer)(
+                &mut self.0,
+                target.device_address,
+                endpoint,
+                target.speed,
+                target.max_packet_length,
+                buffer.cast::<ffi::c_void>(),
+                &mut length,
+                data_toggle,
+                timeout,
+                &translator,
+                &mut status,
+            )
+        }
+        .to_result_with_err(|_| status)
+        .map(|()| length)
+    }
+
+    /// Sends `buffer` to the isochronous endpoint `endpoint` of `target`.
+    pub fn sync_isochronous_send(
+        &mut self,
+        target: &TransferTarget,
+        endpoint: u8,
+        buffer: &[u8],
+    ) -> Result<(), UsbTransferStatus> {
+        self.isochronous_transfer(
+            target,
+            endpoint & !0x80,
+            buffer.as_ptr().cast_mut(),
+            buffer.len(),
+        )
+    }
+
+    /// Fills `buffer` with data from the isochronous endpoint `endpoint` of
+    /// `target`.
+    pub fn sync_isochronous_receive(
+        &mut self,
+        target: &TransferTarget,
+        endpoint: u8,
+        buffer: &mut [u8],
+    ) -> Result<(), UsbTransferStatus> {
+        self.isochronous_transfer(target, endpoint | 0x80, buffer.as_mut_ptr(), buffer.len())
+    }
+
+    fn isochronous_transfer(
+        &mut self,
+        target: &TransferTarget,
+        endpoint: u8,
+        buffer: *mut u8,
+        length: usize,
+    ) -> Result<(), UsbTransferStatus> {
+        let buffers = [buffer.cast_const().cast::<ffi::c_void>()];
+        let translator = target.translator();
+        let mut status = UsbTransferStatus::default();
+
+        unsafe {
+            (self.0.isochronous_transfer)(
+                &mut self.0,
+                target.device_address,
+                endpoint,
+                target.speed,
+                target.max_packet_length,
+                buffers.len() as u8,
+                buffers.as_ptr(),
+                length,
+                &translator,
+                &mut status,
+            )
+        }
+        .to_result_with_err(|_| status)
+    }
+
+    /// Starts polling the interrupt endpoint `endpoint` of `target` every
+    /// `polling_interval` milliseconds for `data_length` bytes of data.
+    ///
+    /// `callback` is called with `context` when data is received, at the
+    /// `TPL_CALLBACK` task priority level. The transfer continues until it
+    /// is cancelled with [`cancel_async_interrupt_transfer`].
+    ///
+    /// # Safety
+    ///
+    /// `callback` must be safe to call with `context` until the transfer is
+    /// cancelled.
+    ///
+    /// [`cancel_async_interrupt_transfer`]: Self::cancel_async_interrupt_transfer
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn async_interrupt_transfer(
+        &mut self,
+        target: &TransferTarget,
+        endpoint: u8,
+        data_toggle: &mut u8,
+        polling_interval: usize,
+        data_length: usize,
+        callback: AsyncUsbTransferCallback,
+        context: *mut ffi::c_void,
+    ) -> Result {
+        let translator = target.translator();
+
+        unsafe {
+            (self.0.async_interrupt_transfer)(
+                &mut self.0,
+                target.device_address,
+                endpoint | 0x80,
+                target.speed,
+                target.max_packet_length,
+                Boolean::TRUE,
+                data_toggle,
+                polling_interval,
+                data_length,
+                &translator,
+                callback,
+                context,
+            )
+        }
+        .to_result()
+    }
+
+    /// Cancels the asynchronous interrupt transfer of the endpoint
+    /// `endpoint` of `target`.
+    ///
+    /// `data_toggle` is set to the data toggle of the endpoint when the
+    /// transfer was cancelled.
+    pub fn cancel_async_interrupt_transfer(
+        &mut self,
+        target: &TransferTarget,
+        endpoint: u8,
+        data_toggle: &mut u8,
+    ) -> Result {
+        let translator = target.translator();
+
+        unsafe {
+            (self.0.async_interrupt_transfer)(
+                &mut self.0,
+                target.device_address,
+                endpoint | 0x80,
+                target.speed,
+                target.max_packet_length,
+                Boolean::FALSE,
+                data_toggle,
+                0,
+                0,
+                &translator,
+                noop_callback,
+                ptr::null_mut(),
+            )
+        }
+        .to_result()
+    }
+}
+
+/// Callback passed when cancelling a transfer, which is never called.
+#[allow(clippy::missing_const_for_fn)]
+unsafe extern "efiapi" fn noop_callback(
+    _data: *mut ffi::c_void,
+    _data_length: usize,
+    _context: *mut ffi::c_void,
+    _status: UsbTransferStatus,
+) -> crate::Status {
+    crate::Status::SUCCESS
+}
+
+/// Capabilities of a [`Usb2HostController`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Capability {
+    /// Highest speed supported by the controller.
+    pub max_speed: Speed,
+    /// Number of ports of the root hub.
+    pub num_ports: u8,
+    /// The controller supports 64-bit memory addressing.
+    pub is_64_bit_capable: bool,
+}
+
+/// Device and endpoint addressed by the transfers of a
+/// [`Usb2HostController`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TransferTarget {
+    /// Address of the device on the bus.
+    pub device_address: u8,
+    /// Speed of the device.
+    pub speed: Speed,
+    /// Maximum packet size of the endpoint, in bytes.
+    pub max_packet_length: usize,
+    /// Transaction translator of the high-speed hub a low or full speed
+    /// device is connected to, or `None` if there is none.
+    pub translator: Option<TransactionTranslator>,
+}
+
+impl TransferTarget {
+    const fn translator(&self) -> TransactionTranslator {
+        match self.translator {
+            Some(translator) => translator,
+            None => TransactionTranslator {
+                hub_address: 0,
+                port_number: 0,
+            },
+        }
+    }
+}
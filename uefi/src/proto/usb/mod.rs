@@ -4,6 +4,13 @@
 //!
 //! These protocols can be used to interact with and configure USB devices.
 
+#[cfg(feature = "alloc")]
+pub mod descriptor;
+#[cfg(feature = "alloc")]
+pub mod enumerate;
+#[cfg(feature = "alloc")]
+pub mod hid;
+pub mod host_controller;
 pub mod io;
 
 pub use uefi_raw::protocol::usb::{
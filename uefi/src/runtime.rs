@@ -7,12 +7,13 @@
 //! functions after exiting boot services; see the "Calling Convention" section
 //! of the UEFI specification for details.
 
-use crate::data_types::PhysicalAddress;
+use crate::data_types::{PhysicalAddress, VirtualAddress};
+use crate::mem::memory_map::{MemoryAttribute, MemoryMap};
 use crate::table::{self, Revision};
 use crate::{CStr16, Error, Result, Status, StatusExt};
 use core::fmt::{self, Debug, Display, Formatter};
 use core::ptr::{self, NonNull};
-use uefi_raw::table::boot::MemoryDescriptor;
+use uefi_raw::table::boot::{MemoryDescriptor, PAGE_SIZE};
 
 #[cfg(feature = "alloc")]
 use {
@@ -558,6 +559,108 @@ pub unsafe fn set_virtual_address_map(
     Ok(())
 }
 
+/// Builds a virtual address map suitable for [`set_virtual_address_map`].
+///
+/// Every descriptor in `memory_map` with the [`MemoryAttribute::RUNTIME`]
+/// attribute is copied into `buffer`, and its `virt_start` is set to the
+/// address returned by `policy` for that descriptor. All other descriptors are
+/// skipped, as the firmware only needs to know where runtime memory will live.
+///
+/// This function does not allocate, so it can be used after exiting boot
+/// services with the memory map returned by [`exit_boot_services`].
+///
+/// On success, returns the initialized part of `buffer`.
+///
+/// [`exit_boot_services`]: crate::boot::exit_boot_services
+///
+/// # Errors
+///
+/// * [`Status::BUFFER_TOO_SMALL`]: `buffer` cannot hold all runtime
+///   descriptors. The required number of entries will be returned in the
+///   error data.
+/// * [`Status::INVALID_PARAMETER`]: `policy` returned an address that is not
+///   aligned to [`PAGE_SIZE`].
+pub fn build_virtual_address_map<'buf>(
+    memory_map: &impl MemoryMap,
+    buffer: &'buf mut [MemoryDescriptor],
+    mut policy: impl FnMut(&MemoryDescriptor) -> VirtualAddress,
+) -> Result<&'buf mut [MemoryDescriptor], Option<usize>> {
+    let runtime_entries = memory_map
+        .entries()
+        .filter(|desc| desc.att.contains(MemoryAttribute::RUNTIME));
+
+    let required = runtime_entries.clone().count();
+    if required > buffer.len() {
+        return Err(Error::new(Status::BUFFER_TOO_SMALL, Some(required)));
+    }
+
+    for (dst, src) in buffer.iter_mut().zip(runtime_entries) {
+        let virt_start = policy(src);
+        if virt_start % PAGE_SIZE as u64 != 0 {
+            return Err(Error::new(Status::INVALID_PARAMETER, None));
+        }
+        *dst = MemoryDescriptor { virt_start, ..*src };
+    }
+
+    Ok(&mut buffer[..required])
+}
+
+/// Switches the runtime services to virtual addressing, using the virtual
+/// addresses chosen by `policy` for every runtime memory region.
+///
+/// This builds the map with [`build_virtual_address_map`], translates the
+/// address of the system table with the same mapping, and then calls
+/// [`set_virtual_address_map`]. Afterwards, the functions in this module (such
+/// as [`get_time`], [`get_variable`], and [`reset`]) keep working, but access
+/// the firmware through the new virtual addresses.
+///
+/// `buffer` is used as scratch space for the virtual address map; see
+/// [`build_virtual_address_map`] for how to size it.
+///
+/// # Safety
+///
+/// Boot services must have been exited, and `memory_map` must be the memory
+/// map returned by [`exit_boot_services`]. Before any runtime service is
+/// called afterwards, the active page tables must map every runtime region at
+/// the virtual address returned by `policy`.
+///
+/// [`exit_boot_services`]: crate::boot::exit_boot_services
+///
+/// # Errors
+///
+/// * [`Status::BUFFER_TOO_SMALL`]: `buffer` cannot hold all runtime
+///   descriptors. The required number of entries will be returned in the
+///   error data.
+/// * [`Status::INVALID_PARAMETER`]: `policy` returned an address that is not
+///   aligned to [`PAGE_SIZE`].
+/// * [`Status::NO_MAPPING`]: the system table is not located in runtime
+///   memory.
+///
+/// See [`set_virtual_address_map`] for the errors returned by the firmware.
+pub unsafe fn enter_virtual_mode(
+    memory_map: &impl MemoryMap,
+    buffer: &mut [MemoryDescriptor],
+    policy: impl FnMut(&MemoryDescriptor) -> VirtualAddress,
+) -> Result<(), Option<usize>> {
+    let map = build_virtual_address_map(memory_map, buffer, policy)?;
+
+    let st = table::system_table_raw_panicking();
+    let st_virt = translate_to_virtual(map, st.as_ptr() as PhysicalAddress)
+        .ok_or_else(|| Error::new(Status::NO_MAPPING, None))?;
+
+    unsafe { set_virtual_address_map(map, st_virt as *const _) }
+        .map_err(|err| Error::new(err.status(), None))
+}
+
+/// Translates the physical address `addr` to the virtual address it has in
+/// the virtual address map `map`, if it is covered by any entry.
+fn translate_to_virtual(map: &[MemoryDescriptor], addr: PhysicalAddress) -> Option<VirtualAddress> {
+    map.iter().find_map(|desc| {
+        let offset = addr.checked_sub(desc.phys_start)?;
+        (offset < desc.page_count * PAGE_SIZE as u64).then(|| desc.virt_start + offset)
+    })
+}
+
 /// Date and time representation.
 #[derive(Copy, Clone, Eq, PartialEq)]
 #[repr(transparent)]
@@ -935,3 +1038,88 @@ pub struct CapsuleInfo {
     /// The type of reset required for the capsule update.
     pub reset_type: ResetType,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mem::memory_map::{MemoryMapMeta, MemoryMapRef, MemoryType};
+
+    const MMAP: [MemoryDescriptor; 4] = [
+        MemoryDescriptor {
+            ty: MemoryType::CONVENTIONAL,
+            phys_start: 0x1000,
+            virt_start: 0,
+            page_count: 4,
+            att: MemoryAttribute::WRITE_BACK,
+        },
+        MemoryDescriptor {
+            ty: MemoryType::RUNTIME_SERVICES_CODE,
+            phys_start: 0x5000,
+            virt_start: 0,
+            page_count: 2,
+            att: MemoryAttribute::WRITE_BACK.union(MemoryAttribute::RUNTIME),
+        },
+        MemoryDescriptor {
+            ty: MemoryType::LOADER_DATA,
+            phys_start: 0x7000,
+            virt_start: 0,
+            page_count: 1,
+            att: MemoryAttribute::WRITE_BACK,
+        },
+        MemoryDescriptor {
+            ty: MemoryType::RUNTIME_SERVICES_DATA,
+            phys_start: 0x8000,
+            virt_start: 0,
+            page_count: 3,
+            att: MemoryAttribute::WRITE_BACK.union(MemoryAttribute::RUNTIME),
+        },
+    ];
+
+    fn with_mmap(f: impl FnOnce(&MemoryMapRef)) {
+        let len = size_of_val(&MMAP);
+        let buf = unsafe { core::slice::from_raw_parts(MMAP.as_ptr().cast::<u8>(), len) };
+        let meta = MemoryMapMeta {
+            map_size: len,
+            desc_size: size_of::<MemoryDescriptor>(),
+            map_key: Default::default(),
+            desc_version: MemoryDescriptor::VERSION,
+        };
+        f(&MemoryMapRef::new(buf, meta).unwrap());
+    }
+
+    const OFFSET: u64 = 0xffff_8000_0000_0000;
+
+    #[test]
+    fn test_build_virtual_address_map() {
+        with_mmap(|mmap| {
+            let mut buf = [MemoryDescriptor::default(); 4];
+            let map =
+                build_virtual_address_map(mmap, &mut buf, |desc| desc.phys_start + OFFSET).unwrap();
+            assert_eq!(map.len(), 2);
+            assert_eq!(map[0].ty, MemoryType::RUNTIME_SERVICES_CODE);
+            assert_eq!(map[0].virt_start, 0x5000 + OFFSET);
+            assert_eq!(map[1].ty, MemoryType::RUNTIME_SERVICES_DATA);
+            assert_eq!(map[1].virt_start, 0x8000 + OFFSET);
+
+            assert_eq!(translate_to_virtual(map, 0x9abc), Some(0x9abc + OFFSET));
+            assert_eq!(translate_to_virtual(map, 0xb000), None);
+            assert_eq!(translate_to_virtual(map, 0x1000), None);
+        });
+    }
+
+    #[test]
+    fn test_build_virtual_address_map_errors() {
+        with_mmap(|mmap| {
+            let mut buf = [MemoryDescriptor::default(); 1];
+            let err =
+                build_virtual_address_map(mmap, &mut buf, |desc| desc.phys_start).unwrap_err();
+            assert_eq!(err.status(), Status::BUFFER_TOO_SMALL);
+            assert_eq!(*err.data(), Some(2));
+
+            let mut buf = [MemoryDescriptor::default(); 2];
+            let err =
+                build_virtual_address_map(mmap, &mut buf, |desc| desc.phys_start + 1).unwrap_err();
+            assert_eq!(err.status(), Status::INVALID_PARAMETER);
+        });
+    }
+}
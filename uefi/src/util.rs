@@ -1,6 +1,9 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
+use crate::boot::{self, TplGuard};
+use core::cell::UnsafeCell;
 use core::ptr::{self, NonNull};
+use core::sync::atomic::{AtomicBool, Ordering};
 
 /// Copy the bytes of `val` to `ptr`, then advance pointer to just after the
 /// newly-copied bytes.
@@ -33,6 +36,98 @@ pub fn opt_nonnull_to_ptr<T>(opt: Option<NonNull<T>>) -> *mut T {
     opt.map(NonNull::as_ptr).unwrap_or(ptr::null_mut())
 }
 
+/// Minimal spin lock for state shared between callers of global objects like
+/// loggers and allocators.
+///
+/// The lock is not re-entrant. Where the protected code may call back into
+/// the owner of the lock (e.g. a logger whose sink logs itself), use
+/// [`try_lock`] to avoid deadlocks.
+///
+/// [`try_lock`]: Self::try_lock
+#[derive(Debug)]
+pub(crate) struct Lock<T> {
+    locked: AtomicBool,
+    value: UnsafeCell<T>,
+}
+
+impl<T> Lock<T> {
+    pub(crate) const fn new(value: T) -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// Calls `f` with the protected value, or returns `None` if the lock is
+    /// already held.
+    pub(crate) fn try_lock<R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        if self
+            .locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return None;
+        }
+        // SAFETY: the lock ensures exclusive access.
+        let ret = f(unsafe { &mut *self.value.get() });
+        self.locked.store(false, Ordering::Release);
+        Some(ret)
+    }
+
+    /// Calls `f` with the protected value, waiting until the lock is free.
+    pub(crate) fn lock<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        let mut f = Some(f);
+        loop {
+            if let Some(ret) = self.try_lock(|value| f.take().unwrap()(value)) {
+                return ret;
+            }
+            core::hint::spin_loop();
+        }
+    }
+}
+
+unsafe impl<T: Send> Sync for Lock<T> {}
+
+impl<T> Lock<T> {
+    /// Like [`try_lock`], but with the TPL raised to [`Tpl::NOTIFY`] while
+    /// boot services are active.
+    ///
+    /// Use this where the lock may also be taken from an event notification
+    /// function: with the TPL raised, the notification function cannot
+    /// preempt the holder of the lock on the same CPU and wait for it forever.
+    ///
+    /// [`try_lock`]: Self::try_lock
+    /// [`Tpl::NOTIFY`]: boot::Tpl::NOTIFY
+    #[cfg(all(feature = "logger", feature = "alloc"))]
+    pub(crate) fn try_lock_no_preempt<R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        let _tpl = raise_tpl_to_notify();
+        self.try_lock(f)
+    }
+
+    /// Like [`lock`], but with the TPL raised as in [`try_lock_no_preempt`].
+    ///
+    /// [`lock`]: Self::lock
+    /// [`try_lock_no_preempt`]: Self::try_lock_no_preempt
+    pub(crate) fn lock_no_preempt<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        let _tpl = raise_tpl_to_notify();
+        self.lock(f)
+    }
+}
+
+/// Raises the TPL to [`Tpl::NOTIFY`] if boot services are active and the TPL
+/// is currently below that level.
+///
+/// [`Tpl::NOTIFY`]: boot::Tpl::NOTIFY
+fn raise_tpl_to_notify() -> Option<TplGuard> {
+    if !boot::are_boot_services_active() {
+        return None;
+    }
+    // The TPL must not be raised to a lower level than the current one, so
+    // query the current level first. `HIGH_LEVEL` is always valid.
+    let current = unsafe { boot::raise_tpl(boot::Tpl::HIGH_LEVEL) }.old_tpl();
+    (current < boot::Tpl::NOTIFY).then(|| unsafe { boot::raise_tpl(boot::Tpl::NOTIFY) })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;